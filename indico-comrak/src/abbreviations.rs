@@ -0,0 +1,152 @@
+//! Abbreviations: `*[HTML]: HyperText Markup Language` definitions, which wrap the occurrences
+//! of the abbreviation in the rest of the document in `<abbr>` tags.
+//!
+//! The definitions are removed from the markdown before it is parsed, and the occurrences are
+//! replaced in the text of the AST.
+
+use comrak::{
+    Arena,
+    nodes::{AstNode, NodeValue},
+};
+use regex_lite::Regex;
+use std::{borrow::Cow, cmp::Reverse, collections::HashMap};
+
+use crate::{containers::CodeFences, html::escape_xml, replace_with_nodes};
+
+/// Parse an abbreviation definition, returning the abbreviation and its expansion
+fn parse_definition(line: &str) -> Option<(&str, &str)> {
+    let trimmed = line.trim_start_matches(' ');
+    if line.len() - trimmed.len() > 3 {
+        return None;
+    }
+    let (term, expansion) = trimmed.strip_prefix("*[")?.split_once("]:")?;
+    let term = term.trim();
+    (!term.is_empty() && !term.contains(['[', ']'])).then_some((term, expansion.trim()))
+}
+
+/// Remove the abbreviation definitions from a document, returning them along with the rest of
+/// the document. The lines of the definitions are kept (empty), so that line numbers don't
+/// change.
+pub(crate) fn extract_abbreviations(md_source: &str) -> (Cow<'_, str>, HashMap<String, String>) {
+    let mut abbreviations = HashMap::new();
+    if !md_source.contains("*[") {
+        return (Cow::Borrowed(md_source), abbreviations);
+    }
+
+    let mut out = String::with_capacity(md_source.len());
+    let mut code_fences = CodeFences::default();
+    for line in md_source.split_inclusive('\n') {
+        let content = line.trim_end_matches(['\n', '\r']);
+        if !code_fences.in_code(content)
+            && let Some((term, expansion)) = parse_definition(content)
+        {
+            abbreviations.insert(term.to_string(), expansion.to_string());
+            out.push_str(&line[content.len()..]);
+        } else {
+            out.push_str(line);
+        }
+    }
+    (Cow::Owned(out), abbreviations)
+}
+
+fn abbr_html(term: &str, expansion: &str) -> String {
+    let mut html = String::from("<abbr title=\"");
+    escape_xml(&mut html, expansion, true);
+    html.push_str("\">");
+    escape_xml(&mut html, term, false);
+    html.push_str("</abbr>");
+    html
+}
+
+/// Wrap the abbreviations in the text of the document (except in headings, whose anchors are
+/// generated from their text) in `<abbr>` tags
+pub(crate) fn replace_abbreviations<'a>(
+    root: &'a AstNode<'a>,
+    arena: &'a Arena<'a>,
+    abbreviations: &HashMap<String, String>,
+) {
+    if abbreviations.is_empty() {
+        return;
+    }
+    // longer abbreviations first, so that they win over the ones they contain
+    let mut terms: Vec<&String> = abbreviations.keys().collect();
+    terms.sort_by_key(|term| Reverse(term.len()));
+    let pattern: Vec<String> = terms.iter().map(|term| regex_lite::escape(term)).collect();
+    let re = Regex::new(&pattern.join("|")).unwrap();
+    let is_word_char = |c: char| c.is_alphanumeric() || c == '_';
+
+    let mut to_replace = Vec::new();
+    for node in root.descendants() {
+        let data = node.data.borrow();
+        let NodeValue::Text(ref t) = data.value else {
+            continue;
+        };
+        if node
+            .ancestors()
+            .any(|n| matches!(n.data.borrow().value, NodeValue::Heading(_)))
+        {
+            continue;
+        }
+
+        let mut values = Vec::new();
+        let mut last = 0;
+        for m in re.find_iter(t) {
+            // only whole words are abbreviations
+            if t[..m.start()].chars().next_back().is_some_and(is_word_char)
+                || t[m.end()..].chars().next().is_some_and(is_word_char)
+            {
+                continue;
+            }
+            if m.start() > last {
+                values.push(NodeValue::Text(t[last..m.start()].to_string().into()));
+            }
+            values.push(NodeValue::HtmlInline(abbr_html(
+                m.as_str(),
+                &abbreviations[m.as_str()],
+            )));
+            last = m.end();
+        }
+        if values.is_empty() {
+            continue;
+        }
+        if last < t.len() {
+            values.push(NodeValue::Text(t[last..].to_string().into()));
+        }
+        to_replace.push((node, values));
+    }
+
+    for (node, values) in to_replace {
+        replace_with_nodes(node, arena, values);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::extract_abbreviations;
+
+    #[test]
+    fn test_extract_abbreviations() {
+        let (md, abbreviations) = extract_abbreviations(
+            "The HTML spec\n\n*[HTML]: HyperText Markup Language\n  *[W3C]:World Wide Web Consortium\n\
+             ```\n*[CSS]: Cascading Style Sheets\n```\n",
+        );
+        assert_eq!(
+            md,
+            "The HTML spec\n\n\n\n```\n*[CSS]: Cascading Style Sheets\n```\n"
+        );
+        assert_eq!(abbreviations.len(), 2);
+        assert_eq!(abbreviations["HTML"], "HyperText Markup Language");
+        assert_eq!(abbreviations["W3C"], "World Wide Web Consortium");
+
+        for md in [
+            "*[]: nothing\n",
+            "*[a]b]: no\n",
+            "*[HTML] no colon\n",
+            "    *[HTML]: code\n",
+        ] {
+            let (out, abbreviations) = extract_abbreviations(md);
+            assert_eq!(out, md);
+            assert!(abbreviations.is_empty());
+        }
+    }
+}
@@ -0,0 +1,113 @@
+//! Custom alert kinds (e.g. `> [!DANGER]`), in addition to the ones supported by comrak
+//! (`NOTE`, `TIP`, `IMPORTANT`, `WARNING` and `CAUTION`).
+//!
+//! comrak parses them as regular blockquotes, so their marker is removed from the AST before
+//! rendering, and the blockquotes are rendered as alerts by the formatter.
+
+use comrak::nodes::{AlertType, AstNode, NodeValue};
+use std::collections::HashMap;
+
+use crate::attributes::node_key;
+
+/// The blockquotes which are custom alerts, keyed like
+/// [`NodeAttributes`](crate::attributes::NodeAttributes), with their kind (in lowercase) and
+/// their title, if any
+pub(crate) type CustomAlerts = HashMap<usize, (String, Option<String>)>;
+
+/// Name of the kind of a built-in alert
+pub(crate) fn alert_kind(alert_type: &AlertType) -> &'static str {
+    match alert_type {
+        AlertType::Note => "note",
+        AlertType::Tip => "tip",
+        AlertType::Important => "important",
+        AlertType::Warning => "warning",
+        AlertType::Caution => "caution",
+    }
+}
+
+/// Parse the first line of a blockquote as an alert marker (`[!KIND]`, optionally followed by
+/// a title), for one of the given kinds
+fn parse_marker(line: &str, kinds: &[String]) -> Option<(String, Option<String>)> {
+    let rest = line.trim_start().strip_prefix("[!")?;
+    let end = rest.find(']')?;
+    let kind = rest[..end].to_ascii_lowercase();
+    let title = &rest[end + 1..];
+    if !kinds.iter().any(|k| k.eq_ignore_ascii_case(&kind))
+        || !(title.is_empty() || title.starts_with(char::is_whitespace))
+    {
+        return None;
+    }
+    let title = title.trim();
+    Some((kind, (!title.is_empty()).then(|| title.to_string())))
+}
+
+/// Find the blockquotes which are alerts of one of the given kinds, removing their markers
+pub(crate) fn extract_custom_alerts<'a>(root: &'a AstNode<'a>, kinds: &[String]) -> CustomAlerts {
+    let mut alerts = CustomAlerts::new();
+    if kinds.is_empty() {
+        return alerts;
+    }
+
+    let quotes: Vec<_> = root
+        .descendants()
+        .filter(|node| matches!(node.data.borrow().value, NodeValue::BlockQuote))
+        .collect();
+    for quote in quotes {
+        let Some(paragraph) = quote
+            .first_child()
+            .filter(|node| matches!(node.data.borrow().value, NodeValue::Paragraph))
+        else {
+            continue;
+        };
+
+        // the marker may be split over several text nodes (`[`, `!DANGER`, `]`)
+        let mut line = String::new();
+        let mut marker_nodes = Vec::new();
+        for child in paragraph.children() {
+            marker_nodes.push(child);
+            match child.data.borrow().value {
+                NodeValue::Text(ref text) => line.push_str(text),
+                // the line break after the marker goes with it
+                NodeValue::SoftBreak | NodeValue::LineBreak => break,
+                // titles are plain text
+                _ => {
+                    line.clear();
+                    break;
+                }
+            }
+        }
+
+        if let Some(alert) = parse_marker(&line, kinds) {
+            for node in marker_nodes {
+                node.detach();
+            }
+            if paragraph.first_child().is_none() {
+                paragraph.detach();
+            }
+            alerts.insert(node_key(quote), alert);
+        }
+    }
+    alerts
+}
+
+#[cfg(test)]
+mod tests {
+    use super::parse_marker;
+
+    #[test]
+    fn test_parse_marker() {
+        let kinds = ["danger".to_string(), "info".to_string()];
+        assert_eq!(
+            parse_marker("[!DANGER]", &kinds),
+            Some(("danger".to_string(), None))
+        );
+        assert_eq!(
+            parse_marker("[!Info]  Good to know ", &kinds),
+            Some(("info".to_string(), Some("Good to know".to_string())))
+        );
+
+        for line in ["[!NOTE]", "[!DANGER]foo", "[DANGER]", "text [!DANGER]", ""] {
+            assert_eq!(parse_marker(line, &kinds), None);
+        }
+    }
+}
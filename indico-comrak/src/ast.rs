@@ -0,0 +1,166 @@
+//! Export of the parsed document tree as JSON, for tools which need structured access to
+//! the content (search indexing, migration scripts) rather than rendered HTML.
+
+use comrak::{
+    Arena,
+    arena_tree::NodeEdge,
+    nodes::{AstNode, ListType, NodeValue},
+    parse_document,
+};
+use serde_json::{Map, Value, json};
+
+use crate::{LinkRule, add_links, indico_options};
+
+/// Build the JSON object describing a single node, without its children
+fn node_to_json<'a>(node: &'a AstNode<'a>) -> Map<String, Value> {
+    let ast = node.data.borrow();
+    let mut obj = Map::new();
+
+    obj.insert("type".into(), ast.value.xml_node_name().into());
+    obj.insert(
+        "sourcepos".into(),
+        json!({
+            "start": [ast.sourcepos.start.line, ast.sourcepos.start.column],
+            "end": [ast.sourcepos.end.line, ast.sourcepos.end.column],
+        }),
+    );
+
+    match ast.value {
+        NodeValue::Text(ref t) => {
+            obj.insert("literal".into(), t.to_string().into());
+        }
+        NodeValue::Code(ref nc) => {
+            obj.insert("literal".into(), nc.literal.clone().into());
+        }
+        NodeValue::HtmlInline(ref html) => {
+            obj.insert("literal".into(), html.clone().into());
+        }
+        NodeValue::HtmlBlock(ref nhb) => {
+            obj.insert("literal".into(), nhb.literal.clone().into());
+        }
+        NodeValue::CodeBlock(ref ncb) => {
+            obj.insert("info".into(), ncb.info.clone().into());
+            obj.insert("literal".into(), ncb.literal.clone().into());
+        }
+        NodeValue::Math(ref nm) => {
+            obj.insert("display".into(), nm.display_math.into());
+            obj.insert("literal".into(), nm.literal.clone().into());
+        }
+        NodeValue::Heading(ref nh) => {
+            obj.insert("level".into(), nh.level.into());
+        }
+        NodeValue::List(ref nl) => {
+            obj.insert("ordered".into(), (nl.list_type == ListType::Ordered).into());
+            obj.insert("start".into(), nl.start.into());
+            obj.insert("tight".into(), nl.tight.into());
+        }
+        NodeValue::Link(ref nl) | NodeValue::Image(ref nl) => {
+            obj.insert("url".into(), nl.url.clone().into());
+            obj.insert("title".into(), nl.title.clone().into());
+        }
+        NodeValue::TaskItem(ref nti) => {
+            obj.insert("checked".into(), nti.symbol.is_some().into());
+        }
+        NodeValue::Alert(ref na) => {
+            obj.insert(
+                "alert_type".into(),
+                na.alert_type.default_title().to_lowercase().into(),
+            );
+        }
+        _ => {}
+    }
+
+    obj
+}
+
+/// Parse markdown, apply the given link rules and return the resulting tree as a JSON string.
+///
+/// Every node is an object with a `type`, its `sourcepos` (`[line, column]` pairs) and its
+/// `children`, plus type-specific attributes such as `literal`, `url` or `level`. The text of
+/// the links generated by link rules has a `sourcepos` of `[0, 0]`.
+pub fn indico_markdown_to_ast_json(md_source: &str, autolink_rules: &[LinkRule]) -> String {
+    let options = indico_options();
+    let arena = Arena::new();
+    let mut root = parse_document(&arena, md_source, &options);
+
+    add_links(&mut root, &arena, autolink_rules);
+
+    // the JSON is written as the tree is traversed, rather than built as a `Value` and then
+    // serialized, since both would recurse as deep as the document is nested
+    let mut out = String::new();
+    let mut first_child = true;
+    for edge in root.traverse() {
+        match edge {
+            NodeEdge::Start(node) => {
+                if !first_child {
+                    out.push(',');
+                }
+                let obj = Value::Object(node_to_json(node)).to_string();
+                // reopen the object to add its children
+                out.push_str(&obj[..obj.len() - 1]);
+                out.push_str(if obj.len() > 2 {
+                    ",\"children\":["
+                } else {
+                    "\"children\":["
+                });
+                first_child = true;
+            }
+            NodeEdge::End(_) => {
+                out.push_str("]}");
+                first_child = false;
+            }
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::indico_markdown_to_ast_json;
+    use crate::LinkRule;
+    use serde_json::{Value, json};
+
+    #[test]
+    fn test_ast_json() {
+        let res = indico_markdown_to_ast_json(
+            "## Hi\n\nsee gh:12 now",
+            &[LinkRule::new(r"gh:(\d+)", "https://github.com/{1}").unwrap()],
+        );
+        let tree: Value = serde_json::from_str(&res).unwrap();
+
+        assert_eq!(tree["type"], "document");
+        assert_eq!(tree["children"][0]["type"], "heading");
+        assert_eq!(tree["children"][0]["level"], 2);
+        assert_eq!(
+            tree["children"][0]["sourcepos"],
+            json!({"start": [1, 1], "end": [1, 5]})
+        );
+
+        let para = &tree["children"][1]["children"];
+        assert_eq!(para[0]["literal"], "see ");
+        assert_eq!(para[1]["type"], "link");
+        assert_eq!(para[1]["url"], "https://github.com/12");
+        assert_eq!(para[1]["children"][0]["literal"], "gh:12");
+        assert_eq!(para[2]["literal"], " now");
+        // the text around the links keeps its position
+        assert_eq!(
+            para[0]["sourcepos"],
+            json!({"start": [3, 1], "end": [3, 4]})
+        );
+        assert_eq!(
+            para[1]["sourcepos"],
+            json!({"start": [3, 5], "end": [3, 9]})
+        );
+        assert_eq!(
+            para[2]["sourcepos"],
+            json!({"start": [3, 10], "end": [3, 13]})
+        );
+    }
+
+    #[test]
+    fn test_ast_json_deep_nesting() {
+        let res = indico_markdown_to_ast_json(&"> ".repeat(10_000), &[]);
+        assert_eq!(res.matches("\"type\":\"block_quote\"").count(), 10_000);
+        assert!(res.ends_with(&"]}".repeat(10_001)));
+    }
+}
@@ -0,0 +1,220 @@
+//! Attribute blocks (`{.class #id key=value}`) after headings, images and in the info strings
+//! of fenced code blocks, and on their own line at the end of paragraphs.
+//!
+//! The blocks are removed from the AST before rendering, and the attributes which are
+//! allowed are kept in a map, keyed by the address of the node they belong to.
+
+use comrak::nodes::{AstNode, NodeValue};
+use std::collections::HashMap;
+
+use crate::html::escape_xml;
+
+/// Attributes of the nodes of a document
+pub(crate) type NodeAttributes = HashMap<usize, Vec<(String, String)>>;
+
+/// Key of a node in [`NodeAttributes`]
+pub(crate) fn node_key<'a>(node: &'a AstNode<'a>) -> usize {
+    node as *const AstNode<'a> as usize
+}
+
+/// Parse the content of an attribute block, keeping only the attributes in `allowed`.
+/// Returns `None` if it's not a valid attribute block.
+fn parse_attributes(block: &str, allowed: &[String]) -> Option<Vec<(String, String)>> {
+    let word_end = |s: &str| s.find(char::is_whitespace).unwrap_or(s.len());
+    let mut classes = Vec::new();
+    let mut attrs: Vec<(String, String)> = Vec::new();
+    let mut rest = block;
+
+    loop {
+        rest = rest.trim_start();
+        if rest.is_empty() {
+            break;
+        }
+        if let Some(r) = rest.strip_prefix('.') {
+            let end = word_end(r);
+            if end == 0 {
+                return None;
+            }
+            classes.push(&r[..end]);
+            rest = &r[end..];
+        } else if let Some(r) = rest.strip_prefix('#') {
+            let end = word_end(r);
+            if end == 0 {
+                return None;
+            }
+            attrs.push(("id".into(), r[..end].into()));
+            rest = &r[end..];
+        } else {
+            let eq = rest.find('=')?;
+            let key = &rest[..eq];
+            if key.is_empty()
+                || !key
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+            {
+                return None;
+            }
+            let r = &rest[eq + 1..];
+            let (value, r) = if let Some(quoted) = r.strip_prefix('"') {
+                let end = quoted.find('"')?;
+                (&quoted[..end], &quoted[end + 1..])
+            } else {
+                let end = word_end(r);
+                (&r[..end], &r[end..])
+            };
+            attrs.push((key.to_ascii_lowercase(), value.into()));
+            rest = r;
+        }
+    }
+
+    if !classes.is_empty() {
+        attrs.insert(0, ("class".into(), classes.join(" ")));
+    }
+    let mut result: Vec<(String, String)> = Vec::new();
+    for (key, value) in attrs {
+        if allowed.iter().any(|a| a.eq_ignore_ascii_case(&key))
+            && !result.iter().any(|(k, _)| *k == key)
+        {
+            result.push((key, value));
+        }
+    }
+    Some(result)
+}
+
+/// Split a trailing `{...}` attribute block from a string, returning the rest of the string
+/// and the attributes
+fn split_trailing<'s>(
+    text: &'s str,
+    allowed: &[String],
+) -> Option<(&'s str, Vec<(String, String)>)> {
+    let trimmed = text.trim_end();
+    let inner = trimmed.strip_suffix('}')?;
+    let start = inner.rfind('{')?;
+    let attrs = parse_attributes(&inner[start + 1..], allowed)?;
+    Some((inner[..start].trim_end(), attrs))
+}
+
+/// Split a leading `{...}` attribute block from a string, returning the attributes and the
+/// rest of the string
+fn split_leading<'s>(
+    text: &'s str,
+    allowed: &[String],
+) -> Option<(Vec<(String, String)>, &'s str)> {
+    let inner = text.strip_prefix('{')?;
+    let end = inner.find('}')?;
+    let attrs = parse_attributes(&inner[..end], allowed)?;
+    Some((attrs, &inner[end + 1..]))
+}
+
+/// Remove the attribute blocks from the document, returning the allowed attributes
+pub(crate) fn extract_attributes<'a>(root: &'a AstNode<'a>, allowed: &[String]) -> NodeAttributes {
+    let mut attributes = NodeAttributes::new();
+    let mut to_detach = Vec::new();
+
+    for node in root.descendants() {
+        let mut data = node.data.borrow_mut();
+        match data.value {
+            NodeValue::Heading(_) => {
+                let Some(last) = node.last_child() else {
+                    continue;
+                };
+                if let NodeValue::Text(ref mut text) = last.data.borrow_mut().value
+                    && let Some((rest, attrs)) = split_trailing(text, allowed)
+                {
+                    if rest.is_empty() {
+                        to_detach.push(last);
+                    }
+                    *text = rest.to_string().into();
+                    attributes.insert(node_key(node), attrs);
+                }
+            }
+            NodeValue::Image(_) => {
+                let Some(next) = node.next_sibling() else {
+                    continue;
+                };
+                if let NodeValue::Text(ref mut text) = next.data.borrow_mut().value
+                    && let Some((attrs, rest)) = split_leading(text, allowed)
+                {
+                    if rest.is_empty() {
+                        to_detach.push(next);
+                    }
+                    *text = rest.to_string().into();
+                    attributes.insert(node_key(node), attrs);
+                }
+            }
+            NodeValue::Paragraph => {
+                let Some(last) = node.last_child() else {
+                    continue;
+                };
+                let Some(soft_break) = last
+                    .previous_sibling()
+                    .filter(|prev| matches!(prev.data.borrow().value, NodeValue::SoftBreak))
+                else {
+                    continue;
+                };
+                if let NodeValue::Text(ref text) = last.data.borrow().value
+                    && text.starts_with('{')
+                    && let Some(("", attrs)) = split_trailing(text, allowed)
+                {
+                    to_detach.extend([soft_break, last]);
+                    attributes.insert(node_key(node), attrs);
+                }
+            }
+            NodeValue::CodeBlock(ref mut ncb) if ncb.fenced => {
+                if let Some((rest, attrs)) = split_trailing(&ncb.info, allowed) {
+                    ncb.info = rest.into();
+                    attributes.insert(node_key(node), attrs);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    for node in to_detach {
+        node.detach();
+    }
+    attributes
+}
+
+/// Render attributes, each of them preceded by a space
+pub(crate) fn attributes_html(attrs: &[(String, String)]) -> String {
+    let mut html = String::new();
+    for (key, value) in attrs {
+        html.push(' ');
+        html.push_str(key);
+        html.push_str("=\"");
+        escape_xml(&mut html, value, true);
+        html.push('"');
+    }
+    html
+}
+
+#[cfg(test)]
+mod tests {
+    use super::parse_attributes;
+
+    #[test]
+    fn test_parse_attributes() {
+        let allowed = ["class".to_string(), "id".to_string(), "width".to_string()];
+        let attr = |k: &str, v: &str| (k.to_string(), v.to_string());
+
+        assert_eq!(
+            parse_attributes(".a #b .c width=\"20 px\" onclick=alert(1)", &allowed),
+            Some(vec![
+                attr("class", "a c"),
+                attr("id", "b"),
+                attr("width", "20 px")
+            ])
+        );
+        assert_eq!(
+            parse_attributes("  WIDTH=1 width=2 ", &allowed),
+            Some(vec![attr("width", "1")])
+        );
+        assert_eq!(parse_attributes("", &allowed), Some(vec![]));
+
+        // not attribute blocks
+        for block in ["foo", ". a", "width=\"1", "=1", "a b=1"] {
+            assert_eq!(parse_attributes(block, &allowed), None);
+        }
+    }
+}
@@ -0,0 +1,133 @@
+//! Accessibility checks of documents, so that authors can be warned before saving.
+
+use comrak::{Arena, nodes::NodeValue, parse_document};
+
+use crate::{
+    collect_text,
+    extract::{SourcePositions, SourceRange},
+    indico_options,
+    options::OffsetMode,
+};
+
+/// Link texts which don't say anything about the target of the link
+const VAGUE_LINK_TEXTS: &[&str] = &[
+    "click here",
+    "here",
+    "link",
+    "this link",
+    "this",
+    "more",
+    "read more",
+    "learn more",
+];
+
+/// Kind of an accessibility issue
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AuditIssueKind {
+    /// An image has no alternative text
+    MissingAltText,
+    /// A heading is more than one level below the previous one (e.g. `###` after `#`)
+    SkippedHeadingLevel,
+    /// A link has no text
+    EmptyLink,
+    /// The text of a link doesn't describe its target (e.g. "click here")
+    VagueLinkText,
+}
+
+/// An accessibility issue found in a document
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AuditIssue {
+    pub kind: AuditIssueKind,
+    /// Description of the issue, in English
+    pub message: String,
+    /// Position of the offending element in the markdown source
+    pub sourcepos: SourceRange,
+}
+
+/// Check a document for accessibility issues: images without alternative text, skipped
+/// heading levels, empty links and links whose text doesn't describe their target
+pub fn indico_markdown_audit(md_source: &str, offset_mode: OffsetMode) -> Vec<AuditIssue> {
+    let arena = Arena::new();
+    let root = parse_document(&arena, md_source, &indico_options());
+    let positions = SourcePositions::new(md_source, offset_mode);
+    let mut issues = Vec::new();
+    let mut previous_level: Option<u8> = None;
+
+    for node in root.descendants() {
+        let data = node.data.borrow();
+        let issue = match data.value {
+            NodeValue::Image(_) if collect_text(node).trim().is_empty() => Some((
+                AuditIssueKind::MissingAltText,
+                "Image without alternative text".to_string(),
+            )),
+            NodeValue::Heading(ref nh) => {
+                let skipped = previous_level.filter(|&previous| nh.level > previous + 1);
+                previous_level = Some(nh.level);
+                skipped.map(|previous| {
+                    (
+                        AuditIssueKind::SkippedHeadingLevel,
+                        format!(
+                            "Level {} heading after a level {previous} heading",
+                            nh.level
+                        ),
+                    )
+                })
+            }
+            NodeValue::Link(_) => {
+                let text = collect_text(node);
+                let text = text.trim().trim_end_matches(['.', ':', '!']).to_lowercase();
+                let has_image = node
+                    .descendants()
+                    .any(|n| matches!(n.data.borrow().value, NodeValue::Image(_)));
+                if text.is_empty() && !has_image {
+                    Some((AuditIssueKind::EmptyLink, "Link without text".to_string()))
+                } else if VAGUE_LINK_TEXTS.contains(&text.as_str()) {
+                    Some((
+                        AuditIssueKind::VagueLinkText,
+                        format!("Link text \"{text}\" doesn't describe the target of the link"),
+                    ))
+                } else {
+                    None
+                }
+            }
+            _ => None,
+        };
+
+        if let Some((kind, message)) = issue {
+            issues.push(AuditIssue {
+                kind,
+                message,
+                sourcepos: positions.range(data.sourcepos.into()),
+            });
+        }
+    }
+    issues
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{AuditIssueKind, indico_markdown_audit};
+    use crate::options::OffsetMode;
+
+    #[test]
+    fn test_audit() {
+        let md = "# Event\n\n### Venue\n\n![](map.png) and ![Hall](hall.png)\n\n\
+                  [Click here](/register). [](/empty) [![Logo](logo.png)](/)\n\n\
+                  [The timetable](/timetable)\n\n## Talks\n\n#### Slides";
+        let issues: Vec<_> = indico_markdown_audit(md, OffsetMode::Bytes)
+            .into_iter()
+            .map(|issue| (issue.kind, issue.sourcepos.start.0))
+            .collect();
+        assert_eq!(
+            issues,
+            [
+                (AuditIssueKind::SkippedHeadingLevel, 3),
+                (AuditIssueKind::MissingAltText, 5),
+                (AuditIssueKind::VagueLinkText, 7),
+                (AuditIssueKind::EmptyLink, 7),
+                (AuditIssueKind::SkippedHeadingLevel, 13),
+            ]
+        );
+        assert!(indico_markdown_audit("# Fine\n\n## Also fine", OffsetMode::Bytes).is_empty());
+    }
+}
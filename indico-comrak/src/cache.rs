@@ -0,0 +1,218 @@
+//! A cache of rendered documents, since the same (unchanged) descriptions are rendered over
+//! and over on event pages.
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+use crate::{LinkRule, RenderError, RenderOutput, indico_markdown_render, options::RenderOptions};
+
+/// A cached render, with the time it was last used
+#[derive(Debug)]
+struct CacheEntry {
+    output: RenderOutput,
+    last_used: u64,
+}
+
+#[derive(Debug, Default)]
+struct CacheState {
+    entries: HashMap<CacheKey, CacheEntry>,
+    clock: u64,
+}
+
+/// The inputs of a render, which are compared in full when looking up an output
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct CacheKey {
+    md_source: String,
+    /// Patterns and URL templates of the link rules
+    rules: Vec<(String, String)>,
+    /// Debug representation of the options
+    options: String,
+}
+
+/// An in-process cache of the outputs of [`indico_markdown_render`], keyed by the document,
+/// the link rules and the options. When it is full, the least recently used output is evicted.
+///
+/// Renders with link rules which have a [`LinkResolver`](crate::LinkResolver) are never
+/// cached, since their URLs may change from one render to the next. Options are compared
+/// through their debug representation, in which callbacks (e.g.
+/// [`WikiLinkResolver`](crate::WikiLinkResolver)) can't be told apart: renders whose options
+/// only differ by their callbacks must not share a cache.
+#[derive(Debug)]
+pub struct RenderCache {
+    capacity: usize,
+    state: Mutex<CacheState>,
+}
+
+/// Get the key of the inputs of a render, or `None` if it can't be cached
+fn cache_key(
+    md_source: &str,
+    autolink_rules: &[LinkRule],
+    render_options: &RenderOptions,
+) -> Option<CacheKey> {
+    let rules = autolink_rules
+        .iter()
+        .map(|rule| match rule.resolver {
+            Some(_) => None,
+            None => Some((rule.re.as_str().to_string(), rule.url.clone())),
+        })
+        .collect::<Option<_>>()?;
+    Some(CacheKey {
+        md_source: md_source.to_string(),
+        rules,
+        options: format!("{render_options:?}"),
+    })
+}
+
+impl RenderCache {
+    /// Create a cache holding at most `capacity` outputs
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            state: Mutex::new(CacheState::default()),
+        }
+    }
+
+    /// Same as [`indico_markdown_render`], returning a cached output if the same document was
+    /// already rendered with the same rules and options
+    pub fn render(
+        &self,
+        md_source: &str,
+        autolink_rules: &[LinkRule],
+        render_options: &RenderOptions,
+    ) -> Result<RenderOutput, RenderError> {
+        let Some(key) = cache_key(md_source, autolink_rules, render_options) else {
+            return indico_markdown_render(md_source, autolink_rules, render_options);
+        };
+        {
+            let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+            state.clock += 1;
+            let clock = state.clock;
+            if let Some(entry) = state.entries.get_mut(&key) {
+                entry.last_used = clock;
+                return Ok(entry.output.clone());
+            }
+        }
+
+        // the lock isn't held while rendering, so that other documents can be rendered
+        let output = indico_markdown_render(md_source, autolink_rules, render_options)?;
+        if self.capacity > 0 {
+            let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+            if state.entries.len() >= self.capacity
+                && !state.entries.contains_key(&key)
+                && let Some(oldest) = state
+                    .entries
+                    .iter()
+                    .min_by_key(|(_, entry)| entry.last_used)
+                    .map(|(key, _)| key.clone())
+            {
+                state.entries.remove(&oldest);
+            }
+            state.clock += 1;
+            let last_used = state.clock;
+            state.entries.insert(
+                key,
+                CacheEntry {
+                    output: output.clone(),
+                    last_used,
+                },
+            );
+        }
+        Ok(output)
+    }
+
+    /// Number of cached outputs
+    pub fn len(&self) -> usize {
+        self.state
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .entries
+            .len()
+    }
+
+    /// Check whether the cache is empty
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Remove all the cached outputs
+    pub fn clear(&self) {
+        self.state
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .entries
+            .clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use super::RenderCache;
+    use crate::options::{LanguageDetector, RenderOptions};
+    use crate::{LinkResolver, LinkRule};
+
+    #[test]
+    fn test_render_cache() {
+        // the detector is called once per actual render
+        let renders = Arc::new(AtomicUsize::new(0));
+        let counter = renders.clone();
+        let options = RenderOptions {
+            language_detector: Some(LanguageDetector(Arc::new(move |_: &str| {
+                counter.fetch_add(1, Ordering::Relaxed);
+                None
+            }))),
+            ..Default::default()
+        };
+        let rules = [LinkRule::new(r"#(\d+)", "/tickets/{1}").unwrap()];
+        let cache = RenderCache::new(2);
+
+        let first = cache.render("See #1", &rules, &options).unwrap();
+        assert_eq!(cache.render("See #1", &rules, &options).unwrap(), first);
+        assert_eq!(renders.load(Ordering::Relaxed), 1);
+
+        // different rules or options are different entries
+        cache.render("See #1", &[], &options).unwrap();
+        assert_eq!(renders.load(Ordering::Relaxed), 2);
+        let other_options = RenderOptions {
+            smart_punctuation: Some(crate::options::QuoteStyle::English),
+            ..options.clone()
+        };
+        cache.render("See #1", &rules, &other_options).unwrap();
+        assert_eq!(renders.load(Ordering::Relaxed), 3);
+        assert_eq!(cache.len(), 2);
+
+        // the least recently used output was evicted
+        cache.render("See #1", &rules, &options).unwrap();
+        assert_eq!(renders.load(Ordering::Relaxed), 4);
+        cache.render("See #1", &rules, &other_options).unwrap();
+        assert_eq!(renders.load(Ordering::Relaxed), 4);
+
+        cache.clear();
+        assert!(cache.is_empty());
+    }
+
+    #[test]
+    fn test_render_cache_resolvers() {
+        // the URLs of resolvers may change, so their renders aren't cached
+        let calls = Arc::new(AtomicUsize::new(0));
+        let counter = calls.clone();
+        let resolver = LinkResolver(Arc::new(move |groups: &[Option<&str>]| {
+            let n = counter.fetch_add(1, Ordering::Relaxed);
+            groups[1].map(|id| format!("/tickets/{id}?v={n}"))
+        }));
+        let rules = [LinkRule::with_resolver(r"#(\d+)", resolver).unwrap()];
+        let cache = RenderCache::new(2);
+
+        let first = cache
+            .render("See #1", &rules, &RenderOptions::default())
+            .unwrap();
+        let second = cache
+            .render("See #1", &rules, &RenderOptions::default())
+            .unwrap();
+        assert_ne!(first.html, second.html);
+        assert_eq!(calls.load(Ordering::Relaxed), 2);
+        assert!(cache.is_empty());
+    }
+}
@@ -0,0 +1,217 @@
+//! Pandoc-style fenced containers (`::: warning` ... `:::`) and collapsible sections
+//! (`??? Summary` ... `???`, or `???+ Summary` for sections which are expanded by default).
+//!
+//! comrak doesn't know about them, so the fences are replaced with raw `<div>` and `<details>`
+//! HTML blocks before the markdown is parsed. The blank lines which are added around the tags
+//! make sure that the content of the containers is still rendered as markdown.
+
+use std::borrow::Cow;
+
+use crate::html::escape_xml;
+
+/// Kinds of blocks which can be opened by a fence
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Container {
+    Div,
+    Details,
+}
+
+/// Parse the opening fence of a fenced code block, returning its character and length
+fn code_fence(line: &str) -> Option<(char, usize)> {
+    let trimmed = line.trim_start_matches(' ');
+    if line.len() - trimmed.len() > 3 {
+        return None;
+    }
+    let c = trimmed.chars().next().filter(|&c| c == '`' || c == '~')?;
+    let len = trimmed.chars().take_while(|&x| x == c).count();
+    (len >= 3).then_some((c, len))
+}
+
+/// Keeps track of the fenced code blocks of a document which is processed line by line
+#[derive(Default)]
+pub(crate) struct CodeFences(Option<(char, usize)>);
+
+impl CodeFences {
+    /// Process the next line (without its line break), returning whether it belongs to a
+    /// fenced code block, fences included
+    pub(crate) fn in_code(&mut self, line: &str) -> bool {
+        if let Some((c, len)) = self.0 {
+            if let Some((closing_c, closing_len)) = code_fence(line)
+                && closing_c == c
+                && closing_len >= len
+                && line.trim_start_matches(' ')[closing_len..]
+                    .trim()
+                    .is_empty()
+            {
+                self.0 = None;
+            }
+            return true;
+        }
+        self.0 = code_fence(line);
+        self.0.is_some()
+    }
+}
+
+/// Parse a container fence, returning the name of the container (empty for closing fences)
+fn container_fence(line: &str) -> Option<&str> {
+    let trimmed = line.trim_start_matches(' ');
+    if line.len() - trimmed.len() > 3 {
+        return None;
+    }
+    let rest = trimmed.trim_start_matches(':');
+    if trimmed.len() - rest.len() < 3 {
+        return None;
+    }
+    let name = rest.trim();
+    name.chars()
+        .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+        .then_some(name)
+}
+
+/// Parse a collapsible section fence, returning whether the section is expanded and its
+/// summary (empty for closing fences)
+fn details_fence(line: &str) -> Option<(bool, &str)> {
+    let trimmed = line.trim_start_matches(' ');
+    if line.len() - trimmed.len() > 3 {
+        return None;
+    }
+    let rest = trimmed.strip_prefix("???")?;
+    match rest.strip_prefix('+') {
+        Some(summary) => Some((true, summary.trim())),
+        None if rest.is_empty() || rest.starts_with(char::is_whitespace) => {
+            Some((false, rest.trim()))
+        }
+        None => None,
+    }
+}
+
+/// Replace the fences of the containers whose name is in `names` with `<div>` tags and, if
+/// `collapsible` is set, the fences of collapsible sections with `<details>` tags
+pub(crate) fn expand_containers<'s>(
+    md_source: &'s str,
+    names: &[String],
+    collapsible: bool,
+) -> Cow<'s, str> {
+    if !(!names.is_empty() && md_source.contains(":::") || collapsible && md_source.contains("???"))
+    {
+        return Cow::Borrowed(md_source);
+    }
+
+    let mut out = String::with_capacity(md_source.len());
+    let mut code_fences = CodeFences::default();
+    let mut open: Vec<Container> = Vec::new();
+
+    for line in md_source.split_inclusive('\n') {
+        let content = line.trim_end_matches(['\n', '\r']);
+
+        // nothing inside code blocks is touched
+        if code_fences.in_code(content) {
+            out.push_str(line);
+            continue;
+        }
+
+        if collapsible && let Some((expanded, summary)) = details_fence(content) {
+            if summary.is_empty() {
+                if open.last() == Some(&Container::Details) {
+                    open.pop();
+                    out.push_str("</details>\n\n");
+                    continue;
+                }
+            } else {
+                open.push(Container::Details);
+                out.push_str(if expanded {
+                    "<details open>\n<summary>"
+                } else {
+                    "<details>\n<summary>"
+                });
+                escape_xml(&mut out, summary, false);
+                out.push_str("</summary>\n\n");
+                continue;
+            }
+        }
+
+        match container_fence(content) {
+            Some("") if open.last() == Some(&Container::Div) => {
+                open.pop();
+                out.push_str("</div>\n\n");
+            }
+            Some(name) if names.iter().any(|n| n == name) => {
+                open.push(Container::Div);
+                out.push_str(&format!("<div class=\"admonition {name}\">\n\n"));
+            }
+            _ => out.push_str(line),
+        }
+    }
+
+    // close the containers which were left open
+    for container in open.into_iter().rev() {
+        out.push_str(match container {
+            Container::Div => "\n\n</div>\n",
+            Container::Details => "\n\n</details>\n",
+        });
+    }
+    Cow::Owned(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::expand_containers;
+
+    #[test]
+    fn test_expand_containers() {
+        let names = ["note".to_string(), "warning".to_string()];
+
+        assert_eq!(
+            expand_containers("::: warning\nBe careful\n:::\nafter", &names, false),
+            "<div class=\"admonition warning\">\n\nBe careful\n</div>\n\nafter"
+        );
+        assert_eq!(
+            expand_containers(":::: note\n::: warning\nnested\n:::\n::::", &names, false),
+            "<div class=\"admonition note\">\n\n<div class=\"admonition warning\">\n\nnested\n\
+             </div>\n\n</div>\n\n"
+        );
+        assert_eq!(
+            expand_containers("::: note\nnever closed", &names, false),
+            "<div class=\"admonition note\">\n\nnever closed\n\n</div>\n"
+        );
+
+        // unknown containers, stray closing fences and code blocks are left alone
+        for md in [
+            "::: danger\nfoo\n:::",
+            ":::\nfoo",
+            "```\n::: note\n```",
+            "::: note\"><script>",
+        ] {
+            assert_eq!(expand_containers(md, &names, false), md);
+        }
+        assert_eq!(
+            expand_containers("::: note\nfoo\n:::", &[], false),
+            "::: note\nfoo\n:::"
+        );
+    }
+
+    #[test]
+    fn test_expand_details() {
+        let names = ["note".to_string()];
+
+        assert_eq!(
+            expand_containers("??? Day 1 <b>\n::: note\nfoo\n:::\n???\nbar", &names, true),
+            "<details>\n<summary>Day 1 &lt;b&gt;</summary>\n\n\
+             <div class=\"admonition note\">\n\nfoo\n</div>\n\n</details>\n\nbar"
+        );
+        assert_eq!(
+            expand_containers("???+ Open\n::: note\nfoo", &names, true),
+            "<details open>\n<summary>Open</summary>\n\n\
+             <div class=\"admonition note\">\n\nfoo\n\n</div>\n\n\n</details>\n"
+        );
+
+        // fences have to match, and collapsible sections have to be enabled
+        for md in ["foo\n???", "???\nfoo", "???? foo", "?????"] {
+            assert_eq!(expand_containers(md, &names, true), md);
+        }
+        assert_eq!(
+            expand_containers("??? foo\n???", &names, false),
+            "??? foo\n???"
+        );
+    }
+}
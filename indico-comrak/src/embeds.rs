@@ -0,0 +1,142 @@
+//! Embedded media: bare video URLs on their own line are replaced with a player (or a
+//! placeholder which is turned into one client-side).
+
+use comrak::nodes::{AstNode, NodeValue};
+use regex_lite::Regex;
+use std::sync::LazyLock;
+
+use crate::{
+    collect_text,
+    html::escape_xml,
+    options::{EmbedProvider, EmbedStyle, RenderOptions, RenderProfile},
+};
+
+static YOUTUBE_RE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(
+        r"^https?://(?:(?:www\.|m\.)?youtube\.com/(?:watch\?(?:[^#]*&)?v=|embed/|shorts/)|youtu\.be/)([A-Za-z0-9_-]{11})(?:[?&#].*)?$",
+    )
+    .unwrap()
+});
+static VIMEO_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"^https?://(?:www\.)?vimeo\.com/(\d+)/?(?:[?#].*)?$").unwrap());
+static CDS_VIDEOS_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"^https?://videos\.cern\.ch/record/(\d+)/?(?:[?#].*)?$").unwrap());
+
+impl EmbedProvider {
+    /// Name of the provider, used in class names
+    fn name(&self) -> &'static str {
+        match self {
+            EmbedProvider::YouTube => "youtube",
+            EmbedProvider::Vimeo => "vimeo",
+            EmbedProvider::CdsVideos => "cds",
+        }
+    }
+
+    /// Get the URL of the player for a video URL, if it's one of the provider's
+    fn player_url(&self, url: &str) -> Option<String> {
+        let (re, player) = match self {
+            EmbedProvider::YouTube => (&*YOUTUBE_RE, "https://www.youtube-nocookie.com/embed/{}"),
+            EmbedProvider::Vimeo => (&*VIMEO_RE, "https://player.vimeo.com/video/{}"),
+            EmbedProvider::CdsVideos => (&*CDS_VIDEOS_RE, "https://videos.cern.ch/record/{}/embed"),
+        };
+        let caps = re.captures(url)?;
+        Some(player.replace("{}", &caps[1]))
+    }
+}
+
+/// Render an embedded video
+fn embed_html(provider: &EmbedProvider, url: &str, player_url: &str, style: EmbedStyle) -> String {
+    let name = provider.name();
+    let mut html = format!("<div class=\"embed embed-{name}\"");
+    match style {
+        EmbedStyle::Iframe => {
+            html.push_str("><iframe src=\"");
+            escape_xml(&mut html, player_url, true);
+            html.push_str("\" loading=\"lazy\" allowfullscreen=\"\"></iframe>");
+        }
+        EmbedStyle::Placeholder => {
+            html.push_str(&format!(
+                " data-embed-provider=\"{name}\" data-embed-url=\""
+            ));
+            escape_xml(&mut html, player_url, true);
+            // the link is kept for clients which don't turn the placeholder into a player
+            html.push_str("\"><a href=\"");
+            escape_xml(&mut html, url, true);
+            html.push_str("\" target=\"_blank\">");
+            escape_xml(&mut html, url, false);
+            html.push_str("</a>");
+        }
+    }
+    html.push_str("</div>\n");
+    html
+}
+
+/// Render a paragraph which only consists of a bare URL of one of the allowed providers as
+/// an embedded video. Returns `None` for other paragraphs.
+pub(crate) fn paragraph_embed<'a>(
+    paragraph: &'a AstNode<'a>,
+    options: &RenderOptions,
+) -> Option<String> {
+    let embeds = options.embeds.as_ref()?;
+    // e-mails and feeds can't contain players
+    if options.profile != RenderProfile::Default {
+        return None;
+    }
+    let link = paragraph.first_child()?;
+    if link.next_sibling().is_some() {
+        return None;
+    }
+    let NodeValue::Link(ref nl) = link.data.borrow().value else {
+        return None;
+    };
+    // links with a text of their own are kept
+    if collect_text(link) != nl.url {
+        return None;
+    }
+
+    embeds.providers.iter().find_map(|provider| {
+        let player_url = provider.player_url(&nl.url)?;
+        Some(embed_html(provider, &nl.url, &player_url, embeds.style))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::options::EmbedProvider;
+
+    #[test]
+    fn test_player_url() {
+        for url in [
+            "https://www.youtube.com/watch?v=dQw4w9WgXcQ",
+            "https://youtube.com/watch?feature=share&v=dQw4w9WgXcQ#t=10",
+            "https://youtu.be/dQw4w9WgXcQ?t=42",
+            "https://m.youtube.com/shorts/dQw4w9WgXcQ",
+        ] {
+            assert_eq!(
+                EmbedProvider::YouTube.player_url(url).as_deref(),
+                Some("https://www.youtube-nocookie.com/embed/dQw4w9WgXcQ")
+            );
+        }
+        assert_eq!(
+            EmbedProvider::Vimeo
+                .player_url("https://vimeo.com/76979871")
+                .as_deref(),
+            Some("https://player.vimeo.com/video/76979871")
+        );
+        assert_eq!(
+            EmbedProvider::CdsVideos
+                .player_url("https://videos.cern.ch/record/2295315")
+                .as_deref(),
+            Some("https://videos.cern.ch/record/2295315/embed")
+        );
+
+        for url in [
+            "https://www.youtube.com/watch?v=short",
+            "https://example.com/watch?v=dQw4w9WgXcQ",
+            "https://vimeo.com/channels/staffpicks",
+        ] {
+            assert_eq!(EmbedProvider::YouTube.player_url(url), None);
+            assert_eq!(EmbedProvider::Vimeo.player_url(url), None);
+        }
+    }
+}
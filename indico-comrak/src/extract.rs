@@ -0,0 +1,582 @@
+//! Extraction of information from documents, without rendering them.
+
+use comrak::{
+    Arena,
+    html::Anchorizer,
+    nodes::{AstNode, LineColumn, NodeValue, Sourcepos},
+    parse_document,
+};
+
+use crate::{
+    HEADER_ID_PREFIX, LinkRule, RenderError, RuleMatch, collect_text, comrak_options,
+    find_rule_matches,
+    html::resolve_url,
+    images::expand_image_sizes,
+    indico_options,
+    lint::line_starts,
+    options::{OffsetMode, RenderOptions},
+    plain::indico_markdown_excerpt,
+    prepare_document,
+};
+
+/// A range of the markdown source, as `(line, column)` positions starting at 1. Columns are
+/// counted in bytes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SourceRange {
+    pub start: (usize, usize),
+    pub end: (usize, usize),
+    /// The same positions with the columns counted in UTF-16 code units, with
+    /// [`OffsetMode::Utf16`]
+    pub utf16: Option<((usize, usize), (usize, usize))>,
+}
+
+impl From<Sourcepos> for SourceRange {
+    fn from(sourcepos: Sourcepos) -> Self {
+        Self {
+            start: (sourcepos.start.line, sourcepos.start.column),
+            end: (sourcepos.end.line, sourcepos.end.column),
+            utf16: None,
+        }
+    }
+}
+
+/// Number of UTF-16 code units of the characters of a text which start before a byte offset
+fn utf16_len_before(text: &str, offset: usize) -> usize {
+    text.char_indices()
+        .take_while(|&(i, _)| i < offset)
+        .map(|(_, c)| c.len_utf16())
+        .sum()
+}
+
+/// Positions in a markdown source, which are reported according to an [`OffsetMode`]
+pub(crate) struct SourcePositions<'s> {
+    source: &'s str,
+    /// Offsets of the start of each line, in bytes and in UTF-16 code units, only with
+    /// [`OffsetMode::Utf16`]
+    line_starts: Option<(Vec<usize>, Vec<usize>)>,
+}
+
+impl<'s> SourcePositions<'s> {
+    pub(crate) fn new(source: &'s str, mode: OffsetMode) -> Self {
+        let line_starts = (mode == OffsetMode::Utf16).then(|| {
+            let mut utf16_starts = vec![0];
+            let mut units = 0;
+            for c in source.chars() {
+                units += c.len_utf16();
+                if c == '\n' {
+                    utf16_starts.push(units);
+                }
+            }
+            (line_starts(source), utf16_starts)
+        });
+        Self {
+            source,
+            line_starts,
+        }
+    }
+
+    /// Add the UTF-16 columns to a range of the source, if they are reported
+    pub(crate) fn range(&self, range: SourceRange) -> SourceRange {
+        let Some((line_starts, _)) = &self.line_starts else {
+            return range;
+        };
+        // end positions are inclusive, so the character they are in is counted
+        let utf16_position = |(line, column): (usize, usize), end: bool| {
+            let Some(&start) = line.checked_sub(1).and_then(|line| line_starts.get(line)) else {
+                return (line, column);
+            };
+            let text = &self.source[start..];
+            if end {
+                (line, utf16_len_before(text, column))
+            } else {
+                (line, utf16_len_before(text, column.saturating_sub(1)) + 1)
+            }
+        };
+        SourceRange {
+            utf16: Some((
+                utf16_position(range.start, false),
+                utf16_position(range.end, true),
+            )),
+            ..range
+        }
+    }
+
+    /// Convert a byte offset of the source to UTF-16 code units, if they are reported
+    pub(crate) fn offset(&self, offset: usize) -> Option<usize> {
+        let (line_starts, utf16_starts) = self.line_starts.as_ref()?;
+        let line = line_starts.partition_point(|&start| start <= offset) - 1;
+        let start = line_starts[line];
+        Some(utf16_starts[line] + utf16_len_before(&self.source[start..], offset - start))
+    }
+}
+
+/// A heading of a document
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Heading {
+    /// Level of the heading, from 1 to 6
+    pub level: u8,
+    /// Text of the heading, without any formatting
+    pub text: String,
+    /// Slug generated from the text, unique within the document
+    pub slug: String,
+    /// ID of the heading in the rendered HTML (the slug with a prefix)
+    pub id: String,
+    /// Position of the heading in the markdown source
+    pub sourcepos: SourceRange,
+}
+
+/// Get the headings of a document, in document order, with the same slugs and IDs as the
+/// ones generated by [`indico_markdown_to_html`](crate::indico_markdown_to_html)
+pub fn indico_markdown_outline(md_source: &str, offset_mode: OffsetMode) -> Vec<Heading> {
+    let arena = Arena::new();
+    let root = parse_document(&arena, md_source, &indico_options());
+    let positions = SourcePositions::new(md_source, offset_mode);
+    document_headings(root, &positions)
+}
+
+/// Get the headings of a parsed document, in document order
+pub(crate) fn document_headings<'a>(
+    root: &'a AstNode<'a>,
+    positions: &SourcePositions<'_>,
+) -> Vec<Heading> {
+    let mut anchorizer = Anchorizer::new();
+
+    root.descendants()
+        .filter_map(|node| {
+            let data = node.data.borrow();
+            let NodeValue::Heading(ref nh) = data.value else {
+                return None;
+            };
+            let text = collect_text(node);
+            let slug = anchorizer.anchorize(&text);
+            Some(Heading {
+                level: nh.level,
+                id: format!("{HEADER_ID_PREFIX}{slug}"),
+                text,
+                slug,
+                sourcepos: positions.range(data.sourcepos.into()),
+            })
+        })
+        .collect()
+}
+
+/// Find the heading which is the title of a document: its first level 1 or 2 heading
+pub(crate) fn title_heading<'a>(root: &'a AstNode<'a>) -> Option<&'a AstNode<'a>> {
+    root.descendants().find(
+        |node| matches!(node.data.borrow().value, NodeValue::Heading(ref nh) if nh.level <= 2),
+    )
+}
+
+/// Get the title of a document, i.e. the text of its first level 1 or 2 heading, without any
+/// formatting
+pub fn indico_markdown_extract_title(md_source: &str) -> Option<String> {
+    let arena = Arena::new();
+    let root = parse_document(&arena, md_source, &indico_options());
+    title_heading(root).map(collect_text)
+}
+
+/// A match of a link rule in a document, e.g. a mention of a user or a reference to a ticket
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LinkRuleMatch {
+    /// Index of the rule which matched, in the list of rules
+    pub rule: usize,
+    /// The captured groups, starting with the whole match. Groups which did not participate in
+    /// the match are `None`.
+    pub groups: Vec<Option<String>>,
+    /// URL of the link the match is turned into
+    pub url: String,
+    /// Position of the match in the markdown source. It may be off if the text before it on the
+    /// same line contains escapes or entities.
+    pub sourcepos: SourceRange,
+}
+
+/// Find the matches of link rules in a document, without rendering it. Only the matches which
+/// are turned into links when rendering are returned (i.e. not the ones in code or in links).
+pub fn indico_markdown_rule_matches(
+    md_source: &str,
+    autolink_rules: &[LinkRule],
+    offset_mode: OffsetMode,
+) -> Vec<LinkRuleMatch> {
+    let arena = Arena::new();
+    let root = parse_document(&arena, md_source, &indico_options());
+    let positions = SourcePositions::new(md_source, offset_mode);
+
+    find_rule_matches(root, autolink_rules)
+        .into_iter()
+        .flat_map(|(node, matches, _)| {
+            let data = node.data.borrow();
+            let NodeValue::Text(ref text) = data.value else {
+                return Vec::new();
+            };
+            let start = data.sourcepos.start;
+            matches
+                .into_iter()
+                .map(|rule_match| {
+                    let mut rule_match = link_rule_match(text, start, rule_match);
+                    rule_match.sourcepos = positions.range(rule_match.sourcepos);
+                    rule_match
+                })
+                .collect()
+        })
+        .collect()
+}
+
+/// Build the [`LinkRuleMatch`] of a match in a text node which starts at `start`, with its
+/// position in bytes
+pub(crate) fn link_rule_match(
+    text: &str,
+    start: LineColumn,
+    ((match_start, match_end), rule, groups, url): RuleMatch,
+) -> LinkRuleMatch {
+    LinkRuleMatch {
+        rule,
+        url,
+        groups: groups
+            .into_iter()
+            .map(|group| group.map(|(start, end)| text[start..end].to_string()))
+            .collect(),
+        sourcepos: SourceRange {
+            start: (start.line, start.column + match_start),
+            end: (start.line, start.column + match_end - 1),
+            utf16: None,
+        },
+    }
+}
+
+/// A link of a rendered document
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DocumentLink {
+    /// URL of the link, resolved like in the rendered HTML
+    pub url: String,
+    /// Text of the link, without any formatting
+    pub text: String,
+    /// Position of the link in the markdown source. Positions of the links created by link
+    /// rules may be off if the text before them on the same line contains escapes or entities,
+    /// and links added while rendering (e.g. by a table of contents) have none (all zeros).
+    pub sourcepos: SourceRange,
+}
+
+/// Get the links of a document which is ready to be formatted, in document order. Links
+/// whose URL was removed (e.g. because of a blocked scheme) are left out.
+pub(crate) fn document_links<'a>(
+    root: &'a AstNode<'a>,
+    positions: &SourcePositions<'_>,
+) -> Vec<DocumentLink> {
+    root.descendants()
+        .filter_map(|node| {
+            let data = node.data.borrow();
+            let NodeValue::Link(ref nl) = data.value else {
+                return None;
+            };
+            if nl.url.is_empty() {
+                return None;
+            }
+            Some(DocumentLink {
+                url: nl.url.clone(),
+                text: collect_text(node),
+                sourcepos: positions.range(data.sourcepos.into()),
+            })
+        })
+        .collect()
+}
+
+/// Get the links of a document, including the ones created by link rules, in document order.
+/// Their URLs are resolved against the base URL of the options, like in the rendered HTML.
+pub fn indico_markdown_links(
+    md_source: &str,
+    autolink_rules: &[LinkRule],
+    render_options: &RenderOptions,
+) -> Result<Vec<DocumentLink>, RenderError> {
+    let arena = Arena::new();
+    let options = comrak_options(render_options);
+    let prepared = prepare_document(&arena, &options, md_source, autolink_rules, render_options)?;
+    Ok(prepared.output.links)
+}
+
+/// An image of a document
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DocumentImage {
+    /// URL of the image, resolved like in the rendered HTML
+    pub url: String,
+    /// Alternative text of the image
+    pub alt: String,
+}
+
+/// Check whether an image URL can be used outside of the document (e.g. as a thumbnail):
+/// relative URLs and `http(s)` ones
+fn is_safe_image_url(url: &str) -> bool {
+    match url.split_once(':') {
+        Some((scheme, _)) if !scheme.contains('/') => {
+            scheme.eq_ignore_ascii_case("http") || scheme.eq_ignore_ascii_case("https")
+        }
+        _ => !url.is_empty(),
+    }
+}
+
+/// Get the images of a document, in document order. Their URLs are resolved against the base
+/// URLs of the options, and images whose URL has a scheme other than `http` or `https` (e.g.
+/// `javascript:` or `data:`) are left out. Images in raw HTML are not included.
+pub fn indico_markdown_images(md_source: &str, options: &RenderOptions) -> Vec<DocumentImage> {
+    let md_source = if options.image_size_syntax {
+        expand_image_sizes(md_source)
+    } else {
+        md_source.into()
+    };
+    let arena = Arena::new();
+    let root = parse_document(&arena, &md_source, &indico_options());
+    let base_url = options
+        .media_base_url
+        .as_ref()
+        .or(options.base_url.as_ref());
+
+    root.descendants()
+        .filter_map(|node| {
+            let data = node.data.borrow();
+            let NodeValue::Image(ref nl) = data.value else {
+                return None;
+            };
+            let url = match base_url {
+                Some(base) => resolve_url(base, &nl.url),
+                None => nl.url.clone(),
+            };
+            is_safe_image_url(&url).then(|| DocumentImage {
+                url,
+                alt: collect_text(node),
+            })
+        })
+        .collect()
+}
+
+/// Get the URL of the first image of a document (see [`indico_markdown_images`]), e.g. to use
+/// it as a thumbnail
+pub fn indico_markdown_first_image(md_source: &str, options: &RenderOptions) -> Option<String> {
+    indico_markdown_images(md_source, options)
+        .into_iter()
+        .next()
+        .map(|image| image.url)
+}
+
+/// Maximum number of characters of the description of [`OpenGraph`] metadata
+const OPEN_GRAPH_DESCRIPTION_CHARS: usize = 200;
+
+/// Open Graph metadata of a document, for the previews shown when its page is shared
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OpenGraph {
+    /// Text of the first heading
+    pub title: Option<String>,
+    /// Excerpt of the text of the first paragraph(s)
+    pub description: Option<String>,
+    /// URL of the first image (see [`indico_markdown_first_image`])
+    pub image: Option<String>,
+}
+
+/// Derive Open Graph metadata (`og:title`, `og:description` and `og:image`) from a document
+pub fn indico_markdown_open_graph(md_source: &str, options: &RenderOptions) -> OpenGraph {
+    let description = indico_markdown_excerpt(md_source, OPEN_GRAPH_DESCRIPTION_CHARS);
+    OpenGraph {
+        title: indico_markdown_outline(md_source, OffsetMode::Bytes)
+            .into_iter()
+            .next()
+            .map(|heading| heading.text),
+        description: (!description.is_empty()).then_some(description),
+        image: indico_markdown_first_image(md_source, options),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        SourcePositions, SourceRange, indico_markdown_extract_title, indico_markdown_first_image,
+        indico_markdown_images, indico_markdown_links, indico_markdown_open_graph,
+        indico_markdown_outline, indico_markdown_rule_matches,
+    };
+    use crate::LinkRule;
+    use crate::options::{OffsetMode, RenderOptions};
+
+    #[test]
+    fn test_outline() {
+        let outline = indico_markdown_outline(
+            "# Intro\n\nText\n\n## The *plan*\n\n# Intro\n",
+            OffsetMode::Bytes,
+        );
+        let headings: Vec<_> = outline
+            .iter()
+            .map(|h| (h.level, h.text.as_str(), h.slug.as_str(), h.id.as_str()))
+            .collect();
+        assert_eq!(
+            headings,
+            [
+                (1, "Intro", "intro", "indico-md-intro"),
+                (2, "The plan", "the-plan", "indico-md-the-plan"),
+                (1, "Intro", "intro-1", "indico-md-intro-1"),
+            ]
+        );
+        assert_eq!(
+            outline[1].sourcepos,
+            SourceRange {
+                start: (5, 1),
+                end: (5, 13),
+                utf16: None,
+            }
+        );
+    }
+
+    #[test]
+    fn test_utf16_positions() {
+        let outline = indico_markdown_outline("# Café 😀\n\n## Größe", OffsetMode::Utf16);
+        let positions: Vec<_> = outline
+            .iter()
+            .map(|h| (h.sourcepos.start, h.sourcepos.end, h.sourcepos.utf16))
+            .collect();
+        assert_eq!(
+            positions,
+            [
+                ((1, 1), (1, 12), Some(((1, 1), (1, 9)))),
+                ((3, 1), (3, 10), Some(((3, 1), (3, 8)))),
+            ]
+        );
+
+        let positions = SourcePositions::new("é\n😀x", OffsetMode::Utf16);
+        assert_eq!(positions.offset(3), Some(2));
+        assert_eq!(positions.offset(7), Some(4));
+        assert_eq!(positions.offset(8), Some(5));
+        assert_eq!(SourcePositions::new("é", OffsetMode::Bytes).offset(2), None);
+    }
+
+    #[test]
+    fn test_extract_title() {
+        assert_eq!(
+            indico_markdown_extract_title("Intro\n\n### Details\n\nThe **2026** `meeting`\n---"),
+            Some("The 2026 meeting".into())
+        );
+        assert_eq!(indico_markdown_extract_title("### Details\n\ntext"), None);
+    }
+
+    #[test]
+    fn test_rule_matches() {
+        let rules = [
+            LinkRule::new(r"@(\w+)", "/users/{1}").unwrap(),
+            LinkRule::new(r"#(\d+)", "/tickets/{1}").unwrap(),
+        ];
+        let matches = indico_markdown_rule_matches(
+            "Ping @alice about #12\n\n- `@code` and [@link](/x)\n- @bob",
+            &rules,
+            OffsetMode::Bytes,
+        );
+        let found: Vec<_> = matches
+            .iter()
+            .map(|m| (m.rule, m.groups[1].as_deref(), m.url.as_str()))
+            .collect();
+        assert_eq!(
+            found,
+            [
+                (0, Some("alice"), "/users/alice"),
+                (1, Some("12"), "/tickets/12"),
+                (0, Some("bob"), "/users/bob"),
+            ]
+        );
+        assert_eq!(
+            matches[0].sourcepos,
+            SourceRange {
+                start: (1, 6),
+                end: (1, 11),
+                utf16: None,
+            }
+        );
+        assert_eq!(
+            matches[2].sourcepos,
+            SourceRange {
+                start: (4, 3),
+                end: (4, 6),
+                utf16: None,
+            }
+        );
+    }
+
+    #[test]
+    fn test_links() {
+        let rules = [LinkRule::new(r"#(\d+)", "/tickets/{1}").unwrap()];
+        let options = RenderOptions {
+            base_url: Some("https://indico.example.com/event/1/".into()),
+            ..Default::default()
+        };
+        let links = indico_markdown_links(
+            "Intro\n\n- see [slides](slides.pdf) and #12",
+            &rules,
+            &options,
+        )
+        .unwrap();
+        let found: Vec<_> = links
+            .iter()
+            .map(|link| (link.url.as_str(), link.text.as_str(), link.sourcepos.start))
+            .collect();
+        assert_eq!(
+            found,
+            [
+                (
+                    "https://indico.example.com/event/1/slides.pdf",
+                    "slides",
+                    (3, 7)
+                ),
+                ("https://indico.example.com/tickets/12", "#12", (3, 32)),
+            ]
+        );
+        assert_eq!(links[1].sourcepos.end, (3, 34));
+    }
+
+    #[test]
+    fn test_images() {
+        let md = "![evil](javascript:alert(1)) ![Poster](poster.png =300x)\n\n\
+                  > ![*Hall* map](https://example.com/map.png \"Map\")\n\n`![code](x.png)`";
+        let options = RenderOptions {
+            base_url: Some("https://indico.example.com/event/1/".into()),
+            image_size_syntax: true,
+            ..Default::default()
+        };
+        let images: Vec<_> = indico_markdown_images(md, &options)
+            .into_iter()
+            .map(|image| (image.url, image.alt))
+            .collect();
+        assert_eq!(
+            images,
+            [
+                (
+                    "https://indico.example.com/event/1/poster.png".to_string(),
+                    "Poster".to_string()
+                ),
+                (
+                    "https://example.com/map.png".to_string(),
+                    "Hall map".to_string()
+                ),
+            ]
+        );
+        assert_eq!(
+            indico_markdown_first_image(md, &RenderOptions::default()).as_deref(),
+            Some("https://example.com/map.png")
+        );
+        assert_eq!(indico_markdown_first_image("no images", &options), None);
+    }
+
+    #[test]
+    fn test_open_graph() {
+        let md = "Intro text\n\n## The *conference*\n\n![Poster](poster.png) Welcome to **the** \
+                  conference.";
+        let og = indico_markdown_open_graph(md, &RenderOptions::default());
+        assert_eq!(og.title.as_deref(), Some("The conference"));
+        assert_eq!(
+            og.description.as_deref(),
+            Some("Intro text Poster Welcome to the conference.")
+        );
+        assert_eq!(og.image.as_deref(), Some("poster.png"));
+
+        let og = indico_markdown_open_graph("", &RenderOptions::default());
+        assert_eq!(
+            og,
+            super::OpenGraph {
+                title: None,
+                description: None,
+                image: None
+            }
+        );
+    }
+}
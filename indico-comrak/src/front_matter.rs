@@ -0,0 +1,98 @@
+//! Front matter blocks at the start of a document: YAML between `---` lines or TOML between
+//! `+++` lines, as used by static site generators.
+
+use serde_json::Value;
+
+/// The language a front matter block is written in
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FrontMatterFormat {
+    /// YAML, delimited by `---` lines
+    Yaml,
+    /// TOML, delimited by `+++` lines
+    Toml,
+}
+
+/// A front matter block which was extracted from a document
+#[derive(Debug, Clone, PartialEq)]
+pub struct FrontMatter {
+    /// The language the block is written in
+    pub format: FrontMatterFormat,
+    /// Content of the block, without the delimiters
+    pub raw: String,
+    /// The parsed content, or `None` if it isn't valid YAML/TOML
+    pub data: Option<Value>,
+}
+
+/// Split a leading front matter block from the rest of the document. Documents without a
+/// (closed) front matter block are returned as they are.
+pub(crate) fn split_front_matter(md_source: &str) -> (Option<FrontMatter>, &str) {
+    let mut lines = md_source.split_inclusive('\n');
+    let Some(first) = lines.next() else {
+        return (None, md_source);
+    };
+    let (delimiter, format) = match first.trim_end() {
+        "---" => ("---", FrontMatterFormat::Yaml),
+        "+++" => ("+++", FrontMatterFormat::Toml),
+        _ => return (None, md_source),
+    };
+
+    let start = first.len();
+    let mut end = start;
+    for line in lines {
+        if line.trim_end() == delimiter {
+            let raw = &md_source[start..end];
+            let data = match format {
+                FrontMatterFormat::Yaml => serde_yaml::from_str(raw).ok(),
+                FrontMatterFormat::Toml => toml::from_str(raw).ok(),
+            };
+            let front_matter = FrontMatter {
+                format,
+                raw: raw.into(),
+                data,
+            };
+            return (Some(front_matter), &md_source[end + line.len()..]);
+        }
+        end += line.len();
+    }
+    (None, md_source)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{FrontMatterFormat, split_front_matter};
+    use serde_json::json;
+
+    #[test]
+    fn test_split_front_matter() {
+        let (front_matter, rest) =
+            split_front_matter("---\ntitle: Opening\ntags: [a, b]\n---\n# Welcome\n");
+        let front_matter = front_matter.unwrap();
+        assert_eq!(front_matter.format, FrontMatterFormat::Yaml);
+        assert_eq!(front_matter.raw, "title: Opening\ntags: [a, b]\n");
+        assert_eq!(
+            front_matter.data,
+            Some(json!({"title": "Opening", "tags": ["a", "b"]}))
+        );
+        assert_eq!(rest, "# Welcome\n");
+
+        let (front_matter, rest) = split_front_matter("+++\r\ntitle = \"Opening\"\r\n+++\r\nfoo");
+        let front_matter = front_matter.unwrap();
+        assert_eq!(front_matter.format, FrontMatterFormat::Toml);
+        assert_eq!(front_matter.data, Some(json!({"title": "Opening"})));
+        assert_eq!(rest, "foo");
+
+        // invalid content is kept, but not parsed
+        let (front_matter, rest) = split_front_matter("+++\nnot toml\n+++\n");
+        assert_eq!(front_matter.unwrap().data, None);
+        assert_eq!(rest, "");
+
+        for md in [
+            "---\nunclosed: yes\n",
+            "foo\n---\nbar\n---\n",
+            " ---\na: b\n---\n",
+            "",
+        ] {
+            assert_eq!(split_front_matter(md), (None, md));
+        }
+    }
+}
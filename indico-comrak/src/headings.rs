@@ -0,0 +1,85 @@
+//! Automatic numbering of headings (`1.`, `1.1`, `1.1.1`...), which is prepended to their
+//! content in a `<span class="heading-number">`.
+
+use comrak::{
+    Arena,
+    nodes::{AstNode, NodeValue},
+};
+use std::collections::HashMap;
+
+use crate::{attributes::node_key, options::HeadingNumbering};
+
+/// Numbers of headings which are not part of their text, keyed like
+/// [`NodeAttributes`](crate::attributes::NodeAttributes)
+pub(crate) type HeadingNumbers = HashMap<usize, String>;
+
+/// Format the number of a heading from the counters of its level and the levels above it
+fn format_number(counters: &[usize]) -> String {
+    let number: Vec<String> = counters.iter().map(|c| c.to_string()).collect();
+    let mut number = number.join(".");
+    // top-level sections are `1.`, and subsections `1.1`
+    if counters.len() == 1 {
+        number.push('.');
+    }
+    number
+}
+
+/// Prepend hierarchical numbers to the headings of the document, from the start level down.
+/// Returns the numbers which are not part of the text of their heading (so that they are
+/// left out of its ID).
+pub(crate) fn number_headings<'a>(
+    root: &'a AstNode<'a>,
+    arena: &'a Arena<'a>,
+    numbering: &HeadingNumbering,
+) -> HeadingNumbers {
+    let start_level = usize::from(numbering.start_level.clamp(1, 6));
+    let mut counters = [0; 6];
+    let mut hidden_numbers = HeadingNumbers::new();
+
+    let headings: Vec<_> = root
+        .descendants()
+        .filter(|node| matches!(node.data.borrow().value, NodeValue::Heading(_)))
+        .collect();
+    for heading in headings {
+        let level = match heading.data.borrow().value {
+            NodeValue::Heading(ref nh) => usize::from(nh.level),
+            _ => continue,
+        };
+        let Some(depth) = level.checked_sub(start_level) else {
+            continue;
+        };
+        counters[depth] += 1;
+        counters[depth + 1..].fill(0);
+        let number = format_number(&counters[..=depth]);
+
+        let values = if numbering.in_anchors {
+            vec![
+                NodeValue::HtmlInline("<span class=\"heading-number\">".into()),
+                NodeValue::Text(number.into()),
+                NodeValue::HtmlInline("</span>".into()),
+                NodeValue::Text(" ".to_string().into()),
+            ]
+        } else {
+            // raw HTML is not part of the text the ID is generated from
+            let html = format!("<span class=\"heading-number\">{number}</span> ");
+            hidden_numbers.insert(node_key(heading), number);
+            vec![NodeValue::HtmlInline(html)]
+        };
+        for value in values.into_iter().rev() {
+            heading.prepend(arena.alloc(value.into()));
+        }
+    }
+    hidden_numbers
+}
+
+#[cfg(test)]
+mod tests {
+    use super::format_number;
+
+    #[test]
+    fn test_format_number() {
+        assert_eq!(format_number(&[3]), "3.");
+        assert_eq!(format_number(&[1, 2]), "1.2");
+        assert_eq!(format_number(&[2, 0, 1]), "2.0.1");
+    }
+}
@@ -0,0 +1,1645 @@
+//! HTML rendering: AST preparation passes and the formatter used by
+//! [`indico_markdown_to_html_with_options`](crate::indico_markdown_to_html_with_options).
+
+use comrak::{
+    Arena,
+    html::{Anchorizer, ChildRendering, Context},
+    nodes::{AstNode, NodeCodeBlock, NodeLink, NodeValue, TableAlignment},
+};
+use regex_lite::Regex;
+use std::{
+    fmt::{self, Write},
+    sync::LazyLock,
+};
+
+#[cfg(feature = "emoji")]
+use crate::options::EmojiStyle;
+use crate::{
+    alerts::{CustomAlerts, alert_kind},
+    attributes::{NodeAttributes, attributes_html, extract_attributes, node_key},
+    collect_text, embeds, has_link_ancestor, header_id_prefix,
+    headings::{self, HeadingNumbers},
+    html2md::{Token, decode_entities, tokenize},
+    images, in_tight_list,
+    options::{
+        CodeBlockElement, DocumentOptions, FootnoteOptions, HtmlFormat, MathOutput, MathWrapper,
+        QuoteStyle, RenderOptions, RenderProfile, TableOptions, WikiLinks,
+    },
+    plain::cut_text,
+    replace_with_nodes, toc,
+};
+
+/// Tags which are stripped from the raw HTML of e-mails, keeping their content
+static EMAIL_STRIP_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(?i)<\s*/?\s*(?:details|summary)\b[^>]*>").unwrap());
+/// Tags which are stripped from the raw HTML of feeds, keeping their content
+static FEED_STRIP_RE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(
+        r"(?i)<\s*/?\s*(?:details|summary|button|input|form|select|option|textarea|iframe|object|embed)\b[^>]*>",
+    )
+    .unwrap()
+});
+
+/// State which is made available to the formatter during rendering
+pub(crate) struct HtmlState<'r> {
+    pub options: &'r RenderOptions,
+    /// Number of footnote definitions rendered so far
+    footnote_ix: u32,
+    /// Whether the backlinks of the current footnote have been rendered already
+    footnote_backrefs_written: bool,
+    /// Attributes set through attribute blocks
+    attributes: NodeAttributes,
+    /// Generator of heading IDs, used instead of comrak's when attribute blocks are enabled
+    anchorizer: Anchorizer,
+    /// Number of task list items rendered so far
+    task_index: usize,
+    /// Lines of the task list items in the markdown source, for interactive task lists
+    pub task_lines: Vec<usize>,
+    /// Blockquotes which are rendered as custom alerts
+    pub custom_alerts: CustomAlerts,
+}
+
+impl<'r> HtmlState<'r> {
+    pub fn new(options: &'r RenderOptions, attributes: NodeAttributes) -> Self {
+        Self {
+            options,
+            footnote_ix: 0,
+            footnote_backrefs_written: false,
+            attributes,
+            anchorizer: Anchorizer::new(),
+            task_index: 0,
+            task_lines: Vec::new(),
+            custom_alerts: CustomAlerts::new(),
+        }
+    }
+}
+
+/// Check whether a URL is absolute (has a scheme or is protocol-relative)
+fn is_absolute_url(url: &str) -> bool {
+    url.starts_with("//")
+        || url.split_once(':').is_some_and(|(scheme, _)| {
+            scheme.starts_with(|c: char| c.is_ascii_alphabetic())
+                && scheme
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || matches!(c, '+' | '-' | '.'))
+        })
+}
+
+/// Resolve a (possibly relative) URL against a base URL.
+/// Fragment-only URLs (`#foo`) are left untouched, since they refer to the current page.
+pub(crate) fn resolve_url(base: &str, url: &str) -> String {
+    if url.is_empty() || url.starts_with('#') || is_absolute_url(url) {
+        return url.into();
+    }
+
+    // end of the `scheme://host` part
+    let origin_end = base.find("://").map_or(0, |i| {
+        base[i + 3..]
+            .find('/')
+            .map_or(base.len(), |end| i + 3 + end)
+    });
+
+    if url.starts_with('/') {
+        format!("{}{url}", &base[..origin_end])
+    } else {
+        match base[origin_end..].rfind('/') {
+            Some(i) => format!("{}{url}", &base[..=origin_end + i]),
+            None => format!("{base}/{url}"),
+        }
+    }
+}
+
+/// Size (in pixels) of custom emoji images
+const CUSTOM_EMOJI_SIZE: u32 = 20;
+/// `:shortcode:` of a custom emoji
+static CUSTOM_EMOJI_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r":([a-zA-Z0-9_+-]+):").unwrap());
+
+fn custom_emoji_html(code: &str, url: &str) -> String {
+    let mut html = String::from("<img class=\"emoji\" src=\"");
+    escape_xml(&mut html, url, true);
+    html.push_str("\" alt=\":");
+    escape_xml(&mut html, code, true);
+    html.push_str(":\" title=\":");
+    escape_xml(&mut html, code, true);
+    html.push_str(&format!(
+        ":\" width=\"{CUSTOM_EMOJI_SIZE}\" height=\"{CUSTOM_EMOJI_SIZE}\" />"
+    ));
+    html
+}
+
+/// Replace custom `:shortcode:` emoji with images, outside of links
+fn replace_custom_emoji<'a>(root: &'a AstNode<'a>, arena: &'a Arena<'a>, options: &RenderOptions) {
+    if options.custom_emoji.is_empty() {
+        return;
+    }
+    let re = &*CUSTOM_EMOJI_RE;
+    let mut to_replace = Vec::new();
+
+    for node in root.descendants() {
+        let values = match node.data.borrow().value {
+            NodeValue::Text(ref t) if re.is_match(t) => {
+                let mut values = Vec::new();
+                let mut last = 0;
+                for capture in re.captures_iter(t) {
+                    let m = capture.get(0).unwrap();
+                    if let Some(url) = options.custom_emoji.get(&capture[1]) {
+                        if m.start() > last {
+                            values.push(NodeValue::Text(t[last..m.start()].to_string().into()));
+                        }
+                        values.push(NodeValue::HtmlInline(custom_emoji_html(&capture[1], url)));
+                        last = m.end();
+                    }
+                }
+                if last == 0 {
+                    continue;
+                }
+                if last < t.len() {
+                    values.push(NodeValue::Text(t[last..].to_string().into()));
+                }
+                values
+            }
+            #[cfg(feature = "emoji")]
+            NodeValue::ShortCode(ref nsc) => match options.custom_emoji.get(&nsc.code) {
+                Some(url) => vec![NodeValue::HtmlInline(custom_emoji_html(&nsc.code, url))],
+                None => continue,
+            },
+            _ => continue,
+        };
+        to_replace.push((node, values));
+    }
+
+    for (node, values) in to_replace {
+        if !has_link_ancestor(node) {
+            replace_with_nodes(node, arena, values);
+        }
+    }
+}
+
+/// Replace the English quotes produced by smart punctuation with those of another style.
+/// Right single quotes are also used as apostrophes, so they are only replaced if they close
+/// a quote opened earlier in the same block and are not followed by a letter (`l’école`).
+fn localize_quotes<'a>(root: &'a AstNode<'a>, style: &QuoteStyle) {
+    let [open_double, close_double, open_single, close_single] = style.marks();
+    let mut open_singles = 0usize;
+
+    for node in root.descendants() {
+        let mut data = node.data.borrow_mut();
+        if data.value.block() {
+            open_singles = 0;
+            continue;
+        }
+        let NodeValue::Text(ref mut text) = data.value else {
+            continue;
+        };
+        if !text.contains(['\u{201c}', '\u{201d}', '\u{2018}', '\u{2019}']) {
+            continue;
+        }
+
+        let mut localized = String::with_capacity(text.len());
+        let mut chars = text.chars().peekable();
+        while let Some(c) = chars.next() {
+            match c {
+                '\u{201c}' => localized.push_str(open_double),
+                '\u{201d}' => localized.push_str(close_double),
+                '\u{2018}' => {
+                    open_singles += 1;
+                    localized.push_str(open_single);
+                }
+                '\u{2019}'
+                    if open_singles > 0 && !chars.peek().is_some_and(|c| c.is_alphanumeric()) =>
+                {
+                    open_singles -= 1;
+                    localized.push_str(close_single);
+                }
+                _ => localized.push(c),
+            }
+        }
+        *text = localized.into();
+    }
+}
+
+/// Modify the AST according to the render options, before it gets formatted. Returns the
+/// attributes which were set through attribute blocks.
+pub(crate) fn prepare<'a>(
+    root: &'a AstNode<'a>,
+    arena: &'a Arena<'a>,
+    options: &RenderOptions,
+) -> NodeAttributes {
+    // this goes first, so that attribute blocks don't end up in the table of contents
+    let mut attributes = match &options.attributes {
+        Some(attribute_options) => extract_attributes(root, &attribute_options.allowed),
+        None => NodeAttributes::new(),
+    };
+    if options.image_size_syntax || !options.image_sizes.is_empty() {
+        images::apply_image_sizes(root, &mut attributes, options);
+    }
+    replace_custom_emoji(root, arena, options);
+    // before the table of contents, which lists the numbers of the headings
+    let heading_numbers = match &options.heading_numbers {
+        Some(numbering) => headings::number_headings(root, arena, numbering),
+        None => HeadingNumbers::new(),
+    };
+    if let Some(toc) = &options.toc {
+        toc::replace_toc_markers(root, arena, options, toc, &heading_numbers);
+    }
+    if let Some(style) = &options.smart_punctuation
+        && *style != QuoteStyle::English
+    {
+        localize_quotes(root, style);
+    }
+
+    // tags which are stripped from raw HTML, keeping their content
+    let strip_re = match options.profile {
+        RenderProfile::Default => None,
+        RenderProfile::Email => Some(&*EMAIL_STRIP_RE),
+        RenderProfile::Feed => Some(&*FEED_STRIP_RE),
+    };
+
+    for node in root.descendants() {
+        // emoji are not replaced inside links
+        #[cfg(feature = "emoji")]
+        {
+            let code = match node.data.borrow().value {
+                NodeValue::ShortCode(ref nsc) => Some(nsc.code.clone()),
+                _ => None,
+            };
+            if let Some(code) = code
+                && has_link_ancestor(node)
+            {
+                node.data.borrow_mut().value = NodeValue::Text(format!(":{code}:").into());
+            }
+        }
+
+        match node.data.borrow_mut().value {
+            NodeValue::Link(ref mut nl) => {
+                if let Some(base) = &options.base_url {
+                    nl.url = resolve_url(base, &nl.url);
+                }
+            }
+            NodeValue::Image(ref mut nl) => {
+                if let Some(base) = options
+                    .media_base_url
+                    .as_ref()
+                    .or(options.base_url.as_ref())
+                {
+                    nl.url = resolve_url(base, &nl.url);
+                }
+            }
+            NodeValue::HtmlInline(ref mut html) => {
+                if let Some(re) = strip_re {
+                    *html = re.replace_all(html, "").into_owned();
+                }
+            }
+            NodeValue::HtmlBlock(ref mut nhb) => {
+                if let Some(re) = strip_re {
+                    nhb.literal = re.replace_all(&nhb.literal, "").into_owned();
+                }
+            }
+            _ => {}
+        }
+    }
+
+    attributes
+}
+
+const EMAIL_CODE_STYLE: &str = "font-family:monospace;background-color:#f3f4f6;padding:0 2px";
+const EMAIL_PRE_STYLE: &str = "font-family:monospace;background-color:#f3f4f6;padding:8px";
+const EMAIL_CELL_STYLE: &str = "border:1px solid #d1d5db;padding:4px 8px";
+const EMAIL_ALERT_STYLE: &str = "border-left:4px solid #9ca3af;padding:0 1em;margin:1em 0";
+
+/// Render a task list item with a textual checkbox, rather than an `<input>`
+fn write_task_item(
+    context: &mut Context<&mut HtmlState<'_>>,
+    checked: bool,
+    entering: bool,
+) -> fmt::Result {
+    if !entering {
+        context.write_str("</li>\n")
+    } else if checked {
+        context.write_str("<li>\u{2611} ")
+    } else {
+        context.write_str("<li>\u{2610} ")
+    }
+}
+
+/// Render an alert (or a blockquote which is a custom alert) of the given kind
+fn write_alert(
+    context: &mut Context<&mut HtmlState<'_>>,
+    kind: &str,
+    title: Option<&str>,
+    entering: bool,
+) -> fmt::Result {
+    let options = context.user.options;
+    if !entering {
+        return match options.profile {
+            RenderProfile::Default => context.cr().and_then(|_| context.write_str("</div>\n")),
+            RenderProfile::Email => context.write_str("</div>\n"),
+            RenderProfile::Feed => context.write_str("</blockquote>\n"),
+        };
+    }
+
+    let title = match title {
+        Some(title) => title.to_string(),
+        None => options.alerts.titles.get(kind).cloned().unwrap_or_else(|| {
+            let mut chars = kind.chars();
+            chars
+                .next()
+                .map(|first| first.to_uppercase().chain(chars).collect())
+                .unwrap_or_default()
+        }),
+    };
+    match options.profile {
+        RenderProfile::Default => {
+            let prefix = &options.alerts.class_prefix;
+            context.cr()?;
+            context.write_str("<div class=\"")?;
+            context.escape(&format!("{prefix} {prefix}-{kind}"))?;
+            context.write_str("\">\n<p class=\"")?;
+            context.escape(&format!("{prefix}-title"))?;
+            context.write_str("\">")?;
+        }
+        RenderProfile::Email => write!(
+            context,
+            "<div style=\"{EMAIL_ALERT_STYLE}\">\n<p style=\"font-weight:bold\">"
+        )?,
+        RenderProfile::Feed => context.write_str("<blockquote>\n<p><strong>")?,
+    }
+    context.escape(&title)?;
+    context.write_str(match options.profile {
+        RenderProfile::Feed => "</strong></p>\n",
+        _ => "</p>\n",
+    })
+}
+
+/// Render nodes for the feed profile. Returns `None` for nodes which should be rendered
+/// the default way.
+fn format_feed_node<'a>(
+    context: &mut Context<&mut HtmlState<'_>>,
+    node: &'a AstNode<'a>,
+    entering: bool,
+) -> Option<Result<ChildRendering, fmt::Error>> {
+    let res = match node.data.borrow().value {
+        NodeValue::Highlight => context.write_str(if entering { "<strong>" } else { "</strong>" }),
+        NodeValue::TaskItem(ref nti) => write_task_item(context, nti.symbol.is_some(), entering),
+        NodeValue::Alert(ref na) => write_alert(
+            context,
+            alert_kind(&na.alert_type),
+            na.title.as_deref(),
+            entering,
+        ),
+        _ => return None,
+    };
+    Some(res.map(|_| ChildRendering::HTML))
+}
+
+/// Get the alignment of the column of a table cell
+fn cell_alignment<'a>(cell: &'a AstNode<'a>) -> Option<TableAlignment> {
+    let index = cell.preceding_siblings().count() - 1;
+    let table = cell.parent()?.parent()?;
+    match table.data.borrow().value {
+        NodeValue::Table(ref nt) => nt.alignments.get(index).copied(),
+        _ => None,
+    }
+}
+
+/// Render nodes for the e-mail profile. Returns `None` for nodes which should be rendered
+/// the default way.
+fn format_email_node<'a>(
+    context: &mut Context<&mut HtmlState<'_>>,
+    node: &'a AstNode<'a>,
+    entering: bool,
+) -> Option<Result<ChildRendering, fmt::Error>> {
+    let res = match node.data.borrow().value {
+        NodeValue::Highlight => context.write_str(if entering {
+            "<span style=\"background-color:#fff3a3\">"
+        } else {
+            "</span>"
+        }),
+        NodeValue::Code(ref nc) => {
+            if entering {
+                write!(context, "<code style=\"{EMAIL_CODE_STYLE}\">")
+                    .and_then(|_| context.escape(&nc.literal))
+                    .and_then(|_| context.write_str("</code>"))
+            } else {
+                Ok(())
+            }
+        }
+        NodeValue::CodeBlock(ref ncb) => {
+            if entering {
+                write!(context, "<pre style=\"{EMAIL_PRE_STYLE}\"><code>")
+                    .and_then(|_| context.escape(&ncb.literal))
+                    .and_then(|_| context.write_str("</code></pre>\n"))
+            } else {
+                Ok(())
+            }
+        }
+        NodeValue::Table(..) => context.write_str(if entering {
+            "<table style=\"border-collapse:collapse\">\n"
+        } else {
+            "</table>\n"
+        }),
+        NodeValue::TableRow(..) => context.write_str(if entering { "<tr>\n" } else { "</tr>\n" }),
+        NodeValue::TableCell => {
+            let row = node.parent().unwrap();
+            let header = matches!(row.data.borrow().value, NodeValue::TableRow(true));
+            let tag = if header { "th" } else { "td" };
+
+            if entering {
+                let align = match cell_alignment(node) {
+                    Some(TableAlignment::Left) => ";text-align:left",
+                    Some(TableAlignment::Center) => ";text-align:center",
+                    Some(TableAlignment::Right) => ";text-align:right",
+                    _ => "",
+                };
+                write!(context, "<{tag} style=\"{EMAIL_CELL_STYLE}{align}\">")
+            } else {
+                writeln!(context, "</{tag}>")
+            }
+        }
+        NodeValue::Alert(ref na) => write_alert(
+            context,
+            alert_kind(&na.alert_type),
+            na.title.as_deref(),
+            entering,
+        ),
+        NodeValue::TaskItem(ref nti) => write_task_item(context, nti.symbol.is_some(), entering),
+        NodeValue::Link(ref nl) => {
+            if entering {
+                context
+                    .write_str("<a href=\"")
+                    .and_then(|_| context.escape_href(&nl.url))
+                    .and_then(|_| context.write_str("\""))
+                    .and_then(|_| {
+                        if nl.title.is_empty() {
+                            Ok(())
+                        } else {
+                            context
+                                .write_str(" title=\"")
+                                .and_then(|_| context.escape(&nl.title))
+                                .and_then(|_| context.write_str("\""))
+                        }
+                    })
+                    .and_then(|_| {
+                        if context.user.options.links.target_blank {
+                            context.write_str(" target=\"_blank\"")
+                        } else {
+                            Ok(())
+                        }
+                    })
+                    .and_then(|_| context.write_str(" style=\"color:#1a64a0\">"))
+            } else {
+                context.write_str("</a>")
+            }
+        }
+        _ => return None,
+    };
+    Some(res.map(|_| ChildRendering::HTML))
+}
+
+/// Render a formula on the server, if the options ask for it
+fn render_math(options: &RenderOptions, tex: &str, display: bool) -> Option<String> {
+    match &options.math {
+        MathOutput::Default => None,
+        #[cfg(feature = "mathml")]
+        MathOutput::MathMl => {
+            let style = if display {
+                latex2mathml::DisplayStyle::Block
+            } else {
+                latex2mathml::DisplayStyle::Inline
+            };
+            latex2mathml::latex_to_mathml(tex, style).ok()
+        }
+        MathOutput::Custom(backend) => (backend.0)(tex, display),
+    }
+}
+
+/// Write a formula which is rendered client-side, in the wrapper of the math options
+fn write_math(
+    context: &mut Context<&mut HtmlState<'_>>,
+    tex: &str,
+    display: bool,
+    dollar_math: bool,
+) -> fmt::Result {
+    let options = context.user.options;
+    let style = if display { "display" } else { "inline" };
+    match &options.math_options.wrapper {
+        MathWrapper::Default => {
+            // like comrak, `` $`...`$ `` formulas are kept in code elements
+            let tag = if dollar_math { "span" } else { "code" };
+            write!(context, "<{tag} data-math-style=\"{style}\">")?;
+            context.escape(tex)?;
+            write!(context, "</{tag}>")
+        }
+        MathWrapper::Delimited => {
+            let (open, close) = if display {
+                ("\\[", "\\]")
+            } else {
+                ("\\(", "\\)")
+            };
+            write!(context, "<span class=\"math math-{style}\">{open}")?;
+            context.escape(tex)?;
+            write!(context, "{close}</span>")
+        }
+        MathWrapper::Classes {
+            inline,
+            display: display_class,
+        } => {
+            context.write_str("<span class=\"")?;
+            context.escape(if display { display_class } else { inline })?;
+            context.write_str("\">")?;
+            context.escape(tex)?;
+            context.write_str("</span>")
+        }
+    }
+}
+
+/// Prefix of the IDs of footnotes and footnote references, including the namespace of the
+/// document, if any
+fn footnote_id_prefix(options: &RenderOptions, footnotes: &FootnoteOptions) -> String {
+    match &options.id_namespace {
+        Some(namespace) => format!("{namespace}-{}", footnotes.id_prefix),
+        None => footnotes.id_prefix.clone(),
+    }
+}
+
+/// Write the links from the current footnote back to its references
+fn write_footnote_backrefs(
+    context: &mut Context<&mut HtmlState<'_>>,
+    footnotes: &FootnoteOptions,
+    name: &str,
+    total_references: u32,
+) -> fmt::Result {
+    let ix = context.user.footnote_ix;
+    let prefix = footnote_id_prefix(context.user.options, footnotes);
+    for ref_num in 1..=total_references.max(1) {
+        let suffix = if ref_num > 1 {
+            format!("-{ref_num}")
+        } else {
+            String::new()
+        };
+        context.write_str(" <a href=\"")?;
+        context.escape_href(&format!("#{prefix}fnref-{name}{suffix}"))?;
+        write!(
+            context,
+            "\" class=\"footnote-backref\" data-footnote-backref \
+             data-footnote-backref-idx=\"{ix}{suffix}\" aria-label=\"Back to reference {ix}{suffix}\">"
+        )?;
+        context.escape(&footnotes.backref_symbol)?;
+        context.write_str("</a>")?;
+    }
+    context.user.footnote_backrefs_written = true;
+    Ok(())
+}
+
+/// Render tables with the customizations from the options. Returns `None` for nodes which
+/// should be rendered the default way.
+fn format_table_node<'a>(
+    context: &mut Context<&mut HtmlState<'_>>,
+    tables: &TableOptions,
+    node: &'a AstNode<'a>,
+    entering: bool,
+) -> Option<Result<ChildRendering, fmt::Error>> {
+    let res = match node.data.borrow().value {
+        NodeValue::Table(..) if tables.wrapper || tables.class.is_some() => {
+            if entering {
+                context.cr().and_then(|_| {
+                    if tables.wrapper {
+                        context.write_str("<div class=\"table-wrapper\">\n")?;
+                    }
+                    context.write_str("<table")?;
+                    if let Some(class) = &tables.class {
+                        context.write_str(" class=\"")?;
+                        context.escape(class)?;
+                        context.write_str("\"")?;
+                    }
+                    context.write_str(">\n")
+                })
+            } else {
+                // the body is only opened when there are rows after the header
+                let has_body = node
+                    .first_child()
+                    .is_some_and(|first| first.next_sibling().is_some());
+                let mut res = Ok(());
+                if has_body {
+                    res = context.cr().and_then(|_| context.write_str("</tbody>\n"));
+                }
+                res.and_then(|_| context.cr())
+                    .and_then(|_| context.write_str("</table>\n"))
+                    .and_then(|_| {
+                        if tables.wrapper {
+                            context.write_str("</div>\n")
+                        } else {
+                            Ok(())
+                        }
+                    })
+            }
+        }
+        NodeValue::TableCell if tables.alignment_classes || tables.header_scope => {
+            let header = node
+                .parent()
+                .is_some_and(|row| matches!(row.data.borrow().value, NodeValue::TableRow(true)));
+            let tag = if header { "th" } else { "td" };
+            if entering {
+                let align = match cell_alignment(node) {
+                    Some(TableAlignment::Left) => "left",
+                    Some(TableAlignment::Center) => "center",
+                    Some(TableAlignment::Right) => "right",
+                    _ => "",
+                };
+                let mut attrs = String::new();
+                if header && tables.header_scope {
+                    attrs.push_str(" scope=\"col\"");
+                }
+                if !align.is_empty() {
+                    attrs.push_str(&if tables.alignment_classes {
+                        format!(" class=\"align-{align}\"")
+                    } else {
+                        format!(" align=\"{align}\"")
+                    });
+                }
+                context.cr().and_then(|_| write!(context, "<{tag}{attrs}>"))
+            } else {
+                write!(context, "</{tag}>")
+            }
+        }
+        _ => return None,
+    };
+    Some(res.map(|_| ChildRendering::HTML))
+}
+
+/// Render footnote references and definitions. Returns `None` for nodes which should be
+/// rendered the default way.
+fn format_footnote_node<'a>(
+    context: &mut Context<&mut HtmlState<'_>>,
+    footnotes: &FootnoteOptions,
+    node: &'a AstNode<'a>,
+    entering: bool,
+) -> Option<Result<ChildRendering, fmt::Error>> {
+    let prefix = footnote_id_prefix(context.user.options, footnotes);
+    let res = match node.data.borrow().value {
+        NodeValue::FootnoteReference(ref nfr) => {
+            if entering {
+                let suffix = if nfr.ref_num > 1 {
+                    format!("-{}", nfr.ref_num)
+                } else {
+                    String::new()
+                };
+                context
+                    .write_str("<sup class=\"footnote-ref\"><a href=\"")
+                    .and_then(|_| context.escape_href(&format!("#{prefix}fn-{}", nfr.name)))
+                    .and_then(|_| context.write_str("\" id=\""))
+                    .and_then(|_| context.escape(&format!("{prefix}fnref-{}{suffix}", nfr.name)))
+                    .and_then(|_| write!(context, "\" data-footnote-ref>{}</a></sup>", nfr.ix))
+            } else {
+                Ok(())
+            }
+        }
+        NodeValue::FootnoteDefinition(ref nfd) => {
+            if entering {
+                let mut res = Ok(());
+                if context.user.footnote_ix == 0 {
+                    res = context.write_str("<section class=\"footnotes\" data-footnotes>\n");
+                    if let Some(heading) = &footnotes.heading {
+                        res = res
+                            .and_then(|_| context.write_str("<h2 class=\"footnotes-heading\">"))
+                            .and_then(|_| context.escape(heading))
+                            .and_then(|_| context.write_str("</h2>\n"));
+                    }
+                    res = res.and_then(|_| context.write_str("<ol>\n"));
+                }
+                context.user.footnote_ix += 1;
+                context.user.footnote_backrefs_written = false;
+                res.and_then(|_| context.write_str("<li id=\""))
+                    .and_then(|_| context.escape(&format!("{prefix}fn-{}", nfd.name)))
+                    .and_then(|_| context.write_str("\">\n"))
+            } else if context.user.footnote_backrefs_written {
+                context.write_str("</li>\n")
+            } else {
+                write_footnote_backrefs(context, footnotes, &nfd.name, nfd.total_references)
+                    .and_then(|_| context.write_str("\n</li>\n"))
+            }
+        }
+        // the backlinks go at the end of the last paragraph of the footnote
+        NodeValue::Paragraph if !entering && node.next_sibling().is_none() => {
+            let parent = node.parent()?;
+            let NodeValue::FootnoteDefinition(ref nfd) = parent.data.borrow().value else {
+                return None;
+            };
+            write_footnote_backrefs(context, footnotes, &nfd.name, nfd.total_references)
+                .and_then(|_| context.write_str("</p>\n"))
+        }
+        NodeValue::Document if !entering && context.user.footnote_ix > 0 => {
+            context.write_str("</ol>\n</section>\n")
+        }
+        _ => return None,
+    };
+    Some(res.map(|_| ChildRendering::HTML))
+}
+
+/// Check whether a paragraph only consists of a spoiler, which is then rendered as a block
+fn is_block_spoiler<'a>(paragraph: &'a AstNode<'a>) -> bool {
+    paragraph.first_child().is_some_and(|child| {
+        child.next_sibling().is_none()
+            && matches!(child.data.borrow().value, NodeValue::SpoileredText)
+    })
+}
+
+/// Render nodes which may have attributes set through attribute blocks (or, for images,
+/// through their size and the loading options). Returns `None` for nodes which should be
+/// rendered the default way.
+fn format_node_with_attributes<'a>(
+    context: &mut Context<&mut HtmlState<'_>>,
+    node: &'a AstNode<'a>,
+    entering: bool,
+) -> Option<Result<ChildRendering, fmt::Error>> {
+    let attrs = context
+        .user
+        .attributes
+        .get(&node_key(node))
+        .map(|attrs| attributes_html(attrs));
+
+    let res = match node.data.borrow().value {
+        // comrak's heading IDs are disabled, so they are generated here, the same way
+        NodeValue::Heading(ref nh) => {
+            if entering {
+                let anchor = context.user.anchorizer.anchorize(&collect_text(node));
+                let id_prefix = header_id_prefix(context.user.options);
+                context
+                    .cr()
+                    .and_then(|_| write!(context, "<h{}{}>", nh.level, attrs.unwrap_or_default()))
+                    .and_then(|_| {
+                        write!(
+                            context,
+                            "<a href=\"#{anchor}\" aria-hidden=\"true\" class=\"anchor\" id=\""
+                        )
+                    })
+                    .and_then(|_| context.escape(&format!("{id_prefix}{anchor}")))
+                    .and_then(|_| context.write_str("\"></a>"))
+            } else {
+                writeln!(context, "</h{}>", nh.level)
+            }
+        }
+        // paragraphs of tight lists are rendered without `<p>`, so they can't have attributes
+        NodeValue::Paragraph if attrs.is_some() && !in_tight_list(node) => {
+            if entering {
+                context
+                    .cr()
+                    .and_then(|_| write!(context, "<p{}>", attrs.unwrap_or_default()))
+            } else {
+                writeln!(context, "</p>")
+            }
+        }
+        NodeValue::Image(ref nl) => {
+            let attrs = image_attributes(&context.user, node);
+            if attrs.is_empty() {
+                return None;
+            }
+            let res = if entering {
+                write_image(context, node, nl, true, &attrs)
+            } else {
+                Ok(())
+            };
+            return Some(res.map(|_| ChildRendering::Skip));
+        }
+        _ => return None,
+    };
+    Some(res.map(|_| ChildRendering::HTML))
+}
+
+/// Render an image, with additional (already rendered) attributes
+fn write_image<'a>(
+    context: &mut Context<&mut HtmlState<'_>>,
+    node: &'a AstNode<'a>,
+    nl: &NodeLink,
+    title: bool,
+    attrs: &str,
+) -> fmt::Result {
+    context.write_str("<img src=\"")?;
+    context.escape_href(&nl.url)?;
+    context.write_str("\" alt=\"")?;
+    context.escape(&collect_text(node))?;
+    context.write_str("\"")?;
+    if title && !nl.title.is_empty() {
+        context.write_str(" title=\"")?;
+        context.escape(&nl.title)?;
+        context.write_str("\"")?;
+    }
+    write!(context, "{attrs} />")
+}
+
+/// Render the additional attributes of an image: the ones set through attribute blocks or
+/// sizes, the loading hints and the responsive variants. They are only rendered by the
+/// default profile.
+fn image_attributes<'a>(state: &HtmlState<'_>, image: &'a AstNode<'a>) -> String {
+    let options = state.options;
+    if options.profile != RenderProfile::Default {
+        return String::new();
+    }
+
+    let mut attrs = state
+        .attributes
+        .get(&node_key(image))
+        .cloned()
+        .unwrap_or_default();
+    let responsive = match (&options.responsive_images, &image.data.borrow().value) {
+        (Some(responsive_images), NodeValue::Image(nl)) => (responsive_images.0)(&nl.url),
+        _ => None,
+    };
+    let hints = [
+        ("loading", options.lazy_loading.then_some("lazy")),
+        ("decoding", options.async_decoding.then_some("async")),
+        ("referrerpolicy", options.referrer_policy.as_deref()),
+        ("srcset", responsive.as_ref().map(|r| r.srcset.as_str())),
+        (
+            "sizes",
+            responsive.as_ref().and_then(|r| r.sizes.as_deref()),
+        ),
+    ];
+    for (name, value) in hints {
+        if let Some(value) = value
+            && !attrs.iter().any(|(key, _)| key == name)
+        {
+            attrs.push((name.into(), value.into()));
+        }
+    }
+    attributes_html(&attrs)
+}
+
+/// Get the image of a paragraph which only consists of an image with a title
+fn figure_image<'a>(paragraph: &'a AstNode<'a>) -> Option<&'a AstNode<'a>> {
+    let child = paragraph.first_child()?;
+    let captioned =
+        matches!(child.data.borrow().value, NodeValue::Image(ref nl) if !nl.title.is_empty());
+    (captioned && child.next_sibling().is_none()).then_some(child)
+}
+
+/// Render a paragraph which only consists of an image with a title as a figure, using the
+/// title as its caption
+fn write_figure<'a>(
+    context: &mut Context<&mut HtmlState<'_>>,
+    paragraph: &'a AstNode<'a>,
+) -> fmt::Result {
+    let Some(image) = figure_image(paragraph) else {
+        return Ok(());
+    };
+    let NodeValue::Image(ref nl) = image.data.borrow().value else {
+        return Ok(());
+    };
+    let attrs = image_attributes(&context.user, image);
+
+    context.cr()?;
+    context.write_str("<figure>\n")?;
+    write_image(context, image, nl, false, &attrs)?;
+    context.write_str("\n<figcaption>")?;
+    context.escape(&nl.title)?;
+    context.write_str("</figcaption>\n</figure>\n")
+}
+
+/// Metadata of a fenced code block, parsed from its info string
+/// (e.g. ```` ```python title="run.py" {1,3-5} ````)
+#[derive(Debug, PartialEq, Eq)]
+struct CodeInfo<'i> {
+    /// The language, i.e. the first word
+    lang: &'i str,
+    /// A title (usually a file name), given with `title=...` or `filename=...`
+    title: Option<&'i str>,
+    /// Ranges of lines to highlight, given as `{1,3-5}`
+    hl_lines: Option<String>,
+}
+
+fn parse_info_string(info: &str) -> CodeInfo<'_> {
+    let info = info.trim();
+    let lang_end = info.find(char::is_whitespace).unwrap_or(info.len());
+    let mut code_info = CodeInfo {
+        lang: &info[..lang_end],
+        title: None,
+        hl_lines: None,
+    };
+    let mut rest = info[lang_end..].trim_start();
+
+    while !rest.is_empty() {
+        if let Some(r) = rest.strip_prefix('{') {
+            let Some(end) = r.find('}') else {
+                break;
+            };
+            let spec = r[..end].trim();
+            if !spec.is_empty()
+                && spec
+                    .chars()
+                    .all(|c| c.is_ascii_digit() || matches!(c, ',' | '-' | ' '))
+            {
+                code_info.hl_lines = Some(spec.replace(' ', ""));
+            }
+            rest = &r[end + 1..];
+        } else if let Some((key, r)) = rest.split_once('=')
+            && !key.contains(char::is_whitespace)
+        {
+            let (value, r) = match r.strip_prefix('"') {
+                Some(quoted) => match quoted.find('"') {
+                    Some(end) => (&quoted[..end], &quoted[end + 1..]),
+                    None => (quoted, ""),
+                },
+                None => r.split_at(r.find(char::is_whitespace).unwrap_or(r.len())),
+            };
+            if matches!(key, "title" | "filename") {
+                code_info.title = Some(value);
+            }
+            rest = r;
+        } else {
+            // anything else is ignored
+            rest = &rest[rest.find(char::is_whitespace).unwrap_or(rest.len())..];
+        }
+        rest = rest.trim_start();
+    }
+    code_info
+}
+
+/// Render a fenced code block which has a custom element, attributes or metadata in its
+/// info string. Returns `None` for code blocks which should be rendered the default way.
+fn format_code_block<'a>(
+    context: &mut Context<&mut HtmlState<'_>>,
+    node: &'a AstNode<'a>,
+    ncb: &NodeCodeBlock,
+) -> Option<fmt::Result> {
+    let options = context.user.options;
+    if !ncb.fenced {
+        return None;
+    }
+    // attributes are only rendered by the default profile
+    let attrs = match options.profile {
+        RenderProfile::Default => context
+            .user
+            .attributes
+            .get(&node_key(node))
+            .map(|attrs| attributes_html(attrs))
+            .unwrap_or_default(),
+        _ => String::new(),
+    };
+    let mut info = parse_info_string(&ncb.info);
+
+    if let Some(custom) = options.code_blocks.languages.get(info.lang) {
+        return Some(write_custom_code_block(
+            context,
+            custom,
+            &attrs,
+            &ncb.literal,
+        ));
+    }
+
+    if !options.code_blocks.metadata {
+        info.title = None;
+        info.hl_lines = None;
+    }
+    if attrs.is_empty() && info.title.is_none() && info.hl_lines.is_none() {
+        return None;
+    }
+    Some(write_code_block(context, &info, &attrs, &ncb.literal))
+}
+
+/// Render a code block as a custom element
+fn write_custom_code_block(
+    context: &mut Context<&mut HtmlState<'_>>,
+    custom: &CodeBlockElement,
+    attrs: &str,
+    code: &str,
+) -> fmt::Result {
+    context.cr()?;
+    write!(context, "<{} class=\"", custom.element)?;
+    context.escape(&custom.class)?;
+    write!(context, "\"{attrs}>")?;
+    context.escape(code)?;
+    writeln!(context, "</{}>", custom.element)
+}
+
+/// Render a code block with its metadata, wrapping it with its title if it has one
+fn write_code_block(
+    context: &mut Context<&mut HtmlState<'_>>,
+    info: &CodeInfo<'_>,
+    attrs: &str,
+    code: &str,
+) -> fmt::Result {
+    context.cr()?;
+    if let Some(title) = info.title {
+        context.write_str("<div class=\"code-block\">\n<div class=\"code-title\">")?;
+        context.escape(title)?;
+        context.write_str("</div>\n")?;
+    }
+    context.write_str("<pre")?;
+    if let Some(hl_lines) = &info.hl_lines {
+        write!(context, " data-hl-lines=\"{hl_lines}\"")?;
+    }
+    write!(context, "{attrs}><code")?;
+    if !info.lang.is_empty() {
+        context.write_str(" class=\"language-")?;
+        context.escape(info.lang)?;
+        context.write_str("\"")?;
+    }
+    context.write_str(">")?;
+    context.escape(code)?;
+    context.write_str("</code></pre>\n")?;
+    if info.title.is_some() {
+        context.write_str("</div>\n")?;
+    }
+    Ok(())
+}
+
+/// Resolve the target of a wiki link into a URL
+fn resolve_wiki_link(options: &RenderOptions, target: &str) -> Option<String> {
+    match options.wiki_links.as_ref()? {
+        WikiLinks::Template(template) => Some(template.replace("{0}", target)),
+        WikiLinks::Resolver(resolver) => (resolver.0)(target),
+    }
+}
+
+/// The formatter used to render Indico markdown to HTML
+pub(crate) fn html_formatter<'a>(
+    context: &mut Context<&mut HtmlState<'_>>,
+    node: &'a AstNode<'a>,
+    entering: bool,
+) -> Result<ChildRendering, fmt::Error> {
+    if let Some((kind, title)) = context.user.custom_alerts.get(&node_key(node)).cloned() {
+        return write_alert(context, &kind, title.as_deref(), entering)
+            .map(|_| ChildRendering::HTML);
+    }
+    let profile_res = match context.user.options.profile {
+        RenderProfile::Default => None,
+        RenderProfile::Email => format_email_node(context, node, entering),
+        RenderProfile::Feed => format_feed_node(context, node, entering),
+    };
+    if let Some(res) = profile_res {
+        return res;
+    }
+    let options = context.user.options;
+    if let Some(footnotes) = &options.footnotes
+        && let Some(res) = format_footnote_node(context, footnotes, node, entering)
+    {
+        return res;
+    }
+    if options.profile == RenderProfile::Default
+        && let Some(res) = format_node_with_attributes(context, node, entering)
+    {
+        return res;
+    }
+    if options.profile == RenderProfile::Default
+        && let Some(res) = format_table_node(context, &options.tables, node, entering)
+    {
+        return res;
+    }
+
+    if matches!(node.data.borrow().value, NodeValue::Paragraph)
+        && let Some(html) = embeds::paragraph_embed(node, options)
+    {
+        if entering {
+            context.cr()?;
+            context.write_str(&html)?;
+        }
+        return Ok(ChildRendering::Skip);
+    }
+
+    match node.data.borrow().value {
+        // add `target="_blank"` to all links, unless the options disable it
+        NodeValue::Link(ref nl) => {
+            if entering {
+                context.write_str("<a href=\"")?;
+                context.escape_href(&nl.url)?;
+                context.write_str("\"")?;
+                if !nl.title.is_empty() {
+                    context.write_str(" title=\"")?;
+                    context.escape(&nl.title)?;
+                    context.write_str("\"")?;
+                }
+                if options.links.target_blank {
+                    context.write_str(" target=\"_blank\"")?;
+                }
+                context.write_str(">")?;
+            } else {
+                context.write_str("</a>")?;
+            }
+            Ok(ChildRendering::HTML)
+        }
+        #[cfg(feature = "emoji")]
+        NodeValue::ShortCode(ref nsc)
+            if context.user.options.emoji == Some(EmojiStyle::Accessible) =>
+        {
+            if entering {
+                context.write_str("<span role=\"img\" aria-label=\"")?;
+                context.escape(&nsc.code.replace('_', " "))?;
+                context.write_str("\">")?;
+                context.write_str(&nsc.emoji)?;
+                context.write_str("</span>")?;
+            }
+            Ok(ChildRendering::HTML)
+        }
+        NodeValue::WikiLink(ref nwl) => {
+            if !entering {
+                context.write_str("</a>")?;
+            } else if let Some(url) = resolve_wiki_link(context.user.options, &nwl.url) {
+                context.write_str("<a href=\"")?;
+                context.escape_href(&url)?;
+                context.write_str("\" class=\"internal\">")?;
+            } else {
+                context.write_str("<a class=\"internal unresolved\">")?;
+            }
+            Ok(ChildRendering::HTML)
+        }
+        // block spoilers are only used for regular HTML, as the other profiles drop `<details>`
+        NodeValue::Paragraph
+            if options.spoilers.is_some()
+                && options.profile == RenderProfile::Default
+                && is_block_spoiler(node) =>
+        {
+            if entering {
+                context.write_str("<details class=\"spoiler\">\n<summary>")?;
+                if let Some(spoilers) = &options.spoilers {
+                    context.escape(&spoilers.block_summary)?;
+                }
+                context.write_str("</summary>\n<p>")?;
+            } else {
+                context.write_str("</p>\n</details>\n")?;
+            }
+            Ok(ChildRendering::HTML)
+        }
+        NodeValue::Paragraph if options.figures && figure_image(node).is_some() => {
+            if entering {
+                write_figure(context, node)?;
+            }
+            Ok(ChildRendering::Skip)
+        }
+        NodeValue::SpoileredText
+            if options.profile == RenderProfile::Default
+                && node.parent().is_some_and(is_block_spoiler) =>
+        {
+            Ok(ChildRendering::HTML)
+        }
+        NodeValue::Alert(ref na) => {
+            write_alert(
+                context,
+                alert_kind(&na.alert_type),
+                na.title.as_deref(),
+                entering,
+            )?;
+            Ok(ChildRendering::HTML)
+        }
+        NodeValue::TaskItem(ref nti) if options.interactive_tasks => {
+            if entering {
+                let index = context.user.task_index;
+                context.user.task_index += 1;
+                context.cr()?;
+                write!(
+                    context,
+                    "<li><input type=\"checkbox\" data-index=\"{index}\""
+                )?;
+                if let Some(line) = context.user.task_lines.get(index) {
+                    write!(context, " data-line=\"{line}\"")?;
+                }
+                if nti.symbol.is_some() {
+                    context.write_str(" checked=\"\"")?;
+                }
+                context.write_str(" /> ")?;
+            } else {
+                context.write_str("</li>\n")?;
+            }
+            Ok(ChildRendering::HTML)
+        }
+        NodeValue::CodeBlock(ref ncb) => {
+            if !entering {
+                Ok(ChildRendering::HTML)
+            } else if let Some(res) = format_code_block(context, node, ncb) {
+                res.map(|_| ChildRendering::HTML)
+            } else {
+                comrak::html::format_node_default(context, node, entering)
+            }
+        }
+        NodeValue::Math(ref nm) => {
+            let math_options = &context.user.options.math_options;
+            if !entering
+                || (math_options.macros.is_empty()
+                    && math_options.wrapper == MathWrapper::Default
+                    && matches!(context.user.options.math, MathOutput::Default))
+            {
+                return comrak::html::format_node_default(context, node, entering);
+            }
+            let tex = format!("{}{}", math_options.macros, nm.literal);
+            match render_math(context.user.options, &tex, nm.display_math) {
+                Some(html) => context.write_str(&html)?,
+                None => write_math(context, &tex, nm.display_math, nm.dollar_math)?,
+            }
+            Ok(ChildRendering::HTML)
+        }
+        _ => comrak::html::format_node_default(context, node, entering),
+    }
+}
+
+/// Elements which never have content and need to be self-closed in XHTML
+const VOID_ELEMENTS: &[&str] = &[
+    "area", "base", "br", "col", "embed", "hr", "img", "input", "link", "meta", "source", "track",
+    "wbr",
+];
+
+/// Escape text for use in XML (or HTML) content or, if `attribute` is set, attribute values
+pub(crate) fn escape_xml(out: &mut String, text: &str, attribute: bool) {
+    for c in text.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' if attribute => out.push_str("&quot;"),
+            _ => out.push(c),
+        }
+    }
+}
+
+/// Write a start tag, dropping duplicate attributes (which aren't allowed in XML)
+fn write_start_tag(out: &mut String, name: &str, attrs: &[(String, String)]) {
+    out.push('<');
+    out.push_str(name);
+    let mut seen = Vec::new();
+    for (attr, value) in attrs {
+        if seen.contains(&attr) {
+            continue;
+        }
+        seen.push(attr);
+        out.push(' ');
+        out.push_str(attr);
+        out.push_str("=\"");
+        escape_xml(out, value, true);
+        out.push('"');
+    }
+    if VOID_ELEMENTS.contains(&name) {
+        out.push_str(" />");
+    } else {
+        out.push('>');
+    }
+}
+
+/// Re-serialize HTML as well-formed XHTML. Void elements are self-closed, attribute values
+/// are quoted and escaped, stray closing tags are dropped and unclosed elements are closed.
+pub(crate) fn to_xhtml(html: &str) -> String {
+    let mut out = String::with_capacity(html.len());
+    let mut open: Vec<String> = Vec::new();
+
+    for token in tokenize(html) {
+        match token {
+            Token::Start { name, attrs } => {
+                write_start_tag(&mut out, &name, &attrs);
+                if !VOID_ELEMENTS.contains(&name.as_str()) {
+                    open.push(name);
+                }
+            }
+            Token::End(name) => {
+                if let Some(pos) = open.iter().rposition(|n| *n == name) {
+                    for tag in open.drain(pos..).rev() {
+                        out.push_str(&format!("</{tag}>"));
+                    }
+                }
+            }
+            Token::Text(text) => escape_xml(&mut out, &decode_entities(text), false),
+        }
+    }
+
+    for tag in open.into_iter().rev() {
+        out.push_str(&format!("</{tag}>"));
+    }
+    out
+}
+
+/// Truncate rendered HTML to `max_chars` visible characters (including an ellipsis), on a word
+/// boundary. The result is well-formed: the elements which are open where the text is cut are
+/// closed, and a link which would be cut is dropped entirely.
+pub(crate) fn truncate_html(html: &str, max_chars: usize) -> String {
+    // the newlines between blocks are not visible
+    let visible_len = |text: &str| {
+        if text.trim().is_empty() {
+            0
+        } else {
+            text.chars().count()
+        }
+    };
+    let tokens = tokenize(html);
+    let total: usize = tokens
+        .iter()
+        .map(|token| match token {
+            Token::Text(text) => visible_len(&decode_entities(text)),
+            _ => 0,
+        })
+        .sum();
+    if total <= max_chars {
+        return html.into();
+    }
+    if max_chars == 0 {
+        return String::new();
+    }
+
+    let mut out = String::with_capacity(html.len());
+    // open elements, with the length of the output before their start tag
+    let mut open: Vec<(String, usize)> = Vec::new();
+    // the text is cut somewhere, so one character is kept for the ellipsis
+    let mut remaining = max_chars - 1;
+
+    for token in tokens {
+        match token {
+            Token::Start { name, attrs } => {
+                let start = out.len();
+                write_start_tag(&mut out, &name, &attrs);
+                if !VOID_ELEMENTS.contains(&name.as_str()) {
+                    open.push((name, start));
+                }
+            }
+            Token::End(name) => {
+                if let Some(pos) = open.iter().rposition(|(n, _)| *n == name) {
+                    for (tag, _) in open.drain(pos..).rev() {
+                        out.push_str(&format!("</{tag}>"));
+                    }
+                }
+            }
+            Token::Text(text) => {
+                let text = decode_entities(text);
+                let len = visible_len(&text);
+                if len <= remaining {
+                    escape_xml(&mut out, &text, false);
+                    remaining -= len;
+                    continue;
+                }
+
+                if let Some(pos) = open.iter().rposition(|(n, _)| n == "a") {
+                    out.truncate(open[pos].1);
+                    open.truncate(pos);
+                    out.truncate(out.trim_end().len());
+                    out.push('\u{2026}');
+                } else {
+                    let truncated = cut_text(&text, remaining, "\u{2026}");
+                    escape_xml(&mut out, &truncated, false);
+                }
+                for (tag, _) in open.into_iter().rev() {
+                    out.push_str(&format!("</{tag}>"));
+                }
+                return out;
+            }
+        }
+    }
+    unreachable!("the text which doesn't fit is cut")
+}
+
+/// Elements which are laid out as blocks when pretty-printing
+const BLOCK_ELEMENTS: &[&str] = &[
+    "blockquote",
+    "dd",
+    "details",
+    "div",
+    "dl",
+    "dt",
+    "figcaption",
+    "figure",
+    "h1",
+    "h2",
+    "h3",
+    "h4",
+    "h5",
+    "h6",
+    "hr",
+    "li",
+    "ol",
+    "p",
+    "pre",
+    "section",
+    "summary",
+    "table",
+    "tbody",
+    "td",
+    "tfoot",
+    "th",
+    "thead",
+    "tr",
+    "ul",
+];
+
+/// Elements whose content must be kept verbatim
+const PREFORMATTED_ELEMENTS: &[&str] = &["pre", "script", "style", "textarea"];
+
+/// Change the layout of rendered HTML, either stripping insignificant whitespace or
+/// indenting block-level elements.
+pub(crate) fn reformat(html: &str, format: HtmlFormat) -> String {
+    if format == HtmlFormat::Default {
+        return html.into();
+    }
+    let pretty = format == HtmlFormat::Pretty;
+
+    let mut out = String::with_capacity(html.len());
+    // open block elements, and whether they contain other blocks
+    let mut blocks: Vec<(String, bool)> = Vec::new();
+    let mut preformatted = 0;
+
+    let new_line = |out: &mut String, depth: usize| {
+        let trimmed = out.trim_end_matches(' ').len();
+        out.truncate(trimmed);
+        if !out.is_empty() {
+            out.push('\n');
+            out.push_str(&"  ".repeat(depth));
+        }
+    };
+
+    let is_block_tag = |token: &Token| {
+        matches!(token, Token::Start { name, .. } | Token::End(name)
+            if BLOCK_ELEMENTS.contains(&name.as_str()))
+    };
+    // whether the previous token is a block-level tag (or there is none)
+    let mut after_block = true;
+    let mut tokens = tokenize(html).into_iter().peekable();
+    while let Some(token) = tokens.next() {
+        let token_is_block = is_block_tag(&token);
+        match token {
+            Token::Start { name, attrs } => {
+                let is_block = BLOCK_ELEMENTS.contains(&name.as_str());
+                if pretty && is_block && preformatted == 0 {
+                    new_line(&mut out, blocks.len());
+                }
+                write_start_tag(&mut out, &name, &attrs);
+
+                if PREFORMATTED_ELEMENTS.contains(&name.as_str()) {
+                    preformatted += 1;
+                }
+                if is_block && !VOID_ELEMENTS.contains(&name.as_str()) {
+                    if let Some(parent) = blocks.last_mut() {
+                        parent.1 = true;
+                    }
+                    blocks.push((name, false));
+                } else if is_block && let Some(parent) = blocks.last_mut() {
+                    parent.1 = true;
+                }
+            }
+            Token::End(name) => {
+                if PREFORMATTED_ELEMENTS.contains(&name.as_str()) {
+                    preformatted = usize::saturating_sub(preformatted, 1);
+                }
+                if blocks.last().is_some_and(|(n, _)| *n == name) {
+                    let (_, has_blocks) = blocks.pop().unwrap();
+                    if pretty && has_blocks {
+                        new_line(&mut out, blocks.len());
+                    }
+                }
+                out.push_str(&format!("</{name}>"));
+            }
+            Token::Text(text) => {
+                if preformatted > 0 {
+                    out.push_str(text);
+                } else if text.trim().is_empty() && text.contains('\n') {
+                    // line breaks next to blocks are insignificant, but between inline content
+                    // they separate words
+                    if !after_block && !tokens.peek().is_none_or(is_block_tag) {
+                        out.push(' ');
+                    }
+                } else {
+                    let text = text.replace('\n', " ");
+                    // avoid leading whitespace right after a new line
+                    if pretty && (out.is_empty() || out.ends_with([' ', '\n'])) {
+                        out.push_str(text.trim_start());
+                    } else {
+                        out.push_str(&text);
+                    }
+                }
+            }
+        }
+        after_block = token_is_block;
+    }
+
+    if pretty {
+        out.push('\n');
+    }
+    out
+}
+
+/// Wrap a rendered fragment into a standalone HTML document, in the given language if any
+pub(crate) fn wrap_document(
+    body: &str,
+    doc: &DocumentOptions,
+    xhtml: bool,
+    lang: Option<&str>,
+) -> String {
+    let mut out = String::with_capacity(body.len() + 256);
+    let void_end = if xhtml { " />" } else { ">" };
+
+    out.push_str("<!DOCTYPE html>\n<html");
+    if xhtml {
+        out.push_str(" xmlns=\"http://www.w3.org/1999/xhtml\"");
+    }
+    if let Some(lang) = lang {
+        out.push_str(" lang=\"");
+        escape_xml(&mut out, lang, true);
+        out.push('"');
+        if xhtml {
+            out.push_str(" xml:lang=\"");
+            escape_xml(&mut out, lang, true);
+            out.push('"');
+        }
+    }
+    out.push_str(">\n");
+    out.push_str("<head>\n<meta charset=\"");
+    escape_xml(&mut out, &doc.charset, true);
+    out.push('"');
+    out.push_str(void_end);
+    out.push_str("\n<title>");
+    escape_xml(&mut out, &doc.title, false);
+    out.push_str("</title>\n");
+    if let Some(css) = &doc.stylesheet {
+        out.push_str("<style>\n");
+        // make sure the stylesheet can't close the element early
+        out.push_str(&css.replace("</", "<\\/"));
+        out.push_str("\n</style>\n");
+    }
+    out.push_str("</head>\n<body>\n");
+    out.push_str(body);
+    out.push_str("</body>\n</html>\n");
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{CodeInfo, parse_info_string, reformat, resolve_url, to_xhtml};
+    use crate::options::HtmlFormat;
+
+    #[test]
+    fn test_parse_info_string() {
+        assert_eq!(
+            parse_info_string("python title=\"run me.py\" {1, 3-5} linenums"),
+            CodeInfo {
+                lang: "python",
+                title: Some("run me.py"),
+                hl_lines: Some("1,3-5".into()),
+            }
+        );
+        assert_eq!(
+            parse_info_string("sh filename=setup.sh"),
+            CodeInfo {
+                lang: "sh",
+                title: Some("setup.sh"),
+                hl_lines: None,
+            }
+        );
+        assert_eq!(
+            parse_info_string("  {a} x=\"1"),
+            CodeInfo {
+                lang: "{a}",
+                title: None,
+                hl_lines: None,
+            }
+        );
+    }
+
+    #[test]
+    fn test_resolve_url() {
+        let base = "https://indico.example.com/event/1/";
+        assert_eq!(
+            resolve_url(base, "attachments/slides.pdf"),
+            "https://indico.example.com/event/1/attachments/slides.pdf"
+        );
+        assert_eq!(
+            resolve_url(base, "/category/2"),
+            "https://indico.example.com/category/2"
+        );
+        assert_eq!(
+            resolve_url("https://indico.example.com", "a.png"),
+            "https://indico.example.com/a.png"
+        );
+        assert_eq!(resolve_url(base, "#top"), "#top");
+        assert_eq!(resolve_url(base, "mailto:a@b.c"), "mailto:a@b.c");
+        assert_eq!(resolve_url(base, "//cdn.org/x"), "//cdn.org/x");
+    }
+
+    #[test]
+    fn test_xhtml() {
+        assert_eq!(
+            to_xhtml(
+                "<p>a<br>b <img src=x alt='1 > 0' alt=z> &amp; <span class=c>unclosed</p>\n</div>"
+            ),
+            "<p>a<br />b <img src=\"x\" alt=\"1 &gt; 0\" /> &amp; <span class=\"c\">unclosed</span></p>\n"
+        );
+    }
+
+    #[test]
+    fn test_reformat() {
+        let html = "<h1>T</h1>\n<ul>\n<li>a\n<ul>\n<li>b <em>c</em></li>\n</ul>\n</li>\n</ul>\n\
+                    <pre><code>x\n  y\n</code></pre>\n<p>1<br />\n2</p>\n";
+        assert_eq!(
+            reformat(html, HtmlFormat::Minified),
+            "<h1>T</h1><ul><li>a <ul><li>b <em>c</em></li></ul></li></ul>\
+             <pre><code>x\n  y\n</code></pre><p>1<br /> 2</p>"
+        );
+        assert_eq!(
+            reformat(html, HtmlFormat::Pretty),
+            "<h1>T</h1>\n<ul>\n  <li>a\n    <ul>\n      <li>b <em>c</em></li>\n    </ul>\n  </li>\n</ul>\n\
+             <pre><code>x\n  y\n</code></pre>\n<p>1<br /> 2</p>\n"
+        );
+
+        // whitespace between inline elements separates their words
+        let html = "<p><em>a</em>\n<em>b</em></p>\n<div>\n<span>c</span>\n</div>\n";
+        assert_eq!(
+            reformat(html, HtmlFormat::Minified),
+            "<p><em>a</em> <em>b</em></p><div><span>c</span></div>"
+        );
+        assert_eq!(
+            reformat(html, HtmlFormat::Pretty),
+            "<p><em>a</em> <em>b</em></p>\n<div><span>c</span></div>\n"
+        );
+    }
+}
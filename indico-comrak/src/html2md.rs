@@ -0,0 +1,631 @@
+//! Conversion of HTML back into Indico markdown.
+//!
+//! This is meant for migrating legacy content, which is stored as HTML. Only the subset of
+//! tags which the renderer itself generates is understood (headings, paragraphs, lists,
+//! tables, links, images, code, emphasis, marks, block quotes and alerts); the content of
+//! any other tag is kept as text.
+
+/// An HTML token
+#[derive(Debug, PartialEq)]
+pub(crate) enum Token<'h> {
+    Start {
+        name: String,
+        attrs: Vec<(String, String)>,
+    },
+    End(String),
+    Text(&'h str),
+}
+
+/// Split HTML into start tags, end tags and text. This is a very lenient tokenizer: anything
+/// which doesn't look like a tag is treated as text.
+pub(crate) fn tokenize(html: &str) -> Vec<Token<'_>> {
+    let mut tokens = Vec::new();
+    let mut pos = 0;
+
+    while pos < html.len() {
+        let rest = &html[pos..];
+        let Some(lt) = rest.find('<') else {
+            tokens.push(Token::Text(rest));
+            break;
+        };
+        if lt > 0 {
+            tokens.push(Token::Text(&rest[..lt]));
+        }
+        let tag = &rest[lt..];
+
+        if tag.starts_with("<!--") {
+            pos += lt + tag.find("-->").map_or(tag.len(), |e| e + 3);
+        } else if tag.starts_with("<!") || tag.starts_with("<?") {
+            pos += lt + tag.find('>').map_or(tag.len(), |e| e + 1);
+        } else if let Some(name) = tag.strip_prefix("</") {
+            let end = name.find('>').unwrap_or(name.len());
+            tokens.push(Token::End(name[..end].trim().to_ascii_lowercase()));
+            pos += lt + 2 + (end + 1).min(name.len());
+        } else if tag[1..].starts_with(|c: char| c.is_ascii_alphabetic()) {
+            let (token, len) = parse_start_tag(tag);
+            tokens.push(token);
+            pos += lt + len;
+        } else {
+            tokens.push(Token::Text("<"));
+            pos += lt + 1;
+        }
+    }
+
+    tokens
+}
+
+/// Parse a start tag (including its attributes), returning it along with its length in bytes
+fn parse_start_tag(tag: &str) -> (Token<'_>, usize) {
+    let bytes = tag.as_bytes();
+    let mut i = 1;
+    while i < bytes.len() && (bytes[i].is_ascii_alphanumeric() || bytes[i] == b'-') {
+        i += 1;
+    }
+    let name = tag[1..i].to_ascii_lowercase();
+    let mut attrs = Vec::new();
+
+    loop {
+        while i < bytes.len() && (bytes[i].is_ascii_whitespace() || bytes[i] == b'/') {
+            i += 1;
+        }
+        if i >= bytes.len() || bytes[i] == b'>' {
+            break;
+        }
+
+        let start = i;
+        while i < bytes.len() && !b" \t\r\n=>/".contains(&bytes[i]) {
+            i += 1;
+        }
+        let attr_name = tag[start..i].to_ascii_lowercase();
+        let mut value = String::new();
+
+        if i < bytes.len() && bytes[i] == b'=' {
+            i += 1;
+            if i < bytes.len() && (bytes[i] == b'"' || bytes[i] == b'\'') {
+                let quote = bytes[i];
+                let end = tag[i + 1..]
+                    .bytes()
+                    .position(|b| b == quote)
+                    .map_or(tag.len(), |e| i + 1 + e);
+                value = decode_entities(&tag[i + 1..end]);
+                i = (end + 1).min(tag.len());
+            } else {
+                let start = i;
+                while i < bytes.len() && !bytes[i].is_ascii_whitespace() && bytes[i] != b'>' {
+                    i += 1;
+                }
+                value = decode_entities(&tag[start..i]);
+            }
+        }
+        if attr_name.is_empty() {
+            i += 1;
+        } else {
+            attrs.push((attr_name, value));
+        }
+    }
+
+    (Token::Start { name, attrs }, (i + 1).min(tag.len()))
+}
+
+/// Decode the most common HTML entities
+pub(crate) fn decode_entities(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+
+    while let Some(amp) = rest.find('&') {
+        out.push_str(&rest[..amp]);
+        rest = &rest[amp..];
+
+        let decoded = rest.find(';').filter(|&end| end <= 10).and_then(|end| {
+            let entity = &rest[1..end];
+            let c = match entity {
+                "amp" => Some('&'),
+                "lt" => Some('<'),
+                "gt" => Some('>'),
+                "quot" => Some('"'),
+                "apos" => Some('\''),
+                "nbsp" => Some('\u{a0}'),
+                _ => entity.strip_prefix('#').and_then(|num| {
+                    match num.strip_prefix(['x', 'X']) {
+                        Some(hex) => u32::from_str_radix(hex, 16).ok(),
+                        None => num.parse().ok(),
+                    }
+                    .and_then(char::from_u32)
+                }),
+            };
+            c.map(|c| (c, end))
+        });
+
+        match decoded {
+            Some((c, end)) => {
+                out.push(c);
+                rest = &rest[end + 1..];
+            }
+            None => {
+                out.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+fn attr<'t>(attrs: &'t [(String, String)], name: &str) -> Option<&'t str> {
+    attrs
+        .iter()
+        .find(|(n, _)| n == name)
+        .map(|(_, v)| v.as_str())
+}
+
+/// Kinds of elements whose content is collected separately and post-processed
+#[derive(Debug)]
+enum FrameKind {
+    Root,
+    Link { href: String, title: String },
+    Item { marker: String },
+    Quote { alert: Option<String> },
+    Cell,
+    Pre { lang: String },
+    Discard,
+}
+
+#[derive(Debug)]
+struct Frame {
+    kind: FrameKind,
+    buf: String,
+}
+
+#[derive(Default)]
+struct Table {
+    rows: Vec<Vec<String>>,
+    alignments: Vec<&'static str>,
+}
+
+struct Converter {
+    frames: Vec<Frame>,
+    /// For each open list: whether it's ordered and the number of the next item
+    lists: Vec<(bool, usize)>,
+    tables: Vec<Table>,
+}
+
+impl Converter {
+    fn buf(&mut self) -> &mut String {
+        &mut self.frames.last_mut().unwrap().buf
+    }
+
+    fn push(&mut self, kind: FrameKind) {
+        self.frames.push(Frame {
+            kind,
+            buf: String::new(),
+        });
+    }
+
+    fn in_pre(&self) -> bool {
+        self.frames
+            .iter()
+            .any(|f| matches!(f.kind, FrameKind::Pre { .. }))
+    }
+
+    /// Separate a block from the previous content with a blank line
+    fn block_break(&mut self) {
+        let buf = self.buf();
+        let trimmed = buf.trim_end_matches([' ', '\n']).len();
+        buf.truncate(trimmed);
+        if !buf.is_empty() {
+            buf.push_str("\n\n");
+        }
+    }
+
+    fn text(&mut self, text: &str) {
+        let text = decode_entities(text);
+        if self.in_pre() {
+            self.buf().push_str(&text);
+            return;
+        }
+
+        let in_cell = matches!(self.frames.last().unwrap().kind, FrameKind::Cell);
+        let buf = self.buf();
+        let mut last_space = buf.is_empty() || buf.ends_with([' ', '\n']);
+
+        for c in text.chars() {
+            if c.is_whitespace() && c != '\u{a0}' {
+                if !last_space {
+                    buf.push(' ');
+                    last_space = true;
+                }
+                continue;
+            }
+            if matches!(c, '\\' | '*' | '_' | '`' | '[' | ']' | '<') || (in_cell && c == '|') {
+                buf.push('\\');
+            }
+            buf.push(c);
+            last_space = false;
+        }
+    }
+
+    fn start(&mut self, name: &str, attrs: &[(String, String)]) {
+        let class = attr(attrs, "class").unwrap_or_default();
+
+        match name {
+            "h1" | "h2" | "h3" | "h4" | "h5" | "h6" => {
+                self.block_break();
+                let level = name[1..].parse().unwrap_or(1);
+                self.buf().push_str(&"#".repeat(level));
+                self.buf().push(' ');
+            }
+            "p" if class.contains("markdown-alert-title") => self.push(FrameKind::Discard),
+            "div" if class.contains("markdown-alert") => {
+                let alert = class
+                    .split_whitespace()
+                    .find_map(|c| c.strip_prefix("markdown-alert-"))
+                    .map(str::to_ascii_uppercase);
+                self.block_break();
+                self.push(FrameKind::Quote { alert });
+            }
+            "p" | "div" | "section" | "article" => self.block_break(),
+            "br" => {
+                if matches!(self.frames.last().unwrap().kind, FrameKind::Cell) {
+                    self.buf().push(' ');
+                } else {
+                    self.buf().push_str("\\\n");
+                }
+            }
+            "hr" => {
+                self.block_break();
+                self.buf().push_str("---");
+                self.block_break();
+            }
+            "strong" | "b" => self.buf().push_str("**"),
+            "em" | "i" => self.buf().push('*'),
+            "del" | "s" | "strike" => self.buf().push_str("~~"),
+            "mark" => self.buf().push_str("=="),
+            "u" | "ins" => self.buf().push_str("__"),
+            "code" if !self.in_pre() => self.buf().push('`'),
+            "code" => {
+                if let Some(lang) = class
+                    .split_whitespace()
+                    .find_map(|c| c.strip_prefix("language-"))
+                    && let Some(Frame {
+                        kind: FrameKind::Pre { lang: pre_lang },
+                        ..
+                    }) = self.frames.last_mut()
+                {
+                    *pre_lang = lang.into();
+                }
+            }
+            "pre" => {
+                self.block_break();
+                let lang = attr(attrs, "lang").unwrap_or_default().into();
+                self.push(FrameKind::Pre { lang });
+            }
+            "a" => self.push(FrameKind::Link {
+                href: attr(attrs, "href").unwrap_or_default().into(),
+                title: attr(attrs, "title").unwrap_or_default().into(),
+            }),
+            "img" => {
+                let alt = attr(attrs, "alt").unwrap_or_default().to_string();
+                let src = attr(attrs, "src").unwrap_or_default().to_string();
+                let title = attr(attrs, "title").unwrap_or_default();
+                let title = if title.is_empty() {
+                    String::new()
+                } else {
+                    format!(" \"{}\"", title.replace('"', "\\\""))
+                };
+                self.buf().push_str(&format!("![{alt}]({src}{title})"));
+            }
+            "input" if attr(attrs, "type") == Some("checkbox") => {
+                let checked = attr(attrs, "checked").is_some();
+                self.buf().push_str(if checked { "[x] " } else { "[ ] " });
+            }
+            "ul" | "ol" => {
+                if matches!(self.frames.last().unwrap().kind, FrameKind::Item { .. }) {
+                    let buf = self.buf();
+                    if !buf.is_empty() && !buf.ends_with('\n') {
+                        buf.push('\n');
+                    }
+                } else {
+                    self.block_break();
+                }
+                let start = attr(attrs, "start").and_then(|s| s.parse().ok());
+                self.lists.push((name == "ol", start.unwrap_or(1)));
+            }
+            "li" => {
+                let marker = match self.lists.last_mut() {
+                    Some((true, n)) => {
+                        *n += 1;
+                        format!("{}. ", *n - 1)
+                    }
+                    _ => "- ".into(),
+                };
+                self.push(FrameKind::Item { marker });
+            }
+            "blockquote" => {
+                self.block_break();
+                self.push(FrameKind::Quote { alert: None });
+            }
+            "table" => {
+                self.block_break();
+                self.tables.push(Table::default());
+            }
+            "tr" => {
+                if let Some(table) = self.tables.last_mut() {
+                    table.rows.push(Vec::new());
+                }
+            }
+            "th" | "td" => {
+                if let Some(table) = self.tables.last_mut()
+                    && table.rows.len() == 1
+                {
+                    let style = attr(attrs, "style").unwrap_or_default().replace(' ', "");
+                    let align = attr(attrs, "align").unwrap_or_default();
+                    table.alignments.push(
+                        if align == "center" || style.contains("text-align:center") {
+                            ":-:"
+                        } else if align == "right" || style.contains("text-align:right") {
+                            "--:"
+                        } else if align == "left" || style.contains("text-align:left") {
+                            ":--"
+                        } else {
+                            "---"
+                        },
+                    );
+                }
+                self.push(FrameKind::Cell);
+            }
+            "script" | "style" | "head" | "title" => self.push(FrameKind::Discard),
+            _ => {}
+        }
+    }
+
+    /// Pop the innermost frame of a given kind, if it is the current one
+    fn pop(&mut self, matches_kind: fn(&FrameKind) -> bool) -> Option<Frame> {
+        if self.frames.len() > 1 && matches_kind(&self.frames.last().unwrap().kind) {
+            self.frames.pop()
+        } else {
+            None
+        }
+    }
+
+    fn end(&mut self, name: &str) {
+        match name {
+            "p" => {
+                self.pop(|k| matches!(k, FrameKind::Discard));
+                self.block_break();
+            }
+            "div" => {
+                if let Some(Frame {
+                    kind: FrameKind::Quote { alert },
+                    buf,
+                }) = self.pop(|k| matches!(k, FrameKind::Quote { alert: Some(_) }))
+                {
+                    self.end_quote(alert, &buf);
+                }
+                self.block_break();
+            }
+            "h1" | "h2" | "h3" | "h4" | "h5" | "h6" | "section" | "article" => self.block_break(),
+            "strong" | "b" => self.buf().push_str("**"),
+            "em" | "i" => self.buf().push('*'),
+            "del" | "s" | "strike" => self.buf().push_str("~~"),
+            "mark" => self.buf().push_str("=="),
+            "u" | "ins" => self.buf().push_str("__"),
+            "code" if !self.in_pre() => self.buf().push('`'),
+            "pre" => {
+                if let Some(Frame {
+                    kind: FrameKind::Pre { lang },
+                    buf,
+                }) = self.pop(|k| matches!(k, FrameKind::Pre { .. }))
+                {
+                    let fence = if buf.contains("```") { "~~~~" } else { "```" };
+                    let code = buf.trim_end_matches('\n');
+                    self.buf()
+                        .push_str(&format!("{fence}{lang}\n{code}\n{fence}"));
+                    self.block_break();
+                }
+            }
+            "a" => {
+                if let Some(Frame {
+                    kind: FrameKind::Link { href, title },
+                    buf,
+                }) = self.pop(|k| matches!(k, FrameKind::Link { .. }))
+                {
+                    let text = buf.trim();
+                    if text.is_empty() {
+                        // e.g. heading anchors
+                    } else if text == href.replace('_', "\\_") || href.is_empty() {
+                        self.buf().push_str(text);
+                    } else if title.is_empty() || title == text {
+                        self.buf().push_str(&format!("[{text}]({href})"));
+                    } else {
+                        let title = title.replace('"', "\\\"");
+                        self.buf()
+                            .push_str(&format!("[{text}]({href} \"{title}\")"));
+                    }
+                }
+            }
+            "ul" | "ol" => {
+                self.lists.pop();
+                if !matches!(self.frames.last().unwrap().kind, FrameKind::Item { .. }) {
+                    self.block_break();
+                }
+            }
+            "li" => {
+                if let Some(Frame {
+                    kind: FrameKind::Item { marker },
+                    buf,
+                }) = self.pop(|k| matches!(k, FrameKind::Item { .. }))
+                {
+                    let indent = " ".repeat(marker.len());
+                    let content = buf.trim().replace('\n', &format!("\n{indent}"));
+                    let content = content
+                        .lines()
+                        .map(str::trim_end)
+                        .collect::<Vec<_>>()
+                        .join("\n");
+                    let parent = self.buf();
+                    if !parent.is_empty() && !parent.ends_with('\n') {
+                        parent.push('\n');
+                    }
+                    parent.push_str(&marker);
+                    parent.push_str(&content);
+                    parent.push('\n');
+                }
+            }
+            "blockquote" => {
+                if let Some(Frame {
+                    kind: FrameKind::Quote { alert },
+                    buf,
+                }) = self.pop(|k| matches!(k, FrameKind::Quote { .. }))
+                {
+                    self.end_quote(alert, &buf);
+                }
+            }
+            "th" | "td" => {
+                if let Some(Frame { buf, .. }) = self.pop(|k| matches!(k, FrameKind::Cell))
+                    && let Some(row) = self.tables.last_mut().and_then(|t| t.rows.last_mut())
+                {
+                    row.push(buf.trim().replace('\n', " "));
+                }
+            }
+            "table" => {
+                if let Some(table) = self.tables.pop() {
+                    self.write_table(table);
+                }
+            }
+            "script" | "style" | "head" | "title" => {
+                self.pop(|k| matches!(k, FrameKind::Discard));
+            }
+            _ => {}
+        }
+    }
+
+    fn end_quote(&mut self, alert: Option<String>, content: &str) {
+        let mut quoted = String::new();
+        if let Some(alert) = alert {
+            quoted.push_str(&format!("> [!{alert}]\n"));
+        }
+        for line in content.trim().lines() {
+            if line.is_empty() {
+                quoted.push_str(">\n");
+            } else {
+                quoted.push_str(&format!("> {line}\n"));
+            }
+        }
+        self.block_break();
+        self.buf().push_str(&quoted);
+        self.block_break();
+    }
+
+    fn write_table(&mut self, mut table: Table) {
+        let columns = table.rows.iter().map(Vec::len).max().unwrap_or(0);
+        if columns == 0 {
+            return;
+        }
+        table.alignments.resize(columns, "---");
+
+        let mut md = String::new();
+        for (i, row) in table.rows.iter().enumerate() {
+            md.push('|');
+            for n in 0..columns {
+                md.push_str(&format!(" {} |", row.get(n).map_or("", String::as_str)));
+            }
+            md.push('\n');
+            if i == 0 {
+                md.push('|');
+                for align in &table.alignments {
+                    md.push_str(&format!(" {align} |"));
+                }
+                md.push('\n');
+            }
+        }
+
+        self.block_break();
+        self.buf().push_str(&md);
+        self.block_break();
+    }
+}
+
+/// Convert HTML into Indico markdown.
+///
+/// The conversion targets the tags which [`indico_markdown_to_html`](crate::indico_markdown_to_html)
+/// emits, so that content can be round-tripped through the editor. Unknown tags are dropped,
+/// but their text content is kept.
+pub fn html_to_indico_markdown(html: &str) -> String {
+    let mut converter = Converter {
+        frames: vec![Frame {
+            kind: FrameKind::Root,
+            buf: String::new(),
+        }],
+        lists: Vec::new(),
+        tables: Vec::new(),
+    };
+
+    for token in tokenize(html) {
+        let discarding = matches!(converter.frames.last().unwrap().kind, FrameKind::Discard);
+        match token {
+            Token::Start { .. } | Token::Text(_) if discarding => {}
+            Token::Start { name, attrs } => converter.start(&name, &attrs),
+            Token::End(name) => converter.end(&name),
+            Token::Text(text) => converter.text(text),
+        }
+    }
+
+    // close anything which was left open
+    while converter.frames.len() > 1 {
+        let frame = converter.frames.pop().unwrap();
+        converter.buf().push_str(&frame.buf);
+    }
+
+    let out = converter.frames.pop().unwrap().buf;
+    let out = out.trim();
+    if out.is_empty() {
+        String::new()
+    } else {
+        format!("{out}\n")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::html_to_indico_markdown;
+    use crate::indico_markdown_to_html;
+
+    #[test]
+    fn test_html_to_markdown() {
+        let html = "<h2>Title &amp; more</h2>\n<p>Some <strong>bold</strong>, <em>emph</em> and \
+                    <mark>marked</mark> text with <code>code</code> and a \
+                    <a href=\"https://example.com\">link</a>.<br>Next line</p>\n\
+                    <ul>\n<li>one</li>\n<li>two\n<ol>\n<li>nested</li>\n</ol>\n</li>\n</ul>\n\
+                    <pre><code class=\"language-python\">print(1 &lt; 2)\n</code></pre>\n\
+                    <blockquote><p>quoted</p></blockquote>";
+        assert_eq!(
+            html_to_indico_markdown(html),
+            "## Title & more\n\n\
+             Some **bold**, *emph* and ==marked== text with `code` and a \
+             [link](https://example.com).\\\nNext line\n\n\
+             - one\n- two\n  1. nested\n\n\
+             ```python\nprint(1 < 2)\n```\n\n\
+             > quoted\n"
+        );
+
+        let html = "<table>\n<thead>\n<tr>\n<th align=\"center\">a</th>\n<th>b</th>\n</tr>\n\
+                    </thead>\n<tbody>\n<tr>\n<td>1|2</td>\n<td>3</td>\n</tr>\n</tbody>\n</table>";
+        assert_eq!(
+            html_to_indico_markdown(html),
+            "| a | b |\n| :-: | --- |\n| 1\\|2 | 3 |\n"
+        );
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let md = "## Agenda\n\n\
+                  - [x] **welcome**\n- [ ] ~~coffee~~\n\n\
+                  > [!WARNING]\n> Be *on time*\n\n\
+                  See [the page](https://example.com/a_b \"Page\")\n";
+        let html = indico_markdown_to_html(md, &[]).unwrap();
+        assert_eq!(
+            indico_markdown_to_html(&html_to_indico_markdown(&html), &[]).unwrap(),
+            html
+        );
+    }
+}
@@ -0,0 +1,160 @@
+//! Image dimensions, given in the document with the `![alt](url =300x200)` syntax (either
+//! dimension may be omitted, e.g. `=300x`) or provided by the caller for known URLs.
+//!
+//! comrak doesn't understand the size syntax, so before parsing, the size is moved into the
+//! title of the image, behind a marker. The sizes are then taken out of the titles and added
+//! to the attributes of the images.
+
+use comrak::nodes::{AstNode, NodeValue};
+use regex_lite::{Captures, Regex};
+use std::borrow::Cow;
+
+use crate::{
+    attributes::{NodeAttributes, node_key},
+    containers::CodeFences,
+    options::RenderOptions,
+};
+
+/// Delimits the size which was moved into the title of an image
+const SIZE_MARKER: char = '\u{1}';
+
+fn image_size_regex() -> Regex {
+    Regex::new(
+        r#"!\[([^\]]*)\]\(\s*(<[^>]*>|[^\s)]+)\s+=(\d*)x(\d*)\s*("[^"]*"|'[^']*'|\([^)]*\))?\s*\)"#,
+    )
+    .unwrap()
+}
+
+/// Rewrite an image with a size into an image whose title starts with the (marked) size
+fn move_size_into_title(caps: &Captures) -> String {
+    let size = format!("{SIZE_MARKER}{}x{}{SIZE_MARKER}", &caps[3], &caps[4]);
+    let title = match caps.get(5) {
+        Some(title) => {
+            let (delimiter, rest) = title.as_str().split_at(1);
+            format!("{delimiter}{size}{rest}")
+        }
+        None => format!("\"{size}\""),
+    };
+    format!("![{}]({} {title})", &caps[1], &caps[2])
+}
+
+/// Apply the size syntax to a line, leaving code spans alone
+fn expand_line<'s>(re: &Regex, line: &'s str) -> Cow<'s, str> {
+    if !line.contains('`') {
+        return re.replace_all(line, move_size_into_title);
+    }
+
+    let mut out = String::with_capacity(line.len());
+    let mut rest = line;
+    while let Some(start) = rest.find('`') {
+        out.push_str(&re.replace_all(&rest[..start], move_size_into_title));
+        let ticks = rest[start..].chars().take_while(|&c| c == '`').count();
+        let after = &rest[start + ticks..];
+        match after.find(&rest[start..start + ticks]) {
+            Some(end) => {
+                out.push_str(&rest[start..start + 2 * ticks + end]);
+                rest = &after[end + ticks..];
+            }
+            None => {
+                out.push_str(&rest[start..start + ticks]);
+                rest = after;
+            }
+        }
+    }
+    out.push_str(&re.replace_all(rest, move_size_into_title));
+    Cow::Owned(out)
+}
+
+/// Move the sizes given with the `=WIDTHxHEIGHT` syntax into the titles of the images
+pub(crate) fn expand_image_sizes(md_source: &str) -> Cow<'_, str> {
+    if !md_source.contains("![") {
+        return Cow::Borrowed(md_source);
+    }
+
+    let re = image_size_regex();
+    let mut out = String::with_capacity(md_source.len());
+    let mut code_fences = CodeFences::default();
+    for line in md_source.split_inclusive('\n') {
+        let content = line.trim_end_matches(['\n', '\r']);
+        // neither code blocks (fenced or indented) nor code spans are touched
+        if code_fences.in_code(content) || line.starts_with("    ") || line.starts_with('\t') {
+            out.push_str(line);
+        } else {
+            out.push_str(&expand_line(&re, line));
+        }
+    }
+    Cow::Owned(out)
+}
+
+/// Take the sizes out of the titles of the images, and add them (or the sizes which are
+/// known for their URLs) to their attributes. Attributes which were set explicitly win.
+pub(crate) fn apply_image_sizes<'a>(
+    root: &'a AstNode<'a>,
+    attributes: &mut NodeAttributes,
+    options: &RenderOptions,
+) {
+    for node in root.descendants() {
+        let mut data = node.data.borrow_mut();
+        let NodeValue::Image(ref mut nl) = data.value else {
+            continue;
+        };
+
+        let marked = nl
+            .title
+            .strip_prefix(SIZE_MARKER)
+            .and_then(|rest| rest.split_once(SIZE_MARKER))
+            .map(|(size, title)| {
+                let (width, height) = size.split_once('x').unwrap_or_default();
+                ((width.parse().ok(), height.parse().ok()), title.to_string())
+            });
+        let size = match marked {
+            Some((size, title)) => {
+                nl.title = title;
+                Some(size)
+            }
+            None => options
+                .image_sizes
+                .get(&nl.url)
+                .map(|&(width, height)| (Some(width), Some(height))),
+        };
+
+        let Some((width, height)) = size else {
+            continue;
+        };
+        let attrs = attributes.entry(node_key(node)).or_default();
+        for (name, value) in [("width", width), ("height", height)] {
+            if let Some(value) = value
+                && !attrs.iter().any(|(key, _)| key == name)
+            {
+                attrs.push((name.into(), value.to_string()));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::expand_image_sizes;
+
+    #[test]
+    fn test_expand_image_sizes() {
+        assert_eq!(
+            expand_image_sizes("![a](x.png =300x200) ![b](<y z.png> =x50 'Title')\n"),
+            "![a](x.png \"\u{1}300x200\u{1}\") ![b](<y z.png> '\u{1}x50\u{1}Title')\n"
+        );
+        assert_eq!(
+            expand_image_sizes("`![a](x.png =1x2)` ![b](y.png =3x4)"),
+            "`![a](x.png =1x2)` ![b](y.png \"\u{1}3x4\u{1}\")"
+        );
+
+        for md in [
+            "```\n![a](x.png =1x2)\n```\n",
+            "    ![a](x.png =1x2)\n",
+            "![a](x.png)",
+            "![a](x.png =big)",
+            "[a](x.png =1x2)",
+        ] {
+            assert_eq!(expand_image_sizes(md), md);
+        }
+    }
+}
@@ -0,0 +1,343 @@
+//! Incremental rendering for live previews, where only the blocks around an edit are parsed
+//! and rendered again instead of the whole document on each keystroke.
+
+use comrak::{Arena, Options};
+use regex_lite::Regex;
+use std::ops::Range;
+use std::sync::LazyLock;
+
+use crate::{
+    LinkRule, PreparedDocument, RenderError, comrak_options, format_html, html_state,
+    lint::line_starts,
+    options::{RenderOptions, RenderProfile},
+    prepare_document, render_document,
+};
+
+/// A link reference definition (or footnote definition), which can be used anywhere in the
+/// document
+static REFERENCE_DEFINITION_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(?m)^ {0,3}\[[^\]]+\]:").unwrap());
+
+/// A heading, whose ID depends on the headings before it
+static HEADING_RE: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"<h[1-6][\s>]").unwrap());
+
+/// A top-level block of a rendered document
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RenderedBlock {
+    /// Byte range of the block in the markdown source, including the blank lines after it
+    pub source: Range<usize>,
+    /// HTML of the block
+    pub html: String,
+}
+
+/// A document rendered block by block, see [`indico_markdown_rerender`]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct BlockRender {
+    /// The top-level blocks of the document, in document order
+    pub blocks: Vec<RenderedBlock>,
+    /// Indices of the blocks whose HTML was rendered again, all of them for a first render
+    pub changed: Vec<usize>,
+}
+
+impl BlockRender {
+    /// HTML of the whole document
+    pub fn html(&self) -> String {
+        self.blocks
+            .iter()
+            .map(|block| block.html.as_str())
+            .collect()
+    }
+}
+
+/// Check whether the options render things which depend on the whole document (e.g. the
+/// footnotes section, a table of contents or heading numbers), so that its blocks can't be
+/// rendered on their own
+fn needs_whole_document(render_options: &RenderOptions) -> bool {
+    render_options.document.is_some()
+        || render_options.footnotes.is_some()
+        || render_options.toc.is_some()
+        || render_options.heading_numbers.is_some()
+        || render_options.strip_title
+        || render_options.language_detector.is_some()
+        || render_options.front_matter
+        || render_options.abbreviations
+        || render_options.read_more
+        || render_options.interactive_tasks
+        || render_options.collapsible_sections
+        || !render_options.containers.is_empty()
+}
+
+/// Render the top-level blocks of a range of the source on their own
+fn render_range(
+    md_source: &str,
+    range: Range<usize>,
+    autolink_rules: &[LinkRule],
+    render_options: &RenderOptions,
+    options: &Options,
+) -> Result<Vec<RenderedBlock>, RenderError> {
+    let md_range = &md_source[range.clone()];
+    let arena = Arena::new();
+    let PreparedDocument {
+        root,
+        attributes,
+        task_lines,
+        custom_alerts,
+        ..
+    } = prepare_document(&arena, options, md_range, autolink_rules, render_options)?;
+    let mut state = html_state(render_options, attributes, task_lines, custom_alerts);
+    let line_starts = line_starts(md_range);
+
+    let mut blocks: Vec<RenderedBlock> = Vec::new();
+    for node in root.children() {
+        let line = node.data.borrow().sourcepos.start.line;
+        let html = format_html(node, options, render_options, &mut state)?;
+        match line.checked_sub(1).and_then(|line| line_starts.get(line)) {
+            Some(&start) => blocks.push(RenderedBlock {
+                source: range.start + start..range.end,
+                html,
+            }),
+            // nodes which were added while rendering belong to the block before them
+            None => match blocks.last_mut() {
+                Some(block) => block.html.push_str(&html),
+                None => blocks.push(RenderedBlock {
+                    source: range.clone(),
+                    html,
+                }),
+            },
+        }
+    }
+
+    // blank lines belong to the block before them, and the ones at the start to the first one
+    if let Some(first) = blocks.first_mut() {
+        first.source.start = range.start;
+    }
+    for i in 1..blocks.len() {
+        blocks[i - 1].source.end = blocks[i].source.start;
+    }
+    Ok(blocks)
+}
+
+/// Render a whole document, block by block if the options allow it and as a single block
+/// otherwise
+fn render_all(
+    md_source: &str,
+    autolink_rules: &[LinkRule],
+    render_options: &RenderOptions,
+    options: &Options,
+) -> Result<BlockRender, RenderError> {
+    let blocks = if needs_whole_document(render_options)
+        || REFERENCE_DEFINITION_RE.is_match(md_source)
+    {
+        let arena = Arena::new();
+        let output = render_document(&arena, options, md_source, autolink_rules, render_options)?;
+        vec![RenderedBlock {
+            source: 0..md_source.len(),
+            html: output.html,
+        }]
+    } else {
+        render_range(
+            md_source,
+            0..md_source.len(),
+            autolink_rules,
+            render_options,
+            options,
+        )?
+    };
+    Ok(BlockRender {
+        changed: (0..blocks.len()).collect(),
+        blocks,
+    })
+}
+
+/// Render a document block by block, so that it can be updated with
+/// [`indico_markdown_rerender`] after it is edited
+pub fn indico_markdown_render_blocks(
+    md_source: &str,
+    autolink_rules: &[LinkRule],
+    render_options: &RenderOptions,
+) -> Result<BlockRender, RenderError> {
+    render_all(
+        md_source,
+        autolink_rules,
+        render_options,
+        &comrak_options(render_options),
+    )
+}
+
+/// Update the render of a document after an edit, which replaced the `edit` byte range of
+/// `previous_source` to give `new_source`. Only the top-level blocks around the edit are
+/// parsed and rendered again (more of them if the edit changes the structure of the
+/// following ones, e.g. by opening a code block), and the other ones are kept as they were.
+///
+/// `previous` must be the render of `previous_source` with the same link rules and options.
+/// The whole document is rendered again when it contains link reference definitions, when
+/// the edit is around a heading (whose ID depends on the other headings), and with options
+/// which depend on the whole document such as footnotes or a table of contents.
+pub fn indico_markdown_rerender(
+    previous_source: &str,
+    previous: &BlockRender,
+    new_source: &str,
+    edit: Range<usize>,
+    autolink_rules: &[LinkRule],
+    render_options: &RenderOptions,
+) -> Result<BlockRender, RenderError> {
+    let options = comrak_options(render_options);
+    let blocks = &previous.blocks;
+    let inserted_len = (new_source.len() + edit.len()).checked_sub(previous_source.len());
+    let (Some(inserted_len), Some(last_block)) = (inserted_len, blocks.last()) else {
+        return render_all(new_source, autolink_rules, render_options, &options);
+    };
+    if edit.start > edit.end
+        || edit.end > previous_source.len()
+        || last_block.source.end != previous_source.len()
+        || !new_source.is_char_boundary(edit.start)
+        || !new_source.is_char_boundary(edit.start + inserted_len)
+        || needs_whole_document(render_options)
+        || REFERENCE_DEFINITION_RE.is_match(new_source)
+    {
+        return render_all(new_source, autolink_rules, render_options, &options);
+    }
+    // offsets after the edit move by the difference of the lengths
+    let shift = |offset: usize| offset + new_source.len() - previous_source.len();
+    let same_start = |old: &RenderedBlock, new: &RenderedBlock| {
+        old.source.start + new_source.len() == new.source.start + previous_source.len()
+    };
+    let block_at = |offset: usize| {
+        blocks
+            .iter()
+            .rposition(|block| block.source.start <= offset)
+            .unwrap_or(0)
+    };
+
+    // the blocks which contain the edit, and the ones around them since they may be merged
+    // with them (e.g. a paragraph becoming a setext heading)
+    let mut first = block_at(edit.start).saturating_sub(1);
+    let mut last = (block_at(edit.end) + 1).min(blocks.len() - 1);
+    let new_blocks = loop {
+        let range = blocks[first].source.start..shift(blocks[last].source.end);
+        let new_blocks = render_range(new_source, range, autolink_rules, render_options, &options)?;
+        // the blocks around the ones which were rendered again are only kept if the first and
+        // last ones are still the same, otherwise the edit may have changed them as well
+        let same_first = first == 0
+            || new_blocks.first().is_some_and(|block| {
+                block.source.end == blocks[first].source.end && block.html == blocks[first].html
+            });
+        let same_last = last == blocks.len() - 1
+            || new_blocks.last().is_some_and(|block| {
+                same_start(&blocks[last], block) && block.html == blocks[last].html
+            });
+        if same_first && same_last {
+            break new_blocks;
+        }
+        if !same_first {
+            first -= 1;
+        }
+        if !same_last {
+            last += 1;
+        }
+    };
+
+    if render_options.profile == RenderProfile::Default
+        && blocks[first..=last]
+            .iter()
+            .chain(&new_blocks)
+            .any(|block| HEADING_RE.is_match(&block.html))
+    {
+        return render_all(new_source, autolink_rules, render_options, &options);
+    }
+
+    // the first and last blocks are only rendered again for context, and usually unchanged
+    let unchanged_before = blocks[first..=last]
+        .iter()
+        .zip(&new_blocks)
+        .take_while(|(old, new)| old == new)
+        .count();
+    let unchanged_after = blocks[first..=last]
+        .iter()
+        .rev()
+        .zip(new_blocks.iter().rev())
+        .take(new_blocks.len() - unchanged_before)
+        .take_while(|(old, new)| old.html == new.html && same_start(old, new))
+        .count();
+    let changed = first + unchanged_before..first + new_blocks.len() - unchanged_after;
+
+    let after = blocks[last + 1..].iter().map(|block| RenderedBlock {
+        source: shift(block.source.start)..shift(block.source.end),
+        html: block.html.clone(),
+    });
+    Ok(BlockRender {
+        blocks: blocks[..first]
+            .iter()
+            .cloned()
+            .chain(new_blocks)
+            .chain(after)
+            .collect(),
+        changed: changed.collect(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::ops::Range;
+
+    use super::{BlockRender, indico_markdown_render_blocks, indico_markdown_rerender};
+    use crate::{LinkRule, indico_markdown_to_html_with_options, options::RenderOptions};
+
+    fn rules() -> Vec<LinkRule> {
+        vec![LinkRule::new(r"#(\d+)", "/tickets/{1}").unwrap()]
+    }
+
+    /// Replace a range of a document, and check that the incremental render is the same as a
+    /// full one
+    fn edit(
+        source: &str,
+        render: &BlockRender,
+        range: Range<usize>,
+        text: &str,
+    ) -> (String, BlockRender) {
+        let options = RenderOptions::default();
+        let new_source = format!("{}{text}{}", &source[..range.start], &source[range.end..]);
+        let new_render =
+            indico_markdown_rerender(source, render, &new_source, range, &rules(), &options)
+                .unwrap();
+        assert_eq!(
+            new_render.html(),
+            indico_markdown_to_html_with_options(&new_source, &rules(), &options).unwrap()
+        );
+        let full = indico_markdown_render_blocks(&new_source, &rules(), &options).unwrap();
+        assert_eq!(new_render.blocks, full.blocks);
+        (new_source, new_render)
+    }
+
+    #[test]
+    fn test_rerender() {
+        let source = "Intro\n\nFirst #1\n\n- a\n- b\n\nMiddle\n\n> quote\n\nLast\n";
+        let render =
+            indico_markdown_render_blocks(source, &rules(), &RenderOptions::default()).unwrap();
+        assert_eq!(render.blocks.len(), 6);
+        assert_eq!(render.blocks[1].source, 7..17);
+        assert_eq!(render.changed, [0, 1, 2, 3, 4, 5]);
+
+        // typing in a paragraph only changes that paragraph
+        let (source, render) = edit(source, &render, 15..15, "2");
+        assert_eq!(render.changed, [1]);
+        assert_eq!(
+            render.blocks[1].html,
+            "<p>First <a href=\"/tickets/12\">#12</a></p>\n"
+        );
+
+        // splitting a paragraph in two
+        let (source, render) = edit(&source, &render, 30..30, "\n\n");
+        assert_eq!(render.blocks.len(), 7);
+        assert_eq!(render.changed, [3, 4]);
+
+        // an unclosed code block changes everything after it
+        let (source, render) = edit(&source, &render, 0..0, "```\n");
+        assert_eq!(render.blocks.len(), 1);
+        assert_eq!(render.changed, [0]);
+
+        // and closing it again
+        let (_, render) = edit(&source, &render, 0..4, "");
+        assert_eq!(render.blocks.len(), 7);
+    }
+}
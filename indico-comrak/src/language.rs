@@ -0,0 +1,62 @@
+//! Detection of the language of documents, so that screen readers can pronounce them
+//! correctly.
+
+use comrak::nodes::{AstNode, NodeValue};
+
+use crate::options::LanguageDetector;
+
+/// Get the text of a document (without code) to detect its language from
+fn document_text<'a>(root: &'a AstNode<'a>) -> String {
+    let mut text = String::new();
+    for node in root.descendants() {
+        match node.data.borrow().value {
+            NodeValue::Text(ref t) => text.push_str(t),
+            NodeValue::SoftBreak | NodeValue::LineBreak => text.push(' '),
+            ref value if value.block() => text.push('\n'),
+            _ => {}
+        }
+    }
+    text
+}
+
+/// Check whether a string is a well-formed language tag (e.g. `fr` or `pt-BR`)
+fn is_language_tag(tag: &str) -> bool {
+    let mut subtags = tag.split('-');
+    subtags.next().is_some_and(|language| {
+        (2..=8).contains(&language.len()) && language.chars().all(|c| c.is_ascii_alphabetic())
+    }) && subtags.all(|subtag| {
+        (1..=8).contains(&subtag.len()) && subtag.chars().all(|c| c.is_ascii_alphanumeric())
+    })
+}
+
+/// Detect the language of a document. Tags which aren't well-formed are ignored.
+pub(crate) fn detect_language<'a>(
+    root: &'a AstNode<'a>,
+    detector: &LanguageDetector,
+) -> Option<String> {
+    let text = document_text(root);
+    if text.trim().is_empty() {
+        return None;
+    }
+    (detector.0)(&text).filter(|tag| is_language_tag(tag))
+}
+
+/// Wrap a rendered fragment in a `<div>` declaring its language
+pub(crate) fn wrap_fragment(html: &str, lang: &str) -> String {
+    format!("<div lang=\"{lang}\">\n{html}</div>\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::is_language_tag;
+
+    #[test]
+    fn test_is_language_tag() {
+        for tag in ["fr", "pt-BR", "zh-Hant-TW", "gsw"] {
+            assert!(is_language_tag(tag), "{tag}");
+        }
+        for tag in ["", "f", "fr-", "fr_FR", "1a", "fr\" onclick=\"x"] {
+            assert!(!is_language_tag(tag), "{tag}");
+        }
+    }
+}
@@ -0,0 +1,197 @@
+//! Rendering of markdown into LaTeX, used e.g. for books of abstracts.
+//!
+//! Only a fragment is produced, which is meant to be included in a larger document. It relies
+//! on the `hyperref`, `ulem` (for strikethrough) and `soul` (for highlighting) packages.
+
+use comrak::{
+    Arena,
+    arena_tree::NodeEdge,
+    nodes::{AstNode, ListType, NodeValue, TableAlignment},
+    parse_document,
+};
+
+use crate::{LinkRule, add_links, in_tight_list, indico_options};
+
+/// Escape the characters which have a special meaning in LaTeX
+fn escape_latex(out: &mut String, text: &str) {
+    for c in text.chars() {
+        match c {
+            '\\' => out.push_str(r"\textbackslash{}"),
+            '~' => out.push_str(r"\textasciitilde{}"),
+            '^' => out.push_str(r"\textasciicircum{}"),
+            '&' | '%' | '$' | '#' | '_' | '{' | '}' => {
+                out.push('\\');
+                out.push(c);
+            }
+            _ => out.push(c),
+        }
+    }
+}
+
+/// Escape a URL so that it can be used as the first argument of `\href`
+fn escape_url(out: &mut String, url: &str) {
+    for c in url.chars() {
+        match c {
+            '\\' | '#' | '%' | '{' | '}' => {
+                out.push('\\');
+                out.push(c);
+            }
+            _ => out.push(c),
+        }
+    }
+}
+
+fn enter<'a>(out: &mut String, node: &'a AstNode<'a>) {
+    match node.data.borrow().value {
+        NodeValue::Heading(ref nh) => out.push_str(match nh.level {
+            1 => r"\section*{",
+            2 => r"\subsection*{",
+            3 => r"\subsubsection*{",
+            4 => r"\paragraph*{",
+            _ => r"\subparagraph*{",
+        }),
+        NodeValue::Text(ref t) => escape_latex(out, t),
+        NodeValue::Code(ref nc) => {
+            out.push_str(r"\texttt{");
+            escape_latex(out, &nc.literal);
+            out.push('}');
+        }
+        NodeValue::CodeBlock(ref ncb) => {
+            out.push_str("\\begin{verbatim}\n");
+            out.push_str(&ncb.literal);
+            if !ncb.literal.ends_with('\n') {
+                out.push('\n');
+            }
+            out.push_str("\\end{verbatim}\n\n");
+        }
+        NodeValue::Math(ref nm) => {
+            let (open, close) = if nm.display_math {
+                (r"\[", r"\]")
+            } else {
+                (r"\(", r"\)")
+            };
+            out.push_str(open);
+            out.push_str(&nm.literal);
+            out.push_str(close);
+        }
+        NodeValue::Emph => out.push_str(r"\emph{"),
+        NodeValue::Strong => out.push_str(r"\textbf{"),
+        NodeValue::Strikethrough => out.push_str(r"\sout{"),
+        NodeValue::Underline => out.push_str(r"\underline{"),
+        NodeValue::Highlight => out.push_str(r"\hl{"),
+        NodeValue::Superscript => out.push_str(r"\textsuperscript{"),
+        NodeValue::Link(ref nl) => {
+            out.push_str(r"\href{");
+            escape_url(out, &nl.url);
+            out.push_str("}{");
+        }
+        NodeValue::List(ref nl) => out.push_str(match nl.list_type {
+            ListType::Bullet => "\\begin{itemize}\n",
+            ListType::Ordered => "\\begin{enumerate}\n",
+        }),
+        NodeValue::Item(..) => out.push_str(r"\item "),
+        NodeValue::BlockQuote | NodeValue::Alert(..) => out.push_str("\\begin{quote}\n"),
+        NodeValue::Table(ref nt) => {
+            out.push_str("\\begin{tabular}{");
+            for alignment in &nt.alignments {
+                out.push(match alignment {
+                    TableAlignment::Center => 'c',
+                    TableAlignment::Right => 'r',
+                    TableAlignment::Left | TableAlignment::None => 'l',
+                });
+            }
+            out.push_str("}\n");
+        }
+        NodeValue::TableCell => {
+            if node.previous_sibling().is_some() {
+                out.push_str(" & ");
+            }
+        }
+        NodeValue::SoftBreak => out.push('\n'),
+        NodeValue::LineBreak => out.push_str("\\\\\n"),
+        NodeValue::ThematicBreak => out.push_str("\\noindent\\rule{\\linewidth}{0.4pt}\n\n"),
+        NodeValue::HtmlInline(ref html) => {
+            if html.to_lowercase().starts_with("<br") {
+                out.push_str("\\\\\n");
+            }
+        }
+        _ => {}
+    }
+}
+
+fn leave<'a>(out: &mut String, node: &'a AstNode<'a>) {
+    match node.data.borrow().value {
+        NodeValue::Paragraph => out.push_str(if in_tight_list(node) { "\n" } else { "\n\n" }),
+        NodeValue::Heading(..) => out.push_str("}\n\n"),
+        NodeValue::Emph
+        | NodeValue::Strong
+        | NodeValue::Strikethrough
+        | NodeValue::Underline
+        | NodeValue::Highlight
+        | NodeValue::Superscript
+        | NodeValue::Link(..) => out.push('}'),
+        NodeValue::List(ref nl) => out.push_str(match nl.list_type {
+            ListType::Bullet => "\\end{itemize}\n\n",
+            ListType::Ordered => "\\end{enumerate}\n\n",
+        }),
+        NodeValue::BlockQuote | NodeValue::Alert(..) => out.push_str("\\end{quote}\n\n"),
+        NodeValue::TableRow(header) => {
+            out.push_str(" \\\\\n");
+            if header {
+                out.push_str("\\hline\n");
+            }
+        }
+        NodeValue::Table(..) => out.push_str("\\end{tabular}\n\n"),
+        _ => {}
+    }
+}
+
+/// Convert markdown to a LaTeX fragment, applying the given link rules.
+///
+/// Special characters are escaped, while math is passed through verbatim so that it can be
+/// typeset by LaTeX itself.
+pub fn indico_markdown_to_latex(md_source: &str, autolink_rules: &[LinkRule]) -> String {
+    let options = indico_options();
+    let arena = Arena::new();
+    let mut root = parse_document(&arena, md_source, &options);
+
+    add_links(&mut root, &arena, autolink_rules);
+
+    let mut out = String::new();
+    for edge in root.traverse() {
+        match edge {
+            NodeEdge::Start(node) => enter(&mut out, node),
+            NodeEdge::End(node) => leave(&mut out, node),
+        }
+    }
+
+    out.trim_end().to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::indico_markdown_to_latex;
+    use crate::LinkRule;
+
+    #[test]
+    fn test_latex() {
+        assert_eq!(
+            indico_markdown_to_latex("## Costs & *benefits*\n\n100% of $x^2$ is **{fine}**", &[]),
+            "\\subsection*{Costs \\& \\emph{benefits}}\n\n100\\% of \\(x^2\\) is \\textbf{\\{fine\\}}"
+        );
+
+        assert_eq!(
+            indico_markdown_to_latex(
+                "* see TKT1234567\n* `a_b`",
+                &[LinkRule::new(r"TKT(\d+)", "https://tkt.sys/#{1}").unwrap()]
+            ),
+            "\\begin{itemize}\n\\item see \\href{https://tkt.sys/\\#1234567}{TKT1234567}\n\
+             \\item \\texttt{a\\_b}\n\\end{itemize}"
+        );
+
+        assert_eq!(
+            indico_markdown_to_latex("| a | b |\n|---|--:|\n| 1 | 2 |", &[]),
+            "\\begin{tabular}{lr}\na & b \\\\\n\\hline\n1 & 2 \\\\\n\\end{tabular}"
+        );
+    }
+}
@@ -11,16 +11,94 @@
 //! generate the HTML output.
 
 use comrak::{
-    Arena, Node, Options, create_formatter,
-    html::ChildRendering,
-    nodes::{ListDelimType, ListType, NodeLink, NodeValue},
+    Arena, Node, Options,
+    nodes::{AstNode, LineColumn, NodeLink, NodeValue, Sourcepos},
     parse_document,
 };
 use core::fmt;
 use regex_lite::Regex;
-use std::fmt::{Display, Formatter, Write};
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::fmt::{Display, Formatter};
+use std::sync::Arc;
 
-#[derive(Debug)]
+mod abbreviations;
+mod alerts;
+mod ast;
+mod attributes;
+mod audit;
+mod cache;
+mod containers;
+mod embeds;
+mod extract;
+mod front_matter;
+mod headings;
+mod html;
+mod html2md;
+mod images;
+mod incremental;
+mod language;
+mod latex;
+mod limits;
+mod links;
+mod lint;
+mod math;
+mod odt;
+mod options;
+mod placeholders;
+mod plain;
+mod prefilter;
+mod renderer;
+mod sanitize;
+mod stats;
+mod tasks;
+mod teaser;
+mod toc;
+mod unstyled;
+mod warnings;
+
+pub use ast::indico_markdown_to_ast_json;
+pub use audit::{AuditIssue, AuditIssueKind, indico_markdown_audit};
+pub use cache::RenderCache;
+pub use extract::{
+    DocumentImage, DocumentLink, Heading, LinkRuleMatch, OpenGraph, SourceRange,
+    indico_markdown_extract_title, indico_markdown_first_image, indico_markdown_images,
+    indico_markdown_links, indico_markdown_open_graph, indico_markdown_outline,
+    indico_markdown_rule_matches,
+};
+pub use front_matter::{FrontMatter, FrontMatterFormat};
+pub use html2md::html_to_indico_markdown;
+pub use incremental::{
+    BlockRender, RenderedBlock, indico_markdown_render_blocks, indico_markdown_rerender,
+};
+pub use latex::indico_markdown_to_latex;
+pub use links::{BrokenLink, indico_markdown_broken_links};
+pub use lint::{LintDiagnostic, LintKind, indico_markdown_lint};
+pub use odt::indico_markdown_to_odt;
+#[cfg(feature = "emoji")]
+pub use options::EmojiStyle;
+pub use options::{
+    AlertOptions, AttributeOptions, CodeBlockElement, CodeBlockOptions, DocumentOptions,
+    EmbedOptions, EmbedProvider, EmbedStyle, Extensions, FootnoteOptions, HeadingNumbering,
+    HtmlFormat, LanguageDetector, LinkOptions, MathBackend, MathOptions, MathOutput, MathWrapper,
+    OffsetMode, QuoteStyle, RawHtml, RenderLimits, RenderOptions, RenderProfile, ResponsiveImage,
+    ResponsiveImages, SpoilerOptions, TableOptions, TocOptions, WikiLinkResolver, WikiLinks,
+};
+pub use plain::{
+    LinkStyle, PlainTextOptions, indico_markdown_excerpt, indico_markdown_to_plain_text,
+    indico_markdown_to_plain_text_with_rules, indico_markdown_truncate_plain,
+};
+pub use renderer::IndicoRenderer;
+pub use stats::{DocStats, StatsOptions, indico_markdown_stats};
+pub use tasks::indico_markdown_toggle_task;
+pub use unstyled::{
+    UnstyledHeadings, UnstyledInlineHtml, UnstyledListOptions, UnstyledOptions, UnstyledOutput,
+    UnstyledTables, indico_markdown_to_unstyled_html,
+    indico_markdown_to_unstyled_html_with_options,
+};
+pub use warnings::{RenderWarning, RenderWarningKind};
+
+#[derive(Debug, Clone)]
 /// Represents a rule for matching links.
 ///
 /// The `LinkRule` struct contains a regular expression and a URL string.
@@ -31,193 +109,295 @@ use std::fmt::{Display, Formatter, Write};
 ///
 /// - `re`: A [`Regex`] instance that defines the pattern for matching links.
 /// - `url`: A [`String`] that holds the URL associated with the link rule.
+/// - `resolver`: If set, a [`LinkResolver`] which is used instead of `url`.
 pub struct LinkRule {
     re: Regex,
     url: String,
+    resolver: Option<LinkResolver>,
+    /// Literal which any match starts with, used to skip the rule on texts without it
+    literal: Option<String>,
+}
+
+/// A function which returns the URL of a match of a link rule, given its groups (the whole
+/// match first, and `None` for the groups which did not participate in it), or `None` if the
+/// match shouldn't be turned into a link
+#[derive(Clone)]
+pub struct LinkResolver(pub Arc<dyn Fn(&[Option<&str>]) -> Option<String> + Send + Sync>);
+
+impl fmt::Debug for LinkResolver {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str("LinkResolver(..)")
+    }
 }
 
 #[derive(Debug)]
-/// Error type that occurs when constructing link rules with invalid regular expressions.
-/// Wraps the underlying [`regex_lite::Error`].
-pub struct LinkRuleError(regex_lite::Error);
+/// Error type that occurs when constructing link rules with invalid regular expressions
+/// or URL templates.
+pub enum LinkRuleError {
+    /// The regular expression could not be compiled, wraps the underlying [`regex_lite::Error`]
+    Regex(regex_lite::Error),
+    /// The URL template contains a malformed or unknown placeholder
+    Template(String),
+}
 
 impl LinkRule {
     pub fn new(regex: &str, url: &str) -> Result<Self, LinkRuleError> {
+        let re = Regex::new(regex).map_err(LinkRuleError::Regex)?;
+
+        for n in url_template_groups(url).map_err(LinkRuleError::Template)? {
+            if n >= re.captures_len() {
+                return Err(LinkRuleError::Template(format!(
+                    "unknown placeholder `{{{n}}}`, the expression only has {} group(s)",
+                    re.captures_len() - 1
+                )));
+            }
+        }
+
         Ok(Self {
-            re: Regex::new(regex).map_err(LinkRuleError)?,
+            re,
             url: url.into(),
+            resolver: None,
+            literal: prefilter::required_literal(regex),
+        })
+    }
+
+    /// Creates a rule whose URLs are returned by a function, e.g. to look up the matches in a
+    /// database. The matches for which it returns `None` are not turned into links, and may be
+    /// matched by other rules instead.
+    pub fn with_resolver(regex: &str, resolver: LinkResolver) -> Result<Self, LinkRuleError> {
+        Ok(Self {
+            re: Regex::new(regex).map_err(LinkRuleError::Regex)?,
+            url: String::new(),
+            resolver: Some(resolver),
+            literal: prefilter::required_literal(regex),
         })
     }
+
+    /// URL of a match of the rule, given its groups
+    fn match_url(&self, groups: &[Option<&str>]) -> Option<String> {
+        match &self.resolver {
+            Some(LinkResolver(resolve)) => resolve(groups),
+            None => Some(substitute_url(&self.url, groups)),
+        }
+    }
 }
 
 impl Display for LinkRuleError {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        f.write_str(&self.0.to_string())
+        match self {
+            LinkRuleError::Regex(e) => f.write_str(&e.to_string()),
+            LinkRuleError::Template(msg) => write!(f, "invalid URL template: {msg}"),
+        }
     }
 }
 
-/// Check whether any ancestor of the given node is a link
-fn has_link_ancestor(node: Node<'_>) -> bool {
-    if let NodeValue::Link(_) = node.data.borrow().value {
-        true
-    } else {
-        match node.parent() {
-            None => false,
-            Some(n) => has_link_ancestor(n),
-        }
-    }
+#[derive(Debug)]
+/// Error type that occurs when rendering a document
+pub enum RenderError {
+    /// The HTML could not be written, wraps the underlying [`fmt::Error`]
+    Format(fmt::Error),
+    /// The document has more nodes than [`RenderLimits::max_nodes`], which is wrapped
+    TooManyNodes(usize),
+    /// The text of the document is longer than [`RenderLimits::max_text_len`], which is
+    /// wrapped
+    TextTooLong(usize),
 }
 
-/// Substitute `{1},{2}...{N}` sequences in a given URL, taking into account the
-/// groups which are passed
-fn substitute_url(url: &str, groups: &[Option<String>]) -> String {
-    let mut res: String = url.into();
-    for (n, group) in groups
-        .iter()
-        .enumerate()
-        .filter_map(|(n, g)| g.as_ref().map(|g| (n, g)))
-    {
-        let marker = &format!("{{{}}}", n);
-        res = res.replace(marker, group);
+impl From<fmt::Error> for RenderError {
+    fn from(e: fmt::Error) -> Self {
+        RenderError::Format(e)
     }
-    res
 }
 
-/// A formatter which only allows paragraphs and breaks, and ignores everything else.
-/// The context's user data is a Vec we will use as a stack of lists.
-fn plain_text_formatter<'a>(
-    context: &mut comrak::html::Context<Vec<usize>>,
-    node: &'a comrak::nodes::AstNode<'a>,
-    entering: bool,
-) -> Result<comrak::html::ChildRendering, std::fmt::Error> {
-    match node.data().value {
-        NodeValue::Code(ref nc) => {
-            if entering {
-                context.escape(&nc.literal)?;
+impl Display for RenderError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RenderError::Format(e) => f.write_str(&e.to_string()),
+            RenderError::TooManyNodes(max) => {
+                write!(f, "the document is too large (more than {max} nodes)")
             }
-            Ok(ChildRendering::HTML)
-        }
-        NodeValue::CodeBlock(ref nc) => {
-            if entering {
-                context.write_str("\n")?;
-                context.escape(&nc.literal)?;
-                context.write_str("\n")?;
+            RenderError::TextTooLong(max) => {
+                write!(
+                    f,
+                    "the document is too large (more than {max} bytes of text)"
+                )
             }
-            Ok(ChildRendering::HTML)
         }
-        // Inline <p>...</p> and <br> are taken into account
-        NodeValue::HtmlInline(ref html) => {
-            let br_re = Regex::new(r"<\s*br\s*\/?>").unwrap();
-            let p_open_re = Regex::new(r"<\s*p(?:\s[^>]*)?>").unwrap();
-
-            let html = html.to_lowercase();
-            if entering {
-                if br_re.is_match(&html) {
-                    context.write_str("<br />")?;
-                } else if p_open_re.is_match(&html) {
-                    context.write_str("<p>")?;
-                } else if html == "</p>" {
-                    context.write_str("</p>")?;
+    }
+}
+
+/// Check whether the given node or any of its ancestors is a link
+fn has_link_ancestor(node: Node<'_>) -> bool {
+    node.ancestors().any(|n| {
+        matches!(
+            n.data.borrow().value,
+            NodeValue::Link(_) | NodeValue::WikiLink(_)
+        )
+    })
+}
+
+/// Validate a URL template, returning the capture groups its `{N}` placeholders refer to.
+/// `{{` and `}}` are escapes for literal braces; anything else between braces is an error.
+fn url_template_groups(url: &str) -> Result<Vec<usize>, String> {
+    let mut groups = Vec::new();
+    let mut chars = url.char_indices().peekable();
+
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '{' | '}' if chars.peek().map(|(_, next)| *next) == Some(c) => {
+                // escaped brace
+                chars.next();
+            }
+            '{' => {
+                let end = url[i..]
+                    .find('}')
+                    .map(|e| i + e)
+                    .ok_or_else(|| format!("unclosed placeholder at position {i}"))?;
+                let name = &url[i + 1..end];
+                if name.is_empty() || !name.bytes().all(|b| b.is_ascii_digit()) {
+                    return Err(format!("unknown placeholder `{{{name}}}`"));
                 }
+                let n = name
+                    .parse()
+                    .map_err(|_| format!("unknown placeholder `{{{name}}}`"))?;
+
+                groups.push(n);
+                while chars.next_if(|(j, _)| *j <= end).is_some() {}
             }
-            Ok(ChildRendering::HTML)
+            '}' => return Err(format!("unmatched `}}` at position {i}")),
+            _ => {}
         }
-        // Text, paragraphs and breaks stay the same
-        NodeValue::Text(..)
-        | NodeValue::Paragraph
-        | NodeValue::SoftBreak
-        | NodeValue::LineBreak => comrak::html::format_node_default(context, node, entering),
-        // Text decoration is ignored
-        NodeValue::Strong
-        | NodeValue::Emph
-        | NodeValue::Strikethrough
-        | NodeValue::Highlight
-        | NodeValue::Superscript
-        | NodeValue::BlockQuote => Ok(ChildRendering::HTML),
-        // Lists are rendered in plain text and formatting is handled through a stack
-        NodeValue::List(..) => {
-            context.write_str("\n")?;
-            if entering {
-                context.user.push(1);
-            } else {
-                context.user.pop();
-            }
-            Ok(ChildRendering::HTML)
+    }
+    Ok(groups)
+}
+
+/// Collect the textual content of a node and its descendants, ignoring any formatting
+fn collect_text<'a>(node: &'a AstNode<'a>) -> String {
+    let mut text = String::new();
+    for n in node.descendants() {
+        match n.data.borrow().value {
+            NodeValue::Text(ref t) => text.push_str(t),
+            NodeValue::Code(ref nc) => text.push_str(&nc.literal),
+            NodeValue::Math(ref nm) => text.push_str(&nm.literal),
+            NodeValue::SoftBreak | NodeValue::LineBreak => text.push(' '),
+            _ => {}
         }
-        NodeValue::Item(lst) => {
-            if entering {
-                // add indentation based on stack length
-                context.write_str(&" ".repeat(2 * context.user.len()))?;
-                let item_spec = match lst.list_type {
-                    ListType::Bullet => (lst.bullet_char as char).to_string(),
-                    ListType::Ordered => {
-                        format!(
-                            "{}{}",
-                            context.user.last().unwrap(),
-                            match lst.delimiter {
-                                ListDelimType::Period => ".",
-                                ListDelimType::Paren => ")",
-                            }
-                        )
-                    }
-                };
-                context.write_str(&item_spec)?;
-                context.write_str(&" ".repeat(lst.padding - item_spec.len()))?;
-            } else {
-                context.write_char('\n')?;
-                if let Some(v) = context.user.last_mut() {
-                    *v += 1;
-                };
-            }
-            Ok(ChildRendering::HTML)
+    }
+    text
+}
+
+/// Check whether a node (e.g. a paragraph) lives inside a tight list
+fn in_tight_list<'a>(node: &'a AstNode<'a>) -> bool {
+    node.parent()
+        .and_then(|item| item.parent())
+        .is_some_and(|list| matches!(list.data.borrow().value, NodeValue::List(ref nl) if nl.tight))
+}
+
+/// Turn the raw HTML of a document into text, so that it's shown as it was written
+fn escape_raw_html<'a>(root: &'a AstNode<'a>, arena: &'a Arena<'a>) {
+    for node in root.descendants() {
+        let (text, block) = match node.data.borrow().value {
+            NodeValue::HtmlInline(ref html) => (html.clone(), false),
+            NodeValue::HtmlBlock(ref nhb) => (nhb.literal.trim_end_matches('\n').to_string(), true),
+            _ => continue,
+        };
+        // the text of blocks becomes the child of a paragraph, which is only visited after it
+        if block {
+            node.data.borrow_mut().value = NodeValue::Paragraph;
+            node.append(arena.alloc(NodeValue::Text(text.into()).into()));
+        } else {
+            node.data.borrow_mut().value = NodeValue::Text(text.into());
         }
-        _ => Ok(ChildRendering::HTML),
-    }
-}
-
-// A formatter which adds `target="_blank"` to all links
-create_formatter!(
-    TargetBlankFormatter, {
-        NodeValue::Link(ref nl) => |context, entering| {
-            if entering {
-                context.write_str(&format!("<a href=\"{}\" {}target=\"_blank\">", nl.url, if nl.title.is_empty() {
-                    ""
-                } else {
-                    &format!("title=\"{}\" ", nl.title)
-                }))?;
-            } else {
-                context.write_str("</a>")?;
-            }
+    }
+}
+
+/// Replace a node with a sequence of new nodes, created from the given values
+fn replace_with_nodes<'a>(node: &'a AstNode<'a>, arena: &'a Arena<'a>, values: Vec<NodeValue>) {
+    for value in values {
+        node.insert_before(arena.alloc(value.into()));
+    }
+    node.detach();
+}
+
+/// Substitute `{1},{2}...{N}` sequences in a given URL, taking into account the
+/// groups which are passed. Groups which did not participate in the match are replaced
+/// with an empty string.
+///
+/// The template is scanned once, writing into a single buffer. It must have been validated
+/// by [`url_template_groups`] (which [`LinkRule::new`] does).
+fn substitute_url(url: &str, groups: &[Option<&str>]) -> String {
+    let groups_len: usize = groups.iter().flatten().map(|group| group.len()).sum();
+    let mut res = String::with_capacity(url.len() + groups_len);
+    let mut rest = url;
+
+    while let Some(i) = rest.find(['{', '}']) {
+        res.push_str(&rest[..i]);
+        let (brace, after) = rest[i..].split_at(1);
+        if after.starts_with(brace) || brace == "}" {
+            // escaped brace
+            res.push_str(brace);
+            rest = after.strip_prefix(brace).unwrap_or(after);
+            continue;
+        }
+        let end = after.find('}').unwrap_or(after.len());
+        if let Ok(n) = after[..end].parse::<usize>()
+            && let Some(Some(group)) = groups.get(n)
+        {
+            res.push_str(group);
         }
+        rest = after.get(end + 1..).unwrap_or_default();
     }
-);
+    res.push_str(rest);
+    res
+}
 
-/// Manipulate the AST in order to find text nodes which match the rules, and split them
-/// into the corresponding links.
-fn add_links<'t>(root: &mut Node<'t>, arena: &'t Arena<'t>, link_rules: &[LinkRule]) {
-    let mut to_process = Vec::new();
+/// Maximum number of rule matches which will be turned into links within a single text node
+const MAX_MATCHES_PER_NODE: usize = 1000;
+
+/// A match of a link rule in a text node: its byte range in the text, the index of the rule,
+/// the byte ranges of the captured groups and the URL of the link
+type RuleMatch = ((usize, usize), usize, Vec<Option<(usize, usize)>>, String);
+
+/// Find the text nodes (outside of links) which match the rules, along with the matches (which
+/// don't overlap) and whether there were more matches than the ones kept
+fn find_rule_matches<'t>(
+    root: Node<'t>,
+    link_rules: &[LinkRule],
+) -> Vec<(Node<'t>, Vec<RuleMatch>, bool)> {
+    let mut found = Vec::new();
     let mut in_html_link = false;
+    let prefilter = prefilter::RulePrefilter::new(link_rules);
 
     for node in root.descendants() {
-        let mut n = node.data.borrow_mut();
+        let n = node.data.borrow();
 
-        match &mut n.value {
+        match &n.value {
             // it's a text node, so it's worth a look
             NodeValue::Text(t) => {
-                let mut matches = Vec::new();
+                let mut candidates = Vec::new();
+                let mut capped = false;
 
-                if in_html_link {
-                    // we're in a HTML link, so we shouldn't be doing any changes here
+                if in_html_link || has_link_ancestor(node) {
+                    // we're in a link, so we shouldn't be doing any changes here
                     continue;
                 }
 
-                // check if any of the rules match
-                for LinkRule { re, url } in link_rules {
+                // check if any of the rules which may match do
+                let may_match = prefilter.candidates(t);
+                for (rule_index, rule) in link_rules.iter().enumerate() {
+                    if !may_match[rule_index] {
+                        continue;
+                    }
                     // go over the captured parts of the text
-                    for capture in re.captures_iter(t) {
+                    for (count, capture) in rule.re.captures_iter(t).enumerate() {
+                        if count >= MAX_MATCHES_PER_NODE {
+                            capped = true;
+                            break;
+                        }
+
                         let groups: Vec<_> = capture
                             .iter()
-                            .map(|c| c.map(|m| m.as_str().to_string()))
+                            .map(|c| c.map(|m| (m.start(), m.end())))
                             .collect();
                         let start = capture
                             .iter()
@@ -230,12 +410,44 @@ fn add_links<'t>(root: &mut Node<'t>, arena: &'t Arena<'t>, link_rules: &[LinkRu
                             .max()
                             .unwrap();
 
-                        matches.push(((start, end), url, groups))
+                        // zero-length matches (e.g. `a*`) would produce empty links
+                        if start == end {
+                            continue;
+                        }
+
+                        candidates.push(((start, end), rule_index, groups))
+                    }
+                }
+
+                // matches from different rules may come in any order and overlap,
+                // keep the earliest one in each overlapping run. The URLs are only resolved
+                // for the matches which are kept, and resolvers may skip a match, leaving its
+                // text to the next overlapping one.
+                candidates.sort_by_key(|((start, end), _, _)| (*start, usize::MAX - *end));
+                let mut matches = Vec::new();
+                let mut last_end = 0;
+                for ((start, end), rule_index, groups) in candidates {
+                    if start < last_end {
+                        continue;
+                    }
+                    if matches.len() >= MAX_MATCHES_PER_NODE {
+                        capped = true;
+                        break;
                     }
+                    let texts: Vec<_> = groups
+                        .iter()
+                        .map(|group| group.map(|(start, end)| &t[start..end]))
+                        .collect();
+                    let Some(url) = link_rules[rule_index].match_url(&texts) else {
+                        continue;
+                    };
+                    last_end = end;
+                    matches.push(((start, end), rule_index, groups, url));
                 }
+
                 if !matches.is_empty() {
                     // one line per node
-                    to_process.push((node, t.to_string(), matches));
+                    found.push((node, matches, capped));
                 }
             }
             NodeValue::HtmlInline(content) => {
@@ -250,53 +462,101 @@ fn add_links<'t>(root: &mut Node<'t>, arena: &'t Arena<'t>, link_rules: &[LinkRu
         }
     }
 
-    for (node, text, matches) in to_process {
-        // Exclude nodes whose ancestor is a link
-        if has_link_ancestor(node) {
-            continue;
-        }
+    found
+}
 
-        let parent = node.parent().unwrap();
-        node.detach();
+/// Manipulate the AST in order to find text nodes which match the rules, and split them
+/// into the corresponding links. Returns the positions of the text nodes which had more than
+/// [`MAX_MATCHES_PER_NODE`] matches, and the matches which were turned into links (with their
+/// positions in bytes).
+fn add_links<'t>(
+    root: &mut Node<'t>,
+    arena: &'t Arena<'t>,
+    link_rules: &[LinkRule],
+) -> (Vec<Sourcepos>, Vec<LinkRuleMatch>) {
+    let mut capped_nodes = Vec::new();
+    let mut rule_matches = Vec::new();
+    for (node, matches, capped) in find_rule_matches(*root, link_rules) {
+        let mut data = node.data.borrow_mut();
+        if capped {
+            capped_nodes.push(data.sourcepos);
+        }
+        let sourcepos = data.sourcepos;
+        let NodeValue::Text(ref mut node_text) = data.value else {
+            continue;
+        };
+        let mut text = std::mem::take(node_text).into_owned();
 
-        let mut prev_end = 0;
+        let mut links = Vec::with_capacity(matches.len());
+        for rule_match in matches {
+            let ((start, end), ..) = rule_match;
+            let rule_match = extract::link_rule_match(&text, sourcepos.start, rule_match);
+            links.push((start, end, rule_match.url.clone()));
+            rule_matches.push(rule_match);
+        }
 
-        // let's check each match one by one
-        for ((start, end), url, capture_groups) in &matches {
-            parent.append(
-                arena.alloc(NodeValue::Text(text[prev_end..*start].to_string().into()).into()),
-            );
+        // the text is split from its end, so that each part is only copied once and the text
+        // node keeps the text before the first match, if any
+        let position = |offset: usize| LineColumn {
+            line: sourcepos.start.line,
+            column: sourcepos.start.column + offset,
+        };
+        let mut next_end = sourcepos.end;
+        for (start, end, url) in links.into_iter().rev() {
+            if end < text.len() {
+                let after = arena.alloc(NodeValue::Text(text.split_off(end).into()).into());
+                after.data.borrow_mut().sourcepos = Sourcepos {
+                    start: position(end),
+                    end: next_end,
+                };
+                node.insert_after(after);
+            }
 
+            let link_text = text.split_off(start);
             let link = arena.alloc(
                 NodeValue::Link(Box::new(NodeLink {
-                    url: substitute_url(url, capture_groups),
-                    title: text[*start..*end].into(),
+                    url,
+                    title: link_text.as_str().into(),
                 }))
                 .into(),
             );
-            link.append(arena.alloc(NodeValue::Text(text[*start..*end].to_string().into()).into()));
+            // the match is on the first line of the text node (they don't span lines)
+            link.data.borrow_mut().sourcepos = Sourcepos {
+                start: position(start),
+                end: position(end - 1),
+            };
+            link.append(arena.alloc(NodeValue::Text(link_text.into()).into()));
+            node.insert_after(link);
+            next_end = position(start.saturating_sub(1));
+        }
 
-            parent.append(link);
-            prev_end = *end;
+        if text.is_empty() {
+            drop(data);
+            node.detach();
+        } else {
+            *node_text = text.into();
+            data.sourcepos.end = next_end;
         }
+    }
+    (capped_nodes, rule_matches)
+}
 
-        let last_end = matches.last().unwrap().0.1;
+/// Prefix of the IDs which are given to headings
+const HEADER_ID_PREFIX: &str = "indico-md-";
 
-        if last_end != text.len() {
-            parent.append(arena.alloc(NodeValue::Text(text[last_end..].to_string().into()).into()));
-        }
+/// Prefix of the IDs of headings, including the namespace of the document, if any
+fn header_id_prefix(options: &RenderOptions) -> String {
+    match &options.id_namespace {
+        Some(namespace) => format!("{HEADER_ID_PREFIX}{namespace}-"),
+        None => HEADER_ID_PREFIX.into(),
     }
 }
 
-/// Main function in the module, which takes a markdown string and a list of rules, and returns
-/// the resulting HTML
-pub fn indico_markdown_to_html(
-    md_source: &str,
-    autolink_rules: &[LinkRule],
-) -> Result<String, fmt::Error> {
+/// Options corresponding to the Indico flavor of markdown
+fn indico_options() -> Options<'static> {
     let mut options = Options::default();
     options.extension.strikethrough = true;
-    options.extension.header_ids = Some("indico-md-".into());
+    options.extension.header_ids = Some(HEADER_ID_PREFIX.into());
     options.extension.tagfilter = true;
     options.extension.table = true;
     options.extension.tasklist = true;
@@ -307,20 +567,426 @@ pub fn indico_markdown_to_html(
     options.extension.underline = true;
     options.extension.highlight = true;
     options.render.r#unsafe = true;
+    options
+}
+
+/// Main function in the module, which takes a markdown string and a list of rules, and returns
+/// the resulting HTML
+pub fn indico_markdown_to_html(
+    md_source: &str,
+    autolink_rules: &[LinkRule],
+) -> Result<String, RenderError> {
+    indico_markdown_to_html_with_options(md_source, autolink_rules, &RenderOptions::default())
+}
+
+/// Same as [`indico_markdown_to_html`], but allows customizing the rendering through
+/// [`RenderOptions`]
+pub fn indico_markdown_to_html_with_options(
+    md_source: &str,
+    autolink_rules: &[LinkRule],
+    render_options: &RenderOptions,
+) -> Result<String, RenderError> {
+    indico_markdown_render(md_source, autolink_rules, render_options).map(|output| output.html)
+}
+
+/// The result of [`indico_markdown_render`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct RenderOutput {
+    /// The rendered HTML
+    pub html: String,
+    /// The HTML of the part of the document before its `<!-- more -->` marker, if
+    /// [`RenderOptions::read_more`] is set and the document has one
+    pub teaser: Option<String>,
+    /// The front matter block which was stripped from the document, if
+    /// [`RenderOptions::front_matter`] is set
+    pub front_matter: Option<FrontMatter>,
+    /// Names of the placeholders which were found in the document but have no value, if
+    /// [`RenderOptions::placeholders`] is set
+    pub unknown_placeholders: Vec<String>,
+    /// Content which is rendered differently from what was written (escaped tags, blocked
+    /// URLs...), in document order
+    pub warnings: Vec<RenderWarning>,
+    /// Slugs of the headings, by text, to link to them from other documents. The ID of a
+    /// heading is its slug with the `indico-md-` prefix (and the namespace, if
+    /// [`RenderOptions::id_namespace`] is set). Empty for profiles whose headings have no IDs.
+    pub heading_slugs: HashMap<String, String>,
+    /// Language of the document, if [`RenderOptions::language_detector`] is set and detected it
+    pub language: Option<String>,
+    /// Links of the document, including the ones created by link rules, in document order
+    pub links: Vec<DocumentLink>,
+    /// Headings of the document, in document order, like [`indico_markdown_outline`]
+    pub headings: Vec<Heading>,
+    /// Matches of the link rules which were turned into links, in document order, like
+    /// [`indico_markdown_rule_matches`]
+    pub rule_matches: Vec<LinkRuleMatch>,
+    /// Number of words of the document, like [`DocStats::words`] with the default
+    /// [`StatsOptions`]
+    pub word_count: usize,
+}
+
+/// Same as [`indico_markdown_to_html_with_options`], but also returns the information which
+/// was extracted from the document while rendering it
+pub fn indico_markdown_render(
+    md_source: &str,
+    autolink_rules: &[LinkRule],
+    render_options: &RenderOptions,
+) -> Result<RenderOutput, RenderError> {
+    let arena = Arena::new();
+    render_document(
+        &arena,
+        &comrak_options(render_options),
+        md_source,
+        autolink_rules,
+        render_options,
+    )
+}
+
+/// Options of comrak corresponding to the render options
+fn comrak_options(render_options: &RenderOptions) -> Options<'static> {
+    let mut options = indico_options();
+    #[cfg(feature = "emoji")]
+    {
+        options.extension.shortcodes = render_options.emoji.is_some();
+    }
+    options.extension.wikilinks_title_after_pipe = render_options.wiki_links.is_some();
+    options.extension.footnotes = render_options.footnotes.is_some();
+    options.extension.description_lists = render_options.definition_lists;
+    options.extension.spoiler = render_options.spoilers.is_some();
+    options.extension.table = render_options.extensions.tables;
+    options.extension.alerts = render_options.extensions.alerts;
+    options.extension.tagfilter = render_options.raw_html == RawHtml::Filtered;
+    options.parse.smart = render_options.smart_punctuation.is_some();
+    // bracket delimiters are rewritten with dollars, which are escaped when they aren't
+    // delimiters themselves
+    let math_options = &render_options.math_options;
+    options.extension.math_dollars = math_options.dollars || math_options.brackets;
+    options.extension.math_code = math_options.dollars;
+    // anchors and their classes are of no use in e-mails and feeds, and with the default
+    // profile headings (with their anchors) are rendered by our formatter, whose slugs stay
+    // unique when the blocks of a document are rendered separately
+    options.extension.header_ids = None;
+    options
+}
+
+/// A document which was parsed and transformed, and is ready to be formatted
+struct PreparedDocument<'a> {
+    root: &'a AstNode<'a>,
+    attributes: attributes::NodeAttributes,
+    task_lines: Vec<usize>,
+    custom_alerts: alerts::CustomAlerts,
+    /// Everything which was extracted from the document, without the HTML
+    output: RenderOutput,
+}
+
+/// Parse a document and apply all the transformations of the render options to it, allocating
+/// its nodes in the given arena
+fn prepare_document<'a>(
+    arena: &'a Arena<'a>,
+    options: &Options,
+    md_source: &str,
+    autolink_rules: &[LinkRule],
+    render_options: &RenderOptions,
+) -> Result<PreparedDocument<'a>, RenderError> {
+    // task list items are looked up in the document as it was written, like when toggling them
+    let task_lines = if render_options.interactive_tasks {
+        tasks::task_lines(md_source)
+    } else {
+        Vec::new()
+    };
+    let (front_matter, md_source) = if render_options.front_matter {
+        front_matter::split_front_matter(md_source)
+    } else {
+        (None, md_source)
+    };
+
+    let md_source = containers::expand_containers(
+        md_source,
+        &render_options.containers,
+        render_options.collapsible_sections,
+    );
+    let math_options = &render_options.math_options;
+    let md_source = if math_options.brackets {
+        Cow::Owned(math::convert_bracket_delimiters(&md_source, !math_options.dollars).into_owned())
+    } else {
+        md_source
+    };
+    let md_source = if render_options.image_size_syntax {
+        Cow::Owned(images::expand_image_sizes(&md_source).into_owned())
+    } else {
+        md_source
+    };
+    let (md_source, abbreviations) = if render_options.abbreviations {
+        let (md, abbreviations) = abbreviations::extract_abbreviations(&md_source);
+        (Cow::Owned(md.into_owned()), abbreviations)
+    } else {
+        (md_source, HashMap::new())
+    };
+    let mut root = parse_document(arena, &md_source, options);
+    if let Some(limits) = &render_options.limits {
+        limits::check_limits(root, limits)?;
+    }
+    if render_options.strip_title
+        && let Some(title) = extract::title_heading(root)
+    {
+        title.detach();
+    }
+    let positions = extract::SourcePositions::new(&md_source, render_options.offset_mode);
+    let headings = extract::document_headings(root, &positions);
+    let word_count = stats::document_stats(root, &StatsOptions::default()).words;
+    // before any raw HTML is generated
+    let mut warnings = match render_options.raw_html {
+        RawHtml::Filtered => warnings::filtered_tags(root),
+        RawHtml::Sanitized => sanitize::sanitize_raw_html(root),
+        RawHtml::Escaped => {
+            escape_raw_html(root, arena);
+            Vec::new()
+        }
+        RawHtml::Unfiltered => Vec::new(),
+    };
+
+    let unknown_placeholders = match &render_options.placeholders {
+        Some(values) => placeholders::replace_placeholders(root, values),
+        None => Vec::new(),
+    };
+    // before adding links, so that the markers can't be turned into links
+    let custom_alerts = if render_options.extensions.alerts {
+        alerts::extract_custom_alerts(root, &render_options.alerts.custom_kinds)
+    } else {
+        alerts::CustomAlerts::new()
+    };
+    let (capped_nodes, mut rule_matches) = add_links(&mut root, arena, autolink_rules);
+    for rule_match in &mut rule_matches {
+        rule_match.sourcepos = positions.range(rule_match.sourcepos);
+    }
+    warnings.extend(capped_nodes.into_iter().map(|sourcepos| RenderWarning {
+        kind: RenderWarningKind::RuleMatchLimit,
+        sourcepos: sourcepos.into(),
+        detail: format!(
+            "only the first {MAX_MATCHES_PER_NODE} matches of link rules were turned into links"
+        ),
+    }));
+    warnings.extend(warnings::block_urls(
+        root,
+        &render_options.blocked_url_schemes,
+    ));
+    warnings.sort_by_key(|warning| warning.sourcepos.start);
+    for warning in &mut warnings {
+        warning.sourcepos = positions.range(warning.sourcepos);
+    }
+    let attributes = html::prepare(root, arena, render_options);
+    // after the URLs are resolved
+    let links = extract::document_links(root, &positions);
+    // after the headings are numbered, since the numbers are part of the slugs when they are
+    // in the anchors
+    let heading_slugs = if render_options.profile == RenderProfile::Default {
+        toc::heading_slugs(root)
+    } else {
+        HashMap::new()
+    };
+    let language = render_options
+        .language_detector
+        .as_ref()
+        .and_then(|detector| language::detect_language(root, detector));
+    abbreviations::replace_abbreviations(root, arena, &abbreviations);
+    // again, with the nodes and text which were added (e.g. links and placeholder values)
+    if let Some(limits) = &render_options.limits {
+        limits::check_limits(root, limits)?;
+    }
+
+    Ok(PreparedDocument {
+        root,
+        attributes,
+        task_lines,
+        custom_alerts,
+        output: RenderOutput {
+            html: String::new(),
+            teaser: None,
+            front_matter,
+            unknown_placeholders,
+            warnings,
+            heading_slugs,
+            language,
+            links,
+            headings,
+            rule_matches,
+            word_count,
+        },
+    })
+}
+
+/// State of the HTML formatter for a prepared document
+fn html_state<'r>(
+    render_options: &'r RenderOptions,
+    attributes: attributes::NodeAttributes,
+    task_lines: Vec<usize>,
+    custom_alerts: alerts::CustomAlerts,
+) -> html::HtmlState<'r> {
+    let mut state = html::HtmlState::new(render_options, attributes);
+    state.task_lines = task_lines;
+    state.custom_alerts = custom_alerts;
+    state
+}
 
+/// Render a document, allocating its nodes in the given arena
+fn render_document<'a>(
+    arena: &'a Arena<'a>,
+    options: &Options,
+    md_source: &str,
+    autolink_rules: &[LinkRule],
+    render_options: &RenderOptions,
+) -> Result<RenderOutput, RenderError> {
+    let PreparedDocument {
+        root,
+        attributes,
+        task_lines,
+        custom_alerts,
+        mut output,
+    } = prepare_document(arena, options, md_source, autolink_rules, render_options)?;
+    let language = output.language.clone();
+
+    // the teaser is the document up to the read-more marker, which is dropped from both
+    let teaser_cut = if render_options.read_more
+        && let Some(marker) = teaser::find_marker(root)
+    {
+        let after = teaser::nodes_after(marker);
+        marker.detach();
+        Some(after)
+    } else {
+        None
+    };
+    let teaser_state = teaser_cut.as_ref().map(|_| {
+        html_state(
+            render_options,
+            attributes.clone(),
+            task_lines.clone(),
+            custom_alerts.clone(),
+        )
+    });
+
+    let mut out = format_html(
+        root,
+        options,
+        render_options,
+        &mut html_state(render_options, attributes, task_lines, custom_alerts),
+    )?;
+    if let Some(doc) = &render_options.document {
+        out = html::wrap_document(&out, doc, render_options.xhtml, language.as_deref());
+    } else if let Some(lang) = &language {
+        out = language::wrap_fragment(&out, lang);
+    }
+
+    let teaser = match (teaser_cut, teaser_state) {
+        (Some(nodes), Some(mut state)) => {
+            for node in nodes {
+                node.detach();
+            }
+            let teaser = format_html(root, options, render_options, &mut state)?;
+            Some(match &language {
+                Some(lang) => language::wrap_fragment(&teaser, lang),
+                None => teaser,
+            })
+        }
+        _ => None,
+    };
+
+    output.html = out;
+    output.teaser = teaser;
+    Ok(output)
+}
+
+/// Same as [`indico_markdown_render`], but the HTML is passed to `sink` piece by piece as the
+/// top-level blocks of the document are rendered, instead of being returned at once (the
+/// `html` of the output is empty), so that large documents can be sent while they are being
+/// rendered.
+///
+/// The whole document is still parsed before anything is rendered, since link reference
+/// definitions and footnotes can be anywhere in it. No teaser is rendered, but the read-more
+/// marker is still dropped if [`RenderOptions::read_more`] is set.
+pub fn indico_markdown_render_streaming(
+    md_source: &str,
+    autolink_rules: &[LinkRule],
+    render_options: &RenderOptions,
+    mut sink: impl FnMut(&str),
+) -> Result<RenderOutput, RenderError> {
     let arena = Arena::new();
-    let mut root = parse_document(&arena, md_source, &options);
+    let options = comrak_options(render_options);
+    let PreparedDocument {
+        root,
+        attributes,
+        task_lines,
+        custom_alerts,
+        output,
+    } = prepare_document(&arena, &options, md_source, autolink_rules, render_options)?;
+    if render_options.read_more
+        && let Some(marker) = teaser::find_marker(root)
+    {
+        marker.detach();
+    }
+
+    // the wrapper of the whole document is split around its (empty) body
+    let wrapper = match (&render_options.document, &output.language) {
+        (Some(doc), lang) => Some((
+            html::wrap_document("", doc, render_options.xhtml, lang.as_deref()),
+            "</body>",
+        )),
+        (None, Some(lang)) => Some((language::wrap_fragment("", lang), "</div>")),
+        (None, None) => None,
+    };
+    let (head, tail) = match &wrapper {
+        Some((wrapper, end_tag)) => wrapper.split_at(wrapper.rfind(end_tag).unwrap_or(0)),
+        None => ("", ""),
+    };
+    if !head.is_empty() {
+        sink(head);
+    }
 
-    add_links(&mut root, &arena, autolink_rules);
+    // the state is shared by all the blocks, e.g. for footnote numbers and heading slugs
+    let mut state = html_state(render_options, attributes, task_lines, custom_alerts);
+    let blocks: Vec<_> = root.children().collect();
+    for block in blocks {
+        let chunk = format_html(block, &options, render_options, &mut state)?;
+        block.detach();
+        if !chunk.trim().is_empty() {
+            sink(&chunk);
+        }
+    }
+    // what is only written at the end of the document, e.g. the end of the footnotes
+    let chunk = format_html(root, &options, render_options, &mut state)?;
+    if !chunk.trim().is_empty() {
+        sink(&chunk);
+    }
+
+    if !tail.is_empty() {
+        sink(tail);
+    }
+    Ok(output)
+}
 
+/// Render a prepared document to an HTML fragment
+fn format_html<'a>(
+    root: &'a AstNode<'a>,
+    options: &Options,
+    render_options: &RenderOptions,
+    state: &mut html::HtmlState<'_>,
+) -> Result<String, fmt::Error> {
     let mut out = String::new();
-    TargetBlankFormatter::format_document(root, &options, &mut out)?;
+    comrak::html::format_document_with_formatter(
+        root,
+        options,
+        &mut out,
+        &Default::default(),
+        html::html_formatter,
+        state,
+    )?;
 
-    Ok(out)
+    if render_options.xhtml {
+        out = html::to_xhtml(&out);
+    }
+    Ok(html::reformat(&out, render_options.format))
 }
 
-/// Convert markdown to plain text, which only renders paragraphs and line breaks and ignores all other rendering
-pub fn indico_markdown_to_unstyled_html(md_source: &str) -> Result<String, fmt::Error> {
+/// Parsing options shared by the renderers which strip formatting
+fn unstyled_options() -> Options<'static> {
     let mut options = Options::default();
     options.extension.strikethrough = true;
     options.extension.table = true;
@@ -328,26 +994,65 @@ pub fn indico_markdown_to_unstyled_html(md_source: &str) -> Result<String, fmt::
     options.extension.alerts = true;
     options.extension.underline = true;
     options.extension.highlight = true;
+    options
+}
+
+/// Truncate rendered HTML (e.g. the output of [`indico_markdown_to_html`]) to `max_chars`
+/// visible characters, for previews.
+///
+/// The text is cut on a word boundary and followed by an ellipsis (`…`, which counts as one of
+/// the characters), and the elements which are open at that point are closed. A link which
+/// would be cut is dropped entirely. HTML which fits is returned unchanged.
+pub fn indico_html_truncate(html: &str, max_chars: usize) -> String {
+    html::truncate_html(html, max_chars)
+}
+
+/// Same as [`indico_markdown_to_html_with_options`], but the HTML is truncated to `max_chars`
+/// visible characters (see [`indico_html_truncate`])
+pub fn indico_markdown_to_truncated_html(
+    md_source: &str,
+    autolink_rules: &[LinkRule],
+    render_options: &RenderOptions,
+    max_chars: usize,
+) -> Result<String, RenderError> {
+    let html = indico_markdown_to_html_with_options(md_source, autolink_rules, render_options)?;
+    Ok(html::truncate_html(&html, max_chars))
+}
+
+/// Parse markdown and re-emit it as canonical CommonMark, using the same set of extensions
+/// as [`indico_markdown_to_html`]. This is useful to normalize stored content so that
+/// differences between revisions only reflect actual changes.
+pub fn indico_markdown_normalize(md_source: &str) -> String {
+    let options = indico_options();
 
     let arena = Arena::new();
     let root = parse_document(&arena, md_source, &options);
     let mut out = String::new();
 
-    comrak::html::format_document_with_formatter(
-        root,
-        &options,
-        &mut out,
-        &Default::default(),
-        plain_text_formatter,
-        Vec::new(),
-    )
-    .unwrap_or_else(|_| unreachable!("writing to String cannot fail"));
-    Ok(out)
+    comrak::format_commonmark(root, &options, &mut out)
+        .unwrap_or_else(|_| unreachable!("writing to String cannot fail"));
+    out
 }
 
 #[cfg(test)]
 mod tests {
-    use super::{LinkRule, indico_markdown_to_html, indico_markdown_to_unstyled_html};
+    use std::collections::HashMap;
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use super::{
+        AlertOptions, AttributeOptions, CodeBlockElement, DocumentOptions, EmbedOptions,
+        EmbedProvider, EmbedStyle, Extensions, FootnoteOptions, FrontMatterFormat,
+        HeadingNumbering, LanguageDetector, LinkOptions, LinkResolver, LinkRule, LinkRuleError,
+        MathBackend, MathOptions, MathOutput, MathWrapper, OffsetMode, QuoteStyle, RawHtml,
+        RenderError, RenderLimits, RenderOptions, RenderProfile, RenderWarningKind,
+        ResponsiveImage, ResponsiveImages, SpoilerOptions, StatsOptions, TableOptions, TocOptions,
+        WikiLinkResolver, WikiLinks, indico_html_truncate, indico_markdown_normalize,
+        indico_markdown_outline, indico_markdown_render, indico_markdown_render_streaming,
+        indico_markdown_rule_matches, indico_markdown_stats, indico_markdown_to_html,
+        indico_markdown_to_html_with_options, indico_markdown_to_truncated_html,
+        indico_markdown_to_unstyled_html, indico_markdown_toggle_task,
+    };
 
     #[test]
     fn test_highlight_text() {
@@ -424,56 +1129,1470 @@ and <a href=\"FOOBAR\" title=\"BAR\" target=\"_blank\">BAR</a> is <a href=\"FOOB
     }
 
     #[test]
-    fn test_raw_html() {
-        // raw HTML should be escaped when tagfilter is enabled
-        let md = "<script>alert('x')</script>";
-        let html = indico_markdown_to_html(md, &[]).unwrap();
-        assert_eq!(html, "&lt;script>alert('x')&lt;/script>\n");
-
-        let md = "<div>FOO</div>";
-        let html =
-            indico_markdown_to_html(md, &[LinkRule::new(r"FOO", "https://example/{0}").unwrap()])
-                .unwrap();
-        assert_eq!(html, "<div>FOO</div>\n");
-
-        let md = "<a href=\"http://something.com\">FOO</a>";
-        let html =
-            indico_markdown_to_html(md, &[LinkRule::new(r"FOO", "https://example/{0}").unwrap()])
-                .unwrap();
-        assert_eq!(html, "<p><a href=\"http://something.com\">FOO</a></p>\n");
+    fn test_deep_nesting() {
+        // hostile input mustn't overflow the stack
+        let rules = [LinkRule::new(r"gh:(\d+)", "/gh/{1}").unwrap()];
+        let md = format!("{}[see *gh:1*](/x) gh:2", "> ".repeat(10_000));
+        let html = indico_markdown_to_html(&md, &rules).unwrap();
+        assert_eq!(html.matches("<blockquote>").count(), 10_000);
+        assert_eq!(html.matches("<a ").count(), 2);
 
-        // inline HTML-like tags are also escaped rather than rendered
-        let md = "A <b>bold</b> move";
-        let html = indico_markdown_to_html(md, &[]).unwrap();
-        assert_eq!(html, "<p>A <b>bold</b> move</p>\n");
+        let md = format!("{}item gh:3", "- ".repeat(10_000));
+        let html = indico_markdown_to_html(&md, &rules).unwrap();
+        assert_eq!(html.matches("<li>").count(), 10_000);
+        assert!(html.contains("item <a href=\"/gh/3\""));
     }
 
     #[test]
-    fn test_indico_md_to_plain() {
-        let md = "[**Foo**](https://example.com)\n\n==B`ar`==<div>foo</div>";
-        let html = indico_markdown_to_unstyled_html(md).unwrap();
-        assert_eq!(html, "<p>Foo</p>\n<p>Barfoo</p>\n");
-
-        let md = "soft\\\nvs hard break\n\nhello";
-        let html = indico_markdown_to_unstyled_html(md).unwrap();
-        assert_eq!(html, "<p>soft<br />\nvs hard break</p>\n<p>hello</p>\n");
-
-        let md = "soft<br/>vs hard break<p>hello</p>";
-        let html = indico_markdown_to_unstyled_html(md).unwrap();
-        assert_eq!(html, "<p>soft<br />vs hard break<p>hello</p></p>\n");
-
-        let md = "* a list\n* of\n  - nested\n* things";
-        let html = indico_markdown_to_unstyled_html(md).unwrap();
+    fn test_links_keep_their_place() {
+        let rules = [LinkRule::new(r"gh:(\d+)", "/gh/{1}").unwrap()];
+        let html = indico_markdown_to_html("see gh:1 *and* gh:2 or `gh:3`", &rules).unwrap();
         assert_eq!(
             html,
-            "\n  * a list\n  * of\n    - nested\n\n\n  * things\n\n"
+            "<p>see <a href=\"/gh/1\" title=\"gh:1\" target=\"_blank\">gh:1</a> <em>and</em> \
+             <a href=\"/gh/2\" title=\"gh:2\" target=\"_blank\">gh:2</a> or <code>gh:3</code></p>\n"
         );
+    }
 
-        let md = "1. a list\n2. of\n    - nested\n3. ordered things";
-        let html = indico_markdown_to_unstyled_html(md).unwrap();
+    #[test]
+    fn test_url_template() {
+        let res = indico_markdown_to_html(
+            "gh:12",
+            &[LinkRule::new(r"gh:(\d+)", "https://x/{{1}}/{1}?q={{}}").unwrap()],
+        )
+        .unwrap();
         assert_eq!(
-            html,
-            "\n  1. a list\n  2. of\n    - nested\n\n\n  3. ordered things\n\n"
+            res,
+            "<p><a href=\"https://x/%7B1%7D/12?q=%7B%7D\" title=\"gh:12\" target=\"_blank\">gh:12</a></p>\n"
         );
+
+        // groups which didn't participate in the match are left empty
+        let res =
+            indico_markdown_to_html("ab", &[LinkRule::new(r"a(x)?b", "https://x/{1}").unwrap()])
+                .unwrap();
+        assert_eq!(
+            res,
+            "<p><a href=\"https://x/\" title=\"ab\" target=\"_blank\">ab</a></p>\n"
+        );
+
+        for template in ["{2}", "{foo}", "{}", "{1", "a}b"] {
+            assert!(
+                matches!(
+                    LinkRule::new(r"a(b)", template),
+                    Err(LinkRuleError::Template(_))
+                ),
+                "{template} should be rejected"
+            );
+        }
+        assert!(matches!(
+            LinkRule::new(r"(abc", "{0}"),
+            Err(LinkRuleError::Regex(_))
+        ));
+    }
+
+    #[test]
+    fn test_link_resolver() {
+        let resolver = LinkResolver(Arc::new(|groups: &[Option<&str>]| match groups[1] {
+            Some("1") => Some("/tickets/one".into()),
+            _ => None,
+        }));
+        let rules = [
+            LinkRule::with_resolver(r"#(\d+)", resolver).unwrap(),
+            LinkRule::new(r"#\d+", "/fallback").unwrap(),
+        ];
+        // matches which aren't resolved are left to the other rules
+        assert_eq!(
+            indico_markdown_to_html("#1 #2", &rules).unwrap(),
+            "<p><a href=\"/tickets/one\" title=\"#1\" target=\"_blank\">#1</a> \
+             <a href=\"/fallback\" title=\"#2\" target=\"_blank\">#2</a></p>\n"
+        );
+        assert!(matches!(
+            LinkRule::with_resolver("(", LinkResolver(Arc::new(|_: &[Option<&str>]| None))),
+            Err(LinkRuleError::Regex(_))
+        ));
+    }
+
+    #[test]
+    fn test_link_resolver_overlaps() {
+        // resolvers are only called for the matches which aren't overlapped by a longer one
+        let calls = Arc::new(AtomicUsize::new(0));
+        let counter = calls.clone();
+        let resolver = LinkResolver(Arc::new(move |groups: &[Option<&str>]| {
+            counter.fetch_add(1, Ordering::Relaxed);
+            groups[0].map(|g| format!("/t/{g}"))
+        }));
+        let rules = [
+            LinkRule::new(r"[A-Z]+-\d+", "/issue/{0}").unwrap(),
+            LinkRule::with_resolver(r"\d+", resolver).unwrap(),
+        ];
+        assert_eq!(
+            indico_markdown_to_html("AB-1 AB-2 3", &rules).unwrap(),
+            "<p><a href=\"/issue/AB-1\" title=\"AB-1\" target=\"_blank\">AB-1</a> \
+             <a href=\"/issue/AB-2\" title=\"AB-2\" target=\"_blank\">AB-2</a> \
+             <a href=\"/t/3\" title=\"3\" target=\"_blank\">3</a></p>\n"
+        );
+        assert_eq!(calls.load(Ordering::Relaxed), 1);
+    }
+
+    #[test]
+    fn test_substitute_url() {
+        let groups = [Some("a"), None, Some("c")];
+        assert_eq!(
+            super::substitute_url("/x/{0}/{1}/{2}?q={{2}}&r=}}", &groups),
+            "/x/a//c?q={2}&r=}"
+        );
+        assert_eq!(super::substitute_url("{2}{0}", &groups), "ca");
+        assert_eq!(super::substitute_url("/static", &groups), "/static");
+    }
+
+    #[test]
+    fn test_degenerate_rules() {
+        // zero-length matches are ignored
+        let res = indico_markdown_to_html("bbb", &[LinkRule::new(r"a*", "{0}").unwrap()]).unwrap();
+        assert_eq!(res, "<p>bbb</p>\n");
+
+        let res =
+            indico_markdown_to_html("baab", &[LinkRule::new(r"a*", "https://x/{0}").unwrap()])
+                .unwrap();
+        assert_eq!(
+            res,
+            "<p>b<a href=\"https://x/aa\" title=\"aa\" target=\"_blank\">aa</a>b</p>\n"
+        );
+
+        // overlapping matches from different rules don't corrupt the output
+        let res = indico_markdown_to_html(
+            "BAR FOOBAR",
+            &[
+                LinkRule::new(r"FOO\w*", "https://foo/{0}").unwrap(),
+                LinkRule::new(r"BAR", "https://bar/{0}").unwrap(),
+            ],
+        )
+        .unwrap();
+        assert_eq!(
+            res,
+            "<p><a href=\"https://bar/BAR\" title=\"BAR\" target=\"_blank\">BAR</a> \
+<a href=\"https://foo/FOOBAR\" title=\"FOOBAR\" target=\"_blank\">FOOBAR</a></p>\n"
+        );
+
+        // the number of links per text node is capped
+        let md = "x ".repeat(super::MAX_MATCHES_PER_NODE + 10);
+        let res = indico_markdown_to_html(&md, &[LinkRule::new(r"x", "y").unwrap()]).unwrap();
+        assert_eq!(res.matches("<a ").count(), super::MAX_MATCHES_PER_NODE);
+    }
+
+    #[test]
+    fn test_normalize() {
+        let md = "Title\n=====\n\n_emph_ and __strong__ ==mark==";
+        let normalized = indico_markdown_normalize(md);
+        assert_eq!(normalized, "# Title\n\n*emph* and **strong** ==mark==\n");
+        assert_eq!(indico_markdown_normalize(&normalized), normalized);
+    }
+
+    #[test]
+    fn test_email_profile() {
+        let options = RenderOptions {
+            profile: RenderProfile::Email,
+            base_url: Some("https://indico.example.com/event/1/".into()),
+            ..Default::default()
+        };
+        let md = "## Title\n\n==see== [`slides`](attachments/slides.pdf)\n\n\
+                  <details><summary>More</summary>hidden</details>\n\n\
+                  | a |\n|:-:|\n| 1 |\n\n- [x] done";
+        let html = indico_markdown_to_html_with_options(md, &[], &options).unwrap();
+        assert_eq!(
+            html,
+            "<h2>Title</h2>\n\
+             <p><span style=\"background-color:#fff3a3\">see</span> \
+             <a href=\"https://indico.example.com/event/1/attachments/slides.pdf\" target=\"_blank\" \
+             style=\"color:#1a64a0\"><code style=\"font-family:monospace;background-color:#f3f4f6;\
+             padding:0 2px\">slides</code></a></p>\n\
+             Morehidden\n\
+             <table style=\"border-collapse:collapse\">\n<tr>\n\
+             <th style=\"border:1px solid #d1d5db;padding:4px 8px;text-align:center\">a</th>\n</tr>\n<tr>\n\
+             <td style=\"border:1px solid #d1d5db;padding:4px 8px;text-align:center\">1</td>\n</tr>\n\
+             </table>\n<ul>\n<li>\u{2611} done</li>\n</ul>\n"
+        );
+    }
+
+    #[test]
+    fn test_base_urls() {
+        let options = RenderOptions {
+            base_url: Some("https://indico.example.com/event/1/".into()),
+            media_base_url: Some("https://cdn.example.com/event/1/".into()),
+            ..Default::default()
+        };
+        let md = "[slides](attachments/slides.pdf) ![logo](/logo.png) [home](/) [top](#top)";
+        let html = indico_markdown_to_html_with_options(md, &[], &options).unwrap();
+        assert_eq!(
+            html,
+            "<p><a href=\"https://indico.example.com/event/1/attachments/slides.pdf\" \
+             target=\"_blank\">slides</a> \
+             <img src=\"https://cdn.example.com/logo.png\" alt=\"logo\" /> \
+             <a href=\"https://indico.example.com/\" target=\"_blank\">home</a> \
+             <a href=\"#top\" target=\"_blank\">top</a></p>\n"
+        );
+    }
+
+    #[test]
+    fn test_feed_profile() {
+        let options = RenderOptions {
+            profile: RenderProfile::Feed,
+            base_url: Some("https://indico.example.com/".into()),
+            ..Default::default()
+        };
+        let md = "# News\n\n==new== ![logo](logo.png)\n\n- [ ] todo\n\n\
+                  <form><button>Click</button></form>\n\n> [!NOTE]\n> Hi";
+        let html = indico_markdown_to_html_with_options(md, &[], &options).unwrap();
+        assert_eq!(
+            html,
+            "<h1>News</h1>\n\
+             <p><strong>new</strong> <img src=\"https://indico.example.com/logo.png\" alt=\"logo\" /></p>\n\
+             <ul>\n<li>\u{2610} todo</li>\n</ul>\n\
+             Click\n\
+             <blockquote>\n<p><strong>Note</strong></p>\n<p>Hi</p>\n</blockquote>\n"
+        );
+    }
+
+    #[test]
+    fn test_xhtml_output() {
+        let options = RenderOptions {
+            xhtml: true,
+            ..Default::default()
+        };
+        let html =
+            indico_markdown_to_html_with_options("a<br>b <span class=x>c\n\n---", &[], &options)
+                .unwrap();
+        assert_eq!(html, "<p>a<br />b <span class=\"x\">c</span></p>\n<hr />\n");
+    }
+
+    #[test]
+    fn test_full_document() {
+        let options = RenderOptions {
+            document: Some(DocumentOptions {
+                title: "Minutes <draft>".into(),
+                stylesheet: Some("p { color: red; }".into()),
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        let html = indico_markdown_to_html_with_options("Hello", &[], &options).unwrap();
+        assert_eq!(
+            html,
+            "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n\
+             <title>Minutes &lt;draft&gt;</title>\n<style>\np { color: red; }\n</style>\n\
+             </head>\n<body>\n<p>Hello</p>\n</body>\n</html>\n"
+        );
+    }
+
+    #[test]
+    fn test_math_backend() {
+        let options = RenderOptions {
+            math: MathOutput::Custom(MathBackend(Arc::new(|tex: &str, display: bool| {
+                (tex != "skip").then(|| format!("<x-math display=\"{display}\">{tex}</x-math>"))
+            }))),
+            ..Default::default()
+        };
+        let html = indico_markdown_to_html_with_options("$a$ and\n\n$$b$$", &[], &options).unwrap();
+        assert_eq!(
+            html,
+            "<p><x-math display=\"false\">a</x-math> and</p>\n\
+             <p><x-math display=\"true\">b</x-math></p>\n"
+        );
+
+        // the default rendering is used when the backend gives up
+        let html = indico_markdown_to_html_with_options("$skip$", &[], &options).unwrap();
+        assert_eq!(html, indico_markdown_to_html("$skip$", &[]).unwrap());
+    }
+
+    #[test]
+    fn test_render_warnings() {
+        let md = "<script>alert(1)</script>\n\n[x](javascript:alert(1)) ![y](JAVASCRIPT:z)\n\n\
+                  [ok](https://example.com)";
+        let options = RenderOptions {
+            blocked_url_schemes: vec!["javascript".into()],
+            ..Default::default()
+        };
+        let output = indico_markdown_render(md, &[], &options).unwrap();
+        assert!(output.html.contains("<a href=\"\">x</a>"));
+        assert!(output.html.contains("<img src=\"\" alt=\"y\" />"));
+        assert!(
+            output
+                .html
+                .contains("<a href=\"https://example.com\">ok</a>")
+        );
+        let warnings: Vec<_> = output
+            .warnings
+            .iter()
+            .map(|w| (w.kind, w.sourcepos.start.0, w.detail.as_str()))
+            .collect();
+        assert_eq!(
+            warnings,
+            [
+                (
+                    RenderWarningKind::FilteredTag,
+                    1,
+                    "<script> tags are not allowed"
+                ),
+                (
+                    RenderWarningKind::BlockedUrl,
+                    3,
+                    "javascript: URLs are not allowed"
+                ),
+                (
+                    RenderWarningKind::BlockedUrl,
+                    3,
+                    "javascript: URLs are not allowed"
+                ),
+            ]
+        );
+
+        let rules = [LinkRule::new("a", "/{0}").unwrap()];
+        let output = indico_markdown_render(
+            &"a ".repeat(super::MAX_MATCHES_PER_NODE + 1),
+            &rules,
+            &RenderOptions::default(),
+        )
+        .unwrap();
+        assert_eq!(output.warnings.len(), 1);
+        assert_eq!(output.warnings[0].kind, RenderWarningKind::RuleMatchLimit);
+
+        let options = RenderOptions {
+            blocked_url_schemes: vec!["javascript".into()],
+            offset_mode: OffsetMode::Utf16,
+            ..Default::default()
+        };
+        let output = indico_markdown_render("Ça 😀 [x](javascript:z)", &[], &options).unwrap();
+        let sourcepos = output.warnings[0].sourcepos;
+        assert_eq!((sourcepos.start, sourcepos.end), ((1, 10), (1, 26)));
+        assert_eq!(sourcepos.utf16, Some(((1, 7), (1, 23))));
+    }
+
+    #[test]
+    fn test_truncate_html() {
+        let html = "<p>Hello <strong>brave new</strong> world &amp; more</p>\n<p>Second</p>\n";
+        assert_eq!(indico_html_truncate(html, 100), html);
+        assert_eq!(
+            indico_html_truncate(html, 14),
+            "<p>Hello <strong>brave\u{2026}</strong></p>"
+        );
+        assert_eq!(
+            indico_html_truncate(html, 24),
+            "<p>Hello <strong>brave new</strong> world &amp;\u{2026}</p>"
+        );
+
+        // links which would be cut are dropped
+        let html = "<ul>\n<li>See <a href=\"/agenda\">the full agenda</a> now</li>\n</ul>\n";
+        assert_eq!(
+            indico_html_truncate(html, 10),
+            "<ul>\n<li>See\u{2026}</li></ul>"
+        );
+
+        // the ellipsis counts even when the text before it fits exactly
+        let html = "<p>abc</p><p>def</p>";
+        assert_eq!(indico_html_truncate(html, 6), html);
+        assert_eq!(indico_html_truncate(html, 3), "<p>ab\u{2026}</p>");
+        assert_eq!(indico_html_truncate(html, 4), "<p>abc</p><p>\u{2026}</p>");
+        assert_eq!(indico_html_truncate(html, 0), "");
+
+        let html = indico_markdown_to_truncated_html(
+            "**Hello** world and more",
+            &[],
+            &RenderOptions::default(),
+            12,
+        )
+        .unwrap();
+        assert_eq!(html, "<p><strong>Hello</strong> world\u{2026}</p>");
+    }
+
+    #[test]
+    fn test_math_options() {
+        let md = "\\(a<b\\) costs $5\n\n\\[x\\]";
+        let options = RenderOptions {
+            math_options: MathOptions {
+                dollars: false,
+                brackets: true,
+                wrapper: MathWrapper::Delimited,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let html = indico_markdown_to_html_with_options(md, &[], &options).unwrap();
+        assert_eq!(
+            html,
+            "<p><span class=\"math math-inline\">\\(a&lt;b\\)</span> costs $5</p>\n\
+             <p><span class=\"math math-display\">\\[x\\]</span></p>\n"
+        );
+
+        let options = RenderOptions {
+            math_options: MathOptions {
+                wrapper: MathWrapper::Classes {
+                    inline: "tex".into(),
+                    display: "tex block".into(),
+                },
+                macros: "\\def\\R{\\mathbb{R}}".into(),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let html = indico_markdown_to_html_with_options("$x \\in \\R$", &[], &options).unwrap();
+        assert_eq!(
+            html,
+            "<p><span class=\"tex\">\\def\\R{\\mathbb{R}}x \\in \\R</span></p>\n"
+        );
+
+        // without dollar delimiters, formulas are plain text
+        let options = RenderOptions {
+            math_options: MathOptions {
+                dollars: false,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let html = indico_markdown_to_html_with_options("$x$", &[], &options).unwrap();
+        assert_eq!(html, "<p>$x$</p>\n");
+    }
+
+    #[cfg(feature = "mathml")]
+    #[test]
+    fn test_mathml() {
+        let options = RenderOptions {
+            math: MathOutput::MathMl,
+            ..Default::default()
+        };
+        let html = indico_markdown_to_html_with_options("$x^2$", &[], &options).unwrap();
+        assert!(html.starts_with("<p><math"));
+    }
+
+    #[cfg(feature = "emoji")]
+    #[test]
+    fn test_emoji() {
+        use super::EmojiStyle;
+
+        let md = ":smile: `:smile:` [:smile:](https://example.com) :not_an_emoji:";
+        let html = indico_markdown_to_html(md, &[]).unwrap();
+        assert_eq!(
+            html,
+            "<p>:smile: <code>:smile:</code> <a href=\"https://example.com\" target=\"_blank\">:smile:</a> \
+             :not_an_emoji:</p>\n"
+        );
+
+        let mut options = RenderOptions {
+            emoji: Some(EmojiStyle::Unicode),
+            ..Default::default()
+        };
+        let html = indico_markdown_to_html_with_options(md, &[], &options).unwrap();
+        assert_eq!(
+            html,
+            "<p>\u{1f604} <code>:smile:</code> <a href=\"https://example.com\" target=\"_blank\">:smile:</a> \
+             :not_an_emoji:</p>\n"
+        );
+
+        options.emoji = Some(EmojiStyle::Accessible);
+        let html = indico_markdown_to_html_with_options(":thumbsup:", &[], &options).unwrap();
+        assert_eq!(
+            html,
+            "<p><span role=\"img\" aria-label=\"thumbsup\">\u{1f44d}</span></p>\n"
+        );
+    }
+
+    #[test]
+    fn test_custom_emoji() {
+        let options = RenderOptions {
+            custom_emoji: [("indico".to_string(), "https://cdn/indico.png".to_string())].into(),
+            ..Default::default()
+        };
+        let html = indico_markdown_to_html_with_options(
+            "Hi :indico:! `:indico:` :other: [:indico:](https://x)",
+            &[],
+            &options,
+        )
+        .unwrap();
+        assert_eq!(
+            html,
+            "<p>Hi <img class=\"emoji\" src=\"https://cdn/indico.png\" alt=\":indico:\" \
+             title=\":indico:\" width=\"20\" height=\"20\" />! <code>:indico:</code> :other: \
+             <a href=\"https://x\" target=\"_blank\">:indico:</a></p>\n"
+        );
+    }
+
+    #[test]
+    fn test_wiki_links() {
+        let md = "See [[Venue]], [[Other page|the other page]] and gh:1 [[gh:2]]";
+        let rules = [LinkRule::new(r"gh:(\d+)", "https://github.com/{1}").unwrap()];
+
+        let options = RenderOptions {
+            wiki_links: Some(WikiLinks::Template("/category/wiki/{0}".into())),
+            ..Default::default()
+        };
+        let html = indico_markdown_to_html_with_options(md, &rules, &options).unwrap();
+        assert_eq!(
+            html,
+            "<p>See <a href=\"/category/wiki/Venue\" class=\"internal\">Venue</a>, \
+             <a href=\"/category/wiki/Other%20page\" class=\"internal\">the other page</a> and \
+             <a href=\"https://github.com/1\" title=\"gh:1\" target=\"_blank\">gh:1</a> \
+             <a href=\"/category/wiki/gh:2\" class=\"internal\">gh:2</a></p>\n"
+        );
+
+        let options = RenderOptions {
+            wiki_links: Some(WikiLinks::Resolver(WikiLinkResolver(Arc::new(
+                |target: &str| (target == "Venue").then(|| "/venue".to_string()),
+            )))),
+            ..Default::default()
+        };
+        let html =
+            indico_markdown_to_html_with_options("[[Venue]] [[Nowhere]]", &[], &options).unwrap();
+        assert_eq!(
+            html,
+            "<p><a href=\"/venue\" class=\"internal\">Venue</a> \
+             <a class=\"internal unresolved\">Nowhere</a></p>\n"
+        );
+
+        // without the option, the syntax is left alone
+        let html = indico_markdown_to_html("[[Venue]]", &[]).unwrap();
+        assert_eq!(html, "<p>[[Venue]]</p>\n");
+    }
+
+    #[test]
+    fn test_footnotes() {
+        let md = "Text[^1] and again[^1].\n\n[^1]: A note.";
+        let options = RenderOptions {
+            footnotes: Some(FootnoteOptions {
+                heading: Some("Notes".into()),
+                id_prefix: "abstract-".into(),
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        let html = indico_markdown_to_html_with_options(md, &[], &options).unwrap();
+        assert_eq!(
+            html,
+            "<p>Text<sup class=\"footnote-ref\"><a href=\"#abstract-fn-1\" id=\"abstract-fnref-1\" \
+             data-footnote-ref>1</a></sup> and again<sup class=\"footnote-ref\"><a href=\"#abstract-fn-1\" \
+             id=\"abstract-fnref-1-2\" data-footnote-ref>1</a></sup>.</p>\n\
+             <section class=\"footnotes\" data-footnotes>\n\
+             <h2 class=\"footnotes-heading\">Notes</h2>\n<ol>\n<li id=\"abstract-fn-1\">\n\
+             <p>A note. <a href=\"#abstract-fnref-1\" class=\"footnote-backref\" data-footnote-backref \
+             data-footnote-backref-idx=\"1\" aria-label=\"Back to reference 1\">\u{21a9}</a> \
+             <a href=\"#abstract-fnref-1-2\" class=\"footnote-backref\" data-footnote-backref \
+             data-footnote-backref-idx=\"1-2\" aria-label=\"Back to reference 1-2\">\u{21a9}</a></p>\n\
+             </li>\n</ol>\n</section>\n"
+        );
+
+        // footnotes are disabled by default
+        let html = indico_markdown_to_html("a[^1]", &[]).unwrap();
+        assert_eq!(html, "<p>a[^1]</p>\n");
+    }
+
+    #[test]
+    fn test_definition_lists() {
+        let md = "Speaker\n: Someone from CERN";
+        let options = RenderOptions {
+            definition_lists: true,
+            ..Default::default()
+        };
+        let html = indico_markdown_to_html_with_options(md, &[], &options).unwrap();
+        assert_eq!(
+            html,
+            "<dl>\n<dt>Speaker</dt>\n<dd>Someone from CERN</dd>\n</dl>\n"
+        );
+
+        let html = indico_markdown_to_html(md, &[]).unwrap();
+        assert_eq!(html, "<p>Speaker\n: Someone from CERN</p>\n");
+    }
+
+    #[test]
+    fn test_spoilers() {
+        let md = "Answer: ||42||\n\n||The whole paragraph||";
+        let mut options = RenderOptions {
+            spoilers: Some(SpoilerOptions::default()),
+            ..Default::default()
+        };
+        let html = indico_markdown_to_html_with_options(md, &[], &options).unwrap();
+        assert_eq!(
+            html,
+            "<p>Answer: <span class=\"spoiler\">42</span></p>\n\
+             <details class=\"spoiler\">\n<summary>Spoiler</summary>\n<p>The whole paragraph</p>\n\
+             </details>\n"
+        );
+
+        // e-mails can't hide anything, so block spoilers stay inline there
+        options.profile = RenderProfile::Email;
+        let html = indico_markdown_to_html_with_options(md, &[], &options).unwrap();
+        assert_eq!(
+            html,
+            "<p>Answer: <span class=\"spoiler\">42</span></p>\n\
+             <p><span class=\"spoiler\">The whole paragraph</span></p>\n"
+        );
+
+        let html = indico_markdown_to_html("||42||", &[]).unwrap();
+        assert_eq!(html, "<p>||42||</p>\n");
+    }
+
+    #[test]
+    fn test_containers() {
+        let md = "::: warning\nThe **deadline** is\n\n- today\n:::\n\n::: other\nfoo\n:::";
+        let options = RenderOptions {
+            containers: vec!["note".into(), "warning".into()],
+            ..Default::default()
+        };
+        let html = indico_markdown_to_html_with_options(md, &[], &options).unwrap();
+        assert_eq!(
+            html,
+            "<div class=\"admonition warning\">\n<p>The <strong>deadline</strong> is</p>\n\
+             <ul>\n<li>today</li>\n</ul>\n</div>\n<p>::: other\nfoo\n:::</p>\n"
+        );
+    }
+
+    #[test]
+    fn test_smart_punctuation() {
+        let md = "\"Bonjour\" -- l'école 'là' --- `\"code\"`...";
+        let mut options = RenderOptions {
+            smart_punctuation: Some(QuoteStyle::English),
+            ..Default::default()
+        };
+        let html = indico_markdown_to_html_with_options(md, &[], &options).unwrap();
+        assert_eq!(
+            html,
+            "<p>\u{201c}Bonjour\u{201d} \u{2013} l\u{2019}école \u{2018}là\u{2019} \u{2014} \
+             <code>&quot;code&quot;</code>\u{2026}</p>\n"
+        );
+
+        options.smart_punctuation = Some(QuoteStyle::French);
+        let html = indico_markdown_to_html_with_options(md, &[], &options).unwrap();
+        assert_eq!(
+            html,
+            "<p>\u{ab}\u{a0}Bonjour\u{a0}\u{bb} \u{2013} l\u{2019}école \u{201c}là\u{201d} \u{2014} \
+             <code>&quot;code&quot;</code>\u{2026}</p>\n"
+        );
+
+        // off by default
+        let html = indico_markdown_to_html("\"a\" -- b", &[]).unwrap();
+        assert_eq!(html, "<p>&quot;a&quot; -- b</p>\n");
+    }
+
+    #[test]
+    fn test_front_matter() {
+        let md = "---\ntitle: Opening\n---\n# Welcome";
+        let options = RenderOptions {
+            front_matter: true,
+            profile: RenderProfile::Email,
+            ..Default::default()
+        };
+        let output = indico_markdown_render(md, &[], &options).unwrap();
+        assert_eq!(output.html, "<h1>Welcome</h1>\n");
+        let front_matter = output.front_matter.unwrap();
+        assert_eq!(front_matter.format, FrontMatterFormat::Yaml);
+        assert_eq!(
+            front_matter.data,
+            Some(serde_json::json!({"title": "Opening"}))
+        );
+
+        // without the option, this is a thematic break and a setext heading
+        let html = indico_markdown_to_html("---\ntitle: Opening\n---", &[]).unwrap();
+        assert_eq!(
+            html,
+            "<hr />\n<h2><a href=\"#title-opening\" aria-hidden=\"true\" class=\"anchor\" \
+             id=\"indico-md-title-opening\"></a>title: Opening</h2>\n"
+        );
+    }
+
+    #[test]
+    fn test_toc() {
+        let md = "[TOC]\n\n# Intro\n\n## Details\n\n### Deep\n\n# Intro";
+        let options = RenderOptions {
+            toc: Some(TocOptions {
+                min_level: 1,
+                max_level: 2,
+            }),
+            ..Default::default()
+        };
+        let html = indico_markdown_to_html_with_options(md, &[], &options).unwrap();
+        assert!(html.starts_with(
+            "<ul class=\"toc\">\n<li><a href=\"#indico-md-intro\">Intro</a>\n<ul>\n\
+             <li><a href=\"#indico-md-details\">Details</a></li>\n</ul>\n</li>\n\
+             <li><a href=\"#indico-md-intro-1\">Intro</a></li>\n</ul>\n<h1>"
+        ));
+        assert!(html.contains("id=\"indico-md-intro-1\""));
+
+        // the marker is only replaced if it's on its own
+        let html =
+            indico_markdown_to_html_with_options("see [TOC]\n\n# Intro", &[], &options).unwrap();
+        assert!(html.starts_with("<p>see [TOC]</p>"));
+    }
+
+    #[test]
+    fn test_id_namespace() {
+        let md = "[TOC]\n\n# Intro\n\nText[^1]\n\n[^1]: A note.";
+        let options = RenderOptions {
+            id_namespace: Some("abstract".into()),
+            toc: Some(TocOptions::default()),
+            footnotes: Some(FootnoteOptions::default()),
+            ..Default::default()
+        };
+        let html = indico_markdown_to_html_with_options(md, &[], &options).unwrap();
+        assert!(html.contains("<a href=\"#indico-md-abstract-intro\">Intro</a>"));
+        assert!(html.contains("id=\"indico-md-abstract-intro\""));
+        assert!(html.contains("<a href=\"#abstract-fn-1\" id=\"abstract-fnref-1\""));
+        assert!(html.contains("<li id=\"abstract-fn-1\">"));
+        assert!(html.contains("<a href=\"#abstract-fnref-1\" class=\"footnote-backref\""));
+
+        // headings rendered with attribute blocks get the namespace too
+        let options = RenderOptions {
+            attributes: Some(AttributeOptions::default()),
+            ..options
+        };
+        let html = indico_markdown_to_html_with_options(md, &[], &options).unwrap();
+        assert!(html.contains("id=\"indico-md-abstract-intro\""));
+
+        // the namespace is escaped in the IDs of headings
+        let options = RenderOptions {
+            id_namespace: Some("a\"b".into()),
+            ..Default::default()
+        };
+        let html = indico_markdown_to_html_with_options("# Intro", &[], &options).unwrap();
+        assert!(html.contains("id=\"indico-md-a&quot;b-intro\""));
+        assert!(!html.contains("a\"b"));
+    }
+
+    #[test]
+    fn test_language() {
+        let detector = LanguageDetector(Arc::new(|text: &str| {
+            Some(if text.contains("Bonjour") { "fr" } else { "en" }.into())
+        }));
+        let md = "# Bienvenue\n\nBonjour à tous\n\nWelcome to all\n{lang=en}\n\n- one\n{lang=en}";
+        let options = RenderOptions {
+            language_detector: Some(detector.clone()),
+            attributes: Some(AttributeOptions::default()),
+            ..Default::default()
+        };
+        let output = indico_markdown_render(md, &[], &options).unwrap();
+        assert_eq!(output.language.as_deref(), Some("fr"));
+        assert!(output.html.starts_with("<div lang=\"fr\">\n<h1>"));
+        assert!(output.html.contains("<p lang=\"en\">Welcome to all</p>"));
+        // paragraphs of tight lists have no element to set attributes on
+        assert!(output.html.contains("<li>one</li>"));
+        assert!(output.html.ends_with("</div>\n"));
+
+        let options = RenderOptions {
+            language_detector: Some(detector),
+            document: Some(DocumentOptions::default()),
+            ..Default::default()
+        };
+        let html = indico_markdown_to_html_with_options("Bonjour", &[], &options).unwrap();
+        assert!(html.starts_with("<!DOCTYPE html>\n<html lang=\"fr\">\n"));
+        assert!(html.contains("<body>\n<p>Bonjour</p>\n</body>"));
+        let html = indico_markdown_to_html_with_options("", &[], &options).unwrap();
+        assert!(html.starts_with("<!DOCTYPE html>\n<html>\n"));
+    }
+
+    #[test]
+    fn test_strip_title() {
+        let md = "# Opening\n\nWelcome\n\n## Agenda";
+        let options = RenderOptions {
+            strip_title: true,
+            ..Default::default()
+        };
+        let html = indico_markdown_to_html_with_options(md, &[], &options).unwrap();
+        assert!(html.starts_with("<p>Welcome</p>\n<h2>"));
+        assert!(!html.contains("Opening"));
+        assert!(html.contains("Agenda"));
+    }
+
+    #[test]
+    fn test_heading_slugs() {
+        let md = "# Intro\n\n## The `plan`\n\n# Intro";
+        let output = indico_markdown_render(md, &[], &RenderOptions::default()).unwrap();
+        assert_eq!(output.heading_slugs.len(), 2);
+        assert_eq!(output.heading_slugs["Intro"], "intro");
+        assert_eq!(output.heading_slugs["The plan"], "the-plan");
+        assert!(output.html.contains("id=\"indico-md-the-plan\""));
+
+        let options = RenderOptions {
+            profile: RenderProfile::Email,
+            ..Default::default()
+        };
+        let output = indico_markdown_render(md, &[], &options).unwrap();
+        assert!(output.heading_slugs.is_empty());
+    }
+
+    #[test]
+    fn test_render_links() {
+        let md = "See [the *docs*](guide.html), #1 and <https://example.com>\n\n\
+                  [bad](javascript:alert(1))";
+        let options = RenderOptions {
+            base_url: Some("https://indico.example.com/event/1/".into()),
+            blocked_url_schemes: vec!["javascript".into()],
+            ..Default::default()
+        };
+        let rules = [LinkRule::new(r"#(\d+)", "/tickets/{1}").unwrap()];
+        let output = indico_markdown_render(md, &rules, &options).unwrap();
+        let links: Vec<_> = output
+            .links
+            .iter()
+            .map(|link| (link.url.as_str(), link.text.as_str()))
+            .collect();
+        assert_eq!(
+            links,
+            [
+                ("https://indico.example.com/event/1/guide.html", "the docs"),
+                ("https://indico.example.com/tickets/1", "#1"),
+                ("https://example.com", "https://example.com"),
+            ]
+        );
+        assert_eq!(output.links[0].sourcepos.start, (1, 5));
+        assert_eq!(output.links[1].sourcepos.start, (1, 31));
+        assert_eq!(output.links[1].sourcepos.end, (1, 32));
+    }
+
+    #[test]
+    fn test_render_metadata() {
+        // the metadata collected while rendering is the same as the one of the extractors
+        let md = "# Intro\n\nSee #1 and `#2`, then #3\n\n## Intro";
+        let rules = [LinkRule::new(r"#(\d+)", "/tickets/{1}").unwrap()];
+        let output = indico_markdown_render(md, &rules, &RenderOptions::default()).unwrap();
+        assert_eq!(
+            output.headings,
+            indico_markdown_outline(md, OffsetMode::Bytes)
+        );
+        assert_eq!(
+            output.rule_matches,
+            indico_markdown_rule_matches(md, &rules, OffsetMode::Bytes)
+        );
+        assert_eq!(output.rule_matches.len(), 2);
+        assert_eq!(
+            output.word_count,
+            indico_markdown_stats(md, &StatsOptions::default()).words
+        );
+    }
+
+    #[test]
+    fn test_heading_numbers() {
+        let md = "[TOC]\n\n# Title\n\n## Intro\n\n### Scope\n\n## Details";
+        let mut numbering = HeadingNumbering {
+            start_level: 2,
+            in_anchors: false,
+        };
+        let options = RenderOptions {
+            toc: Some(TocOptions::default()),
+            heading_numbers: Some(numbering.clone()),
+            ..Default::default()
+        };
+        let html = indico_markdown_to_html_with_options(md, &[], &options).unwrap();
+        assert!(html.contains(
+            "<h2><a href=\"#intro\" aria-hidden=\"true\" class=\"anchor\" id=\"indico-md-intro\"></a>\
+             <span class=\"heading-number\">1.</span> Intro</h2>"
+        ));
+        assert!(html.contains("<h1><a href=\"#title\""));
+        assert!(html.contains("<a href=\"#indico-md-scope\">1.1 Scope</a>"));
+        assert!(html.contains("<span class=\"heading-number\">2.</span> Details</h2>"));
+
+        numbering.in_anchors = true;
+        let options = RenderOptions {
+            heading_numbers: Some(numbering),
+            ..options
+        };
+        let html = indico_markdown_to_html_with_options(md, &[], &options).unwrap();
+        assert!(html.contains("id=\"indico-md-11-scope\""));
+        assert!(html.contains("<a href=\"#indico-md-11-scope\">1.1 Scope</a>"));
+        assert!(html.contains("<span class=\"heading-number\">1.1</span> Scope</h3>"));
+    }
+
+    #[test]
+    fn test_attributes() {
+        let md = "# Intro {.big #start data-x=1 onclick=alert(1)}\n\n\
+                  ![Logo](/logo.png \"Our logo\"){.right width=200}\n\n\
+                  ```python {.wide}\nprint(1)\n```";
+        let options = RenderOptions {
+            attributes: Some(AttributeOptions::default()),
+            ..Default::default()
+        };
+        let html = indico_markdown_to_html_with_options(md, &[], &options).unwrap();
+        assert_eq!(
+            html,
+            "<h1 class=\"big\" id=\"start\"><a href=\"#intro\" aria-hidden=\"true\" class=\"anchor\" \
+             id=\"indico-md-intro\"></a>Intro</h1>\n\
+             <p><img src=\"/logo.png\" alt=\"Logo\" title=\"Our logo\" class=\"right\" width=\"200\" /></p>\n\
+             <pre class=\"wide\"><code class=\"language-python\">print(1)\n</code></pre>\n"
+        );
+
+        // headings without attributes are rendered as usual
+        let html = indico_markdown_to_html_with_options("# Intro", &[], &options).unwrap();
+        assert_eq!(html, indico_markdown_to_html("# Intro", &[]).unwrap());
+    }
+
+    #[test]
+    fn test_collapsible_sections() {
+        let md = "??? Day 1\n- **Opening**\n- Coffee\n???\n\n??? Not closed";
+        let options = RenderOptions {
+            collapsible_sections: true,
+            ..Default::default()
+        };
+        let html = indico_markdown_to_html_with_options(md, &[], &options).unwrap();
+        assert_eq!(
+            html,
+            "<details>\n<summary>Day 1</summary>\n<ul>\n<li><strong>Opening</strong></li>\n\
+             <li>Coffee</li>\n</ul>\n</details>\n<details>\n<summary>Not closed</summary>\n</details>\n"
+        );
+
+        let html = indico_markdown_to_html("??? Day 1\n???", &[]).unwrap();
+        assert_eq!(html, "<p>??? Day 1\n???</p>\n");
+    }
+
+    #[test]
+    fn test_code_block_elements() {
+        let md = "```mermaid\ngraph TD; A-->B\n```\n\n```rust\nfn main() {}\n```";
+        let html = indico_markdown_to_html(md, &[]).unwrap();
+        assert_eq!(
+            html,
+            "<pre class=\"diagram mermaid\">graph TD; A--&gt;B\n</pre>\n\
+             <pre><code class=\"language-rust\">fn main() {}\n</code></pre>\n"
+        );
+
+        let mut options = RenderOptions::default();
+        options.code_blocks.languages.insert(
+            "rust".into(),
+            CodeBlockElement {
+                element: "div".into(),
+                class: "runnable".into(),
+            },
+        );
+        let html =
+            indico_markdown_to_html_with_options("```rust\nfn main() {}\n```", &[], &options)
+                .unwrap();
+        assert_eq!(html, "<div class=\"runnable\">fn main() {}\n</div>\n");
+
+        let md = "```python title=\"run.py\" {1,3-5}\nprint(1)\n```\n\n```sh {2}\nls\n```";
+        let html = indico_markdown_to_html(md, &[]).unwrap();
+        assert_eq!(
+            html,
+            "<div class=\"code-block\">\n<div class=\"code-title\">run.py</div>\n\
+             <pre data-hl-lines=\"1,3-5\"><code class=\"language-python\">print(1)\n</code></pre>\n\
+             </div>\n<pre data-hl-lines=\"2\"><code class=\"language-sh\">ls\n</code></pre>\n"
+        );
+    }
+
+    #[test]
+    fn test_image_sizes() {
+        let options = RenderOptions {
+            image_size_syntax: true,
+            ..Default::default()
+        };
+        let md = "![Logo](/logo.png =300x200 \"Our logo\") ![x](/x.png =x50) `![a](b =1x2)`";
+        let html = indico_markdown_to_html_with_options(md, &[], &options).unwrap();
+        assert_eq!(
+            html,
+            "<p><img src=\"/logo.png\" alt=\"Logo\" title=\"Our logo\" width=\"300\" height=\"200\" /> \
+             <img src=\"/x.png\" alt=\"x\" height=\"50\" /> <code>![a](b =1x2)</code></p>\n"
+        );
+
+        // sizes from the caller are only used for images without one
+        let mut options = RenderOptions::default();
+        options.image_sizes.insert("/map.png".into(), (640, 480));
+        options.image_sizes.insert("/logo.png".into(), (1, 1));
+        options.image_size_syntax = true;
+        let html = indico_markdown_to_html_with_options(
+            "![Map](/map.png) ![](/logo.png =2x)",
+            &[],
+            &options,
+        )
+        .unwrap();
+        assert_eq!(
+            html,
+            "<p><img src=\"/map.png\" alt=\"Map\" width=\"640\" height=\"480\" /> \
+             <img src=\"/logo.png\" alt=\"\" width=\"2\" /></p>\n"
+        );
+
+        // the syntax isn't enabled by default
+        let html = indico_markdown_to_html("![a](/a.png =1x2)", &[]).unwrap();
+        assert_eq!(html, "<p>![a](/a.png =1x2)</p>\n");
+    }
+
+    #[test]
+    fn test_figures() {
+        let options = RenderOptions {
+            figures: true,
+            image_size_syntax: true,
+            ..Default::default()
+        };
+        let md = "![Poster](/poster.png =400x \"The poster\")\n\n![a](/a.png \"A\") and text\n\n![b](/b.png)";
+        let html = indico_markdown_to_html_with_options(md, &[], &options).unwrap();
+        assert_eq!(
+            html,
+            "<figure>\n<img src=\"/poster.png\" alt=\"Poster\" width=\"400\" />\n\
+             <figcaption>The poster</figcaption>\n</figure>\n\
+             <p><img src=\"/a.png\" alt=\"a\" title=\"A\" /> and text</p>\n\
+             <p><img src=\"/b.png\" alt=\"b\" /></p>\n"
+        );
+    }
+
+    #[test]
+    fn test_image_loading() {
+        let options = RenderOptions {
+            lazy_loading: true,
+            async_decoding: true,
+            referrer_policy: Some("no-referrer".into()),
+            ..Default::default()
+        };
+        let html =
+            indico_markdown_to_html_with_options("![a](https://example.com/a.png)", &[], &options)
+                .unwrap();
+        assert_eq!(
+            html,
+            "<p><img src=\"https://example.com/a.png\" alt=\"a\" loading=\"lazy\" \
+             decoding=\"async\" referrerpolicy=\"no-referrer\" /></p>\n"
+        );
+
+        // the hints are of no use in e-mails
+        let options = RenderOptions {
+            profile: RenderProfile::Email,
+            ..options
+        };
+        let html = indico_markdown_to_html_with_options("![a](/a.png)", &[], &options).unwrap();
+        assert_eq!(html, "<p><img src=\"/a.png\" alt=\"a\" /></p>\n");
+    }
+
+    #[test]
+    fn test_responsive_images() {
+        let options = RenderOptions {
+            base_url: Some("https://indico.example/event/1/".into()),
+            responsive_images: Some(ResponsiveImages(Arc::new(|url: &str| {
+                let thumb = url.strip_suffix(".png")?;
+                Some(ResponsiveImage {
+                    srcset: format!("{thumb}-480.png 480w, {url} 1200w"),
+                    sizes: Some("(max-width: 600px) 480px, 1200px".into()),
+                })
+            }))),
+            ..Default::default()
+        };
+        let html =
+            indico_markdown_to_html_with_options("![a](a.png) ![b](b.svg)", &[], &options).unwrap();
+        assert_eq!(
+            html,
+            "<p><img src=\"https://indico.example/event/1/a.png\" alt=\"a\" \
+             srcset=\"https://indico.example/event/1/a-480.png 480w, \
+             https://indico.example/event/1/a.png 1200w\" \
+             sizes=\"(max-width: 600px) 480px, 1200px\" /> \
+             <img src=\"https://indico.example/event/1/b.svg\" alt=\"b\" /></p>\n"
+        );
+    }
+
+    #[test]
+    fn test_interactive_tasks() {
+        let options = RenderOptions {
+            interactive_tasks: true,
+            front_matter: true,
+            ..Default::default()
+        };
+        let md = "---\ntitle: Minutes\n---\n- [ ] agenda\n- [x] budget\n- other";
+        let html = indico_markdown_to_html_with_options(md, &[], &options).unwrap();
+        assert_eq!(
+            html,
+            "<ul>\n<li><input type=\"checkbox\" data-index=\"0\" data-line=\"4\" /> agenda</li>\n\
+             <li><input type=\"checkbox\" data-index=\"1\" data-line=\"5\" checked=\"\" /> budget</li>\n\
+             <li>other</li>\n</ul>\n"
+        );
+        assert_eq!(
+            indico_markdown_toggle_task(md, 1).unwrap(),
+            "---\ntitle: Minutes\n---\n- [ ] agenda\n- [ ] budget\n- other"
+        );
+
+        let html = indico_markdown_to_html("- [x] done", &[]).unwrap();
+        assert_eq!(
+            html,
+            "<ul>\n<li><input type=\"checkbox\" checked=\"\" disabled=\"\" /> done</li>\n</ul>\n"
+        );
+    }
+
+    #[test]
+    fn test_tables() {
+        let md = "| a | b |\n|:-:|---|\n| 1 | 2 |";
+        let html = indico_markdown_to_html(md, &[]).unwrap();
+        assert_eq!(
+            html,
+            "<table>\n<thead>\n<tr>\n<th align=\"center\">a</th>\n<th>b</th>\n</tr>\n</thead>\n\
+             <tbody>\n<tr>\n<td align=\"center\">1</td>\n<td>2</td>\n</tr>\n</tbody>\n</table>\n"
+        );
+
+        let options = RenderOptions {
+            tables: TableOptions {
+                class: Some("table".into()),
+                alignment_classes: true,
+                wrapper: true,
+                header_scope: true,
+            },
+            ..Default::default()
+        };
+        let html = indico_markdown_to_html_with_options(md, &[], &options).unwrap();
+        assert_eq!(
+            html,
+            "<div class=\"table-wrapper\">\n<table class=\"table\">\n<thead>\n<tr>\n\
+             <th scope=\"col\" class=\"align-center\">a</th>\n<th scope=\"col\">b</th>\n</tr>\n\
+             </thead>\n<tbody>\n<tr>\n<td class=\"align-center\">1</td>\n<td>2</td>\n</tr>\n\
+             </tbody>\n</table>\n</div>\n"
+        );
+
+        // tables without a body
+        let html = indico_markdown_to_html_with_options("| a |\n|---|", &[], &options).unwrap();
+        assert_eq!(
+            html,
+            "<div class=\"table-wrapper\">\n<table class=\"table\">\n<thead>\n<tr>\n\
+             <th scope=\"col\">a</th>\n</tr>\n</thead>\n</table>\n</div>\n"
+        );
+    }
+
+    #[test]
+    fn test_alerts() {
+        let md = "> [!NOTE]\n> Read this\n\n> [!DANGER] Hot\n> Don't touch\n\n> [!OTHER]\n> text";
+        let html = indico_markdown_to_html(md, &[]).unwrap();
+        assert_eq!(
+            html,
+            "<div class=\"markdown-alert markdown-alert-note\">\n\
+             <p class=\"markdown-alert-title\">Note</p>\n<p>Read this</p>\n</div>\n\
+             <blockquote>\n<p>[!DANGER] Hot\nDon't touch</p>\n</blockquote>\n\
+             <blockquote>\n<p>[!OTHER]\ntext</p>\n</blockquote>\n"
+        );
+
+        let mut options = RenderOptions {
+            alerts: AlertOptions {
+                class_prefix: "alert".into(),
+                custom_kinds: vec!["danger".into()],
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        options
+            .alerts
+            .titles
+            .insert("note".into(), "Remarque".into());
+        options
+            .alerts
+            .titles
+            .insert("danger".into(), "Danger !".into());
+        let html = indico_markdown_to_html_with_options(md, &[], &options).unwrap();
+        assert_eq!(
+            html,
+            "<div class=\"alert alert-note\">\n<p class=\"alert-title\">Remarque</p>\n\
+             <p>Read this</p>\n</div>\n\
+             <div class=\"alert alert-danger\">\n<p class=\"alert-title\">Hot</p>\n\
+             <p>Don't touch</p>\n</div>\n\
+             <blockquote>\n<p>[!OTHER]\ntext</p>\n</blockquote>\n"
+        );
+
+        let options = RenderOptions {
+            profile: RenderProfile::Feed,
+            ..options
+        };
+        let html =
+            indico_markdown_to_html_with_options("> [!DANGER]\n> Hot", &[], &options).unwrap();
+        assert_eq!(
+            html,
+            "<blockquote>\n<p><strong>Danger !</strong></p>\n<p>Hot</p>\n</blockquote>\n"
+        );
+    }
+
+    #[test]
+    fn test_abbreviations() {
+        let options = RenderOptions {
+            abbreviations: true,
+            ..Default::default()
+        };
+        let md = "## HTML\n\nHTML and HTML5 by the W3C, `HTML`\n\n\
+                  *[HTML]: HyperText Markup Language\n*[W3C]: World Wide Web \"Consortium\"";
+        let html = indico_markdown_to_html_with_options(md, &[], &options).unwrap();
+        assert_eq!(
+            html,
+            "<h2><a href=\"#html\" aria-hidden=\"true\" class=\"anchor\" id=\"indico-md-html\"></a>HTML</h2>\n\
+             <p><abbr title=\"HyperText Markup Language\">HTML</abbr> and HTML5 by the \
+             <abbr title=\"World Wide Web &quot;Consortium&quot;\">W3C</abbr>, <code>HTML</code></p>\n"
+        );
+
+        let html = indico_markdown_to_html("*[W3C]: World Wide Web Consortium", &[]).unwrap();
+        assert_eq!(html, "<p>*[W3C]: World Wide Web Consortium</p>\n");
+    }
+
+    #[test]
+    fn test_embeds() {
+        let md = "https://youtu.be/dQw4w9WgXcQ\n\n\
+                  [our video](https://vimeo.com/76979871)\n\n\
+                  https://vimeo.com/76979871\n\n\
+                  see https://youtu.be/dQw4w9WgXcQ";
+        let options = RenderOptions {
+            embeds: Some(EmbedOptions {
+                providers: vec![EmbedProvider::YouTube],
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        let html = indico_markdown_to_html_with_options(md, &[], &options).unwrap();
+        assert_eq!(
+            html,
+            "<div class=\"embed embed-youtube\"><iframe \
+             src=\"https://www.youtube-nocookie.com/embed/dQw4w9WgXcQ\" loading=\"lazy\" \
+             allowfullscreen=\"\"></iframe></div>\n\
+             <p><a href=\"https://vimeo.com/76979871\" target=\"_blank\">our video</a></p>\n\
+             <p><a href=\"https://vimeo.com/76979871\" target=\"_blank\">https://vimeo.com/76979871</a></p>\n\
+             <p>see <a href=\"https://youtu.be/dQw4w9WgXcQ\" target=\"_blank\">https://youtu.be/dQw4w9WgXcQ</a></p>\n"
+        );
+
+        let options = RenderOptions {
+            embeds: Some(EmbedOptions {
+                style: EmbedStyle::Placeholder,
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        let html =
+            indico_markdown_to_html_with_options("https://vimeo.com/76979871", &[], &options)
+                .unwrap();
+        assert_eq!(
+            html,
+            "<div class=\"embed embed-vimeo\" data-embed-provider=\"vimeo\" \
+             data-embed-url=\"https://player.vimeo.com/video/76979871\">\
+             <a href=\"https://vimeo.com/76979871\" target=\"_blank\">https://vimeo.com/76979871</a></div>\n"
+        );
+    }
+
+    #[test]
+    fn test_placeholders() {
+        let values = HashMap::from([
+            ("event_title".to_string(), "Physics <& more>".to_string()),
+            ("room".to_string(), "**Main**".to_string()),
+        ]);
+        let options = RenderOptions {
+            placeholders: Some(values),
+            ..Default::default()
+        };
+        let md = "Welcome to *{event_title}* in {room}, {unknown} {1,2}\n\n`{room}`";
+        let output = indico_markdown_render(md, &[], &options).unwrap();
+        assert_eq!(
+            output.html,
+            "<p>Welcome to <em>Physics &lt;&amp; more&gt;</em> in **Main**, {unknown} {1,2}</p>\n\
+             <p><code>{room}</code></p>\n"
+        );
+        assert_eq!(output.unknown_placeholders, vec!["unknown".to_string()]);
+
+        let output = indico_markdown_render(md, &[], &RenderOptions::default()).unwrap();
+        assert!(output.unknown_placeholders.is_empty());
+    }
+
+    #[test]
+    fn test_read_more() {
+        let options = RenderOptions {
+            read_more: true,
+            ..Default::default()
+        };
+        let md = "Intro\n\n> quoted\n>\n> <!-- more -->\n>\n> hidden\n\nRest";
+        let output = indico_markdown_render(md, &[], &options).unwrap();
+        assert_eq!(
+            output.html,
+            "<p>Intro</p>\n<blockquote>\n<p>quoted</p>\n<p>hidden</p>\n</blockquote>\n\
+             <p>Rest</p>\n"
+        );
+        assert_eq!(
+            output.teaser.as_deref(),
+            Some("<p>Intro</p>\n<blockquote>\n<p>quoted</p>\n</blockquote>\n")
+        );
+
+        let output = indico_markdown_render("Intro <!--more--> rest", &[], &options).unwrap();
+        assert_eq!(output.html, "<p>Intro  rest</p>\n");
+        assert_eq!(output.teaser.as_deref(), Some("<p>Intro </p>\n"));
+
+        let output = indico_markdown_render("No marker", &[], &options).unwrap();
+        assert_eq!(output.teaser, None);
+    }
+
+    #[test]
+    fn test_render_streaming() {
+        let md = "# Minutes\n\nSee [the agenda][agenda][^1].\n\n- one\n- two\n\n# Minutes\n\n\
+                  [agenda]: /event/1/timetable\n[^1]: Published yesterday.";
+        let options = RenderOptions {
+            footnotes: Some(FootnoteOptions::default()),
+            document: Some(DocumentOptions {
+                title: "Minutes".into(),
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        let mut chunks = Vec::new();
+        let output = indico_markdown_render_streaming(md, &[], &options, |chunk| {
+            chunks.push(chunk.to_string())
+        })
+        .unwrap();
+        let expected = indico_markdown_render(md, &[], &options).unwrap();
+        assert_eq!(chunks.concat(), expected.html);
+        assert!(chunks.len() > 4);
+        assert!(chunks[0].starts_with("<!DOCTYPE html>"));
+        assert!(chunks[1].starts_with("<h1"));
+        assert!(output.html.is_empty());
+        assert_eq!(output.heading_slugs, expected.heading_slugs);
+    }
+
+    #[test]
+    fn test_render_limits() {
+        let rules = [LinkRule::new(r"#(\d+)", "/tickets/{1}").unwrap()];
+        let md = "See #1, #2 and #3";
+        let options = RenderOptions {
+            limits: Some(RenderLimits {
+                max_nodes: 5,
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        assert!(indico_markdown_to_html_with_options(md, &[], &options).is_ok());
+        // the links which are added count as well
+        let err = indico_markdown_to_html_with_options(md, &rules, &options).unwrap_err();
+        assert!(matches!(err, RenderError::TooManyNodes(5)));
+        assert_eq!(
+            err.to_string(),
+            "the document is too large (more than 5 nodes)"
+        );
+
+        let options = RenderOptions {
+            limits: Some(RenderLimits {
+                max_text_len: 10,
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        let err = indico_markdown_to_html_with_options(md, &[], &options).unwrap_err();
+        assert!(matches!(err, RenderError::TextTooLong(10)));
+    }
+
+    #[test]
+    fn test_raw_html() {
+        // raw HTML should be escaped when tagfilter is enabled
+        let md = "<script>alert('x')</script>";
+        let html = indico_markdown_to_html(md, &[]).unwrap();
+        assert_eq!(html, "&lt;script>alert('x')&lt;/script>\n");
+
+        let md = "<div>FOO</div>";
+        let html =
+            indico_markdown_to_html(md, &[LinkRule::new(r"FOO", "https://example/{0}").unwrap()])
+                .unwrap();
+        assert_eq!(html, "<div>FOO</div>\n");
+
+        let md = "<a href=\"http://something.com\">FOO</a>";
+        let html =
+            indico_markdown_to_html(md, &[LinkRule::new(r"FOO", "https://example/{0}").unwrap()])
+                .unwrap();
+        assert_eq!(html, "<p><a href=\"http://something.com\">FOO</a></p>\n");
+
+        // inline HTML-like tags are also escaped rather than rendered
+        let md = "A <b>bold</b> move";
+        let html = indico_markdown_to_html(md, &[]).unwrap();
+        assert_eq!(html, "<p>A <b>bold</b> move</p>\n");
+    }
+
+    #[test]
+    fn test_disabled_extensions() {
+        let options = RenderOptions {
+            extensions: Extensions {
+                tables: false,
+                alerts: false,
+            },
+            raw_html: RawHtml::Unfiltered,
+            links: LinkOptions {
+                target_blank: false,
+            },
+            ..Default::default()
+        };
+        let render = |md| indico_markdown_to_html_with_options(md, &[], &options).unwrap();
+        assert_eq!(render("| a |\n|---|"), "<p>| a |\n|---|</p>\n");
+        assert_eq!(
+            render("> [!NOTE]\n> Read this"),
+            "<blockquote>\n<p>[!NOTE]\nRead this</p>\n</blockquote>\n"
+        );
+        assert_eq!(
+            render("<script>alert('x')</script>"),
+            "<script>alert('x')</script>\n"
+        );
+        assert_eq!(
+            render("[x](https://example.com \"X\")"),
+            "<p><a href=\"https://example.com\" title=\"X\">x</a></p>\n"
+        );
+
+        let options = RenderOptions {
+            profile: RenderProfile::Email,
+            ..options
+        };
+        assert_eq!(
+            indico_markdown_to_html_with_options("[x](https://example.com)", &[], &options)
+                .unwrap(),
+            "<p><a href=\"https://example.com\" style=\"color:#1a64a0\">x</a></p>\n"
+        );
+    }
+
+    #[test]
+    fn test_link_attributes_escaped() {
+        let html = indico_markdown_to_html("[x](<u\" onmouseover=\"alert(1)>)", &[]).unwrap();
+        assert_eq!(
+            html,
+            "<p><a href=\"u%22%20onmouseover=%22alert(1)\" target=\"_blank\">x</a></p>\n"
+        );
+
+        let html = indico_markdown_to_html("[x](/a \"say \\\"hi\\\" <b>\")", &[]).unwrap();
+        assert_eq!(
+            html,
+            "<p><a href=\"/a\" title=\"say &quot;hi&quot; &lt;b&gt;\" target=\"_blank\">x</a></p>\n"
+        );
+    }
+
+    #[test]
+    fn test_escaped_raw_html() {
+        let options = RenderOptions {
+            raw_html: RawHtml::Escaped,
+            ..Default::default()
+        };
+        let render = |md| indico_markdown_to_html_with_options(md, &[], &options).unwrap();
+        assert_eq!(
+            render("A <b>bold</b> move"),
+            "<p>A &lt;b&gt;bold&lt;/b&gt; move</p>\n"
+        );
+        assert_eq!(
+            render("<div onclick=\"x()\">\nhi\n</div>"),
+            "<p>&lt;div onclick=&quot;x()&quot;&gt;\nhi\n&lt;/div&gt;</p>\n"
+        );
+        assert_eq!(render("`<b>`"), "<p><code>&lt;b&gt;</code></p>\n");
+    }
+
+    #[test]
+    fn test_indico_md_to_plain() {
+        let md = "[**Foo**](https://example.com)\n\n==B`ar`==<div>foo</div>";
+        let html = indico_markdown_to_unstyled_html(md, &[]).unwrap();
+        assert_eq!(html, "<p>Foo</p>\n<p>Barfoo</p>\n");
+
+        let md = "soft\\\nvs hard break\n\nhello";
+        let html = indico_markdown_to_unstyled_html(md, &[]).unwrap();
+        assert_eq!(html, "<p>soft<br />\nvs hard break</p>\n<p>hello</p>\n");
+
+        let md = "soft<br/>vs hard break<p>hello</p>";
+        let html = indico_markdown_to_unstyled_html(md, &[]).unwrap();
+        assert_eq!(html, "<p>soft<br />vs hard break</p>\n<p>hello</p>\n");
+
+        let md = "* a list\n* of\n  - nested\n* things";
+        let html = indico_markdown_to_unstyled_html(md, &[]).unwrap();
+        assert_eq!(
+            html,
+            "\n  * a list\n  * of\n    - nested\n\n\n  * things\n\n"
+        );
+
+        let md = "1. a list\n2. of\n    - nested\n3. ordered things";
+        let html = indico_markdown_to_unstyled_html(md, &[]).unwrap();
+        assert_eq!(
+            html,
+            "\n  1. a list\n  2. of\n    - nested\n\n\n  3. ordered things\n\n"
+        );
+    }
+
+    #[test]
+    fn test_unstyled_many_inline_tags() {
+        // the tags of large documents are matched without recompiling anything per tag
+        let md = "a<br>b <P class=x>c</p> <span>d</span> ".repeat(5000);
+        let html = indico_markdown_to_unstyled_html(&md, &[]).unwrap();
+        assert_eq!(html.matches("<br />").count(), 5000);
+        // each inline <p> and </p> starts a new paragraph
+        assert_eq!(html.matches("<p>").count(), 10001);
+        assert_eq!(html.matches("</p>").count(), 10001);
+        assert!(!html.contains("span"));
     }
 }
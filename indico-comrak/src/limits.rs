@@ -0,0 +1,77 @@
+//! Limits of the size of documents, so that the memory used to render untrusted ones is
+//! bounded.
+
+use comrak::nodes::{AstNode, NodeValue};
+
+use crate::{RenderError, options::RenderLimits};
+
+/// Length of the text, code or raw HTML of a node
+fn text_len(value: &NodeValue) -> usize {
+    match value {
+        NodeValue::Text(text) => text.len(),
+        NodeValue::HtmlInline(html) => html.len(),
+        NodeValue::Code(nc) => nc.literal.len(),
+        NodeValue::CodeBlock(ncb) => ncb.literal.len(),
+        NodeValue::HtmlBlock(nhb) => nhb.literal.len(),
+        NodeValue::Math(nm) => nm.literal.len(),
+        _ => 0,
+    }
+}
+
+/// Check that a document doesn't exceed the limits, stopping as soon as it does
+pub(crate) fn check_limits<'a>(
+    root: &'a AstNode<'a>,
+    limits: &RenderLimits,
+) -> Result<(), RenderError> {
+    let mut total_text_len = 0;
+    for (count, node) in root.descendants().enumerate() {
+        if count >= limits.max_nodes {
+            return Err(RenderError::TooManyNodes(limits.max_nodes));
+        }
+        total_text_len += text_len(&node.data.borrow().value);
+        if total_text_len > limits.max_text_len {
+            return Err(RenderError::TextTooLong(limits.max_text_len));
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use comrak::{Arena, parse_document};
+
+    use super::check_limits;
+    use crate::{RenderError, indico_options, options::RenderLimits};
+
+    #[test]
+    fn test_check_limits() {
+        let arena = Arena::new();
+        // document, paragraph, text, code and text
+        let root = parse_document(&arena, "Some `code` here", &indico_options());
+        let limits = RenderLimits {
+            max_nodes: 5,
+            max_text_len: 14,
+        };
+        assert!(check_limits(root, &limits).is_ok());
+        assert!(matches!(
+            check_limits(
+                root,
+                &RenderLimits {
+                    max_nodes: 4,
+                    ..limits
+                }
+            ),
+            Err(RenderError::TooManyNodes(4))
+        ));
+        assert!(matches!(
+            check_limits(
+                root,
+                &RenderLimits {
+                    max_text_len: 13,
+                    ..limits
+                }
+            ),
+            Err(RenderError::TextTooLong(13))
+        ));
+    }
+}
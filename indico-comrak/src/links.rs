@@ -0,0 +1,113 @@
+//! Checks of the internal links of documents, so that editors can be warned about broken
+//! links before saving.
+
+use comrak::{Arena, html::Anchorizer, nodes::NodeValue, parse_document};
+use std::collections::HashSet;
+
+use crate::{
+    HEADER_ID_PREFIX, collect_text,
+    extract::{SourcePositions, SourceRange},
+    indico_options,
+    options::OffsetMode,
+    warnings::url_scheme,
+};
+
+/// A link whose target is unknown
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BrokenLink {
+    /// URL of the link, as written
+    pub url: String,
+    /// Position of the link in the markdown source
+    pub sourcepos: SourceRange,
+}
+
+/// Check whether a URL points inside the site (a path or an anchor), rather than to another
+/// site or through another protocol (e.g. `mailto:`)
+fn is_internal_url(url: &str) -> bool {
+    !url.is_empty() && !url.starts_with("//") && url_scheme(url).is_none()
+}
+
+/// Check whether an internal URL is one of the known targets. The query string is ignored, and
+/// a URL with an anchor (`/path#anchor`) is valid if it is known as such, or if its anchor is
+/// known and its path is either known or empty.
+fn is_known_target(url: &str, known: &HashSet<String>) -> bool {
+    let (path, anchor) = match url.split_once('#') {
+        Some((path, anchor)) => (path, Some(anchor)),
+        None => (url, None),
+    };
+    let path = path.split_once('?').map_or(path, |(path, _)| path);
+    match anchor {
+        Some(anchor) => {
+            known.contains(&format!("{path}#{anchor}"))
+                || ((path.is_empty() || known.contains(path))
+                    && known.contains(&format!("#{anchor}")))
+        }
+        None => known.contains(path),
+    }
+}
+
+/// Find the links of a document which point inside the site but not to one of the known
+/// targets, e.g. `/event/1/timetable` or `#registration`. Anchors of the document's own
+/// headings (with or without the `indico-md-` prefix of their IDs) are always known. Links to
+/// other sites, and with other protocols (e.g. `mailto:`), are not checked.
+pub fn indico_markdown_broken_links(
+    md_source: &str,
+    known_targets: &HashSet<String>,
+    offset_mode: OffsetMode,
+) -> Vec<BrokenLink> {
+    let arena = Arena::new();
+    let root = parse_document(&arena, md_source, &indico_options());
+    let positions = SourcePositions::new(md_source, offset_mode);
+    let mut known = known_targets.clone();
+    let mut anchorizer = Anchorizer::new();
+    let mut links = Vec::new();
+
+    for node in root.descendants() {
+        let data = node.data.borrow();
+        match data.value {
+            NodeValue::Heading(_) => {
+                let slug = anchorizer.anchorize(&collect_text(node));
+                known.insert(format!("#{HEADER_ID_PREFIX}{slug}"));
+                known.insert(format!("#{slug}"));
+            }
+            NodeValue::Link(ref nl) if is_internal_url(&nl.url) => links.push(BrokenLink {
+                url: nl.url.clone(),
+                sourcepos: positions.range(data.sourcepos.into()),
+            }),
+            _ => {}
+        }
+    }
+
+    links.retain(|link| !is_known_target(&link.url, &known));
+    links
+}
+
+#[cfg(test)]
+mod tests {
+    use super::indico_markdown_broken_links;
+    use crate::options::OffsetMode;
+    use std::collections::HashSet;
+
+    #[test]
+    fn test_broken_links() {
+        let md = "See the [timetable](/event/1/timetable?view=standard), \
+                  [day 2](/event/1/timetable#day-2) and [the venue](#venue).\n\n\
+                  [Old page](/event/1/old) [Nowhere](#nowhere) \
+                  [Mail](mailto:a@example.com) [Site](https://example.com/x)\n\n## Venue";
+        let known: HashSet<String> = ["/event/1/timetable", "#day-2"]
+            .into_iter()
+            .map(String::from)
+            .collect();
+        let broken: Vec<_> = indico_markdown_broken_links(md, &known, OffsetMode::Bytes)
+            .into_iter()
+            .map(|link| (link.url, link.sourcepos.start))
+            .collect();
+        assert_eq!(
+            broken,
+            [
+                ("/event/1/old".to_string(), (3, 1)),
+                ("#nowhere".to_string(), (3, 26)),
+            ]
+        );
+    }
+}
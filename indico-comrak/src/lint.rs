@@ -0,0 +1,206 @@
+//! Style checks of documents, so that client-side editors can underline likely mistakes.
+
+use comrak::{
+    Arena,
+    nodes::{ListType, NodeValue, Sourcepos},
+    parse_document,
+};
+use std::ops::Range;
+
+use crate::{extract::SourcePositions, indico_options, options::OffsetMode};
+
+/// Kind of a lint diagnostic
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LintKind {
+    /// A bullet list uses a different marker (`-`, `*` or `+`) than the first one of the
+    /// document
+    InconsistentListMarker,
+    /// A URL which isn't written as a link, and is turned into one
+    BareUrl,
+    /// A hard line break made of trailing spaces, which are easy to miss (a backslash is
+    /// clearer)
+    TrailingSpaceBreak,
+    /// An emphasis marker (`*` or `_`) which isn't closed, and is rendered as is
+    UnclosedEmphasis,
+}
+
+/// A likely mistake found in a document
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LintDiagnostic {
+    pub kind: LintKind,
+    /// Description of the problem, in English
+    pub message: String,
+    /// Byte range of the problem in the markdown source
+    pub range: Range<usize>,
+    /// The same range in UTF-16 code units, with [`OffsetMode::Utf16`]
+    pub utf16_range: Option<Range<usize>>,
+}
+
+/// Byte offsets of the start of each line of a text
+pub(crate) fn line_starts(text: &str) -> Vec<usize> {
+    std::iter::once(0)
+        .chain(text.match_indices('\n').map(|(i, _)| i + 1))
+        .collect()
+}
+
+/// Convert a source position to a byte range, given the offsets of the lines of the source
+fn byte_range(line_starts: &[usize], sourcepos: Sourcepos) -> Option<Range<usize>> {
+    let start = line_starts.get(sourcepos.start.line.checked_sub(1)?)? + sourcepos.start.column;
+    let end = line_starts.get(sourcepos.end.line.checked_sub(1)?)? + sourcepos.end.column;
+    Some(start.checked_sub(1)?..end)
+}
+
+/// Find the runs of emphasis markers in a range of the source which may open emphasis (i.e.
+/// are followed by text) but can't be closing one (i.e. are preceded by whitespace, or start a
+/// line). Escaped markers and underscores within words are ignored.
+fn opening_markers(source: &str, range: Range<usize>) -> Vec<Range<usize>> {
+    let mut runs = Vec::new();
+    let mut chars = source[range.clone()].char_indices().peekable();
+    while let Some((i, c)) = chars.next() {
+        if !matches!(c, '*' | '_') {
+            continue;
+        }
+        let start = range.start + i;
+        let mut end = start + 1;
+        while let Some(&(j, next)) = chars.peek()
+            && next == c
+            && range.start + j == end
+        {
+            end += 1;
+            chars.next();
+        }
+        let prev = source[..start].chars().next_back();
+        let next = source[end..].chars().next();
+        if prev != Some('\\')
+            && prev.is_none_or(char::is_whitespace)
+            && next.is_some_and(|next| !next.is_whitespace())
+        {
+            runs.push(start..end);
+        }
+    }
+    runs
+}
+
+/// Check a document for likely mistakes: inconsistent bullet list markers, bare URLs, hard
+/// line breaks made of trailing spaces and unclosed emphasis. The ranges of the diagnostics
+/// are byte ranges of the source (and UTF-16 ones if requested), in document order.
+pub fn indico_markdown_lint(md_source: &str, offset_mode: OffsetMode) -> Vec<LintDiagnostic> {
+    let arena = Arena::new();
+    let root = parse_document(&arena, md_source, &indico_options());
+    let line_starts = line_starts(md_source);
+    let mut diagnostics = Vec::new();
+    let mut bullet_char = None;
+
+    for node in root.descendants() {
+        let data = node.data.borrow();
+        let Some(range) = byte_range(&line_starts, data.sourcepos) else {
+            continue;
+        };
+        let Some(source) = md_source.get(range.clone()) else {
+            continue;
+        };
+        match data.value {
+            NodeValue::List(ref nl) if nl.list_type == ListType::Bullet => {
+                let marker = nl.bullet_char as char;
+                match bullet_char {
+                    None => bullet_char = Some(marker),
+                    Some(first) if first != marker => diagnostics.push(LintDiagnostic {
+                        kind: LintKind::InconsistentListMarker,
+                        message: format!(
+                            "List marked with '{marker}', while the first list uses '{first}'"
+                        ),
+                        range: range.start..range.start + 1,
+                        utf16_range: None,
+                    }),
+                    _ => {}
+                }
+            }
+            NodeValue::Link(_) if !source.starts_with(['[', '<']) => {
+                diagnostics.push(LintDiagnostic {
+                    kind: LintKind::BareUrl,
+                    message: "Bare URL, which is turned into a link".to_string(),
+                    range,
+                    utf16_range: None,
+                });
+            }
+            NodeValue::LineBreak => {
+                // the break is at the end of its line
+                let line = data.sourcepos.start.line;
+                let line_end = line_starts
+                    .get(line)
+                    .map_or(md_source.len(), |&next| next - 1);
+                let text = md_source[line_starts[line - 1]..line_end].trim_end_matches('\r');
+                let spaces = text.len() - text.trim_end_matches(' ').len();
+                if spaces >= 2 {
+                    let end = line_starts[line - 1] + text.len();
+                    diagnostics.push(LintDiagnostic {
+                        kind: LintKind::TrailingSpaceBreak,
+                        message: "Line break made of trailing spaces, use a backslash instead"
+                            .to_string(),
+                        range: end - spaces..end,
+                        utf16_range: None,
+                    });
+                }
+            }
+            NodeValue::Text(_) => {
+                for run in opening_markers(md_source, range) {
+                    diagnostics.push(LintDiagnostic {
+                        kind: LintKind::UnclosedEmphasis,
+                        message: format!("Unclosed '{}'", &md_source[run.clone()]),
+                        range: run,
+                        utf16_range: None,
+                    });
+                }
+            }
+            _ => {}
+        }
+    }
+    diagnostics.sort_by_key(|diagnostic| diagnostic.range.start);
+
+    let positions = SourcePositions::new(md_source, offset_mode);
+    for diagnostic in &mut diagnostics {
+        diagnostic.utf16_range = positions
+            .offset(diagnostic.range.start)
+            .zip(positions.offset(diagnostic.range.end))
+            .map(|(start, end)| start..end);
+    }
+    diagnostics
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{LintKind, indico_markdown_lint, opening_markers};
+    use crate::options::OffsetMode;
+
+    #[test]
+    fn test_opening_markers() {
+        assert_eq!(opening_markers("**bold text", 0..11), [0..2]);
+        assert_eq!(opening_markers("a *b and _c", 0..11), [2..3, 9..10]);
+        assert_eq!(opening_markers("a **b", 2..4), [2..4]);
+        assert!(opening_markers("5 * 3, snake_case, \\*escaped", 0..28).is_empty());
+    }
+
+    #[test]
+    fn test_lint() {
+        let md = "- one\n- two\n\n* three\n\nSee https://example.com and <https://example.org>.  \n\
+                  Some **bold text\\\nend";
+        let diagnostics: Vec<_> = indico_markdown_lint(md, OffsetMode::Bytes)
+            .into_iter()
+            .map(|d| (d.kind, &md[d.range]))
+            .collect();
+        assert_eq!(
+            diagnostics,
+            [
+                (LintKind::InconsistentListMarker, "*"),
+                (LintKind::BareUrl, "https://example.com"),
+                (LintKind::TrailingSpaceBreak, "  "),
+                (LintKind::UnclosedEmphasis, "**"),
+            ]
+        );
+        assert!(indico_markdown_lint("- *fine*\n- [link](/x)", OffsetMode::Bytes).is_empty());
+
+        let diagnostics = indico_markdown_lint("Café 😀 **bold", OffsetMode::Utf16);
+        assert_eq!(diagnostics[0].range, 11..13);
+        assert_eq!(diagnostics[0].utf16_range, Some(8..10));
+    }
+}
@@ -0,0 +1,110 @@
+//! LaTeX-style math delimiters: `\(...\)` for inline formulas and `\[...\]` for display ones.
+//!
+//! comrak only knows about dollar delimiters (and parses `\(` as an escaped parenthesis), so
+//! before parsing, the formulas are rewritten with dollars: `$...$` and `$$...$$`.
+
+use std::borrow::Cow;
+
+use crate::containers::CodeFences;
+
+/// Rewrite the bracket delimiters of a piece of text (which isn't a code block) with dollars,
+/// leaving code spans alone. If dollars aren't delimiters themselves, the other ones are
+/// escaped.
+fn convert_text(text: &str, escape_dollars: bool, out: &mut String) {
+    let mut rest = text;
+    while let Some(start) = rest.find(['\\', '`', '$']) {
+        out.push_str(&rest[..start]);
+        rest = &rest[start..];
+
+        if rest.starts_with('$') {
+            out.push_str(if escape_dollars { "\\$" } else { "$" });
+            rest = &rest[1..];
+        } else if rest.starts_with('`') {
+            let ticks = rest.chars().take_while(|&c| c == '`').count();
+            let after = &rest[ticks..];
+            let len = match after.find(&rest[..ticks]) {
+                Some(end) => 2 * ticks + end,
+                None => ticks,
+            };
+            out.push_str(&rest[..len]);
+            rest = &rest[len..];
+        } else if let Some(formula) = rest.strip_prefix("\\(")
+            && let Some(end) = formula.find("\\)")
+            && !formula[..end].trim().is_empty()
+            && !formula[..end].contains('$')
+        {
+            // inline dollar math can't start or end with a space
+            out.push('$');
+            out.push_str(formula[..end].trim());
+            out.push('$');
+            rest = &formula[end + 2..];
+        } else if let Some(formula) = rest.strip_prefix("\\[")
+            && let Some(end) = formula.find("\\]")
+            && !formula[..end].trim().is_empty()
+            && !formula[..end].contains('$')
+        {
+            out.push_str("$$");
+            out.push_str(&formula[..end]);
+            out.push_str("$$");
+            rest = &formula[end + 2..];
+        } else {
+            // other escapes (including `\\`) are kept as they are
+            let len = 1 + rest[1..].chars().next().map_or(0, char::len_utf8);
+            out.push_str(&rest[..len]);
+            rest = &rest[len..];
+        }
+    }
+    out.push_str(rest);
+}
+
+/// Rewrite the `\(...\)` and `\[...\]` formulas of a document with dollar delimiters, escaping
+/// the dollars which were already in the document if `escape_dollars` is set
+pub(crate) fn convert_bracket_delimiters(md_source: &str, escape_dollars: bool) -> Cow<'_, str> {
+    if !md_source.contains("\\(") && !md_source.contains("\\[") && !escape_dollars {
+        return Cow::Borrowed(md_source);
+    }
+
+    let mut out = String::with_capacity(md_source.len());
+    let mut code_fences = CodeFences::default();
+    // formulas may span several lines, so consecutive lines of text are converted together
+    let mut text_start = 0;
+    let mut offset = 0;
+    for line in md_source.split_inclusive('\n') {
+        let content = line.trim_end_matches(['\n', '\r']);
+        // neither code blocks (fenced or indented) nor code spans are touched
+        if code_fences.in_code(content) || line.starts_with("    ") || line.starts_with('\t') {
+            convert_text(&md_source[text_start..offset], escape_dollars, &mut out);
+            out.push_str(line);
+            text_start = offset + line.len();
+        }
+        offset += line.len();
+    }
+    convert_text(&md_source[text_start..], escape_dollars, &mut out);
+    Cow::Owned(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::convert_bracket_delimiters;
+
+    #[test]
+    fn test_convert_bracket_delimiters() {
+        assert_eq!(
+            convert_bracket_delimiters("Area: \\( \\pi r^2 \\), or\n\\[\nx^2\n\\]\n", false),
+            "Area: $\\pi r^2$, or\n$$\nx^2\n$$\n"
+        );
+        assert_eq!(
+            convert_bracket_delimiters("costs $5, \\(x\\) and `\\(y\\)` and \\\\(z)", true),
+            "costs \\$5, $x$ and `\\(y\\)` and \\\\(z)"
+        );
+        assert_eq!(
+            convert_bracket_delimiters("```\n\\(code\\)\n```\n    \\(indented\\)\n", false),
+            "```\n\\(code\\)\n```\n    \\(indented\\)\n"
+        );
+        // escaped brackets which aren't formulas are kept
+        assert_eq!(
+            convert_bracket_delimiters("\\[citation needed] \\(", false),
+            "\\[citation needed] \\("
+        );
+    }
+}
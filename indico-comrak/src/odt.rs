@@ -0,0 +1,253 @@
+//! Export of markdown to OpenDocument text, as a flat XML document (`.fodt`), which word
+//! processors such as LibreOffice can open directly.
+
+use comrak::{
+    Arena,
+    arena_tree::NodeEdge,
+    nodes::{AstNode, ListType, NodeValue},
+    parse_document,
+};
+
+use crate::{LinkRule, add_links, html::escape_xml, indico_options};
+
+const ODF_HEADER: &str = r##"<?xml version="1.0" encoding="UTF-8"?>
+<office:document xmlns:office="urn:oasis:names:tc:opendocument:xmlns:office:1.0" xmlns:style="urn:oasis:names:tc:opendocument:xmlns:style:1.0" xmlns:text="urn:oasis:names:tc:opendocument:xmlns:text:1.0" xmlns:table="urn:oasis:names:tc:opendocument:xmlns:table:1.0" xmlns:fo="urn:oasis:names:tc:opendocument:xmlns:xsl-fo-compatible:1.0" xmlns:xlink="http://www.w3.org/1999/xlink" office:version="1.3" office:mimetype="application/vnd.oasis.opendocument.text">
+<office:automatic-styles>
+<style:style style:name="T_Strong" style:family="text"><style:text-properties fo:font-weight="bold"/></style:style>
+<style:style style:name="T_Emph" style:family="text"><style:text-properties fo:font-style="italic"/></style:style>
+<style:style style:name="T_Strike" style:family="text"><style:text-properties style:text-line-through-style="solid"/></style:style>
+<style:style style:name="T_Underline" style:family="text"><style:text-properties style:text-underline-style="solid"/></style:style>
+<style:style style:name="T_Mark" style:family="text"><style:text-properties fo:background-color="#ffff00"/></style:style>
+<style:style style:name="T_Code" style:family="text"><style:text-properties fo:font-family="monospace"/></style:style>
+<style:style style:name="P_Code" style:family="paragraph"><style:text-properties fo:font-family="monospace"/></style:style>
+<style:style style:name="P_Quote" style:family="paragraph"><style:paragraph-properties fo:margin-left="0.5in"/></style:style>
+<style:style style:name="P_Rule" style:family="paragraph"><style:paragraph-properties fo:border-bottom="0.5pt solid #000000"/></style:style>
+<text:list-style style:name="L_Bullet"><text:list-level-style-bullet text:level="1" text:bullet-char="•"/><text:list-level-style-bullet text:level="2" text:bullet-char="◦"/><text:list-level-style-bullet text:level="3" text:bullet-char="▪"/></text:list-style>
+<text:list-style style:name="L_Number"><text:list-level-style-number text:level="1" style:num-format="1" style:num-suffix="."/><text:list-level-style-number text:level="2" style:num-format="1" style:num-suffix="."/><text:list-level-style-number text:level="3" style:num-format="1" style:num-suffix="."/></text:list-style>
+</office:automatic-styles>
+<office:body>
+<office:text>
+"##;
+
+const ODF_FOOTER: &str = "</office:text>\n</office:body>\n</office:document>\n";
+
+/// Write text, using the ODF elements for runs of spaces, tabs and line breaks
+fn write_text(out: &mut String, text: &str) {
+    let mut spaces = 0;
+    let flush = |out: &mut String, spaces: &mut usize| {
+        match *spaces {
+            0 => {}
+            1 => out.push(' '),
+            n => out.push_str(&format!(" <text:s text:c=\"{}\"/>", n - 1)),
+        }
+        *spaces = 0;
+    };
+
+    for c in text.chars() {
+        match c {
+            ' ' => spaces += 1,
+            '\t' => {
+                flush(out, &mut spaces);
+                out.push_str("<text:tab/>");
+            }
+            '\n' => {
+                flush(out, &mut spaces);
+                out.push_str("<text:line-break/>");
+            }
+            _ => {
+                flush(out, &mut spaces);
+                escape_xml(out, c.encode_utf8(&mut [0; 4]), false);
+            }
+        }
+    }
+    flush(out, &mut spaces);
+}
+
+fn in_quote<'a>(node: &'a AstNode<'a>) -> bool {
+    node.ancestors().skip(1).any(|n| {
+        matches!(
+            n.data.borrow().value,
+            NodeValue::BlockQuote | NodeValue::Alert(..)
+        )
+    })
+}
+
+/// Whether a list is nested in another one (only top-level lists get a style)
+fn is_nested_list<'a>(node: &'a AstNode<'a>) -> bool {
+    node.ancestors()
+        .skip(1)
+        .any(|n| matches!(n.data.borrow().value, NodeValue::List(..)))
+}
+
+fn span_style(value: &NodeValue) -> Option<&'static str> {
+    match value {
+        NodeValue::Strong => Some("T_Strong"),
+        NodeValue::Emph => Some("T_Emph"),
+        NodeValue::Strikethrough => Some("T_Strike"),
+        NodeValue::Underline => Some("T_Underline"),
+        NodeValue::Highlight => Some("T_Mark"),
+        _ => None,
+    }
+}
+
+fn enter<'a>(out: &mut String, node: &'a AstNode<'a>) {
+    let data = node.data.borrow();
+    let value = &data.value;
+
+    if let Some(style) = span_style(value) {
+        out.push_str(&format!("<text:span text:style-name=\"{style}\">"));
+        return;
+    }
+
+    match value {
+        NodeValue::Paragraph => {
+            if in_quote(node) {
+                out.push_str("<text:p text:style-name=\"P_Quote\">");
+            } else {
+                out.push_str("<text:p>");
+            }
+        }
+        NodeValue::Heading(nh) => {
+            out.push_str(&format!("<text:h text:outline-level=\"{}\">", nh.level));
+        }
+        NodeValue::Text(t) => write_text(out, t),
+        NodeValue::Code(nc) => {
+            out.push_str("<text:span text:style-name=\"T_Code\">");
+            write_text(out, &nc.literal);
+            out.push_str("</text:span>");
+        }
+        NodeValue::Math(nm) => write_text(out, &nm.literal),
+        NodeValue::CodeBlock(ncb) => {
+            for line in ncb.literal.trim_end_matches('\n').split('\n') {
+                out.push_str("<text:p text:style-name=\"P_Code\">");
+                write_text(out, line);
+                out.push_str("</text:p>\n");
+            }
+        }
+        NodeValue::SoftBreak => out.push(' '),
+        NodeValue::LineBreak => out.push_str("<text:line-break/>"),
+        NodeValue::Link(nl) => {
+            out.push_str("<text:a xlink:type=\"simple\" xlink:href=\"");
+            escape_xml(out, &nl.url, true);
+            out.push_str("\">");
+        }
+        NodeValue::List(nl) => {
+            if is_nested_list(node) {
+                out.push_str("<text:list>\n");
+            } else {
+                let style = match nl.list_type {
+                    ListType::Bullet => "L_Bullet",
+                    ListType::Ordered => "L_Number",
+                };
+                out.push_str(&format!("<text:list text:style-name=\"{style}\">\n"));
+            }
+        }
+        NodeValue::Item(..) | NodeValue::TaskItem(..) => out.push_str("<text:list-item>"),
+        NodeValue::Alert(na) => {
+            let title = na
+                .title
+                .clone()
+                .unwrap_or_else(|| na.alert_type.default_title());
+            out.push_str(
+                "<text:p text:style-name=\"P_Quote\"><text:span text:style-name=\"T_Strong\">",
+            );
+            escape_xml(out, &title, false);
+            out.push_str("</text:span></text:p>\n");
+        }
+        NodeValue::Table(nt) => {
+            out.push_str(&format!(
+                "<table:table>\n<table:table-column table:number-columns-repeated=\"{}\"/>\n",
+                nt.alignments.len()
+            ));
+        }
+        NodeValue::TableRow(header) => {
+            if *header {
+                out.push_str("<table:table-header-rows>");
+            }
+            out.push_str("<table:table-row>");
+        }
+        NodeValue::TableCell => {
+            out.push_str("<table:table-cell office:value-type=\"string\"><text:p>");
+        }
+        NodeValue::ThematicBreak => out.push_str("<text:p text:style-name=\"P_Rule\"/>\n"),
+        _ => {}
+    }
+}
+
+fn leave<'a>(out: &mut String, node: &'a AstNode<'a>) {
+    let data = node.data.borrow();
+    let value = &data.value;
+
+    if span_style(value).is_some() {
+        out.push_str("</text:span>");
+        return;
+    }
+
+    match value {
+        NodeValue::Paragraph => out.push_str("</text:p>\n"),
+        NodeValue::Heading(..) => out.push_str("</text:h>\n"),
+        NodeValue::Link(..) => out.push_str("</text:a>"),
+        NodeValue::List(..) => out.push_str("</text:list>\n"),
+        NodeValue::Item(..) | NodeValue::TaskItem(..) => out.push_str("</text:list-item>\n"),
+        NodeValue::Table(..) => out.push_str("</table:table>\n"),
+        NodeValue::TableRow(header) => {
+            out.push_str("</table:table-row>");
+            if *header {
+                out.push_str("</table:table-header-rows>");
+            }
+            out.push('\n');
+        }
+        NodeValue::TableCell => out.push_str("</text:p></table:table-cell>"),
+        _ => {}
+    }
+}
+
+/// Convert markdown to an OpenDocument text document in flat XML format (`.fodt`), applying
+/// the given link rules. Headings, paragraphs, lists, tables, emphasis, code, quotes and links
+/// are kept; raw HTML is dropped.
+pub fn indico_markdown_to_odt(md_source: &str, autolink_rules: &[LinkRule]) -> String {
+    let options = indico_options();
+    let arena = Arena::new();
+    let mut root = parse_document(&arena, md_source, &options);
+
+    add_links(&mut root, &arena, autolink_rules);
+
+    let mut out = String::from(ODF_HEADER);
+    for edge in root.traverse() {
+        match edge {
+            NodeEdge::Start(node) => enter(&mut out, node),
+            NodeEdge::End(node) => leave(&mut out, node),
+        }
+    }
+    out.push_str(ODF_FOOTER);
+
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ODF_FOOTER, ODF_HEADER, indico_markdown_to_odt};
+
+    #[test]
+    fn test_odt() {
+        let md = "# Abstract\n\nWe **measured**  a [peak](https://cds.cern.ch) & more\n\n\
+                  1. one\n   - nested\n\n| a |\n|---|\n| 1 |";
+        let odt = indico_markdown_to_odt(md, &[]);
+        assert_eq!(
+            odt.strip_prefix(ODF_HEADER)
+                .and_then(|body| body.strip_suffix(ODF_FOOTER))
+                .unwrap(),
+            "<text:h text:outline-level=\"1\">Abstract</text:h>\n\
+             <text:p>We <text:span text:style-name=\"T_Strong\">measured</text:span> <text:s text:c=\"1\"/>a \
+             <text:a xlink:type=\"simple\" xlink:href=\"https://cds.cern.ch\">peak</text:a> &amp; more</text:p>\n\
+             <text:list text:style-name=\"L_Number\">\n<text:list-item><text:p>one</text:p>\n\
+             <text:list>\n<text:list-item><text:p>nested</text:p>\n</text:list-item>\n</text:list>\n\
+             </text:list-item>\n</text:list>\n\
+             <table:table>\n<table:table-column table:number-columns-repeated=\"1\"/>\n\
+             <table:table-header-rows><table:table-row><table:table-cell office:value-type=\"string\">\
+             <text:p>a</text:p></table:table-cell></table:table-row></table:table-header-rows>\n\
+             <table:table-row><table:table-cell office:value-type=\"string\"><text:p>1</text:p>\
+             </table:table-cell></table:table-row>\n</table:table>\n"
+        );
+    }
+}
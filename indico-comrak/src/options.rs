@@ -0,0 +1,635 @@
+//! Options which control how markdown is rendered to HTML.
+
+use std::{collections::HashMap, fmt, sync::Arc};
+
+/// Rendering profile, which adapts the generated HTML to the medium it will be displayed on
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum RenderProfile {
+    /// Regular HTML, meant to be displayed on Indico pages
+    #[default]
+    Default,
+    /// HTML meant for e-mail notifications: inline `style` attributes instead of classes,
+    /// simple tables and no elements which mail clients don't support (`<details>`, `<mark>`)
+    Email,
+    /// HTML meant for RSS/Atom feeds: elements which feed readers mangle are downgraded and
+    /// interactive markup (forms, task list checkboxes, `<details>`) is stripped
+    Feed,
+}
+
+/// A function which renders a formula to HTML, given its TeX source and whether it is
+/// displayed as a block. Returning `None` falls back to the default rendering.
+#[derive(Clone)]
+pub struct MathBackend(pub Arc<dyn Fn(&str, bool) -> Option<String> + Send + Sync>);
+
+impl fmt::Debug for MathBackend {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("MathBackend(..)")
+    }
+}
+
+/// A function which maps the target of a wiki link to a URL, or `None` if it can't be resolved
+#[derive(Clone)]
+pub struct WikiLinkResolver(pub Arc<dyn Fn(&str) -> Option<String> + Send + Sync>);
+
+impl fmt::Debug for WikiLinkResolver {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("WikiLinkResolver(..)")
+    }
+}
+
+/// A function which detects the language of a text, returning its language tag (e.g. `fr`),
+/// or `None` if it can't be detected
+#[derive(Clone)]
+pub struct LanguageDetector(pub Arc<dyn Fn(&str) -> Option<String> + Send + Sync>);
+
+impl fmt::Debug for LanguageDetector {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("LanguageDetector(..)")
+    }
+}
+
+/// How the targets of `[[Target]]` / `[[Target|Label]]` links are turned into URLs
+#[derive(Debug, Clone)]
+pub enum WikiLinks {
+    /// A URL template in which `{0}` is replaced with the target
+    Template(String),
+    /// A function resolving targets, which may fail
+    Resolver(WikiLinkResolver),
+}
+
+/// Responsive variants of an image, which are attached to it as `srcset` and `sizes`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ResponsiveImage {
+    /// Value of the `srcset` attribute, e.g. `thumb.png 480w, full.png 1200w`
+    pub srcset: String,
+    /// Value of the `sizes` attribute, if any
+    pub sizes: Option<String>,
+}
+
+/// A function which returns the responsive variants of an image, given its URL, or `None` if
+/// there aren't any
+#[derive(Clone)]
+pub struct ResponsiveImages(pub Arc<dyn Fn(&str) -> Option<ResponsiveImage> + Send + Sync>);
+
+impl fmt::Debug for ResponsiveImages {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("ResponsiveImages(..)")
+    }
+}
+
+/// How math formulas (`$...$`, `$$...$$` and `` $`...`$ ``) are rendered
+#[derive(Debug, Clone, Default)]
+pub enum MathOutput {
+    /// The TeX source is kept, in elements which are meant to be processed client-side
+    /// (e.g. by KaTeX)
+    #[default]
+    Default,
+    /// Formulas are converted to MathML on the server
+    #[cfg(feature = "mathml")]
+    MathMl,
+    /// Formulas are rendered by a custom backend
+    Custom(MathBackend),
+}
+
+/// Elements in which formulas are kept by [`MathOutput::Default`]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum MathWrapper {
+    /// `<span data-math-style="inline">` and `<span data-math-style="display">`
+    #[default]
+    Default,
+    /// `<span class="math math-inline">\(...\)</span>` and
+    /// `<span class="math math-display">\[...\]</span>`, which are found by the auto-render
+    /// extension of KaTeX and by MathJax
+    Delimited,
+    /// `<span>` elements with the given classes
+    Classes { inline: String, display: String },
+}
+
+/// Syntax and output of math formulas
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MathOptions {
+    /// Enable `$...$`, `$$...$$` and `` $`...`$ `` formulas (enabled by default)
+    pub dollars: bool,
+    /// Enable `\(...\)` and `\[...\]` formulas. Note that `\[` and `\(` can't be used as escaped
+    /// brackets anymore.
+    pub brackets: bool,
+    /// How formulas are wrapped when they are rendered client-side
+    pub wrapper: MathWrapper,
+    /// Macro definitions (e.g. `\newcommand{\R}{\mathbb{R}}`) which are prepended to each formula
+    pub macros: String,
+}
+
+impl Default for MathOptions {
+    fn default() -> Self {
+        Self {
+            dollars: true,
+            brackets: false,
+            wrapper: MathWrapper::Default,
+            macros: String::new(),
+        }
+    }
+}
+
+/// Layout of the generated HTML
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum HtmlFormat {
+    /// Block-level elements are separated by newlines
+    #[default]
+    Default,
+    /// Insignificant whitespace and newlines are stripped
+    Minified,
+    /// Block-level elements are put on their own lines and indented according to nesting
+    Pretty,
+}
+
+/// How the positions of the markdown source are reported, e.g. in diagnostics
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum OffsetMode {
+    /// Columns and offsets are counted in bytes
+    #[default]
+    Bytes,
+    /// Columns and offsets are also counted in UTF-16 code units, like in JavaScript strings
+    Utf16,
+}
+
+/// How `:shortcode:` emoji are rendered
+#[cfg(feature = "emoji")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EmojiStyle {
+    /// The Unicode character itself
+    Unicode,
+    /// The character wrapped in `<span role="img" aria-label="...">`, for screen readers
+    Accessible,
+}
+
+/// Options for rendering footnotes (`[^1]` references and `[^1]: ...` definitions)
+#[derive(Debug, Clone)]
+pub struct FootnoteOptions {
+    /// Content of the links from a footnote back to its references
+    pub backref_symbol: String,
+    /// If set, a heading with this text is added above the footnotes
+    pub heading: Option<String>,
+    /// Prefix added to the footnote IDs, so that several documents rendered on the same page
+    /// don't collide
+    pub id_prefix: String,
+}
+
+impl Default for FootnoteOptions {
+    fn default() -> Self {
+        Self {
+            backref_symbol: "\u{21a9}".into(),
+            heading: None,
+            id_prefix: String::new(),
+        }
+    }
+}
+
+/// Options for rendering alerts (`> [!NOTE]`, `> [!WARNING]`, ...)
+#[derive(Debug, Clone)]
+pub struct AlertOptions {
+    /// Titles of the alerts which don't have one, keyed by their kind in lowercase (`note`,
+    /// `tip`, `important`, `warning`, `caution` or a custom kind), e.g. for localization.
+    /// Kinds without a title here use their name, capitalized.
+    pub titles: HashMap<String, String>,
+    /// Prefix of the classes of alerts, which are rendered as
+    /// `<div class="PREFIX PREFIX-KIND">` with a `<p class="PREFIX-title">` title. Only used by
+    /// the default profile.
+    pub class_prefix: String,
+    /// Additional kinds of alerts (e.g. `danger` for `> [!DANGER]`)
+    pub custom_kinds: Vec<String>,
+}
+
+impl Default for AlertOptions {
+    fn default() -> Self {
+        Self {
+            titles: HashMap::new(),
+            class_prefix: "markdown-alert".into(),
+            custom_kinds: Vec::new(),
+        }
+    }
+}
+
+/// A provider of videos which can be embedded
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EmbedProvider {
+    /// `youtube.com/watch?v=...`, `youtu.be/...` and `youtube.com/shorts/...` URLs
+    YouTube,
+    /// `vimeo.com/...` URLs
+    Vimeo,
+    /// CERN Document Server videos (`videos.cern.ch/record/...` URLs)
+    CdsVideos,
+}
+
+/// How embedded videos are rendered
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum EmbedStyle {
+    /// An `<iframe>` with the player of the provider
+    #[default]
+    Iframe,
+    /// A `<div>` with the URL of the player in `data-embed-url` and a link to the video, to be
+    /// turned into a player client-side
+    Placeholder,
+}
+
+/// Options for embedding videos
+#[derive(Debug, Clone)]
+pub struct EmbedOptions {
+    /// The providers whose videos are embedded
+    pub providers: Vec<EmbedProvider>,
+    /// How the videos are rendered
+    pub style: EmbedStyle,
+}
+
+impl Default for EmbedOptions {
+    fn default() -> Self {
+        Self {
+            providers: vec![
+                EmbedProvider::YouTube,
+                EmbedProvider::Vimeo,
+                EmbedProvider::CdsVideos,
+            ],
+            style: EmbedStyle::default(),
+        }
+    }
+}
+
+/// Options for rendering tables. They are only used by the default profile.
+#[derive(Debug, Clone, Default)]
+pub struct TableOptions {
+    /// If set, the class of the `<table>` elements
+    pub class: Option<String>,
+    /// Render the alignment of columns as `align-left`, `align-center` and `align-right`
+    /// classes instead of `align` attributes
+    pub alignment_classes: bool,
+    /// Wrap tables in a `<div class="table-wrapper">`, which can be made scrollable for wide
+    /// tables
+    pub wrapper: bool,
+    /// Add `scope="col"` to header cells
+    pub header_scope: bool,
+}
+
+/// Options for rendering `||spoiler||` text
+#[derive(Debug, Clone)]
+pub struct SpoilerOptions {
+    /// Summary shown for block spoilers (paragraphs which only consist of a spoiler), which
+    /// are rendered as `<details>` elements
+    pub block_summary: String,
+}
+
+impl Default for SpoilerOptions {
+    fn default() -> Self {
+        Self {
+            block_summary: "Spoiler".into(),
+        }
+    }
+}
+
+/// Quotation marks used by smart punctuation
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum QuoteStyle {
+    /// “double” and ‘single’ quotes
+    #[default]
+    English,
+    /// « double » (with no-break spaces) and “single” quotes
+    French,
+    /// „double“ and ‚single‘ quotes
+    German,
+    /// Custom opening and closing double quotes, followed by opening and closing single quotes
+    Custom([String; 4]),
+}
+
+impl QuoteStyle {
+    /// The opening and closing double quotes, followed by the opening and closing single quotes
+    pub(crate) fn marks(&self) -> [&str; 4] {
+        match self {
+            QuoteStyle::English => ["\u{201c}", "\u{201d}", "\u{2018}", "\u{2019}"],
+            QuoteStyle::French => ["\u{ab}\u{a0}", "\u{a0}\u{bb}", "\u{201c}", "\u{201d}"],
+            QuoteStyle::German => ["\u{201e}", "\u{201c}", "\u{201a}", "\u{2018}"],
+            QuoteStyle::Custom([open_double, close_double, open_single, close_single]) => [
+                open_double.as_str(),
+                close_double.as_str(),
+                open_single.as_str(),
+                close_single.as_str(),
+            ],
+        }
+    }
+}
+
+/// Options for the table of contents which replaces `[TOC]` (or `[[_TOC_]]`) markers
+#[derive(Debug, Clone)]
+pub struct TocOptions {
+    /// Level of the highest headings which are listed
+    pub min_level: u8,
+    /// Level of the lowest headings which are listed
+    pub max_level: u8,
+}
+
+impl Default for TocOptions {
+    fn default() -> Self {
+        Self {
+            min_level: 1,
+            max_level: 6,
+        }
+    }
+}
+
+/// Options for the automatic numbering of headings
+#[derive(Debug, Clone)]
+pub struct HeadingNumbering {
+    /// Level of the headings which are numbered `1.`, `2.`... (with their subheadings numbered
+    /// `1.1`, `1.2`...). Headings above this level are not numbered.
+    pub start_level: u8,
+    /// Include the numbers in the IDs generated for the headings (e.g. `1-introduction`
+    /// instead of `introduction`)
+    pub in_anchors: bool,
+}
+
+impl Default for HeadingNumbering {
+    fn default() -> Self {
+        Self {
+            start_level: 1,
+            in_anchors: false,
+        }
+    }
+}
+
+/// Options for `{.class #id key=value}` attribute blocks after headings and images and in the
+/// info strings of fenced code blocks
+#[derive(Debug, Clone)]
+pub struct AttributeOptions {
+    /// Names of the attributes which may be set; any other attribute is dropped
+    pub allowed: Vec<String>,
+}
+
+impl Default for AttributeOptions {
+    fn default() -> Self {
+        Self {
+            allowed: ["class", "id", "title", "lang", "dir", "width", "height"]
+                .map(String::from)
+                .into(),
+        }
+    }
+}
+
+/// Element which is used for fenced code blocks in a given language
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CodeBlockElement {
+    /// Name of the element
+    pub element: String,
+    /// Value of its `class` attribute
+    pub class: String,
+}
+
+/// Options for rendering fenced code blocks
+#[derive(Debug, Clone)]
+pub struct CodeBlockOptions {
+    /// Languages whose code blocks are rendered as a custom element (containing the escaped
+    /// code) instead of `<pre><code class="language-...">`. By default, this is the case for
+    /// diagrams, so that client-side renderers can pick them up.
+    pub languages: HashMap<String, CodeBlockElement>,
+    /// Render the metadata given in info strings (```` ```python title="run.py" {1,3-5} ````):
+    /// a title, shown above the code block, and the lines to highlight, in a `data-hl-lines`
+    /// attribute
+    pub metadata: bool,
+}
+
+impl Default for CodeBlockOptions {
+    fn default() -> Self {
+        let languages = ["mermaid", "plantuml", "graphviz"]
+            .into_iter()
+            .map(|lang| {
+                let element = CodeBlockElement {
+                    element: "pre".into(),
+                    class: format!("diagram {lang}"),
+                };
+                (lang.to_string(), element)
+            })
+            .collect();
+        Self {
+            languages,
+            metadata: true,
+        }
+    }
+}
+
+/// Options for emitting a complete, standalone HTML document rather than a fragment
+#[derive(Debug, Clone)]
+pub struct DocumentOptions {
+    /// Content of the `<title>` element
+    pub title: String,
+    /// Character set declared in the `<meta charset>` element
+    pub charset: String,
+    /// CSS which is inlined in a `<style>` element in the `<head>`
+    pub stylesheet: Option<String>,
+}
+
+impl Default for DocumentOptions {
+    fn default() -> Self {
+        Self {
+            title: String::new(),
+            charset: "utf-8".into(),
+            stylesheet: None,
+        }
+    }
+}
+
+/// Limits of the size of a single document, which approximate the memory used to render it,
+/// e.g. to bound the worst case of the requests of a server
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RenderLimits {
+    /// Maximum number of nodes of the document, including the ones added while rendering it
+    /// (e.g. the links of link rules)
+    pub max_nodes: usize,
+    /// Maximum total length, in bytes, of the text, code and raw HTML of the document
+    pub max_text_len: usize,
+}
+
+impl Default for RenderLimits {
+    fn default() -> Self {
+        Self {
+            max_nodes: 1_000_000,
+            max_text_len: 16 * 1024 * 1024,
+        }
+    }
+}
+
+/// Extensions of the markdown syntax which are enabled by default, and can be disabled for
+/// contexts which only need simpler documents
+#[derive(Debug, Clone)]
+pub struct Extensions {
+    /// Enable `| a | b |` tables
+    pub tables: bool,
+    /// Enable `> [!NOTE]` alerts, including the custom kinds of [`AlertOptions`]
+    pub alerts: bool,
+}
+
+impl Default for Extensions {
+    fn default() -> Self {
+        Self {
+            tables: true,
+            alerts: true,
+        }
+    }
+}
+
+/// How raw HTML in the document is rendered
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum RawHtml {
+    /// Raw HTML is kept, except for the tags which are escaped by the tag filter of GFM
+    /// (`<script>`, `<style>`, `<iframe>`...)
+    #[default]
+    Filtered,
+    /// Raw HTML is kept as it is, e.g. for documents which come from trusted users
+    Unfiltered,
+    /// Only the tags and attributes of an allowlist (e.g. `<sup>`, `<table>` or `<a href>`)
+    /// are kept, like with Bleach, and URLs in their attributes must be relative or use
+    /// `http`, `https` or `mailto`. Other tags are escaped and comments are removed. The URLs
+    /// of markdown links and images are not checked (see
+    /// [`RenderOptions::blocked_url_schemes`]).
+    Sanitized,
+    /// Raw HTML is escaped and shown as it was written, e.g. for documents which come from
+    /// anonymous users. HTML blocks become paragraphs.
+    Escaped,
+}
+
+/// Options for rendering links
+#[derive(Debug, Clone)]
+pub struct LinkOptions {
+    /// Open links in a new tab, with `target="_blank"`
+    pub target_blank: bool,
+}
+
+impl Default for LinkOptions {
+    fn default() -> Self {
+        Self { target_blank: true }
+    }
+}
+
+/// Options for [`indico_markdown_to_html_with_options`](crate::indico_markdown_to_html_with_options)
+#[derive(Debug, Clone, Default)]
+pub struct RenderOptions {
+    /// The rendering profile to use
+    pub profile: RenderProfile,
+    /// If set, relative link and image URLs are resolved against this URL
+    pub base_url: Option<String>,
+    /// If set, relative image URLs are resolved against this URL instead of `base_url`
+    pub media_base_url: Option<String>,
+    /// Generate well-formed XHTML: void elements are self-closed, attributes are quoted and
+    /// escaped and raw HTML tags are balanced
+    pub xhtml: bool,
+    /// If set, a complete HTML document is generated instead of a fragment
+    pub document: Option<DocumentOptions>,
+    /// How math formulas are rendered
+    pub math: MathOutput,
+    /// Delimiters, wrappers and macros of math formulas
+    pub math_options: MathOptions,
+    /// Layout of the generated HTML
+    pub format: HtmlFormat,
+    /// Extensions of the markdown syntax which are enabled
+    pub extensions: Extensions,
+    /// How raw HTML is rendered
+    pub raw_html: RawHtml,
+    /// How links are rendered
+    pub links: LinkOptions,
+    /// If set, `:shortcode:` emoji (outside of code and links) are replaced
+    #[cfg(feature = "emoji")]
+    pub emoji: Option<EmojiStyle>,
+    /// Custom `:shortcode:` emoji, mapped to the URL of their image
+    pub custom_emoji: HashMap<String, String>,
+    /// If set, `[[Target]]` and `[[Target|Label]]` wiki links are enabled. They are rendered
+    /// with an `internal` class, plus `unresolved` if the target couldn't be resolved
+    pub wiki_links: Option<WikiLinks>,
+    /// If set, footnotes are enabled and rendered in a section at the end of the document
+    pub footnotes: Option<FootnoteOptions>,
+    /// Enable definition lists (`Term` followed by `: definition`), rendered as `<dl>`
+    pub definition_lists: bool,
+    /// If set, `||hidden text||` is rendered as a `<span class="spoiler">`
+    pub spoilers: Option<SpoilerOptions>,
+    /// Names of the allowed `::: name` fenced containers, which are rendered as
+    /// `<div class="admonition name">`
+    pub containers: Vec<String>,
+    /// Enable collapsible sections (`??? Summary` ... `???`, or `???+ Summary` for sections
+    /// which are expanded by default), rendered as `<details>`
+    pub collapsible_sections: bool,
+    /// If set, straight quotes are turned into curly quotes of the given style, `--` and `---`
+    /// into en and em dashes and `...` into an ellipsis
+    pub smart_punctuation: Option<QuoteStyle>,
+    /// Strip a leading front matter block (YAML between `---` lines or TOML between `+++`
+    /// lines) from the document. It is returned by
+    /// [`indico_markdown_render`](crate::indico_markdown_render).
+    pub front_matter: bool,
+    /// If set, paragraphs which only consist of a `[TOC]` (or `[[_TOC_]]`) marker are replaced
+    /// with a table of contents linking to the headings of the document
+    pub toc: Option<TocOptions>,
+    /// Schemes (e.g. `javascript`) of the link and image URLs which are removed from the
+    /// document, leaving the links and images without a target
+    pub blocked_url_schemes: Vec<String>,
+    /// If set, the IDs of headings and footnotes (and the links to them) include this
+    /// namespace, so that several documents rendered on the same page don't collide
+    pub id_namespace: Option<String>,
+    /// If set, hierarchical numbers are prepended to headings, in a
+    /// `<span class="heading-number">`
+    pub heading_numbers: Option<HeadingNumbering>,
+    /// Remove the title of the document (see
+    /// [`indico_markdown_extract_title`](crate::indico_markdown_extract_title)), e.g. when it is
+    /// already displayed by the page
+    pub strip_title: bool,
+    /// If set, the language of the document is detected from its text and declared in a `lang`
+    /// attribute, on the `<html>` element if [`document`](Self::document) is set and on a `<div>`
+    /// wrapping the HTML otherwise
+    pub language_detector: Option<LanguageDetector>,
+    /// If set, paragraphs which only consist of the bare URL of a video of one of the allowed
+    /// providers are rendered as an embedded player (in a `<div class="embed embed-PROVIDER">`).
+    /// Only used by the default profile.
+    pub embeds: Option<EmbedOptions>,
+    /// If set, `{name}` placeholders in the text of the document (but not in code) are replaced
+    /// with the value of `name`. Placeholders without a value are kept, and returned by
+    /// [`indico_markdown_render`](crate::indico_markdown_render).
+    pub placeholders: Option<HashMap<String, String>>,
+    /// Drop the `<!-- more -->` marker from the document, and also render the part of the
+    /// document before it as a teaser, returned by
+    /// [`indico_markdown_render`](crate::indico_markdown_render)
+    pub read_more: bool,
+    /// Enable abbreviations: `*[HTML]: HyperText Markup Language` definitions are removed,
+    /// and the occurrences of `HTML` are rendered as `<abbr>` with the expansion as title
+    pub abbreviations: bool,
+    /// How tables are rendered
+    pub tables: TableOptions,
+    /// How alerts are rendered
+    pub alerts: AlertOptions,
+    /// If set, attribute blocks are enabled. They are only rendered by the default profile.
+    pub attributes: Option<AttributeOptions>,
+    /// How fenced code blocks are rendered
+    pub code_blocks: CodeBlockOptions,
+    /// Enable the `![alt](url =WIDTHxHEIGHT)` syntax for image sizes (either dimension may be
+    /// omitted). Sizes are only rendered by the default profile.
+    pub image_size_syntax: bool,
+    /// Render the checkboxes of task list items without `disabled`, with their index (see
+    /// [`indico_markdown_toggle_task`](crate::indico_markdown_toggle_task)) and line in the
+    /// markdown source as `data-index` and `data-line`. Only used by the default profile.
+    pub interactive_tasks: bool,
+    /// Render paragraphs which only consist of an image with a title as a `<figure>`, with the
+    /// title as its `<figcaption>`
+    pub figures: bool,
+    /// Add `loading="lazy"` to images, so that browsers only load them when they are about to
+    /// be displayed. Only rendered by the default profile.
+    pub lazy_loading: bool,
+    /// Add `decoding="async"` to images. Only rendered by the default profile.
+    pub async_decoding: bool,
+    /// If set, the `referrerpolicy` of images (e.g. `no-referrer`). Only rendered by the
+    /// default profile.
+    pub referrer_policy: Option<String>,
+    /// If set, called with the (resolved) URL of each image to get its `srcset` and `sizes`.
+    /// Only rendered by the default profile.
+    pub responsive_images: Option<ResponsiveImages>,
+    /// Known sizes (width, height) of images, keyed by their URL as written in the document.
+    /// They are added to images without a size, so that browsers can reserve space for them.
+    pub image_sizes: HashMap<String, (u32, u32)>,
+    /// If set, rendering fails with [`RenderError::TooManyNodes`](crate::RenderError::TooManyNodes)
+    /// or [`RenderError::TextTooLong`](crate::RenderError::TextTooLong) when the document
+    /// exceeds these limits
+    pub limits: Option<RenderLimits>,
+    /// How the positions of the warnings of [`indico_markdown_render`](crate::indico_markdown_render)
+    /// are reported
+    pub offset_mode: OffsetMode,
+}
@@ -0,0 +1,65 @@
+//! Placeholders (`{event_title}`) in the text of a document, which are replaced with values
+//! provided by the caller. Code is left untouched.
+
+use comrak::nodes::{AstNode, NodeValue};
+use regex_lite::{Captures, Regex};
+use std::collections::HashMap;
+
+/// Merge adjacent text nodes, as comrak may split text at characters which could have been
+/// markup (e.g. the `_` of `{event_title}`)
+fn merge_text_nodes<'a>(root: &'a AstNode<'a>) {
+    let texts: Vec<_> = root
+        .descendants()
+        .filter(|node| matches!(node.data.borrow().value, NodeValue::Text(_)))
+        .collect();
+    for node in texts {
+        // nodes which were merged into the previous one are detached
+        if node.parent().is_none() {
+            continue;
+        }
+        while let Some(next) = node.next_sibling() {
+            let next_text = match next.data.borrow().value {
+                NodeValue::Text(ref next_text) => next_text.to_string(),
+                _ => break,
+            };
+            if let NodeValue::Text(ref mut text) = node.data.borrow_mut().value {
+                *text = format!("{text}{next_text}").into();
+            }
+            next.detach();
+        }
+    }
+}
+
+/// Replace the placeholders in the text of the document with their values, returning the
+/// names of the placeholders which don't have a value (and are kept as they are)
+pub(crate) fn replace_placeholders<'a>(
+    root: &'a AstNode<'a>,
+    values: &HashMap<String, String>,
+) -> Vec<String> {
+    let re = Regex::new(r"\{([A-Za-z_][A-Za-z0-9_]*)\}").unwrap();
+    let mut unknown = Vec::new();
+
+    merge_text_nodes(root);
+    for node in root.descendants() {
+        let mut data = node.data.borrow_mut();
+        let NodeValue::Text(ref mut text) = data.value else {
+            continue;
+        };
+        if !re.is_match(text) {
+            continue;
+        }
+        let replaced = re
+            .replace_all(text, |caps: &Captures| match values.get(&caps[1]) {
+                Some(value) => value.clone(),
+                None => {
+                    if !unknown.iter().any(|name| name == &caps[1]) {
+                        unknown.push(caps[1].to_string());
+                    }
+                    caps[0].to_string()
+                }
+            })
+            .into_owned();
+        *text = replaced.into();
+    }
+    unknown
+}
@@ -0,0 +1,782 @@
+//! Rendering of markdown into plain text, without any HTML markup.
+//!
+//! This is meant for contexts such as plaintext e-mail bodies or search indexing, where
+//! only the textual content and a minimal amount of structure (paragraphs, list items)
+//! should survive.
+
+use comrak::{
+    Arena,
+    arena_tree::NodeEdge,
+    nodes::{AstNode, ListDelimType, ListType, NodeValue},
+    parse_document,
+};
+
+use crate::{LinkRule, add_links, collect_text, unstyled_options};
+
+/// How links are represented in plain text
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum LinkStyle {
+    /// Only the text of the link is kept
+    #[default]
+    TextOnly,
+    /// The URL is appended to the text, e.g. `Indico (https://getindico.io)`
+    Inline,
+    /// Links are numbered, e.g. `Indico [1]`, and a list of references
+    /// (`[1] https://getindico.io`) is appended to the text
+    Footnotes,
+}
+
+/// Options which control how plain text is generated
+#[derive(Debug, Clone)]
+pub struct PlainTextOptions {
+    /// Character used as bullet for unordered list items
+    pub bullet: char,
+    /// How links are rendered
+    pub links: LinkStyle,
+    /// If set, lines are hard-wrapped at this column (wide characters such as CJK count as
+    /// two columns)
+    pub wrap_width: Option<usize>,
+}
+
+impl Default for PlainTextOptions {
+    fn default() -> Self {
+        Self {
+            bullet: '-',
+            links: LinkStyle::TextOnly,
+            wrap_width: None,
+        }
+    }
+}
+
+impl PlainTextOptions {
+    /// Options suitable for the `text/plain` part of e-mails: links are turned into numbered
+    /// references and lines are wrapped at 72 columns
+    pub fn email() -> Self {
+        Self {
+            links: LinkStyle::Footnotes,
+            wrap_width: Some(72),
+            ..Default::default()
+        }
+    }
+}
+
+/// State of a list which is currently being rendered
+struct ListState {
+    list_type: ListType,
+    delimiter: ListDelimType,
+    tight: bool,
+    next_number: usize,
+    /// Indentation of the text of the current item (used for continuation lines)
+    content_indent: usize,
+}
+
+struct PlainTextWriter<'o> {
+    options: &'o PlainTextOptions,
+    out: String,
+    lists: Vec<ListState>,
+    /// A list item bullet has just been written, so the next block goes on the same line
+    at_item_start: bool,
+    /// URLs referenced by links, when rendering them as footnotes
+    link_refs: Vec<String>,
+    /// Ranges of the lines of preformatted text (code blocks and tables), which aren't wrapped
+    verbatim_lines: Vec<(usize, usize)>,
+    /// Line on which the current table starts
+    table_start: usize,
+}
+
+impl<'o> PlainTextWriter<'o> {
+    fn new(options: &'o PlainTextOptions) -> Self {
+        Self {
+            options,
+            out: String::new(),
+            lists: Vec::new(),
+            at_item_start: false,
+            link_refs: Vec::new(),
+            verbatim_lines: Vec::new(),
+            table_start: 0,
+        }
+    }
+
+    /// Index of the line which is being written
+    fn line(&self) -> usize {
+        self.out.matches('\n').count()
+    }
+
+    /// Make sure that the output ends with at least `n` newlines (unless it's empty)
+    fn ensure_newlines(&mut self, n: usize) {
+        if self.out.is_empty() {
+            return;
+        }
+        let existing = self.out.len() - self.out.trim_end_matches('\n').len();
+        for _ in existing..n {
+            self.out.push('\n');
+        }
+    }
+
+    /// Separate a new block from whatever came before it
+    fn start_block(&mut self) {
+        if self.at_item_start {
+            self.at_item_start = false;
+        } else if self.lists.last().is_some_and(|l| l.tight) {
+            self.ensure_newlines(1);
+            self.write_indent();
+        } else {
+            self.ensure_newlines(2);
+            self.write_indent();
+        }
+    }
+
+    fn write_indent(&mut self) {
+        if let Some(list) = self.lists.last() {
+            let indent = list.content_indent;
+            self.out.extend(std::iter::repeat_n(' ', indent));
+        }
+    }
+
+    /// Write a line break, keeping the indentation of the current list item
+    fn line_break(&mut self) {
+        self.out.push('\n');
+        self.write_indent();
+    }
+
+    fn write_text(&mut self, text: &str) {
+        let mut lines = text.split('\n');
+        if let Some(first) = lines.next() {
+            self.out.push_str(first);
+        }
+        for line in lines {
+            self.line_break();
+            self.out.push_str(line);
+        }
+    }
+
+    fn enter<'a>(&mut self, node: &'a AstNode<'a>) {
+        match node.data.borrow().value {
+            NodeValue::Paragraph | NodeValue::Heading(..) | NodeValue::HtmlBlock(..) => {
+                self.start_block()
+            }
+            NodeValue::Table(..) => {
+                self.start_block();
+                self.table_start = self.line();
+            }
+            NodeValue::ThematicBreak => {
+                self.start_block();
+                self.out.push_str("----");
+            }
+            NodeValue::CodeBlock(ref ncb) => {
+                self.start_block();
+                let start = self.line();
+                self.write_text(ncb.literal.trim_end_matches('\n'));
+                self.verbatim_lines.push((start, self.line()));
+            }
+            NodeValue::List(ref nl) => {
+                if !self.at_item_start {
+                    let tight = self.lists.last().is_some_and(|l| l.tight);
+                    self.ensure_newlines(if tight { 1 } else { 2 });
+                }
+                let parent_indent = self.lists.last().map_or(0, |l| l.content_indent);
+                self.lists.push(ListState {
+                    list_type: nl.list_type,
+                    delimiter: nl.delimiter,
+                    tight: nl.tight,
+                    next_number: nl.start,
+                    content_indent: parent_indent,
+                });
+            }
+            NodeValue::Item(..) => {
+                let Some(list) = self.lists.last() else {
+                    return;
+                };
+                let bullet = match list.list_type {
+                    ListType::Bullet => format!("{} ", self.options.bullet),
+                    ListType::Ordered => format!(
+                        "{}{} ",
+                        list.next_number,
+                        match list.delimiter {
+                            ListDelimType::Period => '.',
+                            ListDelimType::Paren => ')',
+                        }
+                    ),
+                };
+                let tight = list.tight;
+                let indent = self.lists[..self.lists.len() - 1]
+                    .last()
+                    .map_or(0, |l| l.content_indent);
+
+                // nested lists start on a line of their own
+                self.at_item_start = false;
+                self.ensure_newlines(if tight { 1 } else { 2 });
+                self.out.extend(std::iter::repeat_n(' ', indent));
+                self.out.push_str(&bullet);
+
+                if let Some(list) = self.lists.last_mut() {
+                    list.content_indent = indent + bullet.chars().count();
+                    list.next_number += 1;
+                }
+                self.at_item_start = true;
+            }
+            NodeValue::TableRow(..) => {
+                if node.previous_sibling().is_some() {
+                    self.line_break();
+                }
+            }
+            NodeValue::TableCell => {
+                if node.previous_sibling().is_some() {
+                    self.out.push('\t');
+                }
+            }
+            NodeValue::Text(ref t) => self.write_text(t),
+            NodeValue::Code(ref nc) => self.write_text(&nc.literal),
+            NodeValue::Math(ref nm) => self.write_text(&nm.literal),
+            NodeValue::SoftBreak | NodeValue::LineBreak => self.line_break(),
+            NodeValue::HtmlInline(ref html) => {
+                let html = html.to_lowercase();
+                if html.starts_with("<br") {
+                    self.line_break();
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn leave<'a>(&mut self, node: &'a AstNode<'a>) {
+        match node.data.borrow().value {
+            NodeValue::List(..) => {
+                self.lists.pop();
+            }
+            NodeValue::Table(..) => self.verbatim_lines.push((self.table_start, self.line())),
+            NodeValue::Item(..) => {
+                // an empty item shouldn't swallow the next block
+                self.at_item_start = false;
+            }
+            NodeValue::Link(ref nl) => {
+                // links whose text is the URL itself don't need it repeated
+                if nl.url.is_empty() || self.out.ends_with(&nl.url) {
+                    return;
+                }
+                match self.options.links {
+                    LinkStyle::TextOnly => {}
+                    LinkStyle::Inline => self.out.push_str(&format!(" ({})", nl.url)),
+                    LinkStyle::Footnotes => {
+                        let n = match self.link_refs.iter().position(|u| *u == nl.url) {
+                            Some(i) => i + 1,
+                            None => {
+                                self.link_refs.push(nl.url.clone());
+                                self.link_refs.len()
+                            }
+                        };
+                        self.out.push_str(&format!(" [{n}]"));
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Convert markdown to plain text, without any HTML tags.
+///
+/// Paragraphs and other blocks are separated by blank lines and list items are rendered
+/// with simple bullets (or numbers, for ordered lists). All inline formatting is dropped.
+pub fn indico_markdown_to_plain_text(md_source: &str, opts: &PlainTextOptions) -> String {
+    indico_markdown_to_plain_text_with_rules(md_source, &[], opts)
+}
+
+/// Same as [`indico_markdown_to_plain_text`], but also turns the matches of link rules into
+/// links, which are rendered according to [`PlainTextOptions::links`]
+pub fn indico_markdown_to_plain_text_with_rules(
+    md_source: &str,
+    autolink_rules: &[LinkRule],
+    opts: &PlainTextOptions,
+) -> String {
+    let options = unstyled_options();
+    let arena = Arena::new();
+    let mut root = parse_document(&arena, md_source, &options);
+    add_links(&mut root, &arena, autolink_rules);
+
+    let mut writer = PlainTextWriter::new(opts);
+    for edge in root.traverse() {
+        match edge {
+            NodeEdge::Start(node) => writer.enter(node),
+            NodeEdge::End(node) => writer.leave(node),
+        }
+    }
+
+    let mut out = writer.out.trim_end().to_string();
+
+    if !writer.link_refs.is_empty() {
+        out.push('\n');
+        for (i, url) in writer.link_refs.iter().enumerate() {
+            out.push_str(&format!("\n[{}] {url}", i + 1));
+        }
+    }
+
+    match opts.wrap_width {
+        Some(width) => wrap_text(
+            &out,
+            width,
+            &[opts.bullet.to_string()],
+            &writer.verbatim_lines,
+        ),
+        None => out,
+    }
+}
+
+/// Split the content of a paragraph into words. Links and code spans are never split, and
+/// are glued to the surrounding text if there's no whitespace in between.
+fn paragraph_words<'a>(paragraph: &'a AstNode<'a>, words: &mut Vec<String>) {
+    let mut word = String::new();
+    // the link whose descendants have already been collected
+    let mut skip_until = None;
+
+    for edge in paragraph.traverse() {
+        let node = match edge {
+            NodeEdge::Start(node) if skip_until.is_none() => node,
+            NodeEdge::End(node) if skip_until.is_some_and(|n| std::ptr::eq(n, node)) => {
+                skip_until = None;
+                continue;
+            }
+            _ => continue,
+        };
+
+        match node.data.borrow().value {
+            NodeValue::Text(ref t) => {
+                for c in t.chars() {
+                    if c.is_whitespace() {
+                        if !word.is_empty() {
+                            words.push(std::mem::take(&mut word));
+                        }
+                    } else {
+                        word.push(c);
+                    }
+                }
+            }
+            NodeValue::Code(ref nc) => word.push_str(&nc.literal),
+            NodeValue::Link(..) => {
+                word.push_str(collect_text(node).trim());
+                skip_until = Some(node);
+            }
+            NodeValue::SoftBreak | NodeValue::LineBreak => {
+                if !word.is_empty() {
+                    words.push(std::mem::take(&mut word));
+                }
+            }
+            _ => {}
+        }
+    }
+
+    if !word.is_empty() {
+        words.push(word);
+    }
+}
+
+/// Generate a plain-text excerpt of a document, of at most `max_chars` characters.
+///
+/// The text of the first paragraph(s) is used, and it is truncated on a word boundary with an
+/// ellipsis (`…`) if needed. Links and code spans are never cut in the middle.
+pub fn indico_markdown_excerpt(md_source: &str, max_chars: usize) -> String {
+    if max_chars == 0 {
+        return String::new();
+    }
+    let options = unstyled_options();
+    let arena = Arena::new();
+    let root = parse_document(&arena, md_source, &options);
+
+    let mut words = Vec::new();
+    let mut total = 0;
+    for node in root.descendants() {
+        if !matches!(node.data.borrow().value, NodeValue::Paragraph) {
+            continue;
+        }
+        paragraph_words(node, &mut words);
+        total = words.iter().map(|w| w.chars().count() + 1).sum();
+        if total > max_chars {
+            break;
+        }
+    }
+
+    if total <= max_chars + 1 {
+        // everything fits (`total` counts a separator after the last word)
+        return words.join(" ");
+    }
+
+    let mut excerpt = String::new();
+    let mut len = 0;
+    for word in words {
+        let word_len = word.chars().count();
+        let sep = usize::from(!excerpt.is_empty());
+        // keep room for the ellipsis
+        if len + sep + word_len + 1 > max_chars {
+            break;
+        }
+        if sep == 1 {
+            excerpt.push(' ');
+        }
+        excerpt.push_str(&word);
+        len += sep + word_len;
+    }
+    excerpt.push('\u{2026}');
+    excerpt
+}
+
+/// Check whether a character is part of the same user-perceived character as the previous one
+/// (combining marks, variation selectors, emoji modifiers...)
+fn is_extender(c: char) -> bool {
+    matches!(
+        c,
+        '\u{0300}'..='\u{036f}'
+            | '\u{200d}'
+            | '\u{20e3}'
+            | '\u{fe00}'..='\u{fe0f}'
+            | '\u{1f3fb}'..='\u{1f3ff}'
+            | '\u{e0020}'..='\u{e007f}'
+    )
+}
+
+/// Number of columns a character takes in a monospace font: none for the ones which are part
+/// of the previous character (or invisible), and two for wide ones such as CJK and most emoji
+fn char_width(c: char) -> usize {
+    if is_extender(c) || c.is_control() || ('\u{200b}'..='\u{200f}').contains(&c) {
+        0
+    } else if matches!(
+        c,
+        '\u{1100}'..='\u{115f}'
+            | '\u{2e80}'..='\u{303e}'
+            | '\u{3041}'..='\u{33ff}'
+            | '\u{3400}'..='\u{4dbf}'
+            | '\u{4e00}'..='\u{9fff}'
+            | '\u{a000}'..='\u{a4cf}'
+            | '\u{ac00}'..='\u{d7a3}'
+            | '\u{f900}'..='\u{faff}'
+            | '\u{fe30}'..='\u{fe4f}'
+            | '\u{ff00}'..='\u{ff60}'
+            | '\u{ffe0}'..='\u{ffe6}'
+            | '\u{1f300}'..='\u{1f64f}'
+            | '\u{1f900}'..='\u{1f9ff}'
+            | '\u{20000}'..='\u{3fffd}'
+    ) {
+        2
+    } else {
+        1
+    }
+}
+
+/// Number of columns text takes in a monospace font
+pub(crate) fn display_width(text: &str) -> usize {
+    text.chars().map(char_width).sum()
+}
+
+fn is_regional_indicator(c: char) -> bool {
+    ('\u{1f1e6}'..='\u{1f1ff}').contains(&c)
+}
+
+/// Move a byte index of a text back to the start of the user-perceived character (e.g. an
+/// emoji with a skin tone, or a flag) it is in
+fn cluster_start(text: &str, mut index: usize) -> usize {
+    while let Some(prev) = text[..index].chars().next_back() {
+        let next = text[index..].chars().next();
+        if next.is_some_and(is_extender) || prev == '\u{200d}' {
+            index -= prev.len_utf8();
+        } else {
+            break;
+        }
+    }
+    // flags are pairs of regional indicators
+    if text[index..].starts_with(is_regional_indicator) {
+        let preceding = text[..index]
+            .chars()
+            .rev()
+            .take_while(|&c| is_regional_indicator(c))
+            .count();
+        if preceding % 2 == 1 {
+            index -= 4;
+        }
+    }
+    index
+}
+
+/// Truncate text to at most `max_chars` characters (including the ellipsis), on a word
+/// boundary. A single word which is too long is cut, but never in the middle of a character.
+pub(crate) fn truncate_text(text: &str, max_chars: usize, ellipsis: &str) -> String {
+    if text.chars().count() <= max_chars {
+        return text.to_string();
+    }
+    if max_chars == 0 {
+        return String::new();
+    }
+    cut_text(
+        text,
+        max_chars.saturating_sub(ellipsis.chars().count()),
+        ellipsis,
+    )
+}
+
+/// Cut text to at most `budget` characters on a word boundary (like [`truncate_text`]), and
+/// append the ellipsis, even if the text fits
+pub(crate) fn cut_text(text: &str, budget: usize, ellipsis: &str) -> String {
+    let end = text
+        .char_indices()
+        .nth(budget)
+        .map_or(text.len(), |(i, _)| i);
+    let mut truncated = &text[..end];
+    if !text[end..].starts_with(char::is_whitespace) {
+        // the last word doesn't fit
+        truncated = match truncated.rfind(char::is_whitespace) {
+            Some(i) => &truncated[..i],
+            None => &truncated[..cluster_start(text, end)],
+        };
+    }
+
+    let mut out = truncated.trim_end().to_string();
+    out.push_str(ellipsis);
+    out
+}
+
+/// Convert markdown to plain text (like [`indico_markdown_to_plain_text`] with the default
+/// options) and truncate it to at most `max_chars` characters, including the `ellipsis` which
+/// is appended if the text is truncated.
+///
+/// The text is truncated on a word boundary, unless its first word is too long on its own.
+/// Characters (including emoji made of several code points) are never split.
+pub fn indico_markdown_truncate_plain(md_source: &str, max_chars: usize, ellipsis: &str) -> String {
+    let text = indico_markdown_to_plain_text(md_source, &PlainTextOptions::default());
+    truncate_text(&text, max_chars, ellipsis)
+}
+
+/// Width of the list marker (e.g. `- ` or `12. `) a line starts with, if any
+fn list_marker_width(line: &str, bullets: &[String]) -> usize {
+    for bullet in bullets {
+        if line.starts_with(bullet.as_str()) && line[bullet.len()..].starts_with(' ') {
+            return display_width(bullet) + 1;
+        }
+    }
+    let digits = line.bytes().take_while(u8::is_ascii_digit).count();
+    if digits > 0 && (line[digits..].starts_with(". ") || line[digits..].starts_with(") ")) {
+        digits + 2
+    } else {
+        0
+    }
+}
+
+/// Hard-wrap text at the given column (counting wide characters as two), breaking lines on
+/// spaces. Continuation lines keep the indentation and blockquote markers of the line (and
+/// list item with one of the given bullets) they belong to, and the spacing between words
+/// which stay on the same line is kept.
+/// Words which are longer than the width (e.g. URLs) are never broken, and the lines in the
+/// `verbatim` ranges (inclusive line indices, e.g. of code blocks) are left as they are.
+pub(crate) fn wrap_text(
+    text: &str,
+    width: usize,
+    bullets: &[String],
+    verbatim: &[(usize, usize)],
+) -> String {
+    let mut out = String::with_capacity(text.len());
+
+    for (i, line) in text.split('\n').enumerate() {
+        if i > 0 {
+            out.push('\n');
+        }
+        if display_width(line) <= width
+            || verbatim
+                .iter()
+                .any(|&(start, end)| (start..=end).contains(&i))
+        {
+            out.push_str(line);
+            continue;
+        }
+
+        // the indentation and `> ` markers are kept as they are
+        let content = line
+            .trim_start_matches(' ')
+            .trim_start_matches("> ")
+            .trim_start_matches(' ');
+        let prefix = &line[..line.len() - content.len()];
+        let mut continuation = prefix.to_string();
+        continuation.push_str(&" ".repeat(list_marker_width(content, bullets)));
+
+        out.push_str(prefix);
+        let mut col = display_width(prefix);
+        let mut line_start = true;
+
+        let mut rest = content;
+        loop {
+            let spaces = &rest[..rest.len() - rest.trim_start_matches(' ').len()];
+            rest = &rest[spaces.len()..];
+            let word = rest.split(' ').next().unwrap_or_default();
+            // spaces at the end of the line are dropped
+            if word.is_empty() {
+                break;
+            }
+            rest = &rest[word.len()..];
+
+            let len = display_width(word);
+            if !line_start && col + spaces.len() + len > width {
+                out.push('\n');
+                out.push_str(&continuation);
+                col = display_width(&continuation);
+                line_start = true;
+            }
+            if !line_start {
+                out.push_str(spaces);
+                col += spaces.len();
+            }
+            out.push_str(word);
+            col += len;
+            line_start = false;
+        }
+    }
+
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        LinkStyle, PlainTextOptions, indico_markdown_excerpt, indico_markdown_to_plain_text,
+        indico_markdown_to_plain_text_with_rules, indico_markdown_truncate_plain,
+    };
+    use crate::LinkRule;
+
+    #[test]
+    fn test_plain_text() {
+        let opts = PlainTextOptions::default();
+
+        let md = "# Title\n\nSome **bold** and [a link](https://example.com).\\\nNext line\n\n<b>raw</b>";
+        assert_eq!(
+            indico_markdown_to_plain_text(md, &opts),
+            "Title\n\nSome bold and a link.\nNext line\n\nraw"
+        );
+
+        let md = "* a list\n* of\n  - nested\n* things\n\n1. one\n2. two";
+        assert_eq!(
+            indico_markdown_to_plain_text(md, &opts),
+            "- a list\n- of\n  - nested\n- things\n\n1. one\n2. two"
+        );
+
+        let opts = PlainTextOptions {
+            bullet: '*',
+            links: LinkStyle::Inline,
+            ..Default::default()
+        };
+        let md = "* [a link](https://example.com)\n* https://getindico.io";
+        assert_eq!(
+            indico_markdown_to_plain_text(md, &opts),
+            "* a link (https://example.com)\n* https://getindico.io"
+        );
+    }
+
+    #[test]
+    fn test_plain_wrap_width() {
+        let opts = PlainTextOptions {
+            wrap_width: Some(10),
+            ..Default::default()
+        };
+        // CJK characters take two columns
+        assert_eq!(
+            indico_markdown_to_plain_text("日本語の テキスト です", &opts),
+            "日本語の\nテキスト\nです"
+        );
+        assert_eq!(
+            indico_markdown_to_plain_text("see https://example.com/long/url now", &opts),
+            "see\nhttps://example.com/long/url\nnow"
+        );
+
+        // code blocks and tables aren't wrapped, and the spacing of the text is kept
+        let opts = PlainTextOptions {
+            wrap_width: Some(12),
+            ..Default::default()
+        };
+        let md = "Some words which wrap\n\n\
+                  ```\nlet x = [1,  2,  3]; // long\n```\n\n\
+                  | Name | Description |\n|---|---|\n| a | a long cell text |\n\n\
+                  Two  spaces  here ok";
+        assert_eq!(
+            indico_markdown_to_plain_text(md, &opts),
+            "Some words\nwhich wrap\n\n\
+             let x = [1,  2,  3]; // long\n\n\
+             Name\tDescription\na\ta long cell text\n\n\
+             Two  spaces\nhere ok"
+        );
+    }
+
+    #[test]
+    fn test_plain_email() {
+        let md = "Please check [the timetable](https://indico.example.com/event/1/timetable) \
+                  and [the registration form](https://indico.example.com/event/1/registration) \
+                  before [the deadline](https://indico.example.com/event/1/timetable).\n\n\
+                  - a list item which is long enough to need wrapping at some point, surely";
+        assert_eq!(
+            indico_markdown_to_plain_text(md, &PlainTextOptions::email()),
+            "Please check the timetable [1] and the registration form [2] before the\n\
+             deadline [1].\n\
+             \n\
+             - a list item which is long enough to need wrapping at some point,\n  surely\n\
+             \n\
+             [1] https://indico.example.com/event/1/timetable\n\
+             [2] https://indico.example.com/event/1/registration"
+        );
+    }
+
+    #[test]
+    fn test_plain_link_rules() {
+        let rules = [LinkRule::new(r"#(\d+)", "https://tickets.example.com/{1}").unwrap()];
+        assert_eq!(
+            indico_markdown_to_plain_text_with_rules(
+                "See #12 and `#13`",
+                &rules,
+                &PlainTextOptions::email()
+            ),
+            "See #12 [1] and #13\n\n[1] https://tickets.example.com/12"
+        );
+    }
+
+    #[test]
+    fn test_excerpt() {
+        let md = "# Title\n\nThe [Indico project](https://getindico.io) is great.\n\nSecond `para graph`.";
+        assert_eq!(
+            indico_markdown_excerpt(md, 100),
+            "The Indico project is great. Second para graph."
+        );
+        assert_eq!(indico_markdown_excerpt(md, 16), "The\u{2026}");
+        assert_eq!(
+            indico_markdown_excerpt(md, 19),
+            "The Indico project\u{2026}"
+        );
+        assert_eq!(
+            indico_markdown_excerpt(md, 44),
+            "The Indico project is great. Second\u{2026}"
+        );
+        assert_eq!(indico_markdown_excerpt("ñandú ñandú", 8), "ñandú\u{2026}");
+        assert_eq!(indico_markdown_excerpt(md, 0), "");
+    }
+
+    #[test]
+    fn test_truncate_plain() {
+        let md = "# Agenda\n\nWelcome to the **ñandú** workshop";
+        assert_eq!(
+            indico_markdown_truncate_plain(md, 100, "..."),
+            "Agenda\n\nWelcome to the ñandú workshop"
+        );
+        assert_eq!(
+            indico_markdown_truncate_plain(md, 30, "\u{2026}"),
+            "Agenda\n\nWelcome to the ñandú\u{2026}"
+        );
+        assert_eq!(
+            indico_markdown_truncate_plain(md, 12, " [...]"),
+            "Agenda [...]"
+        );
+
+        // emoji are never split, even in words which are too long
+        let md = "\u{1f44d}\u{1f3fd}\u{1f1e8}\u{1f1ed}\u{1f1eb}\u{1f1f7}";
+        assert_eq!(
+            indico_markdown_truncate_plain(md, 3, "."),
+            "\u{1f44d}\u{1f3fd}."
+        );
+        assert_eq!(
+            indico_markdown_truncate_plain(md, 5, "."),
+            "\u{1f44d}\u{1f3fd}\u{1f1e8}\u{1f1ed}."
+        );
+        assert_eq!(indico_markdown_truncate_plain(md, 2, "."), ".");
+        assert_eq!(indico_markdown_truncate_plain(md, 0, "."), "");
+    }
+}
@@ -0,0 +1,149 @@
+//! A pre-filter of link rules: the literal text which any match of a rule must contain (e.g.
+//! `gh:` for `gh:(\d+)`) is looked for in all text nodes at once, and only the rules whose
+//! literal was found are tried on a node.
+
+use aho_corasick::AhoCorasick;
+
+use crate::LinkRule;
+
+/// Get a literal which starts any match of a regular expression, if there is a simple one.
+/// This is conservative: expressions with alternations or flags have no literal.
+pub(crate) fn required_literal(regex: &str) -> Option<String> {
+    if regex.contains('|') || regex.starts_with("(?") {
+        return None;
+    }
+    let mut chars = regex.chars().peekable();
+    // anchors and word boundaries (e.g. `\<` or `\b{start}`) don't match any text
+    loop {
+        if chars.next_if_eq(&'^').is_some() {
+            continue;
+        }
+        let mut ahead = chars.clone();
+        if ahead.next() == Some('\\') && matches!(ahead.next(), Some('b' | 'B' | 'A' | '<' | '>')) {
+            if ahead.next_if_eq(&'{').is_some() && !ahead.by_ref().any(|c| c == '}') {
+                return None;
+            }
+            chars = ahead;
+            continue;
+        }
+        break;
+    }
+
+    let mut literal = String::new();
+    while let Some(c) = chars.next() {
+        let c = match c {
+            '\\' => match chars.next() {
+                // word boundaries
+                Some('<' | '>') => break,
+                Some(escaped) if escaped.is_ascii_punctuation() => escaped,
+                _ => break,
+            },
+            '.' | '[' | ']' | '(' | ')' | '{' | '}' | '*' | '+' | '?' | '^' | '$' => break,
+            c => c,
+        };
+        // the character is optional
+        if matches!(chars.peek(), Some('?' | '*' | '{')) {
+            break;
+        }
+        literal.push(c);
+    }
+    (!literal.is_empty()).then_some(literal)
+}
+
+/// Finds the rules which may match a text
+pub(crate) struct RulePrefilter {
+    automaton: Option<AhoCorasick>,
+    /// Index of the rule of each pattern of the automaton
+    pattern_rules: Vec<usize>,
+    /// Whether each rule has to be tried on any text (because it has no literal)
+    always: Vec<bool>,
+}
+
+impl RulePrefilter {
+    pub(crate) fn new(rules: &[LinkRule]) -> Self {
+        let mut patterns = Vec::new();
+        let mut pattern_rules = Vec::new();
+        for (index, rule) in rules.iter().enumerate() {
+            if let Some(literal) = &rule.literal {
+                patterns.push(literal.as_str());
+                pattern_rules.push(index);
+            }
+        }
+        let automaton = if patterns.is_empty() {
+            None
+        } else {
+            AhoCorasick::new(&patterns).ok()
+        };
+        Self {
+            // if the automaton can't be built, all the rules are tried
+            always: rules
+                .iter()
+                .map(|rule| rule.literal.is_none() || automaton.is_none())
+                .collect(),
+            automaton,
+            pattern_rules,
+        }
+    }
+
+    /// Get whether each rule may match the text
+    pub(crate) fn candidates(&self, text: &str) -> Vec<bool> {
+        let mut candidates = self.always.clone();
+        if let Some(automaton) = &self.automaton {
+            for m in automaton.find_overlapping_iter(text) {
+                candidates[self.pattern_rules[m.pattern().as_usize()]] = true;
+            }
+        }
+        candidates
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{RulePrefilter, required_literal};
+    use crate::LinkRule;
+
+    #[test]
+    fn test_required_literal() {
+        for (regex, literal) in [
+            (r"gh:(\d+)", Some("gh:")),
+            (r"\bTKT(\d{7})\b", Some("TKT")),
+            (r"^\#(\d+)", Some("#")),
+            (r"ab+c", Some("ab")),
+            (r"abc?", Some("ab")),
+            (r"a*", None),
+            (r"\d+", None),
+            (r"(?i)gh:(\d+)", None),
+            (r"gh:(\d+)|issue (\d+)", None),
+            (r"\<PR-\d+", Some("PR-")),
+            (r"\b{start}PR-\d+", Some("PR-")),
+            (r"PR\>", Some("PR")),
+            (r"a\<b", Some("a")),
+            (r"\bx\By", Some("x")),
+        ] {
+            assert_eq!(required_literal(regex).as_deref(), literal, "{regex}");
+        }
+    }
+
+    #[test]
+    fn test_candidates() {
+        let rules = [
+            LinkRule::new(r"gh:(\d+)", "/gh/{1}").unwrap(),
+            LinkRule::new(r"\d{4}", "/year/{0}").unwrap(),
+            LinkRule::new(r"@(\w+)", "/users/{1}").unwrap(),
+        ];
+        let prefilter = RulePrefilter::new(&rules);
+        assert_eq!(prefilter.candidates("see gh:1"), [true, true, false]);
+        assert_eq!(prefilter.candidates("ping @bob"), [false, true, true]);
+
+        // boundaries aren't part of the literal
+        let rules = [LinkRule::new(r"\<PR-(\d+)", "/pulls/{1}").unwrap()];
+        let prefilter = RulePrefilter::new(&rules);
+        assert_eq!(prefilter.candidates("see PR-12"), [true]);
+        assert_eq!(prefilter.candidates("see <PR-12"), [true]);
+        assert_eq!(prefilter.candidates("see PR 12"), [false]);
+        assert_eq!(
+            crate::indico_markdown_to_html("see PR-12", &rules).unwrap(),
+            "<p>see <a href=\"/pulls/12\" title=\"PR-12\" target=\"_blank\">PR-12</a></p>\n"
+        );
+    }
+}
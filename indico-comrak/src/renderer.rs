@@ -0,0 +1,102 @@
+//! Rendering of many documents with the same link rules and options, e.g. when exporting all
+//! the abstracts of an event.
+
+use comrak::{Arena, Options};
+
+use crate::{
+    LinkRule, RenderError, RenderOutput, comrak_options, options::RenderOptions, render_document,
+};
+
+/// Number of documents whose nodes are allocated in the same arena by
+/// [`IndicoRenderer::render_many`]. Nodes can't be freed individually, so a new arena is
+/// started after this many documents, which bounds the memory used.
+const DOCUMENTS_PER_ARENA: usize = 64;
+
+/// A renderer of documents which share the same link rules and options, which are only
+/// prepared once
+#[derive(Debug)]
+pub struct IndicoRenderer {
+    autolink_rules: Vec<LinkRule>,
+    render_options: RenderOptions,
+    options: Options<'static>,
+}
+
+impl IndicoRenderer {
+    pub fn new(autolink_rules: Vec<LinkRule>, render_options: RenderOptions) -> Self {
+        Self {
+            options: comrak_options(&render_options),
+            autolink_rules,
+            render_options,
+        }
+    }
+
+    /// Render a document, like [`indico_markdown_render`](crate::indico_markdown_render)
+    pub fn render(&self, md_source: &str) -> Result<RenderOutput, RenderError> {
+        let arena = Arena::new();
+        render_document(
+            &arena,
+            &self.options,
+            md_source,
+            &self.autolink_rules,
+            &self.render_options,
+        )
+    }
+
+    /// Render many documents, returning their outputs in the same order. The documents are
+    /// rendered in batches of [`DOCUMENTS_PER_ARENA`], whose nodes are allocated in the same
+    /// arena, which is freed once the whole batch is rendered.
+    pub fn render_many<'s>(
+        &self,
+        md_sources: impl IntoIterator<Item = &'s str>,
+    ) -> Vec<Result<RenderOutput, RenderError>> {
+        let mut outputs = Vec::new();
+        let mut md_sources = md_sources.into_iter().peekable();
+        while md_sources.peek().is_some() {
+            let arena = Arena::new();
+            for md_source in md_sources.by_ref().take(DOCUMENTS_PER_ARENA) {
+                outputs.push(render_document(
+                    &arena,
+                    &self.options,
+                    md_source,
+                    &self.autolink_rules,
+                    &self.render_options,
+                ));
+            }
+        }
+        outputs
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{DOCUMENTS_PER_ARENA, IndicoRenderer};
+    use crate::{LinkRule, indico_markdown_render, options::RenderOptions};
+
+    #[test]
+    fn test_render_many() {
+        let options = RenderOptions {
+            definition_lists: true,
+            ..Default::default()
+        };
+        let renderer = IndicoRenderer::new(
+            vec![LinkRule::new(r"#(\d+)", "/tickets/{1}").unwrap()],
+            options.clone(),
+        );
+        let docs: Vec<String> = (0..DOCUMENTS_PER_ARENA * 2 + 1)
+            .map(|i| format!("# Abstract {i}\n\nSee #{i}\n\nTerm\n: definition"))
+            .collect();
+        let outputs = renderer.render_many(docs.iter().map(String::as_str));
+        assert_eq!(outputs.len(), docs.len());
+
+        let rules = [LinkRule::new(r"#(\d+)", "/tickets/{1}").unwrap()];
+        for (doc, output) in docs.iter().zip(outputs) {
+            let expected = indico_markdown_render(doc, &rules, &options).unwrap();
+            assert_eq!(output.unwrap(), expected);
+        }
+        assert!(renderer.render_many(Vec::<&str>::new()).is_empty());
+        assert_eq!(
+            renderer.render("#1").unwrap().html,
+            "<p><a href=\"/tickets/1\" title=\"#1\" target=\"_blank\">#1</a></p>\n"
+        );
+    }
+}
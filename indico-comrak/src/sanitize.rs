@@ -0,0 +1,267 @@
+//! Sanitization of raw HTML with an allowlist of tags and attributes, for documents which come
+//! from untrusted users but may still use some HTML (e.g. `<sup>` or `<table>`). Only raw HTML
+//! is sanitized: the URLs of markdown links and images are left to
+//! [`block_urls`](crate::warnings::block_urls).
+
+use comrak::nodes::{AstNode, NodeValue};
+use regex_lite::{Captures, Regex};
+use std::sync::LazyLock;
+
+use crate::warnings::{RenderWarning, RenderWarningKind, url_scheme};
+
+/// Tags which are kept, with the attributes they may have besides [`GLOBAL_ATTRIBUTES`]
+const ALLOWED_TAGS: &[(&str, &[&str])] = &[
+    ("a", &["href", "name"]),
+    ("abbr", &[]),
+    ("b", &[]),
+    ("blockquote", &[]),
+    ("br", &[]),
+    ("caption", &[]),
+    ("cite", &[]),
+    ("code", &[]),
+    ("dd", &[]),
+    ("del", &[]),
+    ("details", &["open"]),
+    ("div", &[]),
+    ("dl", &[]),
+    ("dt", &[]),
+    ("em", &[]),
+    ("h1", &[]),
+    ("h2", &[]),
+    ("h3", &[]),
+    ("h4", &[]),
+    ("h5", &[]),
+    ("h6", &[]),
+    ("hr", &[]),
+    ("i", &[]),
+    ("img", &["src", "alt", "width", "height"]),
+    ("ins", &[]),
+    ("kbd", &[]),
+    ("li", &["value"]),
+    ("mark", &[]),
+    ("ol", &["start", "type", "reversed"]),
+    ("p", &[]),
+    ("pre", &[]),
+    ("q", &[]),
+    ("s", &[]),
+    ("small", &[]),
+    ("span", &[]),
+    ("strike", &[]),
+    ("strong", &[]),
+    ("sub", &[]),
+    ("summary", &[]),
+    ("sup", &[]),
+    ("table", &[]),
+    ("tbody", &[]),
+    ("td", &["colspan", "rowspan", "align"]),
+    ("tfoot", &[]),
+    ("th", &["colspan", "rowspan", "align", "scope"]),
+    ("thead", &[]),
+    ("tr", &[]),
+    ("u", &[]),
+    ("ul", &[]),
+];
+/// Attributes which all the allowed tags may have
+const GLOBAL_ATTRIBUTES: &[&str] = &["title", "lang", "dir"];
+/// Attributes whose values are URLs, which are only kept if they are safe (see [`is_safe_url`])
+const URL_ATTRIBUTES: &[&str] = &["href", "src"];
+/// Schemes of the absolute URLs which are kept
+const URL_SCHEMES: &[&str] = &["http", "https", "mailto"];
+
+/// Comments, and start or end tags with their name, attributes and whether they are
+/// self-closing
+static HTML_RE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(
+        r#"<!--[\s\S]*?-->|<(/)?([A-Za-z][A-Za-z0-9-]*)((?:\s+[^\s"'>/=]+(?:\s*=\s*(?:"[^"]*"|'[^']*'|[^\s"'=<>`]+))?)*)\s*(/)?>"#,
+    )
+    .unwrap()
+});
+/// Attributes of a tag, with their (double-quoted, single-quoted or unquoted) value
+static ATTRIBUTE_RE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r#"([^\s"'>/=]+)(?:\s*=\s*(?:"([^"]*)"|'([^']*)'|([^\s"'=<>`]+)))?"#).unwrap()
+});
+
+/// Check whether a URL is relative or has one of the allowed schemes. Relative URLs which
+/// could hide a scheme behind character references (e.g. `&#106;avascript:`) are not safe.
+pub(crate) fn is_safe_url(url: &str) -> bool {
+    match url_scheme(url) {
+        Some(scheme) => URL_SCHEMES.contains(&scheme.as_str()),
+        None => {
+            let prefix = url.split(['/', '?', '#']).next().unwrap_or_default();
+            !prefix.contains([':', '&'])
+        }
+    }
+}
+
+/// Rebuild an allowed tag with its allowed attributes only, adding the URLs which were removed
+/// to `blocked`. Returns `None` if the tag isn't allowed.
+fn sanitize_tag(caps: &Captures, blocked: &mut Vec<String>) -> Option<String> {
+    let name = caps[2].to_ascii_lowercase();
+    let (_, attributes) = ALLOWED_TAGS.iter().find(|(tag, _)| *tag == name)?;
+    if caps.get(1).is_some() {
+        return Some(format!("</{name}>"));
+    }
+
+    let mut tag = format!("<{name}");
+    for attr in ATTRIBUTE_RE.captures_iter(&caps[3]) {
+        let attr_name = attr[1].to_ascii_lowercase();
+        if !GLOBAL_ATTRIBUTES.contains(&attr_name.as_str())
+            && !attributes.contains(&attr_name.as_str())
+        {
+            continue;
+        }
+        let value = attr
+            .get(2)
+            .or(attr.get(3))
+            .or(attr.get(4))
+            .map_or("", |m| m.as_str());
+        if URL_ATTRIBUTES.contains(&attr_name.as_str()) && !is_safe_url(value) {
+            blocked.push(value.to_string());
+            continue;
+        }
+        // values keep their character references, only the quotes need escaping
+        let value = value
+            .replace('"', "&quot;")
+            .replace('<', "&lt;")
+            .replace('>', "&gt;");
+        tag.push_str(&format!(" {attr_name}=\"{value}\""));
+    }
+    if caps.get(4).is_some() {
+        tag.push_str(" /");
+    }
+    tag.push('>');
+    Some(tag)
+}
+
+/// Sanitize a piece of raw HTML: allowed tags are rebuilt with their allowed attributes, the
+/// other tags and any stray `<` are escaped, and comments are removed. The names of the tags
+/// which were escaped are added to `escaped`, and the URLs which were removed to `blocked`.
+fn sanitize_html(html: &str, escaped: &mut Vec<String>, blocked: &mut Vec<String>) -> String {
+    let mut out = String::with_capacity(html.len());
+    let mut last = 0;
+    for caps in HTML_RE.captures_iter(html) {
+        let whole = caps.get(0).unwrap();
+        out.push_str(&html[last..whole.start()].replace('<', "&lt;"));
+        last = whole.end();
+        // comments
+        if caps.get(2).is_none() {
+            continue;
+        }
+        match sanitize_tag(&caps, blocked) {
+            Some(tag) => out.push_str(&tag),
+            None => {
+                let name = caps[2].to_ascii_lowercase();
+                if !escaped.contains(&name) {
+                    escaped.push(name);
+                }
+                out.push_str(
+                    &whole
+                        .as_str()
+                        .replace('&', "&amp;")
+                        .replace('<', "&lt;")
+                        .replace('>', "&gt;"),
+                );
+            }
+        }
+    }
+    out.push_str(&html[last..].replace('<', "&lt;"));
+    out
+}
+
+/// Sanitize the raw HTML of a document, returning warnings about the tags which were escaped
+/// and the URLs which were removed
+pub(crate) fn sanitize_raw_html<'a>(root: &'a AstNode<'a>) -> Vec<RenderWarning> {
+    let mut warnings = Vec::new();
+    for node in root.descendants() {
+        let mut data = node.data.borrow_mut();
+        let sourcepos = data.sourcepos;
+        let html = match data.value {
+            NodeValue::HtmlInline(ref mut html) => html,
+            NodeValue::HtmlBlock(ref mut nhb) => &mut nhb.literal,
+            _ => continue,
+        };
+        let mut escaped = Vec::new();
+        let mut blocked = Vec::new();
+        *html = sanitize_html(html, &mut escaped, &mut blocked);
+
+        warnings.extend(escaped.into_iter().map(|tag| RenderWarning {
+            kind: RenderWarningKind::FilteredTag,
+            sourcepos: sourcepos.into(),
+            detail: format!("<{tag}> tags are not allowed"),
+        }));
+        warnings.extend(blocked.into_iter().map(|url| RenderWarning {
+            kind: RenderWarningKind::BlockedUrl,
+            sourcepos: sourcepos.into(),
+            detail: format!("the URL `{url}` is not allowed"),
+        }));
+    }
+    warnings
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{is_safe_url, sanitize_html};
+    use crate::{
+        RawHtml, RenderOptions, RenderWarningKind, indico_markdown_render,
+        indico_markdown_to_html_with_options,
+    };
+
+    fn sanitize(html: &str) -> String {
+        sanitize_html(html, &mut Vec::new(), &mut Vec::new())
+    }
+
+    #[test]
+    fn test_sanitize_html() {
+        assert_eq!(
+            sanitize("<img src=x onerror=alert(1)><B Title='a \"b\"'>x</b>"),
+            "<img src=\"x\"><b title=\"a &quot;b&quot;\">x</b>"
+        );
+        assert_eq!(
+            sanitize("<a href=\"javascript:alert(1)\" class=x>y</a><br/>"),
+            "<a>y</a><br />"
+        );
+        assert_eq!(
+            sanitize("<script>alert('<b>')</script><!-- hi -->a < b"),
+            "&lt;script&gt;alert('<b>')&lt;/script&gt;a &lt; b"
+        );
+        assert_eq!(
+            sanitize("<style>x</style><a/href=x>"),
+            "&lt;style&gt;x&lt;/style&gt;&lt;a/href=x>"
+        );
+    }
+
+    #[test]
+    fn test_safe_urls() {
+        assert!(is_safe_url("https://example.com/?a=1&amp;b=2"));
+        assert!(is_safe_url("mailto:a@example.com"));
+        assert!(is_safe_url("/event/1?next=a:b"));
+        assert!(is_safe_url("#top"));
+        assert!(!is_safe_url(" JavaScript:alert(1)"));
+        assert!(!is_safe_url("data:text/html,x"));
+        assert!(!is_safe_url("&#106;avascript:alert(1)"));
+        assert!(!is_safe_url("javascript&colon;alert(1)"));
+    }
+
+    #[test]
+    fn test_sanitized_render() {
+        let options = RenderOptions {
+            raw_html: RawHtml::Sanitized,
+            ..Default::default()
+        };
+        let md = "H<sub onclick=\"x()\">2</sub>O\n\n<div>\n<img src=\"javascript:x\">\n</div>";
+        assert_eq!(
+            indico_markdown_to_html_with_options(md, &[], &options).unwrap(),
+            "<p>H<sub>2</sub>O</p>\n<div>\n<img>\n</div>\n"
+        );
+
+        let output = indico_markdown_render("<iframe src=x></iframe>", &[], &options).unwrap();
+        let kinds: Vec<_> = output.warnings.iter().map(|w| w.kind).collect();
+        assert_eq!(kinds, [RenderWarningKind::FilteredTag]);
+
+        // markdown images are not sanitized, only raw HTML
+        assert_eq!(
+            indico_markdown_to_html_with_options("![x](data:image/png,y)", &[], &options).unwrap(),
+            "<p><img src=\"data:image/png,y\" alt=\"x\" /></p>\n"
+        );
+    }
+}
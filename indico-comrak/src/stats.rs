@@ -0,0 +1,132 @@
+//! Statistics about documents (word count, reading time...), e.g. for long minutes.
+
+use comrak::{
+    Arena,
+    nodes::{AstNode, NodeValue},
+    parse_document,
+};
+use std::time::Duration;
+
+use crate::indico_options;
+
+/// Options for [`indico_markdown_stats`]
+#[derive(Debug, Clone)]
+pub struct StatsOptions {
+    /// Count the words in code (spans and blocks) and math formulas
+    pub include_code: bool,
+    /// Count bare URLs (links whose text is their URL) as words
+    pub include_urls: bool,
+    /// Reading speed used to compute the reading time
+    pub words_per_minute: u32,
+}
+
+impl Default for StatsOptions {
+    fn default() -> Self {
+        Self {
+            include_code: false,
+            include_urls: false,
+            words_per_minute: 200,
+        }
+    }
+}
+
+/// Statistics about a document
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DocStats {
+    /// Number of words
+    pub words: usize,
+    /// Number of characters of the text, excluding whitespace
+    pub chars: usize,
+    /// Number of headings
+    pub headings: usize,
+    /// Number of code blocks
+    pub code_blocks: usize,
+    /// Estimated reading time, rounded up to the second
+    pub reading_time: Duration,
+}
+
+/// Compute statistics about a document, from its text (markup and raw HTML are not counted)
+pub fn indico_markdown_stats(md_source: &str, opts: &StatsOptions) -> DocStats {
+    let arena = Arena::new();
+    let root = parse_document(&arena, md_source, &indico_options());
+    document_stats(root, opts)
+}
+
+/// Compute statistics about a parsed document
+pub(crate) fn document_stats<'a>(root: &'a AstNode<'a>, opts: &StatsOptions) -> DocStats {
+    let mut text = String::new();
+    let mut headings = 0;
+    let mut code_blocks = 0;
+    for node in root.descendants() {
+        let data = node.data.borrow();
+        match data.value {
+            NodeValue::Text(ref t) => {
+                let is_url = node.parent().is_some_and(|parent| {
+                    matches!(parent.data.borrow().value, NodeValue::Link(ref nl) if nl.url.ends_with(&**t))
+                });
+                if opts.include_urls || !is_url {
+                    text.push_str(t);
+                }
+            }
+            NodeValue::Code(ref nc) if opts.include_code => text.push_str(&nc.literal),
+            NodeValue::Math(ref nm) if opts.include_code => text.push_str(&nm.literal),
+            NodeValue::CodeBlock(ref ncb) => {
+                code_blocks += 1;
+                if opts.include_code {
+                    text.push(' ');
+                    text.push_str(&ncb.literal);
+                }
+            }
+            NodeValue::Heading(_) => {
+                headings += 1;
+                text.push(' ');
+            }
+            NodeValue::SoftBreak | NodeValue::LineBreak => text.push(' '),
+            // words of different blocks are never glued together
+            ref value if value.block() => text.push(' '),
+            _ => {}
+        }
+    }
+
+    // punctuation on its own (e.g. after a code span which isn't counted) is not a word
+    let words = text
+        .split_whitespace()
+        .filter(|word| word.chars().any(char::is_alphanumeric))
+        .count();
+    let chars = text.chars().filter(|c| !c.is_whitespace()).count();
+    let words_per_minute = u64::from(opts.words_per_minute.max(1));
+    DocStats {
+        words,
+        chars,
+        headings,
+        code_blocks,
+        reading_time: Duration::from_secs((words as u64 * 60).div_ceil(words_per_minute)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{StatsOptions, indico_markdown_stats};
+    use std::time::Duration;
+
+    #[test]
+    fn test_stats() {
+        let md = "# Minutes\n\nWe **met**, see https://example.com and `the code`.\n\n\
+                  ```\nlet x = 1;\n```\n\n- one\n- two";
+        let stats = indico_markdown_stats(md, &StatsOptions::default());
+        assert_eq!(stats.words, 7);
+        assert_eq!(stats.chars, 26);
+        assert_eq!(stats.headings, 1);
+        assert_eq!(stats.code_blocks, 1);
+        assert_eq!(stats.reading_time, Duration::from_secs(3));
+
+        let opts = StatsOptions {
+            include_code: true,
+            include_urls: true,
+            words_per_minute: 60,
+        };
+        let stats = indico_markdown_stats(md, &opts);
+        assert_eq!(stats.words, 13);
+        assert_eq!(stats.reading_time, Duration::from_secs(13));
+    }
+}
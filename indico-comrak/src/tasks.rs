@@ -0,0 +1,90 @@
+//! Interactive task lists: the checkboxes of task list items are rendered with their index
+//! and line, and can be toggled in the markdown source using that index.
+
+use comrak::{Arena, nodes::NodeValue, parse_document};
+
+use crate::indico_options;
+
+/// A task list item checkbox in a markdown document
+struct TaskCheckbox {
+    /// Line of the item (starting at 1)
+    line: usize,
+    /// Byte offset of the character between the brackets
+    offset: usize,
+    checked: bool,
+}
+
+/// Find the checkboxes of the task list items of a document, in document order
+fn task_checkboxes(md_source: &str) -> Vec<TaskCheckbox> {
+    let arena = Arena::new();
+    let root = parse_document(&arena, md_source, &indico_options());
+    let line_starts: Vec<usize> = std::iter::once(0)
+        .chain(md_source.match_indices('\n').map(|(i, _)| i + 1))
+        .collect();
+
+    root.descendants()
+        .filter_map(|node| {
+            let data = node.data.borrow();
+            let NodeValue::TaskItem(ref nti) = data.value else {
+                return None;
+            };
+            // the item starts at its list marker, which is followed by the checkbox
+            let start = data.sourcepos.start;
+            let item_offset = line_starts.get(start.line.checked_sub(1)?)? + start.column - 1;
+            let bracket = item_offset + md_source.get(item_offset..)?.find('[')?;
+            Some(TaskCheckbox {
+                line: start.line,
+                offset: bracket + 1,
+                checked: nti.symbol.is_some(),
+            })
+        })
+        .collect()
+}
+
+/// Lines of the task list items of a document, in document order
+pub(crate) fn task_lines(md_source: &str) -> Vec<usize> {
+    task_checkboxes(md_source)
+        .iter()
+        .map(|checkbox| checkbox.line)
+        .collect()
+}
+
+/// Check or uncheck the task list item with the given index (counting from 0, in document
+/// order), which is the `data-index` of its checkbox when interactive task lists are enabled.
+/// Returns the updated markdown, or `None` if there is no such item.
+pub fn indico_markdown_toggle_task(md_source: &str, index: usize) -> Option<String> {
+    let checkbox = task_checkboxes(md_source).into_iter().nth(index)?;
+    let symbol_len = md_source[checkbox.offset..].chars().next()?.len_utf8();
+
+    let mut out = String::with_capacity(md_source.len());
+    out.push_str(&md_source[..checkbox.offset]);
+    out.push(if checkbox.checked { ' ' } else { 'x' });
+    out.push_str(&md_source[checkbox.offset + symbol_len..]);
+    Some(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{indico_markdown_toggle_task, task_lines};
+
+    #[test]
+    fn test_toggle_task() {
+        let md =
+            "# Minutes\n\n- [ ] agenda\n- [x] budget\n\n> 1. [X] quoted\n\n```\n- [ ] code\n```\n";
+        assert_eq!(task_lines(md), vec![3, 4, 6]);
+
+        assert_eq!(
+            indico_markdown_toggle_task(md, 0).unwrap(),
+            md.replace("[ ] agenda", "[x] agenda")
+        );
+        assert_eq!(
+            indico_markdown_toggle_task(md, 1).unwrap(),
+            md.replace("[x] budget", "[ ] budget")
+        );
+        assert_eq!(
+            indico_markdown_toggle_task(md, 2).unwrap(),
+            md.replace("[X] quoted", "[ ] quoted")
+        );
+        assert_eq!(indico_markdown_toggle_task(md, 3), None);
+    }
+}
@@ -0,0 +1,48 @@
+//! Teasers: the part of a document before its `<!-- more -->` marker.
+//!
+//! The teaser is rendered from the AST without the nodes which follow the marker, so that all
+//! the elements which contain the marker (lists, blockquotes, ...) are still closed.
+
+use comrak::nodes::{AstNode, NodeValue};
+
+/// Check whether some raw HTML is a read-more marker
+fn is_marker(html: &str) -> bool {
+    html.trim()
+        .strip_prefix("<!--")
+        .and_then(|rest| rest.strip_suffix("-->"))
+        .is_some_and(|comment| comment.trim() == "more")
+}
+
+/// Find the first read-more marker of a document
+pub(crate) fn find_marker<'a>(root: &'a AstNode<'a>) -> Option<&'a AstNode<'a>> {
+    root.descendants()
+        .find(|node| match node.data.borrow().value {
+            NodeValue::HtmlBlock(ref nhb) => is_marker(&nhb.literal),
+            NodeValue::HtmlInline(ref html) => is_marker(html),
+            _ => false,
+        })
+}
+
+/// Get the nodes which follow a node in the document, i.e. its following siblings and the
+/// ones of its ancestors, except for footnote definitions (which are always at the end)
+pub(crate) fn nodes_after<'a>(node: &'a AstNode<'a>) -> Vec<&'a AstNode<'a>> {
+    node.ancestors()
+        .flat_map(|ancestor| ancestor.following_siblings().skip(1))
+        .filter(|n| !matches!(n.data.borrow().value, NodeValue::FootnoteDefinition(_)))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::is_marker;
+
+    #[test]
+    fn test_is_marker() {
+        for html in ["<!--more-->", "<!-- more -->\n", "  <!--  more-->"] {
+            assert!(is_marker(html));
+        }
+        for html in ["<!-- more than that -->", "<!--more", "<p>more</p>"] {
+            assert!(!is_marker(html));
+        }
+    }
+}
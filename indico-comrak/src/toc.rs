@@ -0,0 +1,170 @@
+//! Generated tables of contents, which replace `[TOC]` (or `[[_TOC_]]`) markers.
+
+use comrak::{
+    Arena,
+    html::Anchorizer,
+    nodes::{AstNode, NodeHtmlBlock, NodeValue},
+};
+use std::collections::HashMap;
+
+use crate::{
+    attributes::node_key,
+    collect_text, header_id_prefix,
+    headings::HeadingNumbers,
+    html::escape_xml,
+    options::{RenderOptions, RenderProfile, TocOptions},
+    replace_with_nodes,
+};
+
+/// Map the text of the headings of a prepared document to the slugs of their IDs. When
+/// several headings have the same text, the first one is kept.
+pub(crate) fn heading_slugs<'a>(root: &'a AstNode<'a>) -> HashMap<String, String> {
+    let mut anchorizer = Anchorizer::new();
+    let mut slugs = HashMap::new();
+    for node in root.descendants() {
+        if let NodeValue::Heading(_) = node.data.borrow().value {
+            let text = collect_text(node);
+            let slug = anchorizer.anchorize(&text);
+            slugs.entry(text).or_insert(slug);
+        }
+    }
+    slugs
+}
+
+/// Check whether a paragraph only consists of a table of contents marker
+fn is_toc_marker<'a>(paragraph: &'a AstNode<'a>) -> bool {
+    let Some(child) = paragraph.first_child() else {
+        return false;
+    };
+    match child.data.borrow().value {
+        // `[[_TOC_]]` is parsed as a wiki link when those are enabled
+        NodeValue::WikiLink(ref nwl) => child.next_sibling().is_none() && nwl.url == "_TOC_",
+        _ => {
+            paragraph
+                .children()
+                .all(|n| matches!(n.data.borrow().value, NodeValue::Text(_)))
+                && matches!(collect_text(paragraph).trim(), "[TOC]" | "[[_TOC_]]")
+        }
+    }
+}
+
+/// Render the headings of the document as nested lists
+fn toc_html(headings: &[(u8, String, String)], links: bool) -> String {
+    if headings.is_empty() {
+        return String::new();
+    }
+
+    let mut html = String::from("<ul class=\"toc\">\n<li>");
+    // levels of the headings which are currently open, one per nested list
+    let mut levels = vec![headings[0].0];
+
+    for (i, (level, text, id)) in headings.iter().enumerate() {
+        if i > 0 {
+            if *level > *levels.last().unwrap() {
+                html.push_str("\n<ul>\n<li>");
+                levels.push(*level);
+            } else {
+                html.push_str("</li>\n");
+                while levels.len() > 1 && *level <= levels[levels.len() - 2] {
+                    html.push_str("</ul>\n</li>\n");
+                    levels.pop();
+                }
+                *levels.last_mut().unwrap() = *level;
+                html.push_str("<li>");
+            }
+        }
+        if links {
+            html.push_str("<a href=\"#");
+            escape_xml(&mut html, id, true);
+            html.push_str("\">");
+            escape_xml(&mut html, text, false);
+            html.push_str("</a>");
+        } else {
+            escape_xml(&mut html, text, false);
+        }
+    }
+
+    html.push_str("</li>\n");
+    for _ in 1..levels.len() {
+        html.push_str("</ul>\n</li>\n");
+    }
+    html.push_str("</ul>\n");
+    html
+}
+
+/// Replace table of contents markers with a list of the headings of the document
+pub(crate) fn replace_toc_markers<'a>(
+    root: &'a AstNode<'a>,
+    arena: &'a Arena<'a>,
+    options: &RenderOptions,
+    toc: &TocOptions,
+    heading_numbers: &HeadingNumbers,
+) {
+    let mut markers = Vec::new();
+    let mut headings = Vec::new();
+    // all headings go through the anchorizer (in document order), so that the IDs of
+    // duplicate headings are the same as the ones generated by comrak
+    let mut anchorizer = Anchorizer::new();
+    let id_prefix = header_id_prefix(options);
+
+    for node in root.descendants() {
+        match node.data.borrow().value {
+            NodeValue::Paragraph if is_toc_marker(node) => markers.push(node),
+            NodeValue::Heading(ref nh) => {
+                let mut text = collect_text(node);
+                let id = format!("{id_prefix}{}", anchorizer.anchorize(&text));
+                if let Some(number) = heading_numbers.get(&node_key(node)) {
+                    text = format!("{number} {text}");
+                }
+                if (toc.min_level..=toc.max_level).contains(&nh.level) {
+                    headings.push((nh.level, text, id));
+                }
+            }
+            _ => {}
+        }
+    }
+    if markers.is_empty() {
+        return;
+    }
+
+    // only regular HTML has heading IDs to link to
+    let html = toc_html(&headings, options.profile == RenderProfile::Default);
+    for marker in markers {
+        let values = if html.is_empty() {
+            Vec::new()
+        } else {
+            vec![NodeValue::HtmlBlock(NodeHtmlBlock {
+                block_type: 0,
+                literal: html.clone(),
+            })]
+        };
+        replace_with_nodes(marker, arena, values);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::toc_html;
+
+    #[test]
+    fn test_toc_html() {
+        let headings = [
+            (2, "A".to_string(), "a".to_string()),
+            (4, "B".to_string(), "b".to_string()),
+            (3, "C".to_string(), "c".to_string()),
+            (4, "D & E".to_string(), "d-e".to_string()),
+            (2, "F".to_string(), "f".to_string()),
+        ];
+        assert_eq!(
+            toc_html(&headings, true),
+            "<ul class=\"toc\">\n<li><a href=\"#a\">A</a>\n<ul>\n<li><a href=\"#b\">B</a></li>\n\
+             <li><a href=\"#c\">C</a>\n<ul>\n<li><a href=\"#d-e\">D &amp; E</a></li>\n</ul>\n\
+             </li>\n</ul>\n</li>\n<li><a href=\"#f\">F</a></li>\n</ul>\n"
+        );
+        assert_eq!(
+            toc_html(&headings[..2], false),
+            "<ul class=\"toc\">\n<li>A\n<ul>\n<li>B</li>\n</ul>\n</li>\n</ul>\n"
+        );
+        assert_eq!(toc_html(&[], true), "");
+    }
+}
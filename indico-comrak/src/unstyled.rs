@@ -0,0 +1,1323 @@
+//! Rendering of markdown into "unstyled" HTML, which only keeps paragraphs and line breaks
+//! (plus the elements allowed by the options), e.g. for notifications.
+
+use comrak::{
+    Arena,
+    html::{ChildRendering, Context},
+    nodes::{AstNode, ListDelimType, ListType, NodeAlert, NodeList, NodeValue, TableAlignment},
+    parse_document,
+};
+use regex_lite::Regex;
+use std::borrow::Cow;
+use std::fmt::{self, Write};
+use std::sync::LazyLock;
+
+use crate::{
+    LinkRule, add_links, collect_text,
+    html::escape_xml,
+    in_tight_list,
+    plain::{display_width, wrap_text},
+    sanitize::is_safe_url,
+    unstyled_options,
+};
+
+/// Inline `<br>` tags, which are kept by [`unstyled_formatter`]
+static BR_RE: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"<\s*br\s*\/?>").unwrap());
+/// Inline `<p>` start tags, which are kept by [`unstyled_formatter`]
+static P_OPEN_RE: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"<\s*p(?:\s[^>]*)?>").unwrap());
+/// Inline HTML tags, with their name and whether they are end tags or self-closing
+static TAG_RE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"^<\s*(/)?\s*([a-z][a-z0-9-]*)(?:\s[^>]*?)?\s*(/)?\s*>$").unwrap()
+});
+
+/// Output of the unstyled renderer
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum UnstyledOutput {
+    /// HTML with paragraphs and line breaks
+    #[default]
+    Html,
+    /// Text without any tags, e.g. for calendar descriptions: paragraphs are separated by blank
+    /// lines and line breaks become newlines. The elements allowed by the options are reduced
+    /// to their text as well.
+    Text,
+}
+
+/// How tables are rendered by the unstyled renderer
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum UnstyledTables {
+    /// Columns padded with spaces and separated by ` | `, with a line under the header row
+    #[default]
+    Aligned,
+    /// Cells separated by tabs, e.g. to be pasted into a spreadsheet
+    Tabs,
+}
+
+/// Markers which set headings apart from the text when they aren't kept as elements
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum UnstyledHeadings {
+    /// Headings look like the rest of the text
+    #[default]
+    None,
+    /// Headings are underlined with `=` (level 1) or `-` (other levels)
+    Underline,
+    /// Headings are surrounded by as many `=` as their level, e.g. `== Title ==`
+    Equals,
+}
+
+/// Handling of inline HTML by the unstyled renderer, besides `<br>` and `<p>` tags which
+/// always become line and paragraph breaks
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum UnstyledInlineHtml {
+    /// Tags are removed and the text between them is kept
+    #[default]
+    Strip,
+    /// Tags are written as text
+    Escape,
+    /// The tags with these names (e.g. `sup`) are kept without their attributes and the other
+    /// ones are removed, as in text output. Elements which are still open at the end of their
+    /// block are closed.
+    Allow(Vec<String>),
+}
+
+/// Formatting of the lists which are rendered as text
+#[derive(Debug, Clone)]
+pub struct UnstyledListOptions {
+    /// Number of spaces by which items are indented per nesting level
+    pub indent: usize,
+    /// Bullets of the items of unordered lists per nesting level (the last one is used for
+    /// deeper levels), instead of the ones in the source
+    pub bullets: Vec<String>,
+    /// Don't add blank lines around lists and nested lists
+    pub compact: bool,
+}
+
+impl Default for UnstyledListOptions {
+    fn default() -> Self {
+        Self {
+            indent: 2,
+            bullets: Vec::new(),
+            compact: false,
+        }
+    }
+}
+
+/// Options for [`indico_markdown_to_unstyled_html_with_options`], i.e. the elements which are
+/// kept besides paragraphs and line breaks. Everything else is reduced to its text.
+#[derive(Debug, Clone, Default)]
+pub struct UnstyledOptions {
+    /// Whether to render HTML or text without any tags
+    pub output: UnstyledOutput,
+    /// Keep links as `<a>` elements
+    pub links: bool,
+    /// If set, the `target` of the links which are kept (e.g. `_blank`)
+    pub link_target: Option<String>,
+    /// If set, the `rel` of the links which are kept (e.g. `noopener noreferrer`)
+    pub link_rel: Option<String>,
+    /// How inline HTML is handled
+    pub inline_html: UnstyledInlineHtml,
+    /// Keep emphasis, strong emphasis, strikethrough, highlights, underlines and superscripts
+    pub emphasis: bool,
+    /// Keep lists as `<ul>` and `<ol>` elements, instead of rendering their items as text with
+    /// bullets or numbers
+    pub lists: bool,
+    /// How lists are formatted when they aren't kept as elements
+    pub list_format: UnstyledListOptions,
+    /// Keep headings as `<h1>` to `<h6>` elements
+    pub headings: bool,
+    /// How headings are marked when they aren't kept as elements
+    pub heading_markers: UnstyledHeadings,
+    /// Prefix the lines of blockquotes with `> `
+    pub quote_prefix: bool,
+    /// If set, images are replaced by this text, where `{alt}` is replaced by their alt text
+    /// (or their URL if they have none), e.g. `[image: {alt}]`. Otherwise only their alt text
+    /// is kept.
+    pub image_placeholder: Option<String>,
+    /// If set, the rendering stops after this many characters of text (at the end of a word,
+    /// followed by an ellipsis) and only the elements which are still open are closed
+    pub max_chars: Option<usize>,
+    /// If set, the lines of text output are hard-wrapped at this column (wide characters such
+    /// as CJK count as two columns), without breaking words or URLs
+    pub wrap_width: Option<usize>,
+    /// How tables are rendered as text
+    pub tables: UnstyledTables,
+}
+
+impl UnstyledOptions {
+    /// Options suitable for the bodies of notifications, which keep links (opened in a new
+    /// tab), emphasis and lists
+    pub fn notification() -> Self {
+        Self {
+            output: UnstyledOutput::Html,
+            links: true,
+            link_target: Some("_blank".into()),
+            link_rel: Some("noopener noreferrer".into()),
+            emphasis: true,
+            lists: true,
+            image_placeholder: Some("[image: {alt}]".into()),
+            ..Self::default()
+        }
+    }
+}
+
+/// An inline element which is open in HTML output
+struct OpenTag {
+    /// Name of the element
+    name: String,
+    /// Start tag of the element, to open it again in a new paragraph
+    start: String,
+}
+
+/// State of the unstyled formatter
+struct UnstyledState<'o> {
+    options: &'o UnstyledOptions,
+    /// Stack of the lists rendered as text, with the number of their next item
+    lists: Vec<usize>,
+    /// Whether anything was written yet, in text output
+    written: bool,
+    /// Newlines to write before the next text, in text output
+    pending_newlines: usize,
+    /// Depth of the blockquotes around the current node
+    quotes: usize,
+    /// Depth of the blockquotes around the last text which was written, in text output
+    written_quotes: usize,
+    /// Whether the marker of a list item was just written, so that the first block of the
+    /// item stays on its line (in text output)
+    at_item_start: bool,
+    /// Number of characters of text which were written
+    chars: usize,
+    /// Whether the text reached the character limit, so that nothing else is written
+    truncated: bool,
+    /// Inline elements which are open in the current block, innermost last (in HTML output)
+    open_tags: Vec<OpenTag>,
+    /// Marker of the alert which was just entered, written at the start of its first paragraph
+    /// (in HTML output)
+    alert_marker: Option<String>,
+    /// Whether the current paragraph has a `<p>` element (in HTML output)
+    paragraph_open: bool,
+    /// Whether an inline `<p>` or `</p>` tag starts a new paragraph before the next content
+    /// (in HTML output)
+    paragraph_break: bool,
+    /// Number of line breaks which were written, in text output
+    lines: usize,
+    /// Whether the text which is written is preformatted (code blocks and tables), in text
+    /// output
+    verbatim: bool,
+    /// Ranges of the lines of preformatted text, which aren't wrapped (in text output)
+    verbatim_lines: Vec<(usize, usize)>,
+}
+
+/// The list of a list item, and for task items whether they are checked
+fn list_item<'a>(node: &'a AstNode<'a>) -> Option<(NodeList, Option<bool>)> {
+    match node.data.borrow().value {
+        NodeValue::Item(lst) => Some((lst, None)),
+        NodeValue::TaskItem(ref nti) => {
+            let list = node.parent()?;
+            let data = list.data.borrow();
+            let NodeValue::List(lst) = data.value else {
+                return None;
+            };
+            Some((lst, Some(nti.symbol.is_some())))
+        }
+        _ => None,
+    }
+}
+
+/// Marker of the state of a task list item
+fn task_marker(checked: bool) -> &'static str {
+    if checked { "[x] " } else { "[ ] " }
+}
+
+/// Marker of an alert (e.g. `[!NOTE]`), followed by its title if it has one
+fn alert_marker(alert: &NodeAlert) -> String {
+    let kind = alert.alert_type.default_title().to_uppercase();
+    match alert.title {
+        Some(ref title) => format!("[!{kind}] {title}"),
+        None => format!("[!{kind}]"),
+    }
+}
+
+/// Text of the marker of a list item rendered as text, including its indentation and the
+/// padding after it
+fn item_marker(state: &UnstyledState<'_>, lst: &NodeList) -> String {
+    let format = &state.options.list_format;
+    let depth = state.lists.len();
+    let item_spec = match lst.list_type {
+        ListType::Bullet => match format.bullets.get(depth.saturating_sub(1)) {
+            Some(bullet) => bullet.clone(),
+            None => match format.bullets.last() {
+                Some(bullet) => bullet.clone(),
+                None => (lst.bullet_char as char).to_string(),
+            },
+        },
+        ListType::Ordered => {
+            format!(
+                "{}{}",
+                state.lists.last().unwrap(),
+                match lst.delimiter {
+                    ListDelimType::Period => ".",
+                    ListDelimType::Paren => ")",
+                }
+            )
+        }
+    };
+    // add indentation based on stack length
+    let padding = lst.padding.saturating_sub(item_spec.chars().count()).max(1);
+    format!(
+        "{}{item_spec}{}",
+        " ".repeat(format.indent * depth),
+        " ".repeat(padding)
+    )
+}
+
+/// Pad the text of a table cell to the width of its column
+fn pad_cell(text: &str, width: usize, alignment: TableAlignment) -> String {
+    let fill = width.saturating_sub(display_width(text));
+    let (before, after) = match alignment {
+        TableAlignment::Right => (fill, 0),
+        TableAlignment::Center => (fill / 2, fill - fill / 2),
+        TableAlignment::Left | TableAlignment::None => (0, fill),
+    };
+    format!("{}{text}{}", " ".repeat(before), " ".repeat(after))
+}
+
+/// Lines of the text of a table
+fn table_lines<'a>(table: &'a AstNode<'a>, style: UnstyledTables) -> Vec<String> {
+    let alignments = match table.data.borrow().value {
+        NodeValue::Table(ref nt) => nt.alignments.clone(),
+        _ => Vec::new(),
+    };
+    let rows: Vec<(bool, Vec<String>)> = table
+        .children()
+        .filter_map(|row| match row.data.borrow().value {
+            NodeValue::TableRow(header) => {
+                Some((header, row.children().map(collect_text).collect()))
+            }
+            _ => None,
+        })
+        .collect();
+
+    if style == UnstyledTables::Tabs {
+        return rows
+            .into_iter()
+            .map(|(_, cells)| cells.join("\t"))
+            .collect();
+    }
+    let mut widths: Vec<usize> = Vec::new();
+    for (_, cells) in &rows {
+        for (i, cell) in cells.iter().enumerate() {
+            let width = display_width(cell);
+            match widths.get_mut(i) {
+                Some(w) => *w = (*w).max(width),
+                None => widths.push(width),
+            }
+        }
+    }
+    let mut lines = Vec::new();
+    for (header, cells) in rows {
+        let line = cells
+            .iter()
+            .enumerate()
+            .map(|(i, cell)| {
+                let alignment = alignments.get(i).copied().unwrap_or(TableAlignment::None);
+                pad_cell(cell, widths[i], alignment)
+            })
+            .collect::<Vec<_>>()
+            .join(" | ");
+        lines.push(line.trim_end().to_string());
+        if header {
+            let separator: Vec<String> = widths.iter().map(|w| "-".repeat(*w)).collect();
+            lines.push(separator.join("-+-"));
+        }
+    }
+    lines
+}
+
+/// Write text as it is in text output, after the newlines which separate it from what was
+/// written before
+fn write_text(context: &mut Context<&mut UnstyledState<'_>>, text: &str) -> fmt::Result {
+    if text.is_empty() {
+        return Ok(());
+    }
+    let state = &mut context.user;
+    let quotes = if state.options.quote_prefix {
+        state.quotes
+    } else {
+        0
+    };
+    let mut out = String::new();
+    let at_line_start = !state.written || state.pending_newlines > 0;
+    if state.written && state.pending_newlines > 0 {
+        // blank lines only belong to a blockquote if the text on both sides does
+        let blank_line = "> ".repeat(quotes.min(state.written_quotes));
+        out.push('\n');
+        for _ in 1..state.pending_newlines {
+            out.push_str(blank_line.trim_end());
+            out.push('\n');
+        }
+    }
+    let prefix = "> ".repeat(quotes);
+    for (i, line) in text.split('\n').enumerate() {
+        if i > 0 {
+            out.push('\n');
+        }
+        if i > 0 || at_line_start {
+            out.push_str(if line.is_empty() {
+                prefix.trim_end()
+            } else {
+                &prefix
+            });
+        }
+        out.push_str(line);
+    }
+    let newlines = out.matches('\n').count();
+    if state.verbatim {
+        let end = state.lines + newlines;
+        state
+            .verbatim_lines
+            .push((end - text.matches('\n').count(), end));
+    }
+    state.lines += newlines;
+    state.pending_newlines = 0;
+    state.written = true;
+    state.written_quotes = quotes;
+    state.at_item_start = false;
+    context.write_str(&out)
+}
+
+/// Text of a heading with the markers which set it apart
+fn heading_text(text: &str, level: u8, markers: UnstyledHeadings) -> String {
+    match markers {
+        UnstyledHeadings::None => text.to_string(),
+        UnstyledHeadings::Underline => {
+            let underline = if level == 1 { "=" } else { "-" };
+            format!("{text}\n{}", underline.repeat(text.chars().count()))
+        }
+        UnstyledHeadings::Equals => {
+            let marker = "=".repeat(level as usize);
+            format!("{marker} {text} {marker}")
+        }
+    }
+}
+
+/// Text which replaces an image, if the options have a placeholder for images
+fn image_placeholder<'a>(
+    options: &UnstyledOptions,
+    node: &'a AstNode<'a>,
+    url: &str,
+) -> Option<String> {
+    let placeholder = options.image_placeholder.as_ref()?;
+    let alt = collect_text(node);
+    Some(placeholder.replace("{alt}", if alt.is_empty() { url } else { &alt }))
+}
+
+/// Cut text which goes over the character limit of the options at the end of a word, and add
+/// an ellipsis after it
+fn limit_text<'t>(state: &mut UnstyledState<'_>, text: &'t str) -> Cow<'t, str> {
+    let Some(max_chars) = state.options.max_chars else {
+        return text.into();
+    };
+    let len = text.chars().count();
+    if state.chars + len <= max_chars {
+        state.chars += len;
+        return text.into();
+    }
+    state.truncated = true;
+    let remaining = max_chars.saturating_sub(state.chars);
+    let cut = text
+        .char_indices()
+        .nth(remaining)
+        .map_or(text.len(), |(i, _)| i);
+    // words are not cut in the middle, even if they started in a previous text
+    let kept = if text[cut..].starts_with(char::is_whitespace) {
+        &text[..cut]
+    } else {
+        text[..cut]
+            .rfind(char::is_whitespace)
+            .map_or("", |i| &text[..i])
+    };
+    state.chars = max_chars;
+    format!("{}\u{2026}", kept.trim_end()).into()
+}
+
+/// Write the text of a node, escaped in HTML output and up to the character limit
+fn write_content(context: &mut Context<&mut UnstyledState<'_>>, text: &str) -> fmt::Result {
+    if context.user.truncated {
+        return Ok(());
+    }
+    if context.user.options.output == UnstyledOutput::Text {
+        let text = limit_text(&mut context.user, text);
+        write_text(context, &text)
+    } else {
+        write_paragraph_break(context)?;
+        let text = limit_text(&mut context.user, text);
+        context.escape(&text)
+    }
+}
+
+/// Separate the next text from what was written before with at least `count` newlines
+fn break_lines(state: &mut UnstyledState<'_>, count: usize) {
+    state.pending_newlines = state.pending_newlines.max(count);
+}
+
+/// Separate a block from what was written before, unless it's the first one of a list item
+fn start_block<'a>(state: &mut UnstyledState<'_>, node: &'a AstNode<'a>) {
+    if !state.at_item_start {
+        break_lines(state, if in_tight_list(node) { 1 } else { 2 });
+    }
+}
+
+/// Name of the element of an inline node which is kept as an element
+fn inline_element(value: &NodeValue) -> Option<&'static str> {
+    match value {
+        NodeValue::Strong => Some("strong"),
+        NodeValue::Emph => Some("em"),
+        NodeValue::Strikethrough => Some("del"),
+        NodeValue::Highlight => Some("mark"),
+        NodeValue::Underline => Some("u"),
+        NodeValue::Superscript => Some("sup"),
+        NodeValue::Link(..) => Some("a"),
+        _ => None,
+    }
+}
+
+/// Open an inline element, in HTML output
+fn open_tag(
+    context: &mut Context<&mut UnstyledState<'_>>,
+    name: &str,
+    start: String,
+) -> fmt::Result {
+    write_paragraph_break(context)?;
+    context.write_str(&start)?;
+    context.user.open_tags.push(OpenTag {
+        name: name.to_string(),
+        start,
+    });
+    Ok(())
+}
+
+/// Close the innermost open inline element with a given name, and the ones inside it (in HTML
+/// output). Nothing is written if no such element is open.
+fn close_tag(context: &mut Context<&mut UnstyledState<'_>>, name: &str) -> fmt::Result {
+    let Some(index) = context
+        .user
+        .open_tags
+        .iter()
+        .rposition(|tag| tag.name == name)
+    else {
+        return Ok(());
+    };
+    close_tags(context, index)
+}
+
+/// Close the open inline elements from a given index of the stack, in HTML output
+fn close_tags(context: &mut Context<&mut UnstyledState<'_>>, index: usize) -> fmt::Result {
+    while context.user.open_tags.len() > index {
+        let tag = context.user.open_tags.pop().unwrap();
+        write!(context, "</{}>", tag.name)?;
+    }
+    Ok(())
+}
+
+/// Start a new paragraph if an inline `<p>` or `</p>` tag asked for one, closing the open
+/// inline elements and opening them again inside it (in HTML output). Outside of `<p>`
+/// elements, e.g. in headings, a line break is written instead.
+fn write_paragraph_break(context: &mut Context<&mut UnstyledState<'_>>) -> fmt::Result {
+    if !context.user.paragraph_break {
+        return Ok(());
+    }
+    context.user.paragraph_break = false;
+    if !context.user.paragraph_open {
+        return context.write_str("<br />");
+    }
+    for tag in context.user.open_tags.iter().rev() {
+        write!(context, "</{}>", tag.name)?;
+    }
+    context.write_str("</p>\n<p>")?;
+    write_quote_prefix(context)?;
+    let starts: String = context
+        .user
+        .open_tags
+        .iter()
+        .map(|tag| tag.start.as_str())
+        .collect();
+    context.write_str(&starts)
+}
+
+/// Write inline HTML other than `<br>` and `<p>` according to the options, in HTML output
+fn write_inline_html(context: &mut Context<&mut UnstyledState<'_>>, html: &str) -> fmt::Result {
+    match context.user.options.inline_html {
+        UnstyledInlineHtml::Strip => Ok(()),
+        UnstyledInlineHtml::Escape => write_content(context, html),
+        UnstyledInlineHtml::Allow(ref names) => {
+            let html = html.to_lowercase();
+            let Some(caps) = TAG_RE.captures(&html) else {
+                return Ok(());
+            };
+            let name = &caps[2];
+            if !names
+                .iter()
+                .any(|allowed| allowed.eq_ignore_ascii_case(name))
+            {
+                return Ok(());
+            }
+            if caps.get(1).is_some() {
+                close_tag(context, name)
+            } else if caps.get(3).is_some() {
+                write_paragraph_break(context)?;
+                write!(context, "<{name} />")
+            } else {
+                open_tag(context, name, format!("<{name}>"))
+            }
+        }
+    }
+}
+
+/// Write the prefix of the lines of the current blockquotes, in HTML output
+fn write_quote_prefix(context: &mut Context<&mut UnstyledState<'_>>) -> fmt::Result {
+    if context.user.options.quote_prefix {
+        let prefix = "&gt; ".repeat(context.user.quotes);
+        context.write_str(&prefix)?;
+    }
+    Ok(())
+}
+
+/// The formatter of the text output, which writes the text of the nodes without any tags
+fn text_formatter<'a>(
+    context: &mut Context<&mut UnstyledState<'_>>,
+    node: &'a AstNode<'a>,
+    entering: bool,
+) -> Result<ChildRendering, fmt::Error> {
+    match node.data().value {
+        NodeValue::Text(ref text) if entering => write_content(context, text)?,
+        NodeValue::Code(ref nc) if entering => write_content(context, &nc.literal)?,
+        NodeValue::Image(ref nl) if entering => {
+            if let Some(text) = image_placeholder(context.user.options, node, &nl.url) {
+                write_content(context, &text)?;
+                return Ok(ChildRendering::Skip);
+            }
+        }
+        NodeValue::CodeBlock(ref ncb) if entering => {
+            start_block(&mut context.user, node);
+            context.user.verbatim = true;
+            write_content(context, ncb.literal.trim_end_matches('\n'))?;
+            context.user.verbatim = false;
+        }
+        NodeValue::SoftBreak | NodeValue::LineBreak if entering => {
+            context.user.pending_newlines += 1;
+        }
+        NodeValue::HtmlInline(ref raw_html) if entering => {
+            let html = raw_html.to_lowercase();
+            if BR_RE.is_match(&html) {
+                context.user.pending_newlines += 1;
+            } else if P_OPEN_RE.is_match(&html) || html == "</p>" {
+                break_lines(&mut context.user, 2);
+            } else if context.user.options.inline_html == UnstyledInlineHtml::Escape {
+                write_content(context, raw_html)?;
+            }
+        }
+        NodeValue::Heading(ref nh) if entering => {
+            start_block(&mut context.user, node);
+            let markers = context.user.options.heading_markers;
+            if markers != UnstyledHeadings::None {
+                write_content(
+                    context,
+                    &heading_text(&collect_text(node), nh.level, markers),
+                )?;
+                return Ok(ChildRendering::Skip);
+            }
+        }
+        NodeValue::Paragraph if entering => start_block(&mut context.user, node),
+        NodeValue::BlockQuote => {
+            if entering {
+                context.user.quotes += 1;
+            } else {
+                context.user.quotes -= 1;
+            }
+        }
+        NodeValue::Table(..) => {
+            start_block(&mut context.user, node);
+            let lines = table_lines(node, context.user.options.tables);
+            context.user.verbatim = true;
+            write_content(context, &lines.join("\n"))?;
+            context.user.verbatim = false;
+            return Ok(ChildRendering::Skip);
+        }
+        NodeValue::List(..) => {
+            if entering {
+                context.user.lists.push(1);
+            } else {
+                context.user.lists.pop();
+            }
+        }
+        NodeValue::Alert(ref na) if entering => {
+            start_block(&mut context.user, node);
+            write_content(context, &alert_marker(na))?;
+            // the content of the alert starts on the next line
+            break_lines(&mut context.user, 1);
+            context.user.at_item_start = true;
+        }
+        NodeValue::Item(..) | NodeValue::TaskItem(..) => {
+            let Some((lst, task)) = list_item(node) else {
+                return Ok(ChildRendering::HTML);
+            };
+            if entering {
+                let first = node.previous_sibling().is_none();
+                let newlines = if context.user.options.list_format.compact {
+                    1
+                } else if !lst.tight || (first && context.user.lists.len() == 1) {
+                    2
+                } else {
+                    1
+                };
+                break_lines(&mut context.user, newlines);
+                let mut marker = item_marker(&context.user, &lst);
+                if let Some(checked) = task {
+                    marker.push_str(task_marker(checked));
+                }
+                write_text(context, &marker)?;
+                context.user.at_item_start = true;
+            } else {
+                context.user.at_item_start = false;
+                if let Some(v) = context.user.lists.last_mut() {
+                    *v += 1;
+                };
+            }
+        }
+        NodeValue::Document if !entering && context.user.written => context.write_str("\n")?,
+        _ => {}
+    }
+    Ok(ChildRendering::HTML)
+}
+
+/// A formatter which only allows paragraphs and breaks (and the elements allowed by the
+/// options), and ignores everything else
+fn unstyled_formatter<'a>(
+    context: &mut Context<&mut UnstyledState<'_>>,
+    node: &'a AstNode<'a>,
+    entering: bool,
+) -> Result<ChildRendering, fmt::Error> {
+    let options = context.user.options;
+    // once the character limit is reached, only the elements which are open are closed
+    if entering && context.user.truncated {
+        return Ok(ChildRendering::Skip);
+    }
+    if options.output == UnstyledOutput::Text {
+        return text_formatter(context, node, entering);
+    }
+    let data = node.data();
+    let value = &data.value;
+    if entering && !matches!(value, NodeValue::HtmlInline(..)) {
+        write_paragraph_break(context)?;
+    }
+    // the inline elements which are still open are closed at the end of their block
+    if !entering && matches!(value, NodeValue::Paragraph | NodeValue::Heading(..)) {
+        close_tags(context, 0)?;
+        context.user.paragraph_break = false;
+        context.user.paragraph_open = false;
+    }
+    match *value {
+        NodeValue::Code(ref nc) => {
+            if entering {
+                write_content(context, &nc.literal)?;
+            }
+            Ok(ChildRendering::HTML)
+        }
+        NodeValue::CodeBlock(ref nc) => {
+            if entering {
+                context.write_str("\n")?;
+                write_content(context, &nc.literal)?;
+                context.write_str("\n")?;
+            }
+            Ok(ChildRendering::HTML)
+        }
+        // Inline <br> are line breaks and <p>...</p> paragraph breaks, the rest depends on the
+        // options
+        NodeValue::HtmlInline(ref raw_html) => {
+            let html = raw_html.to_lowercase();
+            if entering {
+                if BR_RE.is_match(&html) {
+                    context.write_str("<br />")?;
+                    write_quote_prefix(context)?;
+                } else if P_OPEN_RE.is_match(&html) || html == "</p>" {
+                    context.user.paragraph_break = true;
+                } else {
+                    write_inline_html(context, raw_html)?;
+                }
+            }
+            Ok(ChildRendering::HTML)
+        }
+        // Paragraphs and line breaks in blockquotes start with the prefix of their lines
+        NodeValue::Paragraph | NodeValue::LineBreak => {
+            let res = comrak::html::format_node_default(context, node, entering)?;
+            if entering {
+                if matches!(value, NodeValue::Paragraph) {
+                    context.user.paragraph_open = !in_tight_list(node);
+                }
+                write_quote_prefix(context)?;
+                if let Some(marker) = context.user.alert_marker.take() {
+                    write_content(context, &marker)?;
+                    context.write_str("<br />\n")?;
+                }
+            }
+            Ok(res)
+        }
+        NodeValue::Text(ref text) => {
+            if entering {
+                write_content(context, text)?;
+            }
+            Ok(ChildRendering::HTML)
+        }
+        NodeValue::SoftBreak => comrak::html::format_node_default(context, node, entering),
+        NodeValue::Image(ref nl) if options.image_placeholder.is_some() => {
+            if let Some(text) = image_placeholder(options, node, &nl.url) {
+                write_content(context, &text)?;
+            }
+            Ok(ChildRendering::Skip)
+        }
+        // links with an unsafe URL (e.g. `javascript:`) only keep their text
+        NodeValue::Link(ref nl) if options.links && is_safe_url(&nl.url) => {
+            if entering {
+                let mut start = String::from("<a href=\"");
+                escape_xml(&mut start, &nl.url, true);
+                if let Some(target) = &options.link_target {
+                    start.push_str("\" target=\"");
+                    escape_xml(&mut start, target, true);
+                }
+                if let Some(rel) = &options.link_rel {
+                    start.push_str("\" rel=\"");
+                    escape_xml(&mut start, rel, true);
+                }
+                start.push_str("\">");
+                open_tag(context, "a", start)?;
+            } else {
+                close_tag(context, "a")?;
+            }
+            Ok(ChildRendering::HTML)
+        }
+        NodeValue::Strong
+        | NodeValue::Emph
+        | NodeValue::Strikethrough
+        | NodeValue::Highlight
+        | NodeValue::Underline
+        | NodeValue::Superscript
+            if options.emphasis =>
+        {
+            let name = inline_element(value).unwrap();
+            if entering {
+                open_tag(context, name, format!("<{name}>"))?;
+            } else {
+                close_tag(context, name)?;
+            }
+            Ok(ChildRendering::HTML)
+        }
+        NodeValue::Heading(..) if options.headings => {
+            comrak::html::format_node_default(context, node, entering)
+        }
+        NodeValue::Heading(ref nh) if options.heading_markers != UnstyledHeadings::None => {
+            let text = heading_text(&collect_text(node), nh.level, options.heading_markers);
+            context.write_str("<p>")?;
+            for (i, line) in text.lines().enumerate() {
+                if i > 0 {
+                    context.write_str("<br />\n")?;
+                }
+                write_content(context, line)?;
+            }
+            context.write_str("</p>\n")?;
+            Ok(ChildRendering::Skip)
+        }
+        NodeValue::BlockQuote => {
+            if entering {
+                context.user.quotes += 1;
+            } else {
+                context.user.quotes -= 1;
+            }
+            Ok(ChildRendering::HTML)
+        }
+        NodeValue::Alert(ref na) => {
+            context.user.alert_marker = entering.then(|| alert_marker(na));
+            Ok(ChildRendering::HTML)
+        }
+        NodeValue::List(..) | NodeValue::Item(..) if options.lists => {
+            comrak::html::format_node_default(context, node, entering)
+        }
+        // Task list items have a marker of their state instead of a checkbox
+        NodeValue::TaskItem(ref nti) if options.lists => {
+            if entering {
+                context.cr()?;
+                context.write_str("<li>")?;
+                write_content(context, task_marker(nti.symbol.is_some()))?;
+            } else {
+                context.write_str("</li>\n")?;
+            }
+            Ok(ChildRendering::HTML)
+        }
+        // Tables are rendered as text, one line per row
+        NodeValue::Table(..) => {
+            context.write_str("<p>")?;
+            for (i, line) in table_lines(node, options.tables).iter().enumerate() {
+                if i > 0 {
+                    context.write_str("<br />\n")?;
+                }
+                write_content(context, line)?;
+            }
+            context.write_str("</p>\n")?;
+            Ok(ChildRendering::Skip)
+        }
+        // Text decoration is ignored
+        NodeValue::Strong
+        | NodeValue::Emph
+        | NodeValue::Strikethrough
+        | NodeValue::Highlight
+        | NodeValue::Superscript => Ok(ChildRendering::HTML),
+        // Lists are rendered in plain text and formatting is handled through a stack
+        NodeValue::List(..) => {
+            if !options.list_format.compact {
+                context.write_str("\n")?;
+            } else if entering {
+                context.cr()?;
+            }
+            if entering {
+                context.user.lists.push(1);
+            } else {
+                context.user.lists.pop();
+            }
+            Ok(ChildRendering::HTML)
+        }
+        NodeValue::Item(..) | NodeValue::TaskItem(..) => {
+            let Some((lst, task)) = list_item(node) else {
+                return Ok(ChildRendering::HTML);
+            };
+            if entering {
+                let marker = item_marker(&context.user, &lst);
+                context.write_str(&marker)?;
+                if let Some(checked) = task {
+                    write_content(context, task_marker(checked))?;
+                }
+            } else {
+                if options.list_format.compact {
+                    context.cr()?;
+                } else {
+                    context.write_char('\n')?;
+                }
+                if let Some(v) = context.user.lists.last_mut() {
+                    *v += 1;
+                };
+            }
+            Ok(ChildRendering::HTML)
+        }
+        _ => Ok(ChildRendering::HTML),
+    }
+}
+
+/// Convert markdown to plain text, which only renders paragraphs and line breaks and ignores all other rendering
+pub fn indico_markdown_to_unstyled_html(
+    md_source: &str,
+    autolink_rules: &[LinkRule],
+) -> Result<String, fmt::Error> {
+    indico_markdown_to_unstyled_html_with_options(
+        md_source,
+        autolink_rules,
+        &UnstyledOptions::default(),
+    )
+}
+
+/// Same as [`indico_markdown_to_unstyled_html`], but also keeps the elements allowed by the
+/// options, or renders text without any tags with [`UnstyledOutput::Text`]
+pub fn indico_markdown_to_unstyled_html_with_options(
+    md_source: &str,
+    autolink_rules: &[LinkRule],
+    unstyled: &UnstyledOptions,
+) -> Result<String, fmt::Error> {
+    let options = unstyled_options();
+
+    let arena = Arena::new();
+    let mut root = parse_document(&arena, md_source, &options);
+    add_links(&mut root, &arena, autolink_rules);
+    let mut out = String::new();
+    let mut state = UnstyledState {
+        options: unstyled,
+        lists: Vec::new(),
+        written: false,
+        pending_newlines: 0,
+        quotes: 0,
+        written_quotes: 0,
+        at_item_start: false,
+        chars: 0,
+        truncated: false,
+        open_tags: Vec::new(),
+        alert_marker: None,
+        paragraph_open: false,
+        paragraph_break: false,
+        lines: 0,
+        verbatim: false,
+        verbatim_lines: Vec::new(),
+    };
+
+    comrak::html::format_document_with_formatter(
+        root,
+        &options,
+        &mut out,
+        &Default::default(),
+        unstyled_formatter,
+        &mut state,
+    )
+    .unwrap_or_else(|_| unreachable!("writing to String cannot fail"));
+
+    if let Some(width) = unstyled.wrap_width
+        && unstyled.output == UnstyledOutput::Text
+    {
+        let bullets = match unstyled.list_format.bullets.as_slice() {
+            [] => vec!["-".into(), "*".into(), "+".into()],
+            bullets => bullets.to_vec(),
+        };
+        out = wrap_text(&out, width, &bullets, &state.verbatim_lines);
+    }
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        UnstyledHeadings, UnstyledInlineHtml, UnstyledListOptions, UnstyledOptions, UnstyledOutput,
+        UnstyledTables, indico_markdown_to_unstyled_html_with_options,
+    };
+    use crate::LinkRule;
+
+    #[test]
+    fn test_unstyled_allowed_elements() {
+        let md =
+            "# Agenda\n\nSee **the** [timetable](https://example.com/t?a=1&b=2).\n\n- one\n- two";
+        let options = UnstyledOptions {
+            links: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            indico_markdown_to_unstyled_html_with_options(md, &[], &options).unwrap(),
+            "Agenda\n<p>See the <a href=\"https://example.com/t?a=1&amp;b=2\">timetable</a>.</p>\n\
+             \n  - one\n  - two\n\n"
+        );
+
+        let options = UnstyledOptions {
+            headings: true,
+            ..UnstyledOptions::notification()
+        };
+        assert_eq!(
+            indico_markdown_to_unstyled_html_with_options(md, &[], &options).unwrap(),
+            "<h1>Agenda</h1>\n<p>See <strong>the</strong> \
+             <a href=\"https://example.com/t?a=1&amp;b=2\" target=\"_blank\" \
+             rel=\"noopener noreferrer\">timetable</a>.</p>\n\
+             <ul>\n<li>one</li>\n<li>two</li>\n</ul>\n"
+        );
+    }
+
+    #[test]
+    fn test_unstyled_links() {
+        let md = "[**Register** now](/register) or <https://example.com>";
+        let options = UnstyledOptions {
+            links: true,
+            link_rel: Some("nofollow \"x\"".into()),
+            ..Default::default()
+        };
+        assert_eq!(
+            indico_markdown_to_unstyled_html_with_options(md, &[], &options).unwrap(),
+            "<p><a href=\"/register\" rel=\"nofollow &quot;x&quot;\">Register now</a> or \
+             <a href=\"https://example.com\" rel=\"nofollow &quot;x&quot;\">https://example.com</a></p>\n"
+        );
+
+        let md = "[x](javascript:alert(1)) [y](data:text/html,z) [z](mailto:a@example.com)";
+        assert_eq!(
+            indico_markdown_to_unstyled_html_with_options(
+                md,
+                &[],
+                &UnstyledOptions::notification()
+            )
+            .unwrap(),
+            "<p>x y <a href=\"mailto:a@example.com\" target=\"_blank\" \
+             rel=\"noopener noreferrer\">z</a></p>\n"
+        );
+    }
+
+    #[test]
+    fn test_unstyled_text() {
+        let md = "# Agenda\n\nCoffee & <b>talks</b>,<br>then **lunch**\nat [noon](/x).\n\n\
+                  1. one\n2. two\n   - nested\n\n```\ncode\n```";
+        let options = UnstyledOptions {
+            output: UnstyledOutput::Text,
+            ..UnstyledOptions::notification()
+        };
+        assert_eq!(
+            indico_markdown_to_unstyled_html_with_options(md, &[], &options).unwrap(),
+            "Agenda\n\nCoffee & talks,\nthen lunch\nat noon.\n\n  1. one\n  2. two\n    - nested\n\n\
+             code\n"
+        );
+    }
+
+    #[test]
+    fn test_unstyled_tables() {
+        let md = "| Time | Talk | Room |\n|---:|---|:-:|\n| 9:00 | Welcome & intro | A |\n\
+                  | 10:30 | `Rust` | B12 |";
+        let options = UnstyledOptions {
+            output: UnstyledOutput::Text,
+            ..Default::default()
+        };
+        assert_eq!(
+            indico_markdown_to_unstyled_html_with_options(md, &[], &options).unwrap(),
+            " Time | Talk            | Room\n\
+             ------+-----------------+-----\n \
+              9:00 | Welcome & intro |  A\n\
+             10:30 | Rust            | B12\n"
+        );
+
+        let options = UnstyledOptions {
+            tables: UnstyledTables::Tabs,
+            ..Default::default()
+        };
+        assert_eq!(
+            indico_markdown_to_unstyled_html_with_options(md, &[], &options).unwrap(),
+            "<p>Time\tTalk\tRoom<br />\n9:00\tWelcome &amp; intro\tA<br />\n10:30\tRust\tB12</p>\n"
+        );
+    }
+
+    #[test]
+    fn test_unstyled_structure() {
+        let md = "Intro\n\n## Program\n\n> Quoted\\\n> text\n>\n> > nested\n\nAfter";
+        let options = UnstyledOptions {
+            output: UnstyledOutput::Text,
+            heading_markers: UnstyledHeadings::Equals,
+            quote_prefix: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            indico_markdown_to_unstyled_html_with_options(md, &[], &options).unwrap(),
+            "Intro\n\n== Program ==\n\n> Quoted\n> text\n>\n> > nested\n\nAfter\n"
+        );
+
+        let options = UnstyledOptions {
+            heading_markers: UnstyledHeadings::Underline,
+            quote_prefix: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            indico_markdown_to_unstyled_html_with_options(md, &[], &options).unwrap(),
+            "<p>Intro</p>\n<p>Program<br />\n-------</p>\n<p>&gt; Quoted<br />\n&gt; text</p>\n\
+             <p>&gt; &gt; nested</p>\n<p>After</p>\n"
+        );
+    }
+
+    #[test]
+    fn test_unstyled_images() {
+        let md = "See ![the detector](/d.png) and ![](/plots/2.svg) or ![**bold** alt](/x.png)";
+        assert_eq!(
+            indico_markdown_to_unstyled_html_with_options(md, &[], &UnstyledOptions::default())
+                .unwrap(),
+            "<p>See the detector and  or bold alt</p>\n"
+        );
+
+        let options = UnstyledOptions {
+            output: UnstyledOutput::Text,
+            image_placeholder: Some("[image: {alt}]".into()),
+            ..Default::default()
+        };
+        assert_eq!(
+            indico_markdown_to_unstyled_html_with_options(md, &[], &options).unwrap(),
+            "See [image: the detector] and [image: /plots/2.svg] or [image: bold alt]\n"
+        );
+        assert_eq!(
+            indico_markdown_to_unstyled_html_with_options(
+                "![a < b](/x.png)",
+                &[],
+                &UnstyledOptions::notification()
+            )
+            .unwrap(),
+            "<p>[image: a &lt; b]</p>\n"
+        );
+    }
+
+    #[test]
+    fn test_unstyled_link_rules() {
+        let rules = vec![LinkRule::new(r"\bTKT(\d{7})\b", "https://tkt.sys/{1}").unwrap()];
+        let md = "Fixed in TKT1234567";
+        assert_eq!(
+            indico_markdown_to_unstyled_html_with_options(md, &rules, &UnstyledOptions::default())
+                .unwrap(),
+            "<p>Fixed in TKT1234567</p>\n"
+        );
+        let options = UnstyledOptions {
+            links: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            indico_markdown_to_unstyled_html_with_options(md, &rules, &options).unwrap(),
+            "<p>Fixed in <a href=\"https://tkt.sys/1234567\">TKT1234567</a></p>\n"
+        );
+    }
+
+    #[test]
+    fn test_unstyled_list_format() {
+        let md = "Items:\n\n* a list\n* of\n  - nested\n    + deeper\n* things\n\n1) one\n2) two";
+        let options = UnstyledOptions {
+            list_format: UnstyledListOptions {
+                indent: 1,
+                bullets: vec!["\u{2022}".into(), "-".into()],
+                compact: true,
+            },
+            ..Default::default()
+        };
+        assert_eq!(
+            indico_markdown_to_unstyled_html_with_options(md, &[], &options).unwrap(),
+            "<p>Items:</p>\n \u{2022} a list\n \u{2022} of\n  - nested\n   - deeper\n \u{2022} things\n \
+             1) one\n 2) two\n"
+        );
+
+        let options = UnstyledOptions {
+            output: UnstyledOutput::Text,
+            ..options
+        };
+        assert_eq!(
+            indico_markdown_to_unstyled_html_with_options(md, &[], &options).unwrap(),
+            "Items:\n \u{2022} a list\n \u{2022} of\n  - nested\n   - deeper\n \u{2022} things\n \
+             1) one\n 2) two\n"
+        );
+    }
+
+    #[test]
+    fn test_unstyled_max_chars() {
+        let md = "First paragraph here.\n\nSecond **bold words** and more\n\n- a\n- b";
+        let options = UnstyledOptions {
+            emphasis: true,
+            max_chars: Some(30),
+            ..Default::default()
+        };
+        assert_eq!(
+            indico_markdown_to_unstyled_html_with_options(md, &[], &options).unwrap(),
+            "<p>First paragraph here.</p>\n<p>Second <strong>\u{2026}</strong></p>\n"
+        );
+
+        let options = UnstyledOptions {
+            output: UnstyledOutput::Text,
+            max_chars: Some(9),
+            ..Default::default()
+        };
+        assert_eq!(
+            indico_markdown_to_unstyled_html_with_options("One two three four", &[], &options)
+                .unwrap(),
+            "One two\u{2026}\n"
+        );
+        let options = UnstyledOptions {
+            max_chars: Some(100),
+            ..options
+        };
+        assert_eq!(
+            indico_markdown_to_unstyled_html_with_options("One two three four", &[], &options)
+                .unwrap(),
+            "One two three four\n"
+        );
+    }
+
+    #[test]
+    fn test_unstyled_inline_html() {
+        let md = "**a<P>b** <span title=x>c<sup>2</sup> <b>d</p>e</b> f";
+        let options = UnstyledOptions {
+            emphasis: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            indico_markdown_to_unstyled_html_with_options(md, &[], &options).unwrap(),
+            "<p><strong>a</strong></p>\n<p><strong>b</strong> c2 d</p>\n<p>e f</p>\n"
+        );
+
+        let options = UnstyledOptions {
+            inline_html: UnstyledInlineHtml::Allow(vec!["sup".into(), "B".into()]),
+            ..options
+        };
+        assert_eq!(
+            indico_markdown_to_unstyled_html_with_options(md, &[], &options).unwrap(),
+            "<p><strong>a</strong></p>\n<p><strong>b</strong> c<sup>2</sup> <b>d</b></p>\n\
+             <p><b>e</b> f</p>\n"
+        );
+
+        let options = UnstyledOptions {
+            inline_html: UnstyledInlineHtml::Escape,
+            ..Default::default()
+        };
+        assert_eq!(
+            indico_markdown_to_unstyled_html_with_options(
+                "x <span title=\"y\">z</span>",
+                &[],
+                &options
+            )
+            .unwrap(),
+            "<p>x &lt;span title=&quot;y&quot;&gt;z&lt;/span&gt;</p>\n"
+        );
+        let options = UnstyledOptions {
+            output: UnstyledOutput::Text,
+            ..options
+        };
+        assert_eq!(
+            indico_markdown_to_unstyled_html_with_options(
+                "x <span title=\"y\">z</span>",
+                &[],
+                &options
+            )
+            .unwrap(),
+            "x <span title=\"y\">z</span>\n"
+        );
+    }
+
+    #[test]
+    fn test_unstyled_wrap_width() {
+        let md = "> Quoted text which is long enough\n\n\
+                  - item with https://example.com/a/very/long/url inside";
+        let options = UnstyledOptions {
+            output: UnstyledOutput::Text,
+            quote_prefix: true,
+            wrap_width: Some(20),
+            ..Default::default()
+        };
+        assert_eq!(
+            indico_markdown_to_unstyled_html_with_options(md, &[], &options).unwrap(),
+            "> Quoted text which\n> is long enough\n\n  - item with\n    \
+             https://example.com/a/very/long/url\n    inside\n"
+        );
+        // code blocks aren't wrapped
+        assert_eq!(
+            indico_markdown_to_unstyled_html_with_options(
+                "Some text which is wrapped\n\n```\nlet x = [1,  2,  3]; // long\n```",
+                &[],
+                &options
+            )
+            .unwrap(),
+            "Some text which is\nwrapped\n\nlet x = [1,  2,  3]; // long\n"
+        );
+    }
+
+    #[test]
+    fn test_unstyled_alerts_and_tasks() {
+        let md = "> [!WARNING]\n> Registration closes **soon**\n\n- [x] book a room\n- [ ] send invitations";
+        assert_eq!(
+            indico_markdown_to_unstyled_html_with_options(md, &[], &UnstyledOptions::default())
+                .unwrap(),
+            "<p>[!WARNING]<br />\nRegistration closes soon</p>\n\
+             \n  - [x] book a room\n  - [ ] send invitations\n\n"
+        );
+        assert_eq!(
+            indico_markdown_to_unstyled_html_with_options(
+                md,
+                &[],
+                &UnstyledOptions::notification()
+            )
+            .unwrap(),
+            "<p>[!WARNING]<br />\nRegistration closes <strong>soon</strong></p>\n\
+             <ul>\n<li>[x] book a room</li>\n<li>[ ] send invitations</li>\n</ul>\n"
+        );
+
+        let options = UnstyledOptions {
+            output: UnstyledOutput::Text,
+            ..Default::default()
+        };
+        assert_eq!(
+            indico_markdown_to_unstyled_html_with_options(md, &[], &options).unwrap(),
+            "[!WARNING]\nRegistration closes soon\n\n  - [x] book a room\n  - [ ] send invitations\n"
+        );
+    }
+}
@@ -0,0 +1,125 @@
+//! Warnings about content which isn't rendered the way it was written (escaped tags, blocked
+//! URLs...), so that authors can be told why.
+
+use comrak::nodes::{AstNode, NodeValue};
+use regex_lite::Regex;
+
+use crate::extract::SourceRange;
+
+/// Kind of a render warning
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RenderWarningKind {
+    /// A raw HTML tag which isn't allowed (e.g. `<script>`) was escaped
+    FilteredTag,
+    /// The URL of a link or image has a blocked scheme, and was removed
+    BlockedUrl,
+    /// A piece of text has too many matches of link rules, and only the first ones were turned
+    /// into links
+    RuleMatchLimit,
+}
+
+/// A warning about something in the document which is rendered differently from what was
+/// written
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RenderWarning {
+    pub kind: RenderWarningKind,
+    /// Position of the content in the markdown source
+    pub sourcepos: SourceRange,
+    /// Details about the warning, in English
+    pub detail: String,
+}
+
+/// Find the raw HTML tags which are escaped by comrak's tag filter
+pub(crate) fn filtered_tags<'a>(root: &'a AstNode<'a>) -> Vec<RenderWarning> {
+    let re = Regex::new(
+        r"(?i)</?(title|textarea|style|xmp|iframe|noembed|noframes|script|plaintext)(?:[\s>]|/>|$)",
+    )
+    .unwrap();
+    let mut warnings = Vec::new();
+
+    for node in root.descendants() {
+        let data = node.data.borrow();
+        let html = match data.value {
+            NodeValue::HtmlInline(ref html) => html,
+            NodeValue::HtmlBlock(ref nhb) => &nhb.literal,
+            _ => continue,
+        };
+        // one warning per tag, not for each of its start and end tags
+        let mut tags: Vec<String> = Vec::new();
+        for caps in re.captures_iter(html) {
+            let tag = caps[1].to_ascii_lowercase();
+            if tags.contains(&tag) {
+                continue;
+            }
+            tags.push(tag.clone());
+            warnings.push(RenderWarning {
+                kind: RenderWarningKind::FilteredTag,
+                sourcepos: data.sourcepos.into(),
+                detail: format!("<{tag}> tags are not allowed"),
+            });
+        }
+    }
+    warnings
+}
+
+/// Get the scheme of a URL, ignoring the whitespace and control characters which browsers
+/// ignore as well
+pub(crate) fn url_scheme(url: &str) -> Option<String> {
+    let url: String = url
+        .chars()
+        .filter(|c| !c.is_ascii_whitespace() && !c.is_ascii_control())
+        .collect();
+    let (scheme, _) = url.split_once(':')?;
+    (scheme.starts_with(|c: char| c.is_ascii_alphabetic())
+        && scheme
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '+' | '-' | '.')))
+    .then(|| scheme.to_ascii_lowercase())
+}
+
+/// Remove the URLs of links and images whose scheme is one of the blocked ones
+pub(crate) fn block_urls<'a>(root: &'a AstNode<'a>, schemes: &[String]) -> Vec<RenderWarning> {
+    let mut warnings = Vec::new();
+    if schemes.is_empty() {
+        return warnings;
+    }
+
+    for node in root.descendants() {
+        let mut data = node.data.borrow_mut();
+        let sourcepos = data.sourcepos;
+        let (NodeValue::Link(ref mut nl) | NodeValue::Image(ref mut nl)) = data.value else {
+            continue;
+        };
+        if let Some(scheme) = url_scheme(&nl.url)
+            && schemes.iter().any(|s| s.eq_ignore_ascii_case(&scheme))
+        {
+            warnings.push(RenderWarning {
+                kind: RenderWarningKind::BlockedUrl,
+                sourcepos: sourcepos.into(),
+                detail: format!("{scheme}: URLs are not allowed"),
+            });
+            nl.url.clear();
+        }
+    }
+    warnings
+}
+
+#[cfg(test)]
+mod tests {
+    use super::url_scheme;
+
+    #[test]
+    fn test_url_scheme() {
+        assert_eq!(
+            url_scheme("JavaScript:alert(1)").as_deref(),
+            Some("javascript")
+        );
+        assert_eq!(
+            url_scheme(" java\tscript:alert(1)").as_deref(),
+            Some("javascript")
+        );
+        assert_eq!(url_scheme("https://example.com").as_deref(), Some("https"));
+        assert_eq!(url_scheme("/event/1?a=b:c"), None);
+        assert_eq!(url_scheme("1:2"), None);
+    }
+}
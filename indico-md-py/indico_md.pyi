@@ -0,0 +1,238 @@
+"""Type stubs of the indico_md native module."""
+
+from typing import Any, Callable, Dict, List, Literal, Mapping, Optional, Sequence, Tuple, TypedDict, Union
+
+Profile = Literal["default", "email", "feed"]
+QuoteStyle = Literal["english", "french", "german"]
+# `True` is the same as "default" (raw HTML limited to an allowlist of tags and attributes),
+# and `False` as "off"
+Sanitize = Union[bool, Literal["strict", "default", "off"]]
+
+# a URL template (`{1}`, `{2}`... are replaced by the groups of the match), or a callable
+# which receives the groups (the whole match first) and returns the URL, or None to skip it
+LinkTarget = Union[str, Callable[..., Optional[str]]]
+
+class LinkRule:
+    def __init__(
+        self,
+        pattern: str,
+        url: LinkTarget,
+        *,
+        case_insensitive: bool = False,
+        whole_word: bool = False,
+    ) -> None: ...
+    @property
+    def pattern(self) -> str: ...
+    @property
+    def url(self) -> LinkTarget: ...
+
+LinkRules = Union[Mapping[str, LinkTarget], Sequence[Union[Tuple[str, LinkTarget], LinkRule]]]
+
+class RuleSet:
+    def __init__(self, rules: LinkRules) -> None: ...
+    def __len__(self) -> int: ...
+
+class LinkRuleError(ValueError):
+    pattern: str
+    index: Optional[int]
+
+class RenderError(RuntimeError): ...
+
+class Heading(TypedDict):
+    level: int
+    text: str
+    slug: str
+    id: str
+    line: int
+
+# positions are `(line, column)` tuples starting at 1, with columns counted in UTF-8 bytes
+Position = Tuple[int, int]
+
+class ExtractedHeading(TypedDict):
+    level: int
+    text: str
+    slug: str
+    id: str
+    start: Position
+    end: Position
+
+class OutlineHeading(ExtractedHeading):
+    children: List["OutlineHeading"]
+
+class ExtractedLink(TypedDict):
+    url: str
+    text: str
+    start: Position
+    end: Position
+
+class Link(TypedDict):
+    url: str
+    text: str
+
+class Mention(TypedDict):
+    rule: int
+    text: str
+    groups: List[Optional[str]]
+    url: str
+    line: int
+
+class Warning(TypedDict):
+    kind: Literal["filtered_tag", "blocked_url", "rule_match_limit"]
+    detail: str
+    line: int
+
+class RenderMeta(TypedDict):
+    html: str
+    headings: List[Heading]
+    links: List[Link]
+    mentions: List[Mention]
+    warnings: List[Warning]
+    word_count: int
+    unknown_placeholders: List[str]
+
+class RuleDiagnostic(TypedDict):
+    index: int
+    pattern: str
+    valid: bool
+    error: Optional[str]
+
+class RuleMatch(TypedDict):
+    rule: int
+    text: str
+    groups: List[Optional[str]]
+    url: str
+    start: Position
+    end: Position
+
+class OpenGraph(TypedDict):
+    title: Optional[str]
+    description: Optional[str]
+    image: Optional[str]
+
+class Renderer:
+    def __init__(
+        self,
+        link_rules: Optional[Union[LinkRules, RuleSet]] = None,
+        *,
+        profile: Profile = "default",
+        base_url: Optional[str] = None,
+        media_base_url: Optional[str] = None,
+        definition_lists: bool = False,
+        smart_punctuation: Optional[QuoteStyle] = None,
+        tables: bool = True,
+        alerts: bool = True,
+        math: bool = True,
+        sanitize: Sanitize = True,
+        heading_id_prefix: Optional[str] = None,
+        target_blank: bool = True,
+        placeholders: Optional[Mapping[str, str]] = None,
+    ) -> None: ...
+    def render(self, md_source: str) -> str: ...
+    def render_unstyled(self, md_source: str) -> str: ...
+    def render_plain(self, md_source: str) -> str: ...
+
+def to_html(
+    md_source: Union[str, bytes],
+    link_rules: Union[LinkRules, RuleSet],
+    *,
+    profile: Profile = "default",
+    base_url: Optional[str] = None,
+    media_base_url: Optional[str] = None,
+    definition_lists: bool = False,
+    smart_punctuation: Optional[QuoteStyle] = None,
+    tables: bool = True,
+    alerts: bool = True,
+    math: bool = True,
+    sanitize: Sanitize = True,
+    heading_id_prefix: Optional[str] = None,
+    target_blank: bool = True,
+    placeholders: Optional[Mapping[str, str]] = None,
+    errors: Literal["strict", "replace"] = "strict",
+) -> str: ...
+def to_html_many(
+    md_sources: Sequence[str],
+    *,
+    link_rules: Optional[Union[LinkRules, RuleSet]] = None,
+    profile: Profile = "default",
+    base_url: Optional[str] = None,
+    media_base_url: Optional[str] = None,
+    definition_lists: bool = False,
+    smart_punctuation: Optional[QuoteStyle] = None,
+    tables: bool = True,
+    alerts: bool = True,
+    math: bool = True,
+    sanitize: Sanitize = True,
+    heading_id_prefix: Optional[str] = None,
+    target_blank: bool = True,
+    placeholders: Optional[Mapping[str, str]] = None,
+) -> List[str]: ...
+def to_html_with_front_matter(
+    md_source: Union[str, bytes],
+    link_rules: Union[LinkRules, RuleSet],
+    *,
+    profile: Profile = "default",
+    base_url: Optional[str] = None,
+    media_base_url: Optional[str] = None,
+    definition_lists: bool = False,
+    smart_punctuation: Optional[QuoteStyle] = None,
+    tables: bool = True,
+    alerts: bool = True,
+    math: bool = True,
+    sanitize: Sanitize = True,
+    heading_id_prefix: Optional[str] = None,
+    target_blank: bool = True,
+    placeholders: Optional[Mapping[str, str]] = None,
+    errors: Literal["strict", "replace"] = "strict",
+) -> Tuple[str, Optional[Dict[str, Any]]]: ...
+def to_html_with_meta(
+    md_source: Union[str, bytes],
+    link_rules: Union[LinkRules, RuleSet],
+    *,
+    profile: Profile = "default",
+    base_url: Optional[str] = None,
+    media_base_url: Optional[str] = None,
+    definition_lists: bool = False,
+    smart_punctuation: Optional[QuoteStyle] = None,
+    tables: bool = True,
+    alerts: bool = True,
+    math: bool = True,
+    sanitize: Sanitize = True,
+    heading_id_prefix: Optional[str] = None,
+    target_blank: bool = True,
+    placeholders: Optional[Mapping[str, str]] = None,
+    errors: Literal["strict", "replace"] = "strict",
+) -> RenderMeta: ...
+def extract_headings(md_source: str) -> List[ExtractedHeading]: ...
+def extract_outline(md_source: str) -> List[OutlineHeading]: ...
+def extract_links(
+    md_source: str,
+    link_rules: Optional[Union[LinkRules, RuleSet]] = None,
+    *,
+    base_url: Optional[str] = None,
+) -> List[ExtractedLink]: ...
+def validate_rules(link_rules: LinkRules) -> List[RuleDiagnostic]: ...
+def preview_rule_matches(md_source: str, link_rules: Union[LinkRules, RuleSet]) -> List[RuleMatch]: ...
+def to_unstyled_html(
+    md_source: str,
+    link_rules: Optional[Union[LinkRules, RuleSet]] = None,
+    *,
+    keep_links: bool = False,
+    link_target: Optional[str] = None,
+    emphasis: bool = False,
+    lists: bool = False,
+    headings: bool = False,
+    allowed_tags: Optional[Sequence[str]] = None,
+    max_chars: Optional[int] = None,
+) -> str: ...
+def to_latex(md_source: str, *, link_rules: Optional[Union[LinkRules, RuleSet]] = None) -> str: ...
+def truncate_html(
+    md_source: str, max_chars: int, *, link_rules: Optional[Union[LinkRules, RuleSet]] = None
+) -> str: ...
+def excerpt(md_source: str, max_chars: int) -> str: ...
+def toggle_task(md_source: str, index: int) -> Optional[str]: ...
+def open_graph(
+    md_source: str,
+    *,
+    base_url: Optional[str] = None,
+    media_base_url: Optional[str] = None,
+) -> OpenGraph: ...
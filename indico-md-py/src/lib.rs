@@ -8,12 +8,381 @@
 //! a set of link rules defined as regular expressions and their corresponding URL replacements.
 //! It returns the resulting HTML as a string, wrapped in a PyResult to handle potential errors
 //! during the conversion process.
-use indico_comrak::{LinkRule, indico_markdown_to_html, indico_markdown_to_unstyled_html};
+use indico_comrak::{
+    Extensions, Heading, IndicoRenderer, LinkOptions, LinkResolver, LinkRule, MathOptions,
+    OffsetMode, PlainTextOptions, QuoteStyle, RawHtml, RenderOptions, RenderProfile,
+    RenderWarningKind, UnstyledInlineHtml, UnstyledOptions, indico_markdown_excerpt,
+    indico_markdown_links, indico_markdown_open_graph, indico_markdown_outline,
+    indico_markdown_render, indico_markdown_rule_matches, indico_markdown_to_html_with_options,
+    indico_markdown_to_latex, indico_markdown_to_plain_text_with_rules,
+    indico_markdown_to_truncated_html, indico_markdown_to_unstyled_html_with_options,
+    indico_markdown_toggle_task,
+};
 use pyo3::{
+    create_exception,
     exceptions::{PyRuntimeError, PyValueError},
     prelude::*,
+    types::{PyBytes, PyDict, PyList, PyString, PyTuple},
 };
+use rayon::prelude::*;
+use std::borrow::Cow;
 use std::collections::HashMap;
+use std::ops::Deref;
+use std::sync::Arc;
+
+create_exception!(
+    indico_md,
+    LinkRuleError,
+    PyValueError,
+    "A link rule is invalid. Its `pattern` and its `index` in the rules (`None` for a \
+     `LinkRule` object) are attributes of the exception."
+);
+create_exception!(
+    indico_md,
+    RenderError,
+    PyRuntimeError,
+    "A document can't be rendered."
+);
+
+/// Set an attribute of a [`LinkRuleError`], leaving the other errors (e.g. a `TypeError` for a
+/// rule of the wrong type) as they are
+fn set_rule_context<'py>(
+    py: Python<'py>,
+    err: PyErr,
+    name: &str,
+    value: impl IntoPyObject<'py>,
+) -> PyErr {
+    if err.is_instance_of::<LinkRuleError>(py)
+        && let Err(e) = err.value(py).setattr(name, value)
+    {
+        return e;
+    }
+    err
+}
+
+/// Convert an error of the core library into a [`RenderError`]
+fn render_error(e: impl ToString) -> PyErr {
+    RenderError::new_err(e.to_string())
+}
+
+/// Call the callable of a link rule with the groups of a match, holding the GIL only for the
+/// call. Exceptions are reported through `sys.unraisablehook`, and the match isn't turned into
+/// a link.
+fn resolve_link(callback: &Py<PyAny>, groups: &[Option<&str>]) -> Option<String> {
+    Python::attach(|py| {
+        PyTuple::new(py, groups.iter().copied())
+            .and_then(|args| callback.call1(py, args))
+            .and_then(|url| url.extract::<Option<String>>(py))
+            .unwrap_or_else(|err| {
+                err.write_unraisable(py, Some(callback.bind(py)));
+                None
+            })
+    })
+}
+
+/// Compile a link rule whose URL is either a template or a callable, which receives the
+/// groups of each match (the whole match first) and returns its URL, or `None` to skip it.
+/// Raises a [`LinkRuleError`] if the rule is invalid.
+fn compile_link_rule(pattern: &str, url: &Bound<'_, PyAny>) -> PyResult<LinkRule> {
+    let rule = if url.is_callable() {
+        let callback = url.clone().unbind();
+        let resolve = move |groups: &[Option<&str>]| resolve_link(&callback, groups);
+        LinkRule::with_resolver(pattern, LinkResolver(Arc::new(resolve)))
+    } else {
+        LinkRule::new(pattern, &url.extract::<String>()?)
+    };
+    rule.map_err(|e| {
+        let py = url.py();
+        let err = set_rule_context(
+            py,
+            LinkRuleError::new_err(e.to_string()),
+            "pattern",
+            pattern,
+        );
+        set_rule_context(py, err, "index", None::<usize>)
+    })
+}
+
+/// Build link rules from a mapping of regular expressions to URL templates (or callables), or
+/// a sequence of `(regex, url)` pairs and [`PyLinkRule`] objects. The rules keep their order,
+/// which decides which one is used when several of them match the same text.
+fn compile_link_rules(link_rules: &Bound<'_, PyAny>) -> PyResult<Vec<LinkRule>> {
+    let py = link_rules.py();
+    if let Ok(dict) = link_rules.extract::<Bound<'_, PyDict>>() {
+        return dict
+            .iter()
+            .enumerate()
+            .map(|(index, (re, url))| {
+                compile_link_rule(&re.extract::<String>()?, &url)
+                    .map_err(|err| set_rule_context(py, err, "index", index))
+            })
+            .collect();
+    }
+    link_rules
+        .try_iter()?
+        .enumerate()
+        .map(|(index, item)| {
+            let item = item?;
+            if let Ok(rule) = item.extract::<Bound<'_, PyLinkRule>>() {
+                return Ok(rule.get().rule.clone());
+            }
+            let (re, url): (String, Bound<'_, PyAny>) = item.extract()?;
+            compile_link_rule(&re, &url).map_err(|err| set_rule_context(py, err, "index", index))
+        })
+        .collect()
+}
+
+/// Link rules given to a rendering function
+enum LinkRules<'py> {
+    /// Rules which were compiled for this call
+    Compiled(Vec<LinkRule>),
+    /// A [`RuleSet`] which was compiled before
+    RuleSet(Bound<'py, RuleSet>),
+}
+
+impl Deref for LinkRules<'_> {
+    type Target = [LinkRule];
+
+    fn deref(&self) -> &[LinkRule] {
+        match self {
+            LinkRules::Compiled(rules) => rules,
+            LinkRules::RuleSet(rule_set) => &rule_set.get().rules,
+        }
+    }
+}
+
+/// Get the link rules given to a rendering function, which are only compiled if they aren't
+/// a [`RuleSet`]
+fn parse_link_rules<'py>(link_rules: &Bound<'py, PyAny>) -> PyResult<LinkRules<'py>> {
+    match link_rules.extract::<Bound<'py, RuleSet>>() {
+        Ok(rule_set) => Ok(LinkRules::RuleSet(rule_set)),
+        Err(_) => compile_link_rules(link_rules).map(LinkRules::Compiled),
+    }
+}
+
+/// A link rule, whose regular expression is compiled once when it is created.
+///
+/// # Example
+///
+/// ```python
+/// rule = indico_md.LinkRule(r"tkt(\d{7})", "https://tkt.sys/{1}", case_insensitive=True)
+/// ```
+#[pyclass(name = "LinkRule", module = "indico_md", frozen)]
+struct PyLinkRule {
+    rule: LinkRule,
+    #[pyo3(get)]
+    pattern: String,
+    #[pyo3(get)]
+    url: Py<PyAny>,
+}
+
+#[pymethods]
+impl PyLinkRule {
+    /// Creates a rule which turns the matches of `pattern` into links to `url`, where `{1}`,
+    /// `{2}`... are replaced by the groups of the match. `url` may also be a callable, which
+    /// receives the groups and returns the URL, or `None` to skip the match. With
+    /// `case_insensitive` the pattern ignores the case of the text, and with `whole_word` it
+    /// only matches whole words.
+    #[new]
+    #[pyo3(signature = (pattern, url, *, case_insensitive = false, whole_word = false))]
+    fn new(
+        pattern: &str,
+        url: &Bound<'_, PyAny>,
+        case_insensitive: bool,
+        whole_word: bool,
+    ) -> PyResult<Self> {
+        let mut regex = pattern.to_string();
+        if whole_word {
+            regex = format!(r"\b(?:{regex})\b");
+        }
+        if case_insensitive {
+            regex.insert_str(0, "(?i)");
+        }
+        // errors refer to the pattern as it was given
+        let rule = compile_link_rule(&regex, url)
+            .map_err(|err| set_rule_context(url.py(), err, "pattern", pattern))?;
+        Ok(Self {
+            rule,
+            pattern: pattern.into(),
+            url: url.clone().unbind(),
+        })
+    }
+
+    fn __repr__(&self, py: Python<'_>) -> PyResult<String> {
+        Ok(format!(
+            "LinkRule({:?}, {})",
+            self.pattern,
+            self.url.bind(py).repr()?
+        ))
+    }
+}
+
+/// An ordered set of link rules, compiled once when it is created and reused by every
+/// rendering function it is given to instead of the rules.
+///
+/// # Example
+///
+/// ```python
+/// rules = indico_md.RuleSet([
+///     indico_md.LinkRule(r"tkt(\d{7})", "https://tkt.sys/{1}", case_insensitive=True),
+///     (r"\bgh:(\d+)\b", "https://github.com/indico/indico/issues/{1}"),
+/// ])
+/// html = indico_md.to_html("See TKT1234567", rules)
+/// ```
+#[pyclass(module = "indico_md", frozen)]
+struct RuleSet {
+    rules: Vec<LinkRule>,
+}
+
+#[pymethods]
+impl RuleSet {
+    /// Creates a rule set from `LinkRule` objects and `(pattern, url)` tuples, or a dict
+    /// mapping patterns to URLs
+    #[new]
+    fn new(rules: &Bound<'_, PyAny>) -> PyResult<Self> {
+        Ok(Self {
+            rules: compile_link_rules(rules)?,
+        })
+    }
+
+    fn __len__(&self) -> usize {
+        self.rules.len()
+    }
+}
+
+/// Keyword arguments of the rendering functions, which are mapped onto [`RenderOptions`]
+struct RenderArgs<'a> {
+    profile: &'a str,
+    base_url: Option<String>,
+    media_base_url: Option<String>,
+    definition_lists: bool,
+    smart_punctuation: Option<&'a str>,
+    tables: bool,
+    alerts: bool,
+    math: bool,
+    sanitize: Sanitize,
+    heading_id_prefix: Option<String>,
+    target_blank: bool,
+    placeholders: Option<HashMap<String, String>>,
+}
+
+/// Build render options from the keyword arguments of the rendering functions
+fn render_options(args: RenderArgs<'_>) -> PyResult<RenderOptions> {
+    let RenderArgs {
+        profile,
+        base_url,
+        media_base_url,
+        definition_lists,
+        smart_punctuation,
+        tables,
+        alerts,
+        math,
+        sanitize,
+        heading_id_prefix,
+        target_blank,
+        placeholders,
+    } = args;
+    let (raw_html, blocked_url_schemes) = sanitize_options(&sanitize)?;
+    Ok(RenderOptions {
+        profile: match profile {
+            "default" => RenderProfile::Default,
+            "email" => RenderProfile::Email,
+            "feed" => RenderProfile::Feed,
+            _ => {
+                return Err(PyValueError::new_err(format!(
+                    "Unknown rendering profile: {profile}"
+                )));
+            }
+        },
+        base_url,
+        media_base_url,
+        definition_lists,
+        smart_punctuation: smart_punctuation.map(quote_style).transpose()?,
+        extensions: Extensions { tables, alerts },
+        math_options: MathOptions {
+            dollars: math,
+            ..Default::default()
+        },
+        raw_html,
+        blocked_url_schemes,
+        id_namespace: heading_id_prefix,
+        links: LinkOptions { target_blank },
+        placeholders,
+        ..Default::default()
+    })
+}
+
+/// The markdown source of a document, as `str` or as UTF-8 `bytes` (e.g. from legacy storage)
+#[derive(FromPyObject)]
+enum MarkdownSource<'py> {
+    #[pyo3(annotation = "str")]
+    Text(Bound<'py, PyString>),
+    #[pyo3(annotation = "bytes")]
+    Bytes(Bound<'py, PyBytes>),
+}
+
+impl MarkdownSource<'_> {
+    /// Get the text of the source, decoding bytes with the `errors` policy: `"strict"` raises
+    /// a `UnicodeDecodeError` for invalid UTF-8, and `"replace"` replaces it with U+FFFD
+    fn decode(&self, errors: &str) -> PyResult<Cow<'_, str>> {
+        if !matches!(errors, "strict" | "replace") {
+            return Err(PyValueError::new_err(format!(
+                "Unknown error handler: {errors}"
+            )));
+        }
+        match self {
+            Self::Text(text) => text.to_cow(),
+            Self::Bytes(bytes) if errors == "replace" => {
+                Ok(String::from_utf8_lossy(bytes.as_bytes()))
+            }
+            Self::Bytes(bytes) => Ok(Cow::Borrowed(std::str::from_utf8(bytes.as_bytes())?)),
+        }
+    }
+}
+
+/// The `sanitize` argument: a sanitization level, or a bool for the default one or none
+#[derive(FromPyObject)]
+enum Sanitize {
+    Flag(bool),
+    Level(String),
+}
+
+/// URL schemes which are removed by the `"default"` sanitization level
+const BLOCKED_URL_SCHEMES: &[&str] = &["javascript", "vbscript"];
+/// URL schemes which are removed by the `"strict"` sanitization level
+const STRICT_BLOCKED_URL_SCHEMES: &[&str] = &["javascript", "vbscript", "data", "file"];
+
+/// Get how raw HTML is rendered and which URL schemes are removed for a sanitization level
+fn sanitize_options(sanitize: &Sanitize) -> PyResult<(RawHtml, Vec<String>)> {
+    let level = match sanitize {
+        Sanitize::Flag(true) => "default",
+        Sanitize::Flag(false) => "off",
+        Sanitize::Level(level) => level.as_str(),
+    };
+    let (raw_html, schemes) = match level {
+        "strict" => (RawHtml::Escaped, STRICT_BLOCKED_URL_SCHEMES),
+        "default" => (RawHtml::Sanitized, BLOCKED_URL_SCHEMES),
+        "off" => (RawHtml::Unfiltered, &[][..]),
+        _ => {
+            return Err(PyValueError::new_err(format!(
+                "Unknown sanitization level: {level}"
+            )));
+        }
+    };
+    Ok((raw_html, schemes.iter().map(|s| s.to_string()).collect()))
+}
+
+/// Get the quote style with the given name
+fn quote_style(name: &str) -> PyResult<QuoteStyle> {
+    match name {
+        "english" => Ok(QuoteStyle::English),
+        "french" => Ok(QuoteStyle::French),
+        "german" => Ok(QuoteStyle::German),
+        _ => Err(PyValueError::new_err(format!(
+            "Unknown quote style: {name}"
+        ))),
+    }
+}
 
 /// Converts Markdown text to HTML with custom link rules.
 ///
@@ -22,9 +391,41 @@ use std::collections::HashMap;
 ///
 /// # Arguments
 ///
-/// * `md_source` - A string slice containing the Markdown text to convert
-/// * `link_rules` - A HashMap containing pairs of regular expression patterns (as strings) and
-///                  their corresponding URL replacements
+/// * `md_source` - The Markdown text to convert, as `str` or as UTF-8 `bytes`
+/// * `link_rules` - A dict mapping regular expression patterns (as strings) to their
+///                  corresponding URL replacements, a list of `(pattern, url)` tuples and
+///                  `LinkRule` objects, or a `RuleSet`. When several rules match the same text,
+///                  the first one is used. A URL may also be a callable, which receives the
+///                  groups of the match (the whole match first) and returns the URL, or `None`
+///                  to leave the match to the other rules. It is called with the GIL held, and
+///                  its exceptions are reported through `sys.unraisablehook`.
+/// * `profile` - The rendering profile: `"default"`, `"email"` (for notification e-mails) or
+///               `"feed"` (for RSS/Atom feeds)
+/// * `base_url` - If given, relative link and image URLs are resolved against it
+/// * `media_base_url` - If given, relative image URLs are resolved against it instead
+/// * `definition_lists` - Enable `Term\n: definition` lists, rendered as `<dl>`
+/// * `smart_punctuation` - If given, enable smart quotes, dashes and ellipses, using the
+///                         quotation marks of `"english"`, `"french"` or `"german"`
+/// * `tables` - Enable `| a | b |` tables
+/// * `alerts` - Enable `> [!NOTE]` alerts
+/// * `math` - Enable `$...$` and `$$...$$` formulas
+/// * `sanitize` - How the document is sanitized: `"default"` (or `True`) only keeps the raw
+///                HTML tags and attributes of an allowlist, like Bleach (`<sup>`, `<table>`,
+///                `<a href>`..., with relative, `http`, `https` or `mailto` URLs), escaping
+///                the other tags, and removes `javascript:` and `vbscript:` links and images,
+///                `"strict"` escapes all raw HTML and also removes `data:` and `file:` links
+///                and images, e.g. for documents which come from anonymous users, and `"off"`
+///                (or `False`) keeps everything, only for documents which come from trusted
+///                users.
+/// * `heading_id_prefix` - If given, the IDs of headings and footnotes include this prefix,
+///                         so that several documents rendered on the same page don't collide
+/// * `target_blank` - Open links in a new tab, with `target="_blank"`
+/// * `placeholders` - If given, a dict whose values replace the `{name}` placeholders in the
+///                    text of the document (but not in code), e.g. `{event_title}`. The values
+///                    are escaped like the rest of the text, and placeholders without a value
+///                    are kept.
+/// * `errors` - How invalid UTF-8 in `bytes` is handled: `"strict"` raises a
+///              `UnicodeDecodeError`, and `"replace"` replaces it with `U+FFFD`
 ///
 /// # Returns
 ///
@@ -32,7 +433,11 @@ use std::collections::HashMap;
 ///
 /// # Errors
 ///
-/// Returns a [`PyValueError`] if any of the regular expressions in the link rules are invalid
+/// Returns a [`LinkRuleError`] (a subclass of `ValueError`) if any of the link rules is invalid,
+/// a [`PyValueError`] if the profile, quote style, sanitization level or error handler is
+/// unknown, a `UnicodeDecodeError` if `md_source` isn't valid UTF-8 with `errors="strict"`, a
+/// `TypeError` if the link rules are neither a dict nor a list of pairs, and a [`RenderError`]
+/// (a subclass of `RuntimeError`) if the document can't be rendered
 ///
 /// # Example
 ///
@@ -46,24 +451,908 @@ use std::collections::HashMap;
 /// # Output: '<p>See issue <a href="https://github.com/org/repo/issues/1234">#1234</a> for details</p>'
 /// ```
 #[pyfunction]
-fn to_html(md_source: &str, link_rules: HashMap<String, String>) -> PyResult<String> {
-    let rules: Vec<_> = link_rules
-        .iter()
-        .map(|(re, url)| LinkRule::new(re, url))
-        .collect::<Result<_, _>>()
-        .map_err(|e| PyValueError::new_err(e.to_string()))?;
+#[pyo3(signature = (
+    md_source,
+    link_rules,
+    *,
+    profile = "default",
+    base_url = None,
+    media_base_url = None,
+    definition_lists = false,
+    smart_punctuation = None,
+    tables = true,
+    alerts = true,
+    math = true,
+    sanitize = Sanitize::Flag(true),
+    heading_id_prefix = None,
+    target_blank = true,
+    placeholders = None,
+    errors = "strict"
+))]
+#[allow(clippy::too_many_arguments)]
+fn to_html(
+    py: Python<'_>,
+    md_source: MarkdownSource<'_>,
+    link_rules: &Bound<'_, PyAny>,
+    profile: &str,
+    base_url: Option<String>,
+    media_base_url: Option<String>,
+    definition_lists: bool,
+    smart_punctuation: Option<&str>,
+    tables: bool,
+    alerts: bool,
+    math: bool,
+    sanitize: Sanitize,
+    heading_id_prefix: Option<String>,
+    target_blank: bool,
+    placeholders: Option<HashMap<String, String>>,
+    errors: &str,
+) -> PyResult<String> {
+    let rules = parse_link_rules(link_rules)?;
+    let md_source = md_source.decode(errors)?;
+    let options = render_options(RenderArgs {
+        profile,
+        base_url,
+        media_base_url,
+        definition_lists,
+        smart_punctuation,
+        tables,
+        alerts,
+        math,
+        sanitize,
+        heading_id_prefix,
+        target_blank,
+        placeholders,
+    })?;
 
-    indico_markdown_to_html(md_source, &rules).map_err(|e| PyRuntimeError::new_err(e.to_string()))
+    // the GIL is only held again by the callables of link rules
+    let rules: &[LinkRule] = &rules;
+    py.detach(|| indico_markdown_to_html_with_options(&md_source, rules, &options))
+        .map_err(render_error)
 }
 
+/// Converts a batch of Markdown texts to HTML like [`to_html`], in parallel and without holding
+/// the GIL, returning the results in the same order.
+///
+/// # Errors
+///
+/// Same as [`to_html`], with a [`RenderError`] if any of the documents can't be rendered
+///
+/// # Example
+///
+/// ```python
+/// htmls = indico_md.to_html_many([abstract.description for abstract in abstracts], link_rules=rules)
+/// ```
+#[pyfunction]
+#[pyo3(signature = (
+    md_sources,
+    *,
+    link_rules = None,
+    profile = "default",
+    base_url = None,
+    media_base_url = None,
+    definition_lists = false,
+    smart_punctuation = None,
+    tables = true,
+    alerts = true,
+    math = true,
+    sanitize = Sanitize::Flag(true),
+    heading_id_prefix = None,
+    target_blank = true,
+    placeholders = None
+))]
+#[allow(clippy::too_many_arguments)]
+fn to_html_many(
+    py: Python<'_>,
+    md_sources: Vec<String>,
+    link_rules: Option<&Bound<'_, PyAny>>,
+    profile: &str,
+    base_url: Option<String>,
+    media_base_url: Option<String>,
+    definition_lists: bool,
+    smart_punctuation: Option<&str>,
+    tables: bool,
+    alerts: bool,
+    math: bool,
+    sanitize: Sanitize,
+    heading_id_prefix: Option<String>,
+    target_blank: bool,
+    placeholders: Option<HashMap<String, String>>,
+) -> PyResult<Vec<String>> {
+    let rules = match link_rules {
+        Some(link_rules) => parse_link_rules(link_rules)?,
+        None => LinkRules::Compiled(Vec::new()),
+    };
+    let options = render_options(RenderArgs {
+        profile,
+        base_url,
+        media_base_url,
+        definition_lists,
+        smart_punctuation,
+        tables,
+        alerts,
+        math,
+        sanitize,
+        heading_id_prefix,
+        target_blank,
+        placeholders,
+    })?;
+
+    let rules: &[LinkRule] = &rules;
+    py.detach(|| {
+        md_sources
+            .par_iter()
+            .map(|md_source| indico_markdown_to_html_with_options(md_source, rules, &options))
+            .collect::<Result<Vec<_>, _>>()
+    })
+    .map_err(render_error)
+}
+
+/// Converts Markdown text to HTML like [`to_html`], stripping a leading front matter block
+/// (YAML between `---` lines or TOML between `+++` lines) from the document.
+///
+/// # Returns
+///
+/// * [`PyResult<(String, Option<PyObject>)>`] - The resulting HTML string, and the content of
+///   the front matter as a dict (`None` if there is no front matter or it cannot be parsed)
+///
+/// # Example
+///
+/// ```python
+/// html, front_matter = indico_md.to_html_with_front_matter("---\ntitle: Hi\n---\ntext", {})
+/// # front_matter == {"title": "Hi"}
+/// ```
+#[pyfunction]
+#[pyo3(signature = (
+    md_source,
+    link_rules,
+    *,
+    profile = "default",
+    base_url = None,
+    media_base_url = None,
+    definition_lists = false,
+    smart_punctuation = None,
+    tables = true,
+    alerts = true,
+    math = true,
+    sanitize = Sanitize::Flag(true),
+    heading_id_prefix = None,
+    target_blank = true,
+    placeholders = None,
+    errors = "strict"
+))]
+#[allow(clippy::too_many_arguments)]
+fn to_html_with_front_matter(
+    py: Python<'_>,
+    md_source: MarkdownSource<'_>,
+    link_rules: &Bound<'_, PyAny>,
+    profile: &str,
+    base_url: Option<String>,
+    media_base_url: Option<String>,
+    definition_lists: bool,
+    smart_punctuation: Option<&str>,
+    tables: bool,
+    alerts: bool,
+    math: bool,
+    sanitize: Sanitize,
+    heading_id_prefix: Option<String>,
+    target_blank: bool,
+    placeholders: Option<HashMap<String, String>>,
+    errors: &str,
+) -> PyResult<(String, Option<Py<PyAny>>)> {
+    let rules = parse_link_rules(link_rules)?;
+    let md_source = md_source.decode(errors)?;
+    let options = RenderOptions {
+        front_matter: true,
+        ..render_options(RenderArgs {
+            profile,
+            base_url,
+            media_base_url,
+            definition_lists,
+            smart_punctuation,
+            tables,
+            alerts,
+            math,
+            sanitize,
+            heading_id_prefix,
+            target_blank,
+            placeholders,
+        })?
+    };
+
+    let rules: &[LinkRule] = &rules;
+    let output = py
+        .detach(|| indico_markdown_render(&md_source, rules, &options))
+        .map_err(render_error)?;
+    let front_matter = match output
+        .front_matter
+        .and_then(|front_matter| front_matter.data)
+    {
+        Some(data) => Some(
+            py.import("json")?
+                .call_method1("loads", (data.to_string(),))?
+                .unbind(),
+        ),
+        None => None,
+    };
+    Ok((output.html, front_matter))
+}
+
+/// Name of a kind of render warning
+fn warning_kind(kind: RenderWarningKind) -> &'static str {
+    match kind {
+        RenderWarningKind::FilteredTag => "filtered_tag",
+        RenderWarningKind::BlockedUrl => "blocked_url",
+        RenderWarningKind::RuleMatchLimit => "rule_match_limit",
+    }
+}
+
+/// Converts Markdown text to HTML like [`to_html`], also returning what was found in the
+/// document.
+///
+/// # Returns
+///
+/// * [`PyResult<Bound<PyDict>>`] - A dict with:
+///   * `html` - The resulting HTML string
+///   * `headings` - The headings, as dicts with their `level`, `text`, `slug`, `id` and `line`
+///   * `links` - The links (including the ones created by link rules), as dicts with their
+///     `url` and `text`
+///   * `mentions` - The matches of link rules, as dicts with the `rule` (its index), `text`,
+///     `groups`, `url` and `line`
+///   * `warnings` - Content which is rendered differently from what was written, as dicts with
+///     their `kind` (`"filtered_tag"`, `"blocked_url"` or `"rule_match_limit"`), `detail` and
+///     `line`
+///   * `word_count` - The number of words of the text
+///   * `unknown_placeholders` - The names of the `{name}` placeholders without a value
+///
+/// # Example
+///
+/// ```python
+/// meta = indico_md.to_html_with_meta("# Intro\n\nSee gh:12", rules)
+/// # meta["headings"] == [{"level": 1, "text": "Intro", "slug": "intro", ...}]
+/// ```
+#[pyfunction]
+#[pyo3(signature = (
+    md_source,
+    link_rules,
+    *,
+    profile = "default",
+    base_url = None,
+    media_base_url = None,
+    definition_lists = false,
+    smart_punctuation = None,
+    tables = true,
+    alerts = true,
+    math = true,
+    sanitize = Sanitize::Flag(true),
+    heading_id_prefix = None,
+    target_blank = true,
+    placeholders = None,
+    errors = "strict"
+))]
+#[allow(clippy::too_many_arguments)]
+fn to_html_with_meta<'py>(
+    py: Python<'py>,
+    md_source: MarkdownSource<'py>,
+    link_rules: &Bound<'py, PyAny>,
+    profile: &str,
+    base_url: Option<String>,
+    media_base_url: Option<String>,
+    definition_lists: bool,
+    smart_punctuation: Option<&str>,
+    tables: bool,
+    alerts: bool,
+    math: bool,
+    sanitize: Sanitize,
+    heading_id_prefix: Option<String>,
+    target_blank: bool,
+    placeholders: Option<HashMap<String, String>>,
+    errors: &str,
+) -> PyResult<Bound<'py, PyDict>> {
+    let rules = parse_link_rules(link_rules)?;
+    let md_source = md_source.decode(errors)?;
+    let options = render_options(RenderArgs {
+        profile,
+        base_url,
+        media_base_url,
+        definition_lists,
+        smart_punctuation,
+        tables,
+        alerts,
+        math,
+        sanitize,
+        heading_id_prefix,
+        target_blank,
+        placeholders,
+    })?;
+
+    let rules: &[LinkRule] = &rules;
+    let output = py
+        .detach(|| indico_markdown_render(&md_source, rules, &options))
+        .map_err(render_error)?;
+
+    let meta = PyDict::new(py);
+    meta.set_item("html", output.html)?;
+    let headings = output
+        .headings
+        .into_iter()
+        .map(|heading| {
+            let item = PyDict::new(py);
+            item.set_item("level", heading.level)?;
+            item.set_item("text", heading.text)?;
+            item.set_item("slug", heading.slug)?;
+            item.set_item("id", heading.id)?;
+            item.set_item("line", heading.sourcepos.start.0)?;
+            Ok(item)
+        })
+        .collect::<PyResult<Vec<_>>>()?;
+    meta.set_item("headings", headings)?;
+    let links = output
+        .links
+        .into_iter()
+        .map(|link| {
+            let item = PyDict::new(py);
+            item.set_item("url", link.url)?;
+            item.set_item("text", link.text)?;
+            Ok(item)
+        })
+        .collect::<PyResult<Vec<_>>>()?;
+    meta.set_item("links", links)?;
+    let mentions = output
+        .rule_matches
+        .into_iter()
+        .map(|mention| {
+            let item = PyDict::new(py);
+            item.set_item("rule", mention.rule)?;
+            item.set_item("text", mention.groups[0].clone())?;
+            item.set_item("groups", mention.groups)?;
+            item.set_item("url", mention.url)?;
+            item.set_item("line", mention.sourcepos.start.0)?;
+            Ok(item)
+        })
+        .collect::<PyResult<Vec<_>>>()?;
+    meta.set_item("mentions", mentions)?;
+    let warnings = output
+        .warnings
+        .into_iter()
+        .map(|warning| {
+            let item = PyDict::new(py);
+            item.set_item("kind", warning_kind(warning.kind))?;
+            item.set_item("detail", warning.detail)?;
+            item.set_item("line", warning.sourcepos.start.0)?;
+            Ok(item)
+        })
+        .collect::<PyResult<Vec<_>>>()?;
+    meta.set_item("warnings", warnings)?;
+    meta.set_item("word_count", output.word_count)?;
+    meta.set_item("unknown_placeholders", output.unknown_placeholders)?;
+    Ok(meta)
+}
+
+/// Checks or unchecks the task list item with the given index (counting from 0, in document
+/// order), returning the updated markdown, or `None` if there is no such item.
+///
+/// # Example
+///
+/// ```python
+/// indico_md.toggle_task("- [ ] agenda\n- [x] budget", 1)
+/// # Output: '- [ ] agenda\n- [ ] budget'
+/// ```
 #[pyfunction]
-fn to_unstyled_html(md_source: &str) -> PyResult<String> {
-    indico_markdown_to_unstyled_html(md_source).map_err(|e| PyRuntimeError::new_err(e.to_string()))
+fn toggle_task(md_source: &str, index: usize) -> Option<String> {
+    indico_markdown_toggle_task(md_source, index)
+}
+
+/// Derives Open Graph metadata from a document: its title (the first heading), description
+/// (an excerpt of its text) and image (the first image, resolved against `base_url` or
+/// `media_base_url`), as a dict whose values are `None` when missing.
+///
+/// # Example
+///
+/// ```python
+/// indico_md.open_graph("# Opening\n\nWelcome!\n\n![](poster.png)")
+/// # Output: {'title': 'Opening', 'description': 'Welcome!', 'image': 'poster.png'}
+/// ```
+#[pyfunction]
+#[pyo3(signature = (md_source, *, base_url = None, media_base_url = None))]
+fn open_graph(
+    md_source: &str,
+    base_url: Option<String>,
+    media_base_url: Option<String>,
+) -> HashMap<&'static str, Option<String>> {
+    let options = RenderOptions {
+        base_url,
+        media_base_url,
+        ..Default::default()
+    };
+    let og = indico_markdown_open_graph(md_source, &options);
+    HashMap::from([
+        ("title", og.title),
+        ("description", og.description),
+        ("image", og.image),
+    ])
+}
+
+/// Converts a heading to a dict, with its `start` and `end` positions as `(line, column)`
+/// tuples
+fn heading_dict<'py>(py: Python<'py>, heading: Heading) -> PyResult<Bound<'py, PyDict>> {
+    let item = PyDict::new(py);
+    item.set_item("level", heading.level)?;
+    item.set_item("text", heading.text)?;
+    item.set_item("slug", heading.slug)?;
+    item.set_item("id", heading.id)?;
+    item.set_item("start", heading.sourcepos.start)?;
+    item.set_item("end", heading.sourcepos.end)?;
+    Ok(item)
+}
+
+/// Gets the headings of a document, in document order, as dicts with their `level`, `text`,
+/// `slug`, `id` (as in the rendered HTML) and their `start` and `end` positions in the source,
+/// as `(line, column)` tuples starting at 1 (columns count UTF-8 bytes).
+///
+/// # Example
+///
+/// ```python
+/// indico_md.extract_headings("# Agenda\n\n## Coffee")
+/// # Output: [{'level': 1, 'text': 'Agenda', 'slug': 'agenda', 'id': 'indico-md-agenda',
+/// #           'start': (1, 1), 'end': (1, 8)}, {'level': 2, 'text': 'Coffee', ...}]
+/// ```
+#[pyfunction]
+fn extract_headings<'py>(py: Python<'py>, md_source: &str) -> PyResult<Vec<Bound<'py, PyDict>>> {
+    py.detach(|| indico_markdown_outline(md_source, OffsetMode::Bytes))
+        .into_iter()
+        .map(|heading| heading_dict(py, heading))
+        .collect()
+}
+
+/// Gets the headings of a document as a tree: like `extract_headings`, but each heading has
+/// the headings of lower levels which follow it in its `children`.
+///
+/// # Example
+///
+/// ```python
+/// indico_md.extract_outline("# Agenda\n\n## Coffee\n\n# Minutes")
+/// # Output: [{'text': 'Agenda', 'children': [{'text': 'Coffee', 'children': [], ...}], ...},
+/// #          {'text': 'Minutes', 'children': [], ...}]
+/// ```
+#[pyfunction]
+fn extract_outline<'py>(py: Python<'py>, md_source: &str) -> PyResult<Bound<'py, PyList>> {
+    let headings = py.detach(|| indico_markdown_outline(md_source, OffsetMode::Bytes));
+    let outline = PyList::empty(py);
+    // the levels and the children of the headings which may still get children
+    let mut parents: Vec<(u8, Bound<'py, PyList>)> = Vec::new();
+    for heading in headings {
+        let level = heading.level;
+        while parents.last().is_some_and(|(parent, _)| *parent >= level) {
+            parents.pop();
+        }
+        let item = heading_dict(py, heading)?;
+        let children = PyList::empty(py);
+        item.set_item("children", &children)?;
+        parents
+            .last()
+            .map_or(&outline, |(_, siblings)| siblings)
+            .append(item)?;
+        parents.push((level, children));
+    }
+    Ok(outline)
+}
+
+/// Gets the links of a document, including the ones created by `link_rules` (see `to_html`),
+/// in document order, as dicts with their `url` (resolved against `base_url`), `text`, and
+/// their `start` and `end` positions in the source, as in `extract_headings`.
+///
+/// # Example
+///
+/// ```python
+/// indico_md.extract_links("See [the slides](slides.pdf) and #12", {r"#(\d+)": "/tickets/{1}"})
+/// # Output: [{'url': 'slides.pdf', 'text': 'the slides', 'start': (1, 5), 'end': (1, 28)},
+/// #          {'url': '/tickets/12', 'text': '#12', 'start': (1, 34), 'end': (1, 36)}]
+/// ```
+#[pyfunction]
+#[pyo3(signature = (md_source, link_rules = None, *, base_url = None))]
+fn extract_links<'py>(
+    py: Python<'py>,
+    md_source: &str,
+    link_rules: Option<&Bound<'py, PyAny>>,
+    base_url: Option<String>,
+) -> PyResult<Vec<Bound<'py, PyDict>>> {
+    let rules = link_rules.map(parse_link_rules).transpose()?;
+    let rules: &[LinkRule] = rules.as_deref().unwrap_or_default();
+    let options = RenderOptions {
+        base_url,
+        ..Default::default()
+    };
+    py.detach(|| indico_markdown_links(md_source, rules, &options))
+        .map_err(render_error)?
+        .into_iter()
+        .map(|link| {
+            let item = PyDict::new(py);
+            item.set_item("url", link.url)?;
+            item.set_item("text", link.text)?;
+            item.set_item("start", link.sourcepos.start)?;
+            item.set_item("end", link.sourcepos.end)?;
+            Ok(item)
+        })
+        .collect()
+}
+
+/// Checks link rules without failing on the first invalid one, e.g. to show what's wrong while
+/// they are edited. Returns a dict per rule, in order, with its `index`, `pattern`, whether it
+/// is `valid` and the `error` message when it isn't.
+///
+/// # Example
+///
+/// ```python
+/// indico_md.validate_rules({r"#(\d+)": "/tickets/{1}", r"gh:(\d+": "/gh/{1}"})
+/// # Output: [{'index': 0, 'pattern': '#(\\d+)', 'valid': True, 'error': None},
+/// #          {'index': 1, 'pattern': 'gh:(\\d+', 'valid': False, 'error': '...'}]
+/// ```
+#[pyfunction]
+fn validate_rules<'py>(
+    py: Python<'py>,
+    link_rules: &Bound<'py, PyAny>,
+) -> PyResult<Vec<Bound<'py, PyDict>>> {
+    let rules: Vec<(String, Option<Bound<'py, PyAny>>)> =
+        if let Ok(dict) = link_rules.extract::<Bound<'py, PyDict>>() {
+            dict.iter()
+                .map(|(re, url)| Ok((re.extract::<String>()?, Some(url))))
+                .collect::<PyResult<_>>()?
+        } else {
+            link_rules
+                .try_iter()?
+                .map(|item| {
+                    let item = item?;
+                    // `LinkRule` objects were already compiled
+                    if let Ok(rule) = item.extract::<Bound<'py, PyLinkRule>>() {
+                        return Ok((rule.get().pattern.clone(), None));
+                    }
+                    let (re, url): (String, Bound<'py, PyAny>) = item.extract()?;
+                    Ok((re, Some(url)))
+                })
+                .collect::<PyResult<_>>()?
+        };
+    rules
+        .into_iter()
+        .enumerate()
+        .map(|(index, (pattern, url))| {
+            let error = match url.map(|url| compile_link_rule(&pattern, &url)) {
+                Some(Err(err)) if err.is_instance_of::<LinkRuleError>(py) => {
+                    Some(err.value(py).str()?.to_string())
+                }
+                Some(Err(err)) => return Err(err),
+                Some(Ok(_)) | None => None,
+            };
+            let item = PyDict::new(py);
+            item.set_item("index", index)?;
+            item.set_item("pattern", pattern)?;
+            item.set_item("valid", error.is_none())?;
+            item.set_item("error", error)?;
+            Ok(item)
+        })
+        .collect()
+}
+
+/// Finds the matches of link rules (see `to_html`) in a document without rendering it, e.g. to
+/// preview rules while they are edited. Only the matches which would be turned into links are
+/// returned (i.e. not the ones in code or in links), as dicts with the `rule` (its index), the
+/// matched `text`, its `groups` (the whole match first, `None` for the groups which did not
+/// participate), the `url`, and the `start` and `end` positions, as in `extract_headings`.
+///
+/// # Example
+///
+/// ```python
+/// indico_md.preview_rule_matches("See #12", {r"#(\d+)": "/tickets/{1}"})
+/// # Output: [{'rule': 0, 'text': '#12', 'groups': ['#12', '12'], 'url': '/tickets/12',
+/// #           'start': (1, 5), 'end': (1, 7)}]
+/// ```
+#[pyfunction]
+fn preview_rule_matches<'py>(
+    py: Python<'py>,
+    md_source: &str,
+    link_rules: &Bound<'py, PyAny>,
+) -> PyResult<Vec<Bound<'py, PyDict>>> {
+    let rules = parse_link_rules(link_rules)?;
+    let rules: &[LinkRule] = &rules;
+    py.detach(|| indico_markdown_rule_matches(md_source, rules, OffsetMode::Bytes))
+        .into_iter()
+        .map(|rule_match| {
+            let item = PyDict::new(py);
+            item.set_item("rule", rule_match.rule)?;
+            item.set_item("text", rule_match.groups[0].clone())?;
+            item.set_item("groups", rule_match.groups)?;
+            item.set_item("url", rule_match.url)?;
+            item.set_item("start", rule_match.sourcepos.start)?;
+            item.set_item("end", rule_match.sourcepos.end)?;
+            Ok(item)
+        })
+        .collect()
+}
+
+/// Converts Markdown text to HTML with all formatting stripped, keeping only paragraphs and
+/// line breaks, plus the elements allowed by the keyword arguments, e.g. for notifications.
+///
+/// # Arguments
+///
+/// * `md_source` - A string slice containing the Markdown text to convert
+/// * `link_rules` - If given, link rules as in `to_html`, whose matches are kept as links
+///                  with `keep_links`
+/// * `keep_links` - Keep links as `<a>` elements, opened in `link_target` if given (e.g.
+///                  `"_blank"`)
+/// * `emphasis` - Keep emphasis, strong emphasis, strikethrough, highlights and underlines
+/// * `lists` - Keep lists as `<ul>` and `<ol>` elements
+/// * `headings` - Keep headings as `<h1>` to `<h6>` elements
+/// * `allowed_tags` - If given, the raw HTML tags with these names (e.g. `["sup", "sub"]`) are
+///                    kept without their attributes, while the other ones are removed
+/// * `max_chars` - If given, the text is cut after this many characters, at the end of a
+///                 word and followed by an ellipsis
+///
+/// # Example
+///
+/// ```python
+/// indico_md.to_unstyled_html("**Hi** [there](https://example.com)", keep_links=True)
+/// # Output: '<p>Hi <a href="https://example.com">there</a></p>\n'
+/// ```
+#[pyfunction]
+#[pyo3(signature = (
+    md_source,
+    link_rules = None,
+    *,
+    keep_links = false,
+    link_target = None,
+    emphasis = false,
+    lists = false,
+    headings = false,
+    allowed_tags = None,
+    max_chars = None
+))]
+#[allow(clippy::too_many_arguments)]
+fn to_unstyled_html(
+    py: Python<'_>,
+    md_source: &str,
+    link_rules: Option<&Bound<'_, PyAny>>,
+    keep_links: bool,
+    link_target: Option<String>,
+    emphasis: bool,
+    lists: bool,
+    headings: bool,
+    allowed_tags: Option<Vec<String>>,
+    max_chars: Option<usize>,
+) -> PyResult<String> {
+    let rules = link_rules.map(parse_link_rules).transpose()?;
+    let rules: &[LinkRule] = rules.as_deref().unwrap_or_default();
+    let options = UnstyledOptions {
+        links: keep_links,
+        link_target,
+        emphasis,
+        lists,
+        headings,
+        inline_html: match allowed_tags {
+            Some(tags) => UnstyledInlineHtml::Allow(tags),
+            None => UnstyledInlineHtml::Strip,
+        },
+        max_chars,
+        ..Default::default()
+    };
+    py.detach(|| indico_markdown_to_unstyled_html_with_options(md_source, rules, &options))
+        .map_err(render_error)
+}
+
+/// Converts Markdown text to a LaTeX fragment, e.g. for books of abstracts, applying the
+/// link rules (see `to_html`) if given. Special characters are escaped, while math is kept
+/// as it is. The fragment relies on the `hyperref`, `ulem` and `soul` packages.
+///
+/// # Example
+///
+/// ```python
+/// indico_md.to_latex("**50%** of $x^2$")
+/// # Output: '\\textbf{50\\%} of \\(x^2\\)'
+/// ```
+#[pyfunction]
+#[pyo3(signature = (md_source, *, link_rules = None))]
+fn to_latex(
+    py: Python<'_>,
+    md_source: &str,
+    link_rules: Option<&Bound<'_, PyAny>>,
+) -> PyResult<String> {
+    let rules = link_rules.map(parse_link_rules).transpose()?;
+    let rules: &[LinkRule] = rules.as_deref().unwrap_or_default();
+    Ok(py.detach(|| indico_markdown_to_latex(md_source, rules)))
+}
+
+/// Converts Markdown text to HTML (like `to_html`, with the link rules if given) truncated to
+/// `max_chars` visible characters, e.g. for listing pages. The text is cut at the end of a word
+/// and followed by an ellipsis, which counts as one of the characters, and the elements which
+/// are open at that point are closed. Links which would be cut are dropped.
+///
+/// # Example
+///
+/// ```python
+/// indico_md.truncate_html("**Hello** world and more", 12)
+/// # Output: '<p><strong>Hello</strong> world…</p>'
+/// ```
+#[pyfunction]
+#[pyo3(signature = (md_source, max_chars, *, link_rules = None))]
+fn truncate_html(
+    py: Python<'_>,
+    md_source: &str,
+    max_chars: usize,
+    link_rules: Option<&Bound<'_, PyAny>>,
+) -> PyResult<String> {
+    let rules = link_rules.map(parse_link_rules).transpose()?;
+    let rules: &[LinkRule] = rules.as_deref().unwrap_or_default();
+    py.detach(|| {
+        indico_markdown_to_truncated_html(md_source, rules, &RenderOptions::default(), max_chars)
+    })
+    .map_err(render_error)
+}
+
+/// Generates a plain-text teaser of a document, of at most `max_chars` characters, e.g. for
+/// the subjects of notifications. The text of its first paragraphs is used, cut at the end of a
+/// word and followed by an ellipsis if needed.
+///
+/// # Example
+///
+/// ```python
+/// indico_md.excerpt("# Title\n\nThe [Indico project](https://getindico.io) is great.", 19)
+/// # Output: 'The Indico project…'
+/// ```
+#[pyfunction]
+fn excerpt(py: Python<'_>, md_source: &str, max_chars: usize) -> String {
+    py.detach(|| indico_markdown_excerpt(md_source, max_chars))
+}
+
+/// A renderer holding link rules and options, which are only prepared once, e.g. to keep it in
+/// the state of the application instead of passing them on each call.
+///
+/// # Example
+///
+/// ```python
+/// renderer = indico_md.Renderer(rules, profile="email", base_url="https://indico.example.com/")
+/// html = renderer.render("See #1234")
+/// text = renderer.render_plain("See #1234")
+/// ```
+#[pyclass(module = "indico_md", frozen)]
+struct Renderer {
+    renderer: IndicoRenderer,
+    /// The rules, which are also used by [`Renderer::render_unstyled`] and
+    /// [`Renderer::render_plain`]
+    rules: Vec<LinkRule>,
+    /// Options of [`Renderer::render_unstyled`], derived from the render options
+    unstyled: UnstyledOptions,
+    /// Options of [`Renderer::render_plain`], derived from the render options
+    plain: PlainTextOptions,
+}
+
+#[pymethods]
+impl Renderer {
+    /// Creates a renderer with the given link rules (see `to_html`, a `RuleSet` isn't
+    /// compiled again) and the same keyword arguments as `to_html`
+    #[new]
+    #[pyo3(signature = (
+        link_rules = None,
+        *,
+        profile = "default",
+        base_url = None,
+        media_base_url = None,
+        definition_lists = false,
+        smart_punctuation = None,
+        tables = true,
+        alerts = true,
+        math = true,
+        sanitize = Sanitize::Flag(true),
+        heading_id_prefix = None,
+        target_blank = true,
+        placeholders = None
+    ))]
+    #[allow(clippy::too_many_arguments)]
+    fn new(
+        link_rules: Option<&Bound<'_, PyAny>>,
+        profile: &str,
+        base_url: Option<String>,
+        media_base_url: Option<String>,
+        definition_lists: bool,
+        smart_punctuation: Option<&str>,
+        tables: bool,
+        alerts: bool,
+        math: bool,
+        sanitize: Sanitize,
+        heading_id_prefix: Option<String>,
+        target_blank: bool,
+        placeholders: Option<HashMap<String, String>>,
+    ) -> PyResult<Self> {
+        let rules = match link_rules {
+            Some(link_rules) => parse_link_rules(link_rules)?.to_vec(),
+            None => Vec::new(),
+        };
+        let options = render_options(RenderArgs {
+            profile,
+            base_url,
+            media_base_url,
+            definition_lists,
+            smart_punctuation,
+            tables,
+            alerts,
+            math,
+            sanitize,
+            heading_id_prefix,
+            target_blank,
+            placeholders,
+        })?;
+        // e-mails keep their links, which open like the ones of the HTML
+        let email = options.profile == RenderProfile::Email;
+        let unstyled = UnstyledOptions {
+            link_target: target_blank.then(|| "_blank".into()),
+            link_rel: target_blank.then(|| "noopener noreferrer".into()),
+            ..if email {
+                UnstyledOptions::notification()
+            } else {
+                UnstyledOptions::default()
+            }
+        };
+        let plain = if email {
+            PlainTextOptions::email()
+        } else {
+            PlainTextOptions::default()
+        };
+        Ok(Self {
+            renderer: IndicoRenderer::new(rules.clone(), options),
+            rules,
+            unstyled,
+            plain,
+        })
+    }
+
+    /// Converts Markdown text to HTML, like `to_html`
+    fn render(&self, py: Python<'_>, md_source: &str) -> PyResult<String> {
+        py.detach(|| self.renderer.render(md_source))
+            .map(|output| output.html)
+            .map_err(render_error)
+    }
+
+    /// Converts Markdown text to HTML with all formatting stripped, like `to_unstyled_html`
+    /// but with the link rules of the renderer. With the `"email"` profile, links, emphasis
+    /// and lists are kept, and links open in a new tab if `target_blank` is set.
+    fn render_unstyled(&self, py: Python<'_>, md_source: &str) -> PyResult<String> {
+        py.detach(|| {
+            indico_markdown_to_unstyled_html_with_options(md_source, &self.rules, &self.unstyled)
+        })
+        .map_err(render_error)
+    }
+
+    /// Converts Markdown text to plain text with the link rules of the renderer, e.g. for the
+    /// `text/plain` part of e-mails. With the `"email"` profile, the URLs of links are listed
+    /// as numbered references and lines are wrapped at 72 columns.
+    fn render_plain(&self, py: Python<'_>, md_source: &str) -> String {
+        py.detach(|| indico_markdown_to_plain_text_with_rules(md_source, &self.rules, &self.plain))
+    }
 }
 
-#[pymodule]
+/// The module doesn't rely on the GIL: its classes are immutable once created, and nothing is
+/// cached between calls, so it can be used on free-threaded builds of Python without enabling
+/// the GIL again. The callables of link rules may be called from several threads at once there
+/// (e.g. by `to_html_many`), and must be thread-safe.
+#[pymodule(gil_used = false)]
 fn indico_md(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add("LinkRuleError", m.py().get_type::<LinkRuleError>())?;
+    m.add("RenderError", m.py().get_type::<RenderError>())?;
+    m.add_class::<PyLinkRule>()?;
+    m.add_class::<RuleSet>()?;
+    m.add_class::<Renderer>()?;
     m.add_function(wrap_pyfunction!(to_html, m)?)?;
+    m.add_function(wrap_pyfunction!(to_html_many, m)?)?;
+    m.add_function(wrap_pyfunction!(to_html_with_front_matter, m)?)?;
+    m.add_function(wrap_pyfunction!(to_html_with_meta, m)?)?;
     m.add_function(wrap_pyfunction!(to_unstyled_html, m)?)?;
+    m.add_function(wrap_pyfunction!(to_latex, m)?)?;
+    m.add_function(wrap_pyfunction!(truncate_html, m)?)?;
+    m.add_function(wrap_pyfunction!(excerpt, m)?)?;
+    m.add_function(wrap_pyfunction!(toggle_task, m)?)?;
+    m.add_function(wrap_pyfunction!(open_graph, m)?)?;
+    m.add_function(wrap_pyfunction!(extract_headings, m)?)?;
+    m.add_function(wrap_pyfunction!(extract_outline, m)?)?;
+    m.add_function(wrap_pyfunction!(extract_links, m)?)?;
+    m.add_function(wrap_pyfunction!(validate_rules, m)?)?;
+    m.add_function(wrap_pyfunction!(preview_rule_matches, m)?)?;
     Ok(())
 }
@@ -1,3 +1,9 @@
+import ast
+import sys
+import sysconfig
+from concurrent.futures import ThreadPoolExecutor
+from pathlib import Path
+
 import pytest
 import indico_md
 
@@ -28,6 +34,129 @@ def test_output_ok():
     )
 
 
+def test_unstyled_options():
+    md = "## Title\n\n**Hi** [there](https://example.com), see #12\n\n- H<sub>2</sub>O<span>!</span>"
+    html = indico_md.to_unstyled_html(md)
+    assert "<strong>" not in html and "<a " not in html and "<ul>" not in html
+
+    html = indico_md.to_unstyled_html(
+        md,
+        {r"#(\d+)": "https://tickets.example.com/{1}"},
+        keep_links=True,
+        link_target="_blank",
+        emphasis=True,
+        lists=True,
+        headings=True,
+        allowed_tags=["sub"],
+    )
+    assert "Title</h2>" in html
+    assert "<strong>Hi</strong>" in html
+    assert '<a href="https://example.com" target="_blank">there</a>' in html
+    assert '<a href="https://tickets.example.com/12" target="_blank">#12</a>' in html
+    assert "<ul>" in html
+    assert "H<sub>2</sub>O!" in html
+
+    # links with unsafe URLs only keep their text
+    html = indico_md.to_unstyled_html("[x](javascript:alert(1)) [y](data:text/html,z)", keep_links=True)
+    assert html == "<p>x y</p>\n"
+
+    html = indico_md.to_unstyled_html("Some rather long text", max_chars=10)
+    assert html.startswith("<p>Some")
+    assert "long" not in html
+
+
+def test_to_latex():
+    assert indico_md.to_latex("## Costs & *benefits*\n\n100% of $x^2$") == (
+        "\\subsection*{Costs \\& \\emph{benefits}}\n\n100\\% of \\(x^2\\)"
+    )
+    assert indico_md.to_latex("see TKT1234567", link_rules={r"TKT(\d+)": "https://tkt.sys/{1}"}) == (
+        "see \\href{https://tkt.sys/1234567}{TKT1234567}"
+    )
+
+
+def test_truncate_html():
+    assert indico_md.truncate_html("**Hello** world and more", 12) == "<p><strong>Hello</strong> world\u2026</p>"
+    assert indico_md.truncate_html("Short", 12) == "<p>Short</p>\n"
+    html = indico_md.truncate_html("See #12 and more", 6, link_rules={r"#(\d+)": "/tickets/{1}"})
+    assert html == "<p>See\u2026</p>"
+
+
+def test_excerpt():
+    md = "# Title\n\nThe [Indico project](https://getindico.io) is great.\n\nSecond `para graph`."
+    assert indico_md.excerpt(md, 100) == "The Indico project is great. Second para graph."
+    assert indico_md.excerpt(md, 19) == "The Indico project\u2026"
+    assert indico_md.excerpt(md, 0) == ""
+
+
+def test_bytes_source():
+    rules = {r"#(\d+)": "/tickets/{1}"}
+    assert indico_md.to_html("Caf\u00e9 #1".encode(), rules) == indico_md.to_html("Caf\u00e9 #1", rules)
+    with pytest.raises(UnicodeDecodeError):
+        indico_md.to_html(b"Caf\xe9 #1", rules)
+    assert indico_md.to_html(b"Caf\xe9", {}, errors="replace") == "<p>Caf\ufffd</p>\n"
+    html, _ = indico_md.to_html_with_front_matter(b"**x\xff**", {}, errors="replace")
+    assert html == "<p><strong>x\ufffd</strong></p>\n"
+    assert indico_md.to_html_with_meta(b"# T\xe9", {}, errors="replace")["headings"][0]["text"] == "T\ufffd"
+
+    with pytest.raises(ValueError, match="Unknown error handler"):
+        indico_md.to_html(b"x", {}, errors="ignore")
+    with pytest.raises(TypeError):
+        indico_md.to_html(42, {})
+
+
+def test_validate_rules():
+    rules = [
+        (r"#(\d+)", "/tickets/{1}"),
+        (r"gh:(\d+", "/gh/{1}"),
+        (r"@(\w+)", "/users/{2}"),
+        indico_md.LinkRule(r"tkt(\d+)", "/tkt/{1}", case_insensitive=True),
+        (r"!(\w+)", lambda text, name: None),
+    ]
+    diagnostics = indico_md.validate_rules(rules)
+    assert [(d["index"], d["pattern"], d["valid"]) for d in diagnostics] == [
+        (0, r"#(\d+)", True),
+        (1, r"gh:(\d+", False),
+        (2, r"@(\w+)", False),
+        (3, r"tkt(\d+)", True),
+        (4, r"!(\w+)", True),
+    ]
+    assert diagnostics[0]["error"] is None
+    assert diagnostics[1]["error"]
+    assert "unknown placeholder" in diagnostics[2]["error"]
+    assert [d["valid"] for d in indico_md.validate_rules({r"#(\d+)": "/{1}", "(": "/"})] == [True, False]
+
+
+def test_preview_rule_matches():
+    rules = {r"#(\d+)": "/tickets/{1}", r"@(\w+)(?:/(\w+))?": "/users/{1}"}
+    assert indico_md.preview_rule_matches("See #12 and `#13`\n\ncc @alice", rules) == [
+        {"rule": 0, "text": "#12", "groups": ["#12", "12"], "url": "/tickets/12", "start": (1, 5), "end": (1, 7)},
+        {
+            "rule": 1,
+            "text": "@alice",
+            "groups": ["@alice", "alice", None],
+            "url": "/users/alice",
+            "start": (3, 4),
+            "end": (3, 9),
+        },
+    ]
+    with pytest.raises(indico_md.LinkRuleError):
+        indico_md.preview_rule_matches("x", {"(": "/"})
+
+
+def test_placeholders():
+    md = "Welcome to **{event_title}**, see `{event_title}` and {unknown}"
+    html = indico_md.to_html(md, {}, placeholders={"event_title": "<Opening> & co"})
+    assert html == (
+        "<p>Welcome to <strong>&lt;Opening&gt; &amp; co</strong>, see <code>{event_title}</code> and {unknown}</p>\n"
+    )
+    assert indico_md.to_html(md, {}) == indico_md.to_html(md, {}, placeholders=None)
+    assert "{event_title}" in indico_md.to_html(md, {})
+    meta = indico_md.to_html_with_meta(md, {}, placeholders={"event_title": "Opening"})
+    assert meta["unknown_placeholders"] == ["unknown"]
+    renderer = indico_md.Renderer(placeholders={"event_title": "Opening"})
+    assert renderer.render("{event_title}") == "<p>Opening</p>\n"
+
+
 def test_exceptions():
     source = "TEST"
     rules = {r"\bTKT(\d{7})\b": "https://tkt.sys/{1}", r"\bgh:(\d+)\b": 1234}
@@ -40,3 +169,335 @@ def test_exceptions():
     }
     with pytest.raises(ValueError):
         indico_md.to_html(source, rules)
+
+
+def test_exception_classes():
+    rules = [(r"#(\d+)", "/tickets/{1}"), (r"(abc", "/abc")]
+    with pytest.raises(indico_md.LinkRuleError) as exc_info:
+        indico_md.to_html("TEST", rules)
+    assert exc_info.value.pattern == "(abc"
+    assert exc_info.value.index == 1
+
+    with pytest.raises(indico_md.LinkRuleError) as exc_info:
+        indico_md.RuleSet({"a": "/a", "b": "/{1}"})
+    assert exc_info.value.pattern == "b"
+    assert exc_info.value.index == 1
+    assert "placeholder" in str(exc_info.value)
+
+    with pytest.raises(indico_md.LinkRuleError) as exc_info:
+        indico_md.LinkRule("(abc", "/abc", case_insensitive=True)
+    assert exc_info.value.pattern == "(abc"
+    assert exc_info.value.index is None
+
+    assert issubclass(indico_md.LinkRuleError, ValueError)
+    assert issubclass(indico_md.RenderError, RuntimeError)
+
+
+def test_ordered_link_rules():
+    first = (r"#(\d+)", "https://first.example.com/{1}")
+    second = (r"#(\d+)", "https://second.example.com/{1}")
+    assert "first.example.com/1" in indico_md.to_html("#1", [first, second])
+    assert "second.example.com/1" in indico_md.to_html("#1", [second, first])
+    # dicts keep their insertion order
+    rules = {r"#(\d+)": "https://second.example.com/{1}", r"#(\d)": "https://first.example.com/{1}"}
+    assert "second.example.com/1" in indico_md.to_html("#1", rules)
+
+    with pytest.raises(TypeError):
+        indico_md.to_html("#1", [(r"#(\d+)", 1)])
+    with pytest.raises(TypeError):
+        indico_md.to_html("#1", 42)
+
+
+def test_rule_set():
+    rule = indico_md.LinkRule(r"tkt(\d+)", "https://tkt.sys/{1}", case_insensitive=True)
+    assert rule.pattern == r"tkt(\d+)"
+    assert rule.url == "https://tkt.sys/{1}"
+    rules = indico_md.RuleSet([rule, (r"\bgh:(\d+)\b", "https://github.com/indico/indico/issues/{1}")])
+    assert len(rules) == 2
+
+    html = indico_md.to_html("TKT1 and gh:2", rules)
+    assert 'href="https://tkt.sys/1"' in html
+    assert 'href="https://github.com/indico/indico/issues/2"' in html
+    assert indico_md.to_html("TKT1 and gh:2", [rule]) == indico_md.to_html("TKT1 and gh:2", {r"(?i)tkt(\d+)": "https://tkt.sys/{1}"})
+
+    word = indico_md.LinkRule("gh", "https://github.com", whole_word=True)
+    assert indico_md.to_html("ghost gh", [word]) == (
+        '<p>ghost <a href="https://github.com" title="gh" target="_blank">gh</a></p>\n'
+    )
+
+    with pytest.raises(ValueError):
+        indico_md.LinkRule("(abc", "https://example.com")
+    with pytest.raises(ValueError):
+        indico_md.RuleSet([("a", "https://example.com/{1}")])
+
+
+def test_callable_link_rules(monkeypatch):
+    calls = []
+
+    def resolve(text, number):
+        calls.append((text, number))
+        return "https://tkt.sys/one" if number == "1" else None
+
+    rules = [(r"#(\d+)", resolve), (r"#\d+", "/fallback")]
+    assert indico_md.to_html("#1 #2", rules) == (
+        '<p><a href="https://tkt.sys/one" title="#1" target="_blank">#1</a> '
+        '<a href="/fallback" title="#2" target="_blank">#2</a></p>\n'
+    )
+    assert calls == [("#1", "1"), ("#2", "2")]
+
+    rule = indico_md.LinkRule(r"#(\d+)", lambda text, number: f"/tickets/{number}")
+    assert 'href="/tickets/3"' in indico_md.to_html("#3", indico_md.RuleSet([rule]))
+    assert indico_md.to_html_many(["#4", "#5"], link_rules={r"#(\d+)": lambda *groups: None}) == [
+        "<p>#4</p>\n",
+        "<p>#5</p>\n",
+    ]
+
+    # exceptions are reported, and the match isn't turned into a link
+    def broken(text, number):
+        raise KeyError(number)
+
+    unraisable = []
+    monkeypatch.setattr(sys, "unraisablehook", unraisable.append)
+    assert indico_md.to_html("#6", {r"#(\d+)": broken}) == "<p>#6</p>\n"
+    assert isinstance(unraisable[0].exc_value, KeyError)
+
+
+def test_to_html_many():
+    sources = [f"item gh:{i}" for i in range(100)]
+    rules = {r"\bgh:(\d+)\b": "https://github.com/indico/indico/issues/{1}"}
+    htmls = indico_md.to_html_many(sources, link_rules=rules, target_blank=False)
+    assert htmls == [indico_md.to_html(source, rules, target_blank=False) for source in sources]
+    assert indico_md.to_html_many(["*a*", "b"]) == ["<p><em>a</em></p>\n", "<p>b</p>\n"]
+    assert indico_md.to_html_many([]) == []
+
+
+def test_to_html_with_meta():
+    md = "# Intro\n\nSee [docs](https://docs.example.com) and gh:12\n\n<script>x</script>"
+    rules = {r"\bgh:(\d+)\b": "https://github.com/indico/indico/issues/{1}"}
+    meta = indico_md.to_html_with_meta(md, rules)
+    assert meta["html"] == indico_md.to_html(md, rules)
+    assert meta["headings"] == [
+        {"level": 1, "text": "Intro", "slug": "intro", "id": "indico-md-intro", "line": 1}
+    ]
+    assert meta["links"] == [
+        {"url": "https://docs.example.com", "text": "docs"},
+        {"url": "https://github.com/indico/indico/issues/12", "text": "gh:12"},
+    ]
+    assert meta["mentions"] == [
+        {
+            "rule": 0,
+            "text": "gh:12",
+            "groups": ["gh:12", "12"],
+            "url": "https://github.com/indico/indico/issues/12",
+            "line": 3,
+        }
+    ]
+    assert [(w["kind"], w["line"]) for w in meta["warnings"]] == [("filtered_tag", 5)]
+    assert meta["word_count"] == 5
+
+
+def test_extract_headings():
+    md = "# Intro\n\nText\n\n## The *plan*\n\n### Details\n\n# Intro\n"
+    headings = indico_md.extract_headings(md)
+    assert [(h["level"], h["text"], h["slug"], h["id"]) for h in headings] == [
+        (1, "Intro", "intro", "indico-md-intro"),
+        (2, "The plan", "the-plan", "indico-md-the-plan"),
+        (3, "Details", "details", "indico-md-details"),
+        (1, "Intro", "intro-1", "indico-md-intro-1"),
+    ]
+    assert (headings[1]["start"], headings[1]["end"]) == ((5, 1), (5, 13))
+
+    outline = indico_md.extract_outline(md)
+    assert [h["slug"] for h in outline] == ["intro", "intro-1"]
+    assert outline[0]["children"][0]["text"] == "The plan"
+    assert outline[0]["children"][0]["children"][0]["text"] == "Details"
+    assert outline[1]["children"] == []
+    assert indico_md.extract_outline("") == []
+
+
+def test_extract_links():
+    md = "See [the slides](slides.pdf) and #12"
+    rules = {r"#(\d+)": "/tickets/{1}"}
+    assert indico_md.extract_links(md, rules) == [
+        {"url": "slides.pdf", "text": "the slides", "start": (1, 5), "end": (1, 28)},
+        {"url": "/tickets/12", "text": "#12", "start": (1, 34), "end": (1, 36)},
+    ]
+    links = indico_md.extract_links(md, base_url="https://indico.example.com/event/1/")
+    assert [link["url"] for link in links] == ["https://indico.example.com/event/1/slides.pdf"]
+
+
+def test_renderer():
+    rules = indico_md.RuleSet({r"\bgh:(\d+)\b": "https://github.com/indico/indico/issues/{1}"})
+    renderer = indico_md.Renderer(rules, target_blank=False)
+    md = "*See* gh:12"
+    assert renderer.render(md) == indico_md.to_html(md, rules, target_blank=False)
+    assert renderer.render_unstyled(md) == "<p>See gh:12</p>\n"
+    assert renderer.render_plain(md) == "See gh:12"
+    assert indico_md.Renderer().render("gh:12") == "<p>gh:12</p>\n"
+
+    # the unstyled and plain renders follow the options of the renderer
+    url = "https://github.com/indico/indico/issues/12"
+    email = indico_md.Renderer(rules, profile="email")
+    assert email.render_unstyled(md) == (
+        f'<p><em>See</em> <a href="{url}" target="_blank" rel="noopener noreferrer">gh:12</a></p>\n'
+    )
+    assert email.render_plain(md) == f"See gh:12 [1]\n\n[1] {url}"
+    email = indico_md.Renderer(rules, profile="email", target_blank=False)
+    assert email.render_unstyled("gh:12") == f'<p><a href="{url}">gh:12</a></p>\n'
+
+    with pytest.raises(ValueError):
+        indico_md.Renderer(profile="print")
+
+
+def test_email_profile():
+    html = indico_md.to_html(
+        "==hi== [there](/event/1)",
+        {},
+        profile="email",
+        base_url="https://indico.example.com",
+    )
+    assert html == (
+        '<p><span style="background-color:#fff3a3">hi</span> '
+        '<a href="https://indico.example.com/event/1" target="_blank" style="color:#1a64a0">'
+        "there</a></p>\n"
+    )
+
+    with pytest.raises(ValueError):
+        indico_md.to_html("foo", {}, profile="fax")
+
+
+def test_base_urls():
+    html = indico_md.to_html(
+        "[a](a.pdf) ![b](b.png)",
+        {},
+        base_url="https://indico.example.com/event/1/",
+        media_base_url="https://cdn.example.com/",
+    )
+    assert html == (
+        '<p><a href="https://indico.example.com/event/1/a.pdf" target="_blank">a</a> '
+        '<img src="https://cdn.example.com/b.png" alt="b" /></p>\n'
+    )
+
+
+def test_definition_lists():
+    source = "Term\n: definition"
+    assert (
+        indico_md.to_html(source, {}, definition_lists=True)
+        == "<dl>\n<dt>Term</dt>\n<dd>definition</dd>\n</dl>\n"
+    )
+    assert indico_md.to_html(source, {}) == "<p>Term\n: definition</p>\n"
+
+
+def test_smart_punctuation():
+    source = "\"Salut\" -- l'école..."
+    assert indico_md.to_html(source, {}, smart_punctuation="english") == (
+        "<p>“Salut” – l’école…</p>\n"
+    )
+    assert indico_md.to_html(source, {}, smart_punctuation="french") == (
+        "<p>«\u00a0Salut\u00a0» – l’école…</p>\n"
+    )
+    assert indico_md.to_html(source, {}) == "<p>&quot;Salut&quot; -- l'école...</p>\n"
+
+    with pytest.raises(ValueError):
+        indico_md.to_html(source, {}, smart_punctuation="klingon")
+
+
+def test_render_options():
+    assert indico_md.to_html("| a |\n|---|", {}, tables=False) == "<p>| a |\n|---|</p>\n"
+    assert indico_md.to_html("> [!NOTE]\n> text", {}, alerts=False) == (
+        "<blockquote>\n<p>[!NOTE]\ntext</p>\n</blockquote>\n"
+    )
+    assert "data-math-style" in indico_md.to_html("$x$", {})
+    assert indico_md.to_html("$x$", {}, math=False) == "<p>$x$</p>\n"
+    assert indico_md.to_html("<script>x</script>", {}) == "&lt;script&gt;x&lt;/script&gt;\n"
+    assert indico_md.to_html("<script>x</script>", {}, sanitize=False) == "<script>x</script>\n"
+    assert 'id="indico-md-minutes-intro"' in indico_md.to_html("# Intro", {}, heading_id_prefix="minutes")
+    assert indico_md.to_html("[x](https://example.com)", {}, target_blank=False) == (
+        '<p><a href="https://example.com">x</a></p>\n'
+    )
+
+
+def test_sanitize():
+    md = "<b>hi</b> [x](javascript:alert(1)) ![](data:image/png;base64,AA==)"
+    default = indico_md.to_html(md, {})
+    assert default == indico_md.to_html(md, {}, sanitize="default")
+    assert "<b>hi</b>" in default
+    assert "javascript:" not in default
+    assert "data:image/png" in default
+    # raw HTML is limited to an allowlist of tags and attributes
+    assert indico_md.to_html('<img src=x onerror="alert(1)"> <a href="javascript:x">y</a>', {}) == (
+        '<p><img src="x"> <a>y</a></p>\n'
+    )
+    assert indico_md.to_html("<div style='x'>\n<iframe src=x>\n</div>", {}) == (
+        "<div>\n&lt;iframe src=x&gt;\n</div>\n"
+    )
+
+    strict = indico_md.to_html(md, {}, sanitize="strict")
+    assert "&lt;b&gt;hi&lt;/b&gt;" in strict
+    assert "javascript:" not in strict
+    assert "data:image/png" not in strict
+    assert indico_md.to_html("<div>\nhi\n</div>", {}, sanitize="strict") == (
+        "<p>&lt;div&gt;\nhi\n&lt;/div&gt;</p>\n"
+    )
+
+    off = indico_md.to_html(md, {}, sanitize="off")
+    assert off == indico_md.to_html(md, {}, sanitize=False)
+    assert "javascript:alert(1)" in off
+
+    with pytest.raises(ValueError, match="Unknown sanitization level"):
+        indico_md.to_html(md, {}, sanitize="paranoid")
+
+
+def test_front_matter():
+    source = "---\ntitle: Opening\ntags: [a, b]\n---\n**Welcome**"
+    html, front_matter = indico_md.to_html_with_front_matter(source, {})
+    assert html == "<p><strong>Welcome</strong></p>\n"
+    assert front_matter == {"title": "Opening", "tags": ["a", "b"]}
+
+    html, front_matter = indico_md.to_html_with_front_matter("+++\ntitle = 1\n+++\n", {})
+    assert html == ""
+    assert front_matter == {"title": 1}
+
+    assert indico_md.to_html_with_front_matter("**Welcome**", {}) == (
+        "<p><strong>Welcome</strong></p>\n",
+        None,
+    )
+
+
+def test_toggle_task():
+    source = "- [ ] agenda\n- [x] budget"
+    assert indico_md.toggle_task(source, 0) == "- [x] agenda\n- [x] budget"
+    assert indico_md.toggle_task(source, 1) == "- [ ] agenda\n- [ ] budget"
+    assert indico_md.toggle_task(source, 2) is None
+
+
+def test_open_graph():
+    source = "# Opening\n\nWelcome to *the* event!\n\n![Poster](poster.png)"
+    assert indico_md.open_graph(source, base_url="https://indico.example.com/event/1/") == {
+        "title": "Opening",
+        "description": "Welcome to the event! Poster",
+        "image": "https://indico.example.com/event/1/poster.png",
+    }
+    assert indico_md.open_graph("") == {"title": None, "description": None, "image": None}
+
+
+def test_type_stubs():
+    stub = ast.parse((Path(__file__).parent.parent / "indico_md.pyi").read_text())
+    stubbed = {node.name for node in stub.body if isinstance(node, (ast.FunctionDef, ast.ClassDef))}
+    public = {name for name in dir(indico_md) if not name.startswith("_")}
+    assert public <= stubbed
+
+
+def test_threads():
+    if sysconfig.get_config_var("Py_GIL_DISABLED"):
+        # importing the module must not enable the GIL again
+        assert not sys._is_gil_enabled()
+
+    rules = indico_md.RuleSet({r"#(\d+)": lambda text, number: f"/tickets/{number}"})
+    renderer = indico_md.Renderer(rules)
+    sources = [f"#{i}" for i in range(200)]
+    with ThreadPoolExecutor(8) as executor:
+        htmls = list(executor.map(renderer.render, sources))
+    assert htmls == [
+        f'<p><a href="/tickets/{i}" title="#{i}" target="_blank">#{i}</a></p>\n' for i in range(200)
+    ]
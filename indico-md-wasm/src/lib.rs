@@ -1,10 +1,82 @@
 use indico_comrak::{
-    LinkRule, indico_markdown_to_html as _indico_md_to_html,
+    LinkRule, QuoteStyle, RenderOptions, indico_markdown_open_graph as _indico_md_open_graph,
+    indico_markdown_render as _indico_md_render, indico_markdown_to_html as _indico_md_to_html,
+    indico_markdown_to_html_with_options as _indico_md_to_html_with_options,
     indico_markdown_to_unstyled_html as _indico_md_to_unstyled_html,
+    indico_markdown_toggle_task as _indico_md_toggle_task,
 };
-use js_sys::Array;
+use js_sys::{Array, JSON, Object, Reflect};
 use wasm_bindgen::prelude::*;
 
+/// Build link rules from a JavaScript array of `[RegExp, string]` pairs
+fn parse_rules(js_rules: &Array) -> Result<Vec<LinkRule>, JsValue> {
+    let mut rules = Vec::new();
+
+    for res in js_rules.values() {
+        let array: js_sys::Array = res?.into();
+        let vec: Vec<_> = array.to_vec();
+        let re: js_sys::RegExp = vec[0].clone().into();
+        let url_pattern = vec[1]
+            .as_string()
+            .ok_or(JsValue::from_str("URL pattern is not a valid string"))?;
+
+        rules.push(
+            LinkRule::new(
+                &re.source().as_string().ok_or(JsValue::from_str(
+                    "Regular expression is not a valid string",
+                ))?,
+                &url_pattern,
+            )
+            .map_err(|e| e.to_string())?,
+        );
+    }
+    Ok(rules)
+}
+
+/// Read an optional boolean property of a JavaScript object
+fn get_bool(obj: &Object, key: &str) -> Result<bool, JsValue> {
+    let value = Reflect::get(obj, &JsValue::from_str(key))?;
+    if value.is_undefined() || value.is_null() {
+        Ok(false)
+    } else {
+        value
+            .as_bool()
+            .ok_or_else(|| JsValue::from_str(&format!("Option '{key}' is not a boolean")))
+    }
+}
+
+/// Read an optional string property of a JavaScript object
+fn get_string(obj: &Object, key: &str) -> Result<Option<String>, JsValue> {
+    let value = Reflect::get(obj, &JsValue::from_str(key))?;
+    if value.is_undefined() || value.is_null() {
+        Ok(None)
+    } else {
+        value
+            .as_string()
+            .map(Some)
+            .ok_or_else(|| JsValue::from_str(&format!("Option '{key}' is not a string")))
+    }
+}
+
+/// Build render options from a JavaScript options object
+fn render_options(options: &Object) -> Result<RenderOptions, JsValue> {
+    let smart_punctuation = match get_string(options, "smartPunctuation")?.as_deref() {
+        None => None,
+        Some("english") => Some(QuoteStyle::English),
+        Some("french") => Some(QuoteStyle::French),
+        Some("german") => Some(QuoteStyle::German),
+        Some(style) => return Err(JsValue::from_str(&format!("Unknown quote style: {style}"))),
+    };
+    Ok(RenderOptions {
+        base_url: get_string(options, "baseUrl")?,
+        interactive_tasks: get_bool(options, "interactiveTasks")?,
+        media_base_url: get_string(options, "mediaBaseUrl")?,
+        definition_lists: get_bool(options, "definitionLists")?,
+        smart_punctuation,
+        ..Default::default()
+    })
+}
+
 /// Converts markdown text to HTML while applying custom link rules
 ///
 /// This function takes markdown text and an array of link rules from JavaScript,
@@ -37,30 +109,136 @@ use wasm_bindgen::prelude::*;
 /// ```
 #[wasm_bindgen(js_name = toHtml)]
 pub fn to_html(md_source: &str, js_rules: &Array) -> Result<String, JsValue> {
-    let mut rules = Vec::new();
+    let rules = parse_rules(js_rules)?;
+    _indico_md_to_html(md_source, &rules).map_err(|e| JsValue::from_str(&e.to_string()))
+}
 
-    for res in js_rules.values() {
-        let array: js_sys::Array = res?.into();
-        let vec: Vec<_> = array.to_vec();
-        let re: js_sys::RegExp = vec[0].clone().into();
-        let url_pattern = vec[1]
-            .as_string()
-            .ok_or(JsValue::from_str("URL pattern is not a valid string"))?;
+/// Converts markdown text to HTML, like `toHtml`, with additional rendering options
+///
+/// # Arguments
+///
+/// * `md_source` - A string slice containing the markdown text to process
+/// * `js_rules` - A JavaScript array containing pairs of RegExp and URL pattern strings
+/// * `options` - A JavaScript object with the rendering options:
+///   * `baseUrl` - resolve relative link and image URLs against this URL
+///   * `mediaBaseUrl` - resolve relative image URLs against this URL instead
+///   * `interactiveTasks` - render task list checkboxes as enabled, with their index and line
+///     (see `toggleTask`)
+///   * `definitionLists` - enable `Term\n: definition` lists
+///   * `smartPunctuation` - enable smart quotes, dashes and ellipses, using the quotation
+///     marks of `"english"`, `"french"` or `"german"`
+///
+/// # Errors
+///
+/// Returns a JsValue error if the link rules or the options are invalid
+///
+/// # Example (JavaScript)
+///
+/// ```javascript
+/// const html = toHtmlWithOptions("Term\n: definition", [], { definitionLists: true });
+/// ```
+#[wasm_bindgen(js_name = toHtmlWithOptions)]
+pub fn to_html_with_options(
+    md_source: &str,
+    js_rules: &Array,
+    options: &Object,
+) -> Result<String, JsValue> {
+    let rules = parse_rules(js_rules)?;
+    let options = render_options(options)?;
+    _indico_md_to_html_with_options(md_source, &rules, &options)
+        .map_err(|e| JsValue::from_str(&e.to_string()))
+}
 
-        rules.push(
-            LinkRule::new(
-                &re.source().as_string().ok_or(JsValue::from_str(
-                    "Regular expression is not a valid string",
-                ))?,
-                &url_pattern,
-            )
-            .map_err(|e| e.to_string())?,
-        );
+/// Converts markdown text to HTML, like `toHtmlWithOptions`, stripping a leading front matter
+/// block (YAML between `---` lines or TOML between `+++` lines) from the document
+///
+/// # Returns
+///
+/// * `Result<Object, JsValue>` - An object with the resulting `html` and the parsed
+///   `frontMatter` (`null` if there is no front matter or it cannot be parsed)
+///
+/// # Example (JavaScript)
+///
+/// ```javascript
+/// const {html, frontMatter} = toHtmlWithFrontMatter("---\ntitle: Hi\n---\ntext", [], {});
+/// ```
+#[wasm_bindgen(js_name = toHtmlWithFrontMatter)]
+pub fn to_html_with_front_matter(
+    md_source: &str,
+    js_rules: &Array,
+    options: &Object,
+) -> Result<Object, JsValue> {
+    let rules = parse_rules(js_rules)?;
+    let options = RenderOptions {
+        front_matter: true,
+        ..render_options(options)?
+    };
+    let output = _indico_md_render(md_source, &rules, &options)
+        .map_err(|e| JsValue::from_str(&e.to_string()))?;
+
+    let front_matter = match output
+        .front_matter
+        .and_then(|front_matter| front_matter.data)
+    {
+        Some(data) => JSON::parse(&data.to_string())?,
+        None => JsValue::NULL,
+    };
+    let result = Object::new();
+    Reflect::set(&result, &"html".into(), &output.html.into())?;
+    Reflect::set(&result, &"frontMatter".into(), &front_matter)?;
+    Ok(result)
+}
+
+/// Checks or unchecks the task list item with the given index (the `data-index` of its
+/// checkbox when `interactiveTasks` is enabled), returning the updated markdown, or
+/// `undefined` if there is no such item
+///
+/// # Example (JavaScript)
+///
+/// ```javascript
+/// const source = toggleTask("- [ ] agenda", 0);
+/// // source === "- [x] agenda"
+/// ```
+#[wasm_bindgen(js_name = toggleTask)]
+pub fn toggle_task(md_source: &str, index: usize) -> Option<String> {
+    _indico_md_toggle_task(md_source, index)
+}
+
+/// Derives Open Graph metadata from a document, to share its page
+///
+/// # Arguments
+///
+/// * `md_source` - A string slice containing the markdown text to process
+/// * `options` - A JavaScript object with the `baseUrl` and `mediaBaseUrl` options of
+///   `toHtmlWithOptions`, used to resolve the URL of the image
+///
+/// # Returns
+///
+/// * `Result<Object, JsValue>` - An object with the `title` (the first heading), `description`
+///   (an excerpt of the text) and `image` (the URL of the first image), which are `null` when
+///   missing
+///
+/// # Example (JavaScript)
+///
+/// ```javascript
+/// const {title, description, image} = openGraph("# Opening\n\nWelcome!", {});
+/// ```
+#[wasm_bindgen(js_name = openGraph)]
+pub fn open_graph(md_source: &str, options: &Object) -> Result<Object, JsValue> {
+    let og = _indico_md_open_graph(md_source, &render_options(options)?);
+    let result = Object::new();
+    for (key, value) in [
+        ("title", og.title),
+        ("description", og.description),
+        ("image", og.image),
+    ] {
+        let value = value.map_or(JsValue::NULL, JsValue::from);
+        Reflect::set(&result, &key.into(), &value)?;
     }
-    _indico_md_to_html(md_source, &rules).map_err(|e| JsValue::from_str(&e.to_string()))
+    Ok(result)
 }
 
 #[wasm_bindgen(js_name = toUnstyledHtml)]
 pub fn to_unstyled_html(md_source: &str) -> Result<String, JsValue> {
-    _indico_md_to_unstyled_html(md_source).map_err(|e| JsValue::from_str(&e.to_string()))
+    _indico_md_to_unstyled_html(md_source, &[]).map_err(|e| JsValue::from_str(&e.to_string()))
 }
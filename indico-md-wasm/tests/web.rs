@@ -3,8 +3,11 @@
 #![cfg(target_arch = "wasm32")]
 
 extern crate wasm_bindgen_test;
-use indico_md_wasm::{to_html, to_unstyled_html};
-use js_sys::{Array, RegExp};
+use indico_md_wasm::{
+    open_graph, to_html, to_html_with_front_matter, to_html_with_options, to_unstyled_html,
+    toggle_task,
+};
+use js_sys::{Array, Object, Reflect, RegExp};
 use wasm_bindgen::JsValue;
 use wasm_bindgen_test::*;
 
@@ -64,3 +67,107 @@ fn interface_test() {
             .contains("not a valid string")
     )
 }
+
+#[wasm_bindgen_test]
+fn options_test() {
+    let options = Object::new();
+    Reflect::set(&options, &"definitionLists".into(), &JsValue::TRUE).unwrap();
+    assert_eq!(
+        to_html_with_options("Term\n: definition", &Array::new(), &options),
+        Ok("<dl>\n<dt>Term</dt>\n<dd>definition</dd>\n</dl>\n".into())
+    );
+
+    // unset options keep their defaults
+    assert_eq!(
+        to_html_with_options("Term\n: definition", &Array::new(), &Object::new()),
+        Ok("<p>Term\n: definition</p>\n".into())
+    );
+
+    Reflect::set(&options, &"smartPunctuation".into(), &"german".into()).unwrap();
+    assert_eq!(
+        to_html_with_options("\"Hallo\"...", &Array::new(), &options),
+        Ok("<p>\u{201e}Hallo\u{201c}\u{2026}</p>\n".into())
+    );
+
+    let options = Object::new();
+    Reflect::set(
+        &options,
+        &"baseUrl".into(),
+        &"https://indico.example.com/".into(),
+    )
+    .unwrap();
+    Reflect::set(
+        &options,
+        &"mediaBaseUrl".into(),
+        &"https://cdn.example.com/".into(),
+    )
+    .unwrap();
+    assert_eq!(
+        to_html_with_options("[a](a.pdf) ![b](b.png)", &Array::new(), &options),
+        Ok(
+            "<p><a href=\"https://indico.example.com/a.pdf\" target=\"_blank\">a</a> \
+            <img src=\"https://cdn.example.com/b.png\" alt=\"b\" /></p>\n"
+                .into()
+        )
+    );
+
+    Reflect::set(&options, &"definitionLists".into(), &"yes".into()).unwrap();
+    assert!(to_html_with_options("foo", &Array::new(), &options).is_err());
+}
+
+#[wasm_bindgen_test]
+fn front_matter_test() {
+    let res = to_html_with_front_matter(
+        "---\ntitle: Opening\n---\n**Welcome**",
+        &Array::new(),
+        &Object::new(),
+    )
+    .unwrap();
+    assert_eq!(
+        Reflect::get(&res, &"html".into()).unwrap(),
+        "<p><strong>Welcome</strong></p>\n"
+    );
+    let front_matter: Object = Reflect::get(&res, &"frontMatter".into()).unwrap().into();
+    assert_eq!(
+        Reflect::get(&front_matter, &"title".into()).unwrap(),
+        "Opening"
+    );
+
+    let res = to_html_with_front_matter("text", &Array::new(), &Object::new()).unwrap();
+    assert!(Reflect::get(&res, &"frontMatter".into()).unwrap().is_null());
+}
+
+#[wasm_bindgen_test]
+fn toggle_task_test() {
+    let options = Object::new();
+    Reflect::set(&options, &"interactiveTasks".into(), &JsValue::TRUE).unwrap();
+    assert_eq!(
+        to_html_with_options("- [ ] agenda", &Array::new(), &options),
+        Ok("<ul>\n<li><input type=\"checkbox\" data-index=\"0\" data-line=\"1\" /> agenda</li>\n</ul>\n".into())
+    );
+
+    assert_eq!(toggle_task("- [ ] agenda", 0), Some("- [x] agenda".into()));
+    assert_eq!(toggle_task("- [ ] agenda", 1), None);
+}
+
+#[wasm_bindgen_test]
+fn open_graph_test() {
+    let options = Object::new();
+    Reflect::set(
+        &options,
+        &"baseUrl".into(),
+        &"https://indico.example.com/event/1/".into(),
+    )
+    .unwrap();
+    let og = open_graph("# Opening\n\nWelcome!\n\n![](poster.png)", &options).unwrap();
+    let get = |key: &str| Reflect::get(&og, &key.into()).unwrap();
+    assert_eq!(get("title"), "Opening");
+    assert_eq!(get("description"), "Welcome!");
+    assert_eq!(
+        get("image"),
+        "https://indico.example.com/event/1/poster.png"
+    );
+
+    let og = open_graph("", &Object::new()).unwrap();
+    assert!(Reflect::get(&og, &"title".into()).unwrap().is_null());
+}